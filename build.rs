@@ -0,0 +1,53 @@
+use std::hash::Hasher;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+// Written at runtime next to the assets during development, never shipped
+fn is_save_file(name: &str) -> bool {
+    name == "settings.json"
+        || name == "progress.json"
+        || name.starts_with("ghost_")
+        || name.ends_with(".session.json")
+}
+
+fn collect(dir: &Path, files: &mut Vec<PathBuf>) {
+    let mut entries: Vec<PathBuf> = std::fs::read_dir(dir)
+        .unwrap()
+        .map(|entry| entry.unwrap().path())
+        .collect();
+    entries.sort();
+    for path in entries {
+        if path.is_dir() {
+            collect(&path, files);
+        } else if !is_save_file(path.file_name().unwrap().to_str().unwrap()) {
+            files.push(path);
+        }
+    }
+}
+
+fn main() {
+    if std::env::var_os("CARGO_FEATURE_EMBED_ASSETS").is_none() {
+        return;
+    }
+    let root = Path::new(&std::env::var("CARGO_MANIFEST_DIR").unwrap()).join("static");
+    println!("cargo:rerun-if-changed={}", root.display());
+    let mut files = Vec::new();
+    collect(&root, &mut files);
+    let mut hasher = std::collections::hash_map::DefaultHasher::new();
+    let out = Path::new(&std::env::var("OUT_DIR").unwrap()).join("embedded_assets.rs");
+    let mut out = std::fs::File::create(out).unwrap();
+    writeln!(out, "pub const EMBEDDED_ASSETS: &[(&str, &[u8])] = &[").unwrap();
+    for path in &files {
+        let name = path
+            .strip_prefix(&root)
+            .unwrap()
+            .to_str()
+            .unwrap()
+            .replace('\\', "/");
+        hasher.write(name.as_bytes());
+        hasher.write(&std::fs::read(path).unwrap());
+        writeln!(out, "    ({:?}, include_bytes!({:?})),", name, path).unwrap();
+    }
+    writeln!(out, "];").unwrap();
+    writeln!(out, "pub const EMBEDDED_HASH: u64 = {};", hasher.finish()).unwrap();
+}
@@ -0,0 +1,169 @@
+use super::*;
+
+const TOAST_DURATION: f32 = 4.0;
+const TOAST_FADE_TIME: f32 = 0.5;
+const TOAST_SIZE: Vec2<f32> = vec2(420.0, 64.0);
+const TOAST_MARGIN: f32 = 20.0;
+// Leaves room for the HUD line at the top
+const TOAST_TOP: f32 = 80.0;
+
+// What the unlock conditions get to look at
+pub struct AchievementCheck<'a> {
+    pub lifetime: &'a LifetimeStats,
+    // Only set on the step a level gets finished
+    pub run: Option<&'a RunStats>,
+    pub collected_all: bool,
+}
+
+// Name and description come from the string tables as
+// `achievement.<id>` and `achievement.<id>.description`
+pub struct Achievement {
+    pub id: &'static str,
+    condition: fn(&AchievementCheck) -> bool,
+}
+
+impl Achievement {
+    pub fn name(&self) -> String {
+        tr(&format!("achievement.{}", self.id))
+    }
+    pub fn description(&self) -> String {
+        tr(&format!("achievement.{}.description", self.id))
+    }
+}
+
+pub const ACHIEVEMENTS: [Achievement; 7] = [
+    Achievement {
+        id: "first_clear",
+        condition: |check| check.run.is_some(),
+    },
+    Achievement {
+        id: "airborne",
+        condition: |check| check.run.is_some_and(|run| !run.touched_ground),
+    },
+    Achievement {
+        id: "deathless",
+        condition: |check| check.run.is_some_and(|run| run.deaths == 0),
+    },
+    Achievement {
+        id: "collector",
+        condition: |check| check.run.is_some() && check.collected_all,
+    },
+    Achievement {
+        id: "pitcher",
+        condition: |check| check.lifetime.throws >= 1000,
+    },
+    Achievement {
+        id: "wrecking_ball",
+        condition: |check| check.lifetime.blocks_broken >= 100,
+    },
+    Achievement {
+        id: "marathon",
+        condition: |check| check.lifetime.distance_swung >= 10000.0,
+    },
+];
+
+pub fn newly_unlocked(save: &SaveData, check: &AchievementCheck) -> Vec<&'static Achievement> {
+    ACHIEVEMENTS
+        .iter()
+        .filter(|achievement| {
+            !save.achievements.contains(achievement.id) && (achievement.condition)(check)
+        })
+        .collect()
+}
+
+// Lifetime stats followed by every achievement, for the menu page
+pub fn achievement_lines(save: &SaveData) -> Vec<String> {
+    let stats = &save.stats;
+    let mut lines = vec![
+        trf(
+            "achievements.stats",
+            &[&stats.throws, &stats.deaths, &stats.blocks_broken],
+        ),
+        trf(
+            "achievements.distance",
+            &[&format!("{:.0}", stats.distance_swung)],
+        ),
+    ];
+    for achievement in &ACHIEVEMENTS {
+        let mark = if save.achievements.contains(achievement.id) {
+            "[x]"
+        } else {
+            "[ ]"
+        };
+        lines.push(format!(
+            "{} {} - {}",
+            mark,
+            achievement.name(),
+            achievement.description()
+        ));
+    }
+    lines
+}
+
+#[derive(Default)]
+pub struct AchievementToasts {
+    // Achievement name and how long it has been shown
+    toasts: Vec<(String, f32)>,
+}
+
+impl AchievementToasts {
+    pub fn push(&mut self, achievement: &Achievement) {
+        self.toasts.push((achievement.name(), 0.0));
+    }
+    pub fn update(&mut self, delta_time: f32) {
+        for (_, time) in &mut self.toasts {
+            *time += delta_time;
+        }
+        self.toasts.retain(|(_, time)| *time < TOAST_DURATION);
+    }
+    pub fn draw(
+        &self,
+        framebuffer: &mut ugli::Framebuffer,
+        renderer: &Renderer,
+        font: &geng::Font,
+        theme: &Theme,
+    ) {
+        let ui_size = UiCamera::size(framebuffer.size().map(|x| x as f32));
+        for (index, (name, time)) in self.toasts.iter().enumerate() {
+            let alpha = clamp((TOAST_DURATION - time) / TOAST_FADE_TIME, 0.0..=1.0)
+                * clamp(time / TOAST_FADE_TIME, 0.0..=1.0);
+            let pos = vec2(
+                ui_size.x - TOAST_MARGIN - TOAST_SIZE.x,
+                ui_size.y - TOAST_TOP - (index + 1) as f32 * (TOAST_SIZE.y + TOAST_MARGIN),
+            );
+            let background = theme.background.rgba();
+            renderer.draw_ui_rect(
+                framebuffer,
+                AABB::pos_size(pos, TOAST_SIZE),
+                Color {
+                    a: background.a * alpha * 0.8,
+                    ..background
+                },
+            );
+            let color = theme.highlight.rgba();
+            let color = Color {
+                a: color.a * alpha,
+                ..color
+            };
+            let center = pos.x + TOAST_SIZE.x / 2.0;
+            draw_ui_text(
+                framebuffer,
+                font,
+                &tr("achievements.unlocked"),
+                vec2(center, pos.y + TOAST_SIZE.y - 26.0),
+                0.5,
+                20.0,
+                color,
+            );
+            draw_ui_text(
+                framebuffer,
+                font,
+                name,
+                vec2(center, pos.y + 10.0),
+                0.5,
+                28.0,
+                color,
+            );
+        }
+    }
+}
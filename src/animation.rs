@@ -0,0 +1,92 @@
+use super::*;
+
+#[derive(Debug, Clone)]
+pub struct AnimationState {
+    pub time: f32,
+    pub speed: f32,
+    pub looping: bool,
+    pub frame_duration: f32,
+}
+
+impl AnimationState {
+    pub fn new(frame_duration: f32) -> Self {
+        Self {
+            time: 0.0,
+            speed: 1.0,
+            looping: true,
+            frame_duration,
+        }
+    }
+    pub fn restart(&mut self) {
+        self.time = 0.0;
+    }
+    pub fn update(&mut self, delta_time: f32) {
+        self.time += delta_time * self.speed;
+    }
+    pub fn frame_index(&self, frames: usize) -> usize {
+        let index = (self.time / self.frame_duration).max(0.0) as usize;
+        if self.looping {
+            index % frames
+        } else {
+            index.min(frames - 1)
+        }
+    }
+    pub fn finished(&self, animation: &Animation) -> bool {
+        !self.looping && self.time >= self.frame_duration * animation.len() as f32
+    }
+    pub fn frame<'a>(&self, animation: &'a Animation) -> &'a ugli::Texture {
+        &animation[self.frame_index(animation.len())]
+    }
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum CharacterAnimation {
+    Idle,
+    Walk,
+    SpinUp,
+    Flying,
+    Reeling,
+}
+
+impl CharacterAnimation {
+    pub fn animation(self, assets: &Assets) -> Ref<'_, Animation> {
+        match self {
+            Self::Idle => assets.player_idle.borrow(),
+            Self::Walk => assets.player_walk.borrow(),
+            Self::SpinUp => assets.player_spin.borrow(),
+            Self::Flying => assets.player_fly.borrow(),
+            Self::Reeling => assets.player_reel.borrow(),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct AnimationController {
+    pub state: CharacterAnimation,
+    pub playback: AnimationState,
+    pub facing_left: bool,
+}
+
+impl AnimationController {
+    pub fn new(frame_duration: f32) -> Self {
+        Self {
+            state: CharacterAnimation::Idle,
+            playback: AnimationState::new(frame_duration),
+            facing_left: false,
+        }
+    }
+    pub fn set_state(&mut self, state: CharacterAnimation) {
+        if self.state != state {
+            self.state = state;
+            self.playback.restart();
+        }
+    }
+    pub fn update(&mut self, delta_time: f32) {
+        self.playback.update(delta_time);
+    }
+    pub fn frame<'a>(&self, assets: &'a Assets) -> Ref<'a, ugli::Texture> {
+        Ref::map(self.state.animation(assets), |animation| {
+            self.playback.frame(animation)
+        })
+    }
+}
@@ -0,0 +1,48 @@
+use super::*;
+
+#[cfg(all(feature = "embed-assets", not(target_arch = "wasm32")))]
+mod embedded {
+    include!(concat!(env!("OUT_DIR"), "/embedded_assets.rs"));
+}
+
+// Directory `Assets` load from: the static folder we run in, or with the
+// `embed-assets` feature the copy built into the executable. geng only loads
+// textures and sounds from paths, so that copy is unpacked once per build.
+pub fn asset_root() -> anyhow::Result<String> {
+    #[cfg(all(feature = "embed-assets", not(target_arch = "wasm32")))]
+    {
+        let dir = unpack_embedded().context("Failed to unpack embedded assets")?;
+        return Ok(dir.to_string_lossy().into_owned());
+    }
+    #[allow(unreachable_code)]
+    Ok(".".to_owned())
+}
+
+#[cfg(all(feature = "embed-assets", not(target_arch = "wasm32")))]
+fn unpack_embedded() -> std::io::Result<std::path::PathBuf> {
+    let dir = std::env::temp_dir().join(format!(
+        "{}-{:x}",
+        env!("CARGO_PKG_NAME"),
+        embedded::EMBEDDED_HASH,
+    ));
+    if dir.is_dir() {
+        return Ok(dir);
+    }
+    // Unpacked next to the final name first so a crash never leaves a half copy behind,
+    // one per process so launches at the same time don't write into each other's
+    let partial = dir.with_extension(format!("partial-{}", std::process::id()));
+    let _ = std::fs::remove_dir_all(&partial);
+    for (name, data) in embedded::EMBEDDED_ASSETS {
+        let path = partial.join(name);
+        std::fs::create_dir_all(path.parent().unwrap())?;
+        std::fs::write(path, data)?;
+    }
+    if let Err(e) = std::fs::rename(&partial, &dir) {
+        let _ = std::fs::remove_dir_all(&partial);
+        // Another launch finished unpacking first
+        if !dir.is_dir() {
+            return Err(e);
+        }
+    }
+    Ok(dir)
+}
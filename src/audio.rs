@@ -0,0 +1,135 @@
+use super::*;
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum SoundEvent {
+    Throw,
+    ChainRattle,
+    Impact,
+    Landing,
+    Death,
+    Pickup,
+}
+
+impl SoundEvent {
+    fn volume(self) -> f64 {
+        match self {
+            Self::Throw => 0.5,
+            Self::ChainRattle => 0.4,
+            Self::Impact => 0.8,
+            Self::Landing => 0.4,
+            Self::Death => 0.7,
+            Self::Pickup => 0.6,
+        }
+    }
+    // Each sound ships as a few pitch-shifted takes since geng can't change playback rate
+    fn variants(self, assets: &Assets) -> &[geng::Sound] {
+        match self {
+            Self::Throw => &assets.throw_sounds,
+            Self::ChainRattle => &assets.chain_rattle_sounds,
+            Self::Impact => &assets.impact_sounds,
+            Self::Landing => &assets.landing_sounds,
+            Self::Death => &assets.death_sounds,
+            Self::Pickup => &assets.pickup_sounds,
+        }
+    }
+}
+
+const FALLOFF_START: f32 = 15.0;
+const FALLOFF_END: f32 = 50.0;
+const OCCLUDED_VOLUME: f64 = 0.35;
+
+// geng only has volume control, so there is no stereo panning,
+// distance and walls in the way just make sounds quieter
+pub struct AudioPlayer {
+    pub volume: f64,
+    pub listener: Vec2<f32>,
+}
+
+impl Default for AudioPlayer {
+    fn default() -> Self {
+        Self {
+            volume: 1.0,
+            listener: vec2(0.0, 0.0),
+        }
+    }
+}
+
+impl AudioPlayer {
+    pub fn play(&self, assets: &Assets, event: SoundEvent) {
+        self.play_scaled(assets, event, 1.0);
+    }
+    pub fn play_scaled(&self, assets: &Assets, event: SoundEvent, scale: f64) {
+        if let Some(sound) = event.variants(assets).choose(&mut global_rng()) {
+            self.play_sound(sound, event.volume() * scale.min(1.0));
+        }
+    }
+    pub fn play_at(
+        &self,
+        assets: &Assets,
+        event: SoundEvent,
+        scale: f64,
+        pos: Vec2<f32>,
+        occluded: bool,
+    ) {
+        self.play_scaled(assets, event, scale.min(1.0) * self.falloff(pos, occluded));
+    }
+    pub fn play_sound_at(&self, sound: &geng::Sound, pos: Vec2<f32>, occluded: bool) {
+        self.play_sound(sound, self.falloff(pos, occluded));
+    }
+    fn play_sound(&self, sound: &geng::Sound, volume: f64) {
+        let mut effect = sound.effect();
+        effect.set_volume(self.volume * volume);
+        effect.play();
+    }
+    fn falloff(&self, pos: Vec2<f32>, occluded: bool) -> f64 {
+        let distance = (pos - self.listener).len();
+        let falloff = 1.0 - (distance - FALLOFF_START) / (FALLOFF_END - FALLOFF_START);
+        let falloff = clamp(falloff, 0.0..=1.0) as f64;
+        if occluded {
+            falloff * OCCLUDED_VOLUME
+        } else {
+            falloff
+        }
+    }
+}
+
+const CHAIN_LOOP_FADE_SPEED: f32 = 8.0;
+
+// Continuous chain rattle, pitch is faked by crossfading loops recorded at rising pitches
+pub struct ChainLoop {
+    effects: Vec<geng::SoundEffect>,
+    intensity: f32,
+}
+
+impl ChainLoop {
+    pub fn new(assets: &Assets) -> Self {
+        Self {
+            effects: assets
+                .chain_loop_sounds
+                .iter()
+                .map(|sound| {
+                    let mut effect = sound.effect();
+                    effect.set_volume(0.0);
+                    effect.play();
+                    effect
+                })
+                .collect(),
+            intensity: 0.0,
+        }
+    }
+    pub fn update(&mut self, target: f32, volume: f64, delta_time: f32) {
+        let target = clamp(target, 0.0..=1.0);
+        self.intensity += (target - self.intensity) * (CHAIN_LOOP_FADE_SPEED * delta_time).min(1.0);
+        let pitch = self.intensity * (self.effects.len().max(1) - 1) as f32;
+        for (index, effect) in self.effects.iter_mut().enumerate() {
+            let weight = (1.0 - (pitch - index as f32).abs()).max(0.0);
+            effect.set_volume(volume * (self.intensity * weight) as f64);
+        }
+    }
+    pub fn silence(&mut self) {
+        self.intensity = 0.0;
+        for effect in &mut self.effects {
+            effect.set_volume(0.0);
+        }
+    }
+}
@@ -1,10 +1,64 @@
 use super::*;
 
+#[derive(ugli::Uniforms)]
+pub struct CameraUniforms {
+    u_projection_matrix: Mat4<f32>,
+    u_view_matrix: Mat4<f32>,
+}
+
+pub trait AbstractCamera {
+    fn view_matrix(&self) -> Mat4<f32>;
+    fn projection_matrix(&self, framebuffer_size: Vec2<f32>) -> Mat4<f32>;
+    fn uniforms(&self, framebuffer_size: Vec2<f32>) -> CameraUniforms {
+        CameraUniforms {
+            u_projection_matrix: self.projection_matrix(framebuffer_size),
+            u_view_matrix: self.view_matrix(),
+        }
+    }
+    fn world_to_screen(&self, framebuffer_size: Vec2<f32>, pos: Vec2<f32>) -> Vec2<f32> {
+        let pos = (self.projection_matrix(framebuffer_size) * self.view_matrix())
+            * pos.extend(0.0).extend(1.0);
+        vec2(
+            (pos.x + 1.0) / 2.0 * framebuffer_size.x,
+            (pos.y + 1.0) / 2.0 * framebuffer_size.y,
+        )
+    }
+    fn screen_to_world(&self, framebuffer_size: Vec2<f32>, pos: Vec2<f32>) -> Vec2<f32> {
+        let pos = vec2(
+            pos.x / framebuffer_size.x * 2.0 - 1.0,
+            pos.y / framebuffer_size.y * 2.0 - 1.0,
+        );
+        let pos = (self.projection_matrix(framebuffer_size) * self.view_matrix()).inverse()
+            * pos.extend(0.0).extend(1.0);
+        pos.xy()
+    }
+}
+
+const FOLLOW_LOOKAHEAD: f32 = 0.3;
+const FOLLOW_DEAD_ZONE: f32 = 2.0;
+const FIT_MARGIN: f32 = 3.0;
+const MIN_FOV: f32 = 30.0;
+const MAX_FOV: f32 = 60.0;
+const SHAKE_DECAY: f32 = 1.5;
+const MAX_SHAKE_OFFSET: f32 = 1.0;
+const KICK_DECAY: f32 = 8.0;
+
 pub struct Camera {
     pub center: Vec2<f32>,
     pub target_position: Vec2<f32>,
     pub fov: f32,
     pub target_fov: f32,
+    pub rotation: f32,
+    pub target_rotation: f32,
+    pub bounds: Option<AABB<f32>>,
+    // Flips the picture horizontally, screen_to_world follows along
+    pub mirror: bool,
+    trauma: f32,
+    shake_time: f32,
+    // Directional jolt on top of the shake, springs back on its own
+    kick: Vec2<f32>,
+    zone: Option<CameraZone>,
+    zone_weight: f32,
 }
 
 impl Camera {
@@ -14,15 +68,182 @@ impl Camera {
             fov,
             target_fov: fov,
             target_position: vec2(0.0, 0.0),
+            rotation: 0.0,
+            target_rotation: 0.0,
+            bounds: None,
+            mirror: false,
+            trauma: 0.0,
+            shake_time: 0.0,
+            kick: vec2(0.0, 0.0),
+            zone: None,
+            zone_weight: 0.0,
         }
     }
+    // Eases out of the current zone before easing into a different one
+    pub fn set_zone(&mut self, zone: Option<&CameraZone>, delta_time: f32) {
+        if self.zone.as_ref() != zone {
+            let blend = self.zone.as_ref().map_or(0.0, |zone| zone.blend);
+            self.zone_weight -= delta_time / blend.max(EPS);
+            if self.zone_weight > 0.0 {
+                return;
+            }
+            self.zone_weight = 0.0;
+            self.zone = zone.cloned();
+        }
+        if let Some(zone) = &self.zone {
+            self.zone_weight = (self.zone_weight + delta_time / zone.blend.max(EPS)).min(1.0);
+        }
+    }
+    pub fn clear_zone(&mut self) {
+        self.zone = None;
+        self.zone_weight = 0.0;
+    }
+    fn zone_blend(&self) -> f32 {
+        let t = self.zone_weight;
+        t * t * (3.0 - 2.0 * t)
+    }
+    fn framed_position(&self) -> Vec2<f32> {
+        let target = self.target_position;
+        let zone = match &self.zone {
+            Some(zone) => zone,
+            None => return target,
+        };
+        let center = zone.aabb.center();
+        let framed = match zone.framing {
+            CameraFraming::Follow => target,
+            CameraFraming::Fixed => center,
+            CameraFraming::Horizontal => vec2(target.x, center.y),
+            CameraFraming::Vertical => vec2(center.x, target.y),
+        };
+        target + (framed - target) * self.zone_blend()
+    }
+    fn framed_fov(&self) -> f32 {
+        match self.zone.as_ref().and_then(|zone| zone.fov) {
+            Some(fov) => self.target_fov + (fov - self.target_fov) * self.zone_blend(),
+            None => self.target_fov,
+        }
+    }
+    pub fn add_shake(&mut self, amount: f32) {
+        self.trauma = (self.trauma + amount).min(1.0);
+    }
+    fn shake_offset(&self) -> Vec2<f32> {
+        let t = self.shake_time;
+        let noise = vec2(
+            ((t * 37.0).sin() + (t * 23.0 + 1.3).sin()) / 2.0,
+            ((t * 31.0 + 0.7).sin() + (t * 19.0 + 2.1).sin()) / 2.0,
+        );
+        noise * self.trauma * self.trauma * MAX_SHAKE_OFFSET + self.kick
+    }
+    pub fn kick(&mut self, offset: Vec2<f32>) {
+        self.kick += offset;
+    }
     pub fn update(&mut self, delta_time: f32) {
+        self.trauma = (self.trauma - SHAKE_DECAY * delta_time).max(0.0);
+        self.shake_time += delta_time;
+        self.kick *= (1.0 - KICK_DECAY * delta_time).max(0.0);
         let delta_time = delta_time * 5.0;
-        self.fov += (self.target_fov - self.fov) * delta_time.min(1.0);
-        self.center += (self.target_position - self.center) * delta_time.min(1.0);
+        self.fov += (self.framed_fov() - self.fov) * delta_time.min(1.0);
+        self.center += (self.framed_position() - self.center) * delta_time.min(1.0);
+        let rotation_delta =
+            (self.target_rotation - self.rotation + f32::PI).rem_euclid(2.0 * f32::PI) - f32::PI;
+        self.rotation += rotation_delta * delta_time.min(1.0);
+    }
+    pub fn fit(&mut self, points: &[Vec2<f32>], framebuffer_size: Vec2<f32>) {
+        let mut half_size = vec2(0.0, 0.0);
+        for &point in points {
+            let offset = point - self.target_position;
+            half_size.x = partial_max(half_size.x, offset.x.abs());
+            half_size.y = partial_max(half_size.y, offset.y.abs());
+        }
+        let half_size = half_size + vec2(FIT_MARGIN, FIT_MARGIN);
+        let aspect = framebuffer_size.x / framebuffer_size.y;
+        self.target_fov = clamp(
+            partial_max(half_size.y, half_size.x / aspect) * 2.0,
+            MIN_FOV..=MAX_FOV,
+        );
+    }
+    pub fn clamp_to_bounds(&mut self, framebuffer_size: Vec2<f32>) {
+        let bounds = match self.bounds {
+            // Bounds are axis-aligned, so a rotated view is left unclamped
+            Some(bounds) if self.rotation.abs() < EPS => bounds,
+            _ => return,
+        };
+        let aspect = framebuffer_size.x / framebuffer_size.y;
+        let max_fov = partial_min(bounds.height(), bounds.width() / aspect);
+        self.fov = partial_min(self.fov, max_fov);
+        self.target_fov = partial_min(self.target_fov, max_fov);
+        let half_size = vec2(self.fov * aspect, self.fov) / 2.0;
+        let clamp_axis = |x: f32, min: f32, max: f32, half: f32| {
+            if max - min < half * 2.0 {
+                (min + max) / 2.0
+            } else {
+                clamp(x, min + half..=max - half)
+            }
+        };
+        self.center = vec2(
+            clamp_axis(self.center.x, bounds.x_min, bounds.x_max, half_size.x),
+            clamp_axis(self.center.y, bounds.y_min, bounds.y_max, half_size.y),
+        );
+    }
+    // Everything that can end up on screen, including the shake offset
+    pub fn view_bounds(&self, framebuffer_size: Vec2<f32>) -> AABB<f32> {
+        let corners = [
+            vec2(0.0, 0.0),
+            vec2(framebuffer_size.x, 0.0),
+            framebuffer_size,
+            vec2(0.0, framebuffer_size.y),
+        ]
+        .map(|pos| self.screen_to_world(framebuffer_size, pos));
+        AABB {
+            x_min: corners.iter().map(|p| p.x).fold(f32::INFINITY, f32::min),
+            x_max: corners
+                .iter()
+                .map(|p| p.x)
+                .fold(f32::NEG_INFINITY, f32::max),
+            y_min: corners.iter().map(|p| p.y).fold(f32::INFINITY, f32::min),
+            y_max: corners
+                .iter()
+                .map(|p| p.y)
+                .fold(f32::NEG_INFINITY, f32::max),
+        }
+        .add_padding(MAX_SHAKE_OFFSET)
     }
+    pub fn pan(&mut self, framebuffer_size: Vec2<f32>, from: Vec2<f32>, to: Vec2<f32>) {
+        let delta = self.screen_to_world(framebuffer_size, from)
+            - self.screen_to_world(framebuffer_size, to);
+        self.center += delta;
+        self.target_position = self.center;
+    }
+    pub fn zoom_at(&mut self, framebuffer_size: Vec2<f32>, pos: Vec2<f32>, factor: f32) {
+        let before = self.screen_to_world(framebuffer_size, pos);
+        self.fov *= factor;
+        self.target_fov = self.fov;
+        let after = self.screen_to_world(framebuffer_size, pos);
+        self.center += before - after;
+        self.target_position = self.center;
+    }
+    pub fn follow(&mut self, target: Vec2<f32>, velocity: Vec2<f32>, delta_time: f32) {
+        let offset = target + velocity * FOLLOW_LOOKAHEAD - self.target_position;
+        if offset.len() > FOLLOW_DEAD_ZONE {
+            self.target_position += offset.normalize() * (offset.len() - FOLLOW_DEAD_ZONE);
+        }
+        self.update(delta_time);
+    }
+}
+
+impl AbstractCamera for Camera {
     fn view_matrix(&self) -> Mat4<f32> {
-        Mat4::scale_uniform(1.0 / self.fov) * Mat4::translate(-self.center.extend(0.0))
+        let flip = if self.mirror { -1.0 } else { 1.0 };
+        Mat4::scale(vec3(flip, 1.0, 1.0))
+            * Mat4::scale_uniform(1.0 / self.fov)
+            * Mat4::rotate_z(-self.rotation)
+            * Mat4::translate(-self.center.extend(0.0))
+    }
+    fn uniforms(&self, framebuffer_size: Vec2<f32>) -> CameraUniforms {
+        CameraUniforms {
+            u_projection_matrix: self.projection_matrix(framebuffer_size),
+            u_view_matrix: self.view_matrix() * Mat4::translate(self.shake_offset().extend(0.0)),
+        }
     }
     fn projection_matrix(&self, framebuffer_size: Vec2<f32>) -> Mat4<f32> {
         Mat4::scale(vec3(
@@ -31,27 +252,31 @@ impl Camera {
             1.0,
         ))
     }
-    pub fn uniforms(&self, framebuffer_size: Vec2<f32>) -> impl ugli::Uniforms {
-        ugli::uniforms! {
-            u_projection_matrix: self.projection_matrix(framebuffer_size),
-            u_view_matrix: self.view_matrix(),
-        }
+}
+
+// Screen space with a fixed virtual height, so the UI scales with the window.
+// Narrow windows like phones in portrait keep a minimum width and grow taller instead.
+pub struct UiCamera;
+
+impl UiCamera {
+    pub const HEIGHT: f32 = 720.0;
+    pub const MIN_WIDTH: f32 = 960.0;
+    pub fn size(framebuffer_size: Vec2<f32>) -> Vec2<f32> {
+        let aspect = framebuffer_size.x / framebuffer_size.y;
+        let height = partial_max(Self::HEIGHT, Self::MIN_WIDTH / aspect);
+        vec2(height * aspect, height)
     }
-    pub fn world_to_screen(&self, framebuffer_size: Vec2<f32>, pos: Vec2<f32>) -> Vec2<f32> {
-        let pos = (self.projection_matrix(framebuffer_size) * self.view_matrix())
-            * pos.extend(0.0).extend(1.0);
-        vec2(
-            (pos.x + 1.0) / 2.0 * framebuffer_size.x,
-            (pos.y + 1.0) / 2.0 * framebuffer_size.y,
-        )
+    pub fn scale(framebuffer_size: Vec2<f32>) -> f32 {
+        framebuffer_size.y / Self::size(framebuffer_size).y
     }
-    pub fn screen_to_world(&self, framebuffer_size: Vec2<f32>, pos: Vec2<f32>) -> Vec2<f32> {
-        let pos = vec2(
-            pos.x / framebuffer_size.x * 2.0 - 1.0,
-            pos.y / framebuffer_size.y * 2.0 - 1.0,
-        );
-        let pos = (self.projection_matrix(framebuffer_size) * self.view_matrix()).inverse()
-            * pos.extend(0.0).extend(1.0);
-        pos.xy()
+}
+
+impl AbstractCamera for UiCamera {
+    fn view_matrix(&self) -> Mat4<f32> {
+        Mat4::identity()
+    }
+    fn projection_matrix(&self, framebuffer_size: Vec2<f32>) -> Mat4<f32> {
+        let size = Self::size(framebuffer_size);
+        Mat4::translate(vec3(-1.0, -1.0, 0.0)) * Mat4::scale(vec3(2.0 / size.x, 2.0 / size.y, 1.0))
     }
 }
@@ -0,0 +1,115 @@
+use super::*;
+
+#[derive(ugli::Vertex, Clone)]
+struct ChainVertex {
+    a_pos: Vec2<f32>,
+    a_vt: Vec2<f32>,
+}
+
+// Every other link is seen edge-on and drawn narrower
+const EDGE_LINK_WIDTH: f32 = 0.5;
+
+pub struct ChainRenderer {
+    pub(crate) program: Rc<ugli::Program>,
+    vertices: RefCell<ugli::VertexBuffer<ChainVertex>>,
+}
+
+// Point and direction at the given distance along the path
+fn point_at(path: &[Vec2<f32>], mut distance: f32) -> (Vec2<f32>, Vec2<f32>) {
+    for segment in path.windows(2) {
+        let delta = segment[1] - segment[0];
+        let len = delta.len();
+        if len < EPS {
+            continue;
+        }
+        if distance <= len {
+            return (segment[0] + delta * (distance / len), delta / len);
+        }
+        distance -= len;
+    }
+    let last = path.len() - 1;
+    (path[last], (path[last] - path[last - 1]).normalize())
+}
+
+impl ChainRenderer {
+    pub fn new(geng: &Rc<Geng>, shaders: &Shaders) -> Self {
+        Self {
+            program: shaders.programs().chain.clone(),
+            vertices: RefCell::new(ugli::VertexBuffer::new_dynamic(geng.ugli(), Vec::new())),
+        }
+    }
+    // Tiles links of a fixed size along the path, the last one is cut short
+    #[allow(clippy::too_many_arguments)]
+    pub fn draw(
+        &self,
+        framebuffer: &mut ugli::Framebuffer,
+        camera: &impl AbstractCamera,
+        path: &[Vec2<f32>],
+        link_size: Vec2<f32>,
+        texture: &ugli::Texture,
+        uv: AABB<f32>,
+        color: Color<f32>,
+    ) {
+        if path.len() < 2 {
+            return;
+        }
+        let total: f32 = path
+            .windows(2)
+            .map(|segment| (segment[1] - segment[0]).len())
+            .sum();
+        if total < EPS {
+            return;
+        }
+        let mut vertices = self.vertices.borrow_mut();
+        vertices.clear();
+        let count = (total / link_size.y).ceil() as usize;
+        for i in 0..count {
+            let start = i as f32 * link_size.y;
+            let length = partial_min(link_size.y, total - start);
+            let (center, along) = point_at(path, start + length / 2.0);
+            let width = if i % 2 == 0 {
+                link_size.x
+            } else {
+                link_size.x * EDGE_LINK_WIDTH
+            };
+            let side = Vec2::rotate_90(along) * width / 2.0;
+            let along = along * length / 2.0;
+            let cut = length / link_size.y;
+            let corner = |x: f32, y: f32| ChainVertex {
+                a_pos: center + side * (x * 2.0 - 1.0) + along * (y * 2.0 - 1.0),
+                a_vt: vec2(
+                    uv.x_min + uv.width() * x,
+                    uv.y_min + uv.height() * (1.0 - y * cut),
+                ),
+            };
+            vertices.extend([
+                corner(0.0, 0.0),
+                corner(1.0, 0.0),
+                corner(1.0, 1.0),
+                corner(0.0, 0.0),
+                corner(1.0, 1.0),
+                corner(0.0, 1.0),
+            ]);
+        }
+        let camera_uniforms = camera.uniforms(framebuffer.size().map(|x| x as f32));
+        let uniforms = (
+            camera_uniforms,
+            ugli::uniforms! {
+                u_model_matrix: Mat4::identity(),
+                u_texture: texture,
+                u_color: color,
+            },
+        );
+        ugli::draw(
+            framebuffer,
+            &self.program,
+            ugli::DrawMode::Triangles,
+            &*vertices,
+            uniforms,
+            ugli::DrawParameters {
+                blend_mode: Some(default()),
+                ..default()
+            },
+        );
+    }
+}
@@ -0,0 +1,537 @@
+use super::*;
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash, Default, Serialize, Deserialize)]
+pub enum Material {
+    #[default]
+    Stone,
+    Ice,
+    Rubber,
+    Sticky,
+    Spikes,
+    Lava,
+    Breakable,
+}
+
+impl Material {
+    pub fn bounciness(self) -> f32 {
+        match self {
+            Self::Stone => 0.0,
+            Self::Ice => 0.1,
+            Self::Rubber => 0.8,
+            Self::Sticky => 0.0,
+            Self::Spikes => 0.0,
+            Self::Lava => 0.0,
+            Self::Breakable => 0.0,
+        }
+    }
+    pub fn friction(self) -> f32 {
+        match self {
+            Self::Stone => 0.5,
+            Self::Ice => 0.0,
+            Self::Rubber => 0.8,
+            Self::Sticky => 1.0,
+            Self::Spikes => 0.5,
+            Self::Lava => 0.5,
+            Self::Breakable => 0.5,
+        }
+    }
+    pub fn sticky(self) -> bool {
+        self == Self::Sticky
+    }
+    pub fn hazard(self) -> bool {
+        matches!(self, Self::Spikes | Self::Lava)
+    }
+}
+
+#[derive(Debug, Copy, Clone, Serialize, Deserialize)]
+#[serde(from = "SegmentData")]
+pub struct Segment {
+    pub p1: Vec2<f32>,
+    pub p2: Vec2<f32>,
+    pub material: Material,
+    pub one_way: bool,
+    #[serde(skip)]
+    pub vel: Vec2<f32>,
+}
+
+impl Segment {
+    pub fn new(p1: Vec2<f32>, p2: Vec2<f32>) -> Self {
+        Self {
+            p1,
+            p2,
+            material: default(),
+            one_way: false,
+            vel: vec2(0.0, 0.0),
+        }
+    }
+    pub fn aabb(&self) -> AABB<f32> {
+        AABB::from_corners(self.p1, self.p2)
+    }
+    pub fn closest_point(&self, pos: Vec2<f32>) -> Vec2<f32> {
+        let edge = self.p2 - self.p1;
+        let t = Vec2::dot(pos - self.p1, edge) / Vec2::dot(edge, edge).max(EPS);
+        self.p1 + edge * clamp(t, 0.0..=1.0)
+    }
+    // Distance along dir in units of its length, parallel rays never hit
+    pub fn ray_hit(&self, origin: Vec2<f32>, dir: Vec2<f32>, max_dist: f32) -> Option<f32> {
+        let edge = self.p2 - self.p1;
+        let denom = Vec2::skew(dir, edge);
+        if denom.abs() < EPS {
+            return None;
+        }
+        let delta = self.p1 - origin;
+        let t = Vec2::skew(delta, edge) / denom;
+        let u = Vec2::skew(delta, dir) / denom;
+        ((0.0..=max_dist).contains(&t) && (0.0..=1.0).contains(&u)).then_some(t)
+    }
+    // Facing against dir
+    fn normal_against(&self, dir: Vec2<f32>) -> Vec2<f32> {
+        let normal = Vec2::rotate_90((self.p2 - self.p1).normalize());
+        if Vec2::dot(normal, dir) > 0.0 {
+            -normal
+        } else {
+            normal
+        }
+    }
+}
+
+// Time of impact in 0..1 along from..to and the contact normal
+pub fn sweep_circle(
+    from: Vec2<f32>,
+    to: Vec2<f32>,
+    radius: f32,
+    &Segment { p1, p2, .. }: &Segment,
+) -> Option<(f32, Vec2<f32>)> {
+    let movement = to - from;
+    let mut result: Option<(f32, Vec2<f32>)> = None;
+    let mut consider = |t: f32, normal: Vec2<f32>| {
+        if (0.0..=1.0).contains(&t) && !matches!(result, Some((best, _)) if best <= t) {
+            result = Some((t, normal));
+        }
+    };
+    let v = p2 - p1;
+    if v.len() > EPS {
+        let n = Vec2::rotate_90(v.normalize());
+        let d0 = Vec2::dot(n, from - p1);
+        let d1 = Vec2::dot(n, to - p1);
+        for &side in &[1.0, -1.0] {
+            let (d0, d1) = (d0 * side, d1 * side);
+            if d0 >= radius && d1 < radius {
+                let t = (d0 - radius) / (d0 - d1);
+                let contact = from + movement * t;
+                let along = Vec2::dot(v, contact - p1) / Vec2::dot(v, v);
+                if (0.0..=1.0).contains(&along) {
+                    consider(t, n * side);
+                }
+            }
+        }
+    }
+    let a = Vec2::dot(movement, movement);
+    if a > EPS {
+        for &p in &[p1, p2] {
+            let offset = from - p;
+            let c = Vec2::dot(offset, offset) - radius * radius;
+            if c < 0.0 {
+                continue;
+            }
+            let b = Vec2::dot(offset, movement);
+            let discriminant = b * b - a * c;
+            if b < 0.0 && discriminant >= 0.0 {
+                let t = (-b - discriminant.sqrt()) / a;
+                consider(t, (from + movement * t - p).normalize());
+            }
+        }
+    }
+    result
+}
+
+#[derive(Debug, Copy, Clone)]
+pub struct RayHit {
+    pub id: usize,
+    pub distance: f32,
+    // Where the ray touched the segment, for circle casts the contact on its surface
+    pub point: Vec2<f32>,
+    pub normal: Vec2<f32>,
+}
+
+// Old levels store segments as plain point pairs
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum SegmentData {
+    Plain([Vec2<f32>; 2]),
+    Tagged {
+        p1: Vec2<f32>,
+        p2: Vec2<f32>,
+        #[serde(default)]
+        material: Material,
+        #[serde(default)]
+        one_way: bool,
+    },
+}
+
+impl From<SegmentData> for Segment {
+    fn from(data: SegmentData) -> Self {
+        match data {
+            SegmentData::Plain([p1, p2]) => Self::new(p1, p2),
+            SegmentData::Tagged {
+                p1,
+                p2,
+                material,
+                one_way,
+            } => Self {
+                material,
+                one_way,
+                ..Self::new(p1, p2)
+            },
+        }
+    }
+}
+
+// Snaps points so that endpoints computed in different ways compare equal
+fn point_key(p: Vec2<f32>) -> Vec2<i64> {
+    p.map(|x| (x * 1024.0).round() as i64)
+}
+
+// Same for both directions of a segment
+pub(crate) fn edge_key(segment: &Segment) -> (Vec2<i64>, Vec2<i64>) {
+    let (a, b) = (point_key(segment.p1), point_key(segment.p2));
+    if (a.x, a.y) < (b.x, b.y) {
+        (a, b)
+    } else {
+        (b, a)
+    }
+}
+
+fn collinear(a: Vec2<f32>, b: Vec2<f32>, c: Vec2<f32>) -> bool {
+    let (e1, e2) = (b - a, c - b);
+    Vec2::dot(e1, e2) > 0.0 && Vec2::skew(e1, e2).abs() < EPS * e1.len() * e2.len()
+}
+
+// Joins chains of segments that continue each other in a straight line
+pub fn merge_collinear(segments: &[Segment]) -> Vec<Segment> {
+    let mut adjacency: HashMap<Vec2<i64>, Vec<usize>> = HashMap::new();
+    for (id, segment) in segments.iter().enumerate() {
+        adjacency.entry(point_key(segment.p1)).or_default().push(id);
+        adjacency.entry(point_key(segment.p2)).or_default().push(id);
+    }
+    let mut used = vec![false; segments.len()];
+    let mut result = Vec::new();
+    for start in 0..segments.len() {
+        if used[start] {
+            continue;
+        }
+        used[start] = true;
+        let mut merged = segments[start];
+        for _ in 0..2 {
+            loop {
+                let ids = &adjacency[&point_key(merged.p2)];
+                if ids.len() != 2 {
+                    break;
+                }
+                let next = match ids.iter().copied().find(|&id| !used[id]) {
+                    Some(next) => next,
+                    None => break,
+                };
+                let other = &segments[next];
+                let far = if point_key(other.p1) == point_key(merged.p2) {
+                    other.p2
+                } else {
+                    other.p1
+                };
+                if other.material != merged.material
+                    || other.one_way
+                    || merged.one_way
+                    || !collinear(merged.p1, merged.p2, far)
+                {
+                    break;
+                }
+                used[next] = true;
+                merged.p2 = far;
+            }
+            mem::swap(&mut merged.p1, &mut merged.p2);
+        }
+        result.push(merged);
+    }
+    result
+}
+
+// Drops repeated edges and joins straight runs into single segments
+pub fn optimize_segments(segments: &[Segment]) -> Vec<Segment> {
+    let mut seen = HashSet::new();
+    let unique: Vec<Segment> = segments
+        .iter()
+        .filter(|segment| seen.insert((edge_key(segment), segment.material, segment.one_way)))
+        .copied()
+        .collect();
+    merge_collinear(&unique)
+}
+
+const DEFAULT_CELL_SIZE: f32 = 4.0;
+
+pub struct CollisionWorld {
+    cell_size: f32,
+    segments: Vec<Option<Segment>>,
+    free_ids: Vec<usize>,
+    cells: HashMap<Vec2<i32>, Vec<usize>>,
+}
+
+impl CollisionWorld {
+    pub fn new(segments: &[Segment]) -> Self {
+        Self::with_cell_size(segments, DEFAULT_CELL_SIZE)
+    }
+    pub fn with_cell_size(segments: &[Segment], cell_size: f32) -> Self {
+        let mut world = Self {
+            cell_size,
+            segments: Vec::new(),
+            free_ids: Vec::new(),
+            cells: HashMap::new(),
+        };
+        for &segment in segments {
+            world.insert(segment);
+        }
+        world
+    }
+    fn cell_range(&self, aabb: AABB<f32>) -> AABB<i32> {
+        aabb.map(|x| (x / self.cell_size).floor() as i32)
+    }
+    fn cells_of(&self, aabb: AABB<f32>) -> impl Iterator<Item = Vec2<i32>> {
+        let range = self.cell_range(aabb);
+        (range.x_min..=range.x_max)
+            .flat_map(move |x| (range.y_min..=range.y_max).map(move |y| vec2(x, y)))
+    }
+    pub fn insert(&mut self, segment: Segment) -> usize {
+        let id = match self.free_ids.pop() {
+            Some(id) => {
+                self.segments[id] = Some(segment);
+                id
+            }
+            None => {
+                self.segments.push(Some(segment));
+                self.segments.len() - 1
+            }
+        };
+        for cell in self.cells_of(segment.aabb()).collect::<Vec<_>>() {
+            self.cells.entry(cell).or_default().push(id);
+        }
+        id
+    }
+    pub fn remove(&mut self, id: usize) -> Option<Segment> {
+        let segment = self.segments.get_mut(id)?.take()?;
+        for cell in self.cells_of(segment.aabb()).collect::<Vec<_>>() {
+            if let Some(ids) = self.cells.get_mut(&cell) {
+                ids.retain(|&other| other != id);
+                if ids.is_empty() {
+                    self.cells.remove(&cell);
+                }
+            }
+        }
+        self.free_ids.push(id);
+        Some(segment)
+    }
+    pub fn get(&self, id: usize) -> Option<&Segment> {
+        self.segments.get(id)?.as_ref()
+    }
+    pub fn segments(&self) -> impl Iterator<Item = (usize, &Segment)> {
+        self.segments
+            .iter()
+            .enumerate()
+            .filter_map(|(id, segment)| Some((id, segment.as_ref()?)))
+    }
+    pub fn bounds(&self) -> Option<AABB<f32>> {
+        self.segments()
+            .map(|(_, segment)| segment.aabb())
+            .reduce(|a, b| AABB {
+                x_min: a.x_min.min(b.x_min),
+                y_min: a.y_min.min(b.y_min),
+                x_max: a.x_max.max(b.x_max),
+                y_max: a.y_max.max(b.y_max),
+            })
+    }
+    pub fn cells(&self) -> impl Iterator<Item = AABB<f32>> + '_ {
+        self.cells.keys().map(move |&cell| {
+            AABB::pos_size(
+                cell.map(|x| x as f32 * self.cell_size),
+                vec2(self.cell_size, self.cell_size),
+            )
+        })
+    }
+    pub fn query_ids(&self, aabb: AABB<f32>) -> Vec<usize> {
+        let mut ids: Vec<usize> = self
+            .cells_of(aabb)
+            .filter_map(|cell| self.cells.get(&cell))
+            .flatten()
+            .copied()
+            .collect();
+        ids.sort_unstable();
+        ids.dedup();
+        ids
+    }
+    pub fn query(&self, aabb: AABB<f32>) -> impl Iterator<Item = &Segment> {
+        self.query_ids(aabb)
+            .into_iter()
+            .filter_map(move |id| self.get(id))
+    }
+    fn query_entries(&self, aabb: AABB<f32>) -> impl Iterator<Item = (usize, &Segment)> {
+        self.query_ids(aabb)
+            .into_iter()
+            .filter_map(move |id| Some((id, self.get(id)?)))
+    }
+    // Nearest segment along a ray, dir does not need to be normalized
+    pub fn raycast(&self, origin: Vec2<f32>, dir: Vec2<f32>, max_dist: f32) -> Option<RayHit> {
+        if dir.len() < EPS {
+            return None;
+        }
+        let dir = dir.normalize();
+        self.query_entries(AABB::from_corners(origin, origin + dir * max_dist))
+            .filter_map(|(id, segment)| {
+                let distance = segment.ray_hit(origin, dir, max_dist)?;
+                Some(RayHit {
+                    id,
+                    distance,
+                    point: origin + dir * distance,
+                    normal: segment.normal_against(dir),
+                })
+            })
+            .min_by(|a, b| a.distance.partial_cmp(&b.distance).unwrap())
+    }
+    // Like raycast but for a moving circle, the distance is how far its center gets
+    pub fn circle_cast(
+        &self,
+        origin: Vec2<f32>,
+        radius: f32,
+        dir: Vec2<f32>,
+        max_dist: f32,
+    ) -> Option<RayHit> {
+        if dir.len() < EPS {
+            return None;
+        }
+        let target = origin + dir.normalize() * max_dist;
+        self.query_entries(AABB::from_corners(origin, target).add_padding(radius))
+            .filter_map(|(id, segment)| {
+                let (t, normal) = sweep_circle(origin, target, radius, segment)?;
+                let center = origin + (target - origin) * t;
+                Some(RayHit {
+                    id,
+                    distance: max_dist * t,
+                    point: center - normal * radius,
+                    normal,
+                })
+            })
+            .min_by(|a, b| a.distance.partial_cmp(&b.distance).unwrap())
+    }
+    // Segments touching the circle, nearest first
+    pub fn overlap_circle(&self, center: Vec2<f32>, radius: f32) -> Vec<usize> {
+        let mut hits: Vec<(usize, f32)> = self
+            .query_entries(AABB::pos_size(center, vec2(0.0, 0.0)).add_padding(radius))
+            .map(|(id, segment)| (id, (segment.closest_point(center) - center).len()))
+            .filter(|&(_, distance)| distance <= radius)
+            .collect();
+        hits.sort_by(|a, b| a.1.partial_cmp(&b.1).unwrap());
+        hits.into_iter().map(|(id, _)| id).collect()
+    }
+    // One-way platforms let things through, so they don't block either
+    pub fn blocked(&self, from: Vec2<f32>, to: Vec2<f32>) -> bool {
+        self.query(AABB::from_corners(from, to))
+            .any(|segment| !segment.one_way && segment.ray_hit(from, to - from, 1.0).is_some())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn world(segments: &[(Vec2<f32>, Vec2<f32>)]) -> CollisionWorld {
+        let segments: Vec<Segment> = segments
+            .iter()
+            .map(|&(p1, p2)| Segment::new(p1, p2))
+            .collect();
+        CollisionWorld::new(&segments)
+    }
+
+    fn wall(x: f32) -> (Vec2<f32>, Vec2<f32>) {
+        (vec2(x, -5.0), vec2(x, 5.0))
+    }
+
+    fn close(a: f32, b: f32) -> bool {
+        (a - b).abs() < 1e-3
+    }
+
+    #[test]
+    fn raycast_hits_nearest_segment() {
+        let world = world(&[wall(5.0), wall(2.0), wall(-3.0)]);
+        let hit = world.raycast(vec2(0.0, 0.0), vec2(3.0, 0.0), 10.0).unwrap();
+        assert_eq!(hit.id, 1);
+        assert!(close(hit.distance, 2.0));
+        assert!(close(hit.point.x, 2.0) && close(hit.point.y, 0.0));
+        assert!(close(hit.normal.x, -1.0) && close(hit.normal.y, 0.0));
+    }
+
+    #[test]
+    fn raycast_respects_max_distance() {
+        let world = world(&[wall(2.0)]);
+        assert!(world.raycast(vec2(0.0, 0.0), vec2(1.0, 0.0), 1.5).is_none());
+        assert!(world.raycast(vec2(0.0, 0.0), vec2(1.0, 0.0), 2.5).is_some());
+    }
+
+    #[test]
+    fn raycast_misses_parallel_and_behind() {
+        let world = world(&[wall(2.0), (vec2(-5.0, 1.0), vec2(5.0, 1.0))]);
+        assert!(world
+            .raycast(vec2(0.0, 0.0), vec2(-1.0, 0.0), 10.0)
+            .is_none());
+        let hit = world.raycast(vec2(0.0, 0.0), vec2(0.0, 1.0), 10.0).unwrap();
+        assert_eq!(hit.id, 1);
+        assert!(world
+            .raycast(vec2(0.0, 0.0), vec2(0.0, 0.0), 10.0)
+            .is_none());
+    }
+
+    #[test]
+    fn raycast_crosses_grid_cells() {
+        let world = world(&[wall(37.0)]);
+        let hit = world.raycast(vec2(0.5, 0.5), vec2(1.0, 0.0), 50.0).unwrap();
+        assert!(close(hit.distance, 36.5));
+    }
+
+    #[test]
+    fn raycast_ignores_removed_segments() {
+        let mut world = world(&[wall(2.0), wall(4.0)]);
+        world.remove(0);
+        let hit = world.raycast(vec2(0.0, 0.0), vec2(1.0, 0.0), 10.0).unwrap();
+        assert_eq!(hit.id, 1);
+        assert!(close(hit.distance, 4.0));
+    }
+
+    #[test]
+    fn circle_cast_stops_at_radius() {
+        let world = world(&[wall(3.0), wall(6.0)]);
+        let hit = world
+            .circle_cast(vec2(0.0, 0.0), 0.5, vec2(1.0, 0.0), 10.0)
+            .unwrap();
+        assert_eq!(hit.id, 0);
+        assert!(close(hit.distance, 2.5));
+        assert!(close(hit.point.x, 3.0));
+        assert!(close(hit.normal.x, -1.0));
+    }
+
+    #[test]
+    fn circle_cast_hits_endpoint() {
+        // The ray itself passes above the segment, only the circle touches its end
+        let world = world(&[(vec2(3.0, -5.0), vec2(3.0, 0.0))]);
+        let hit = world
+            .circle_cast(vec2(0.0, 0.5), 1.0, vec2(1.0, 0.0), 10.0)
+            .unwrap();
+        assert!(close((hit.point - vec2(3.0, 0.0)).len(), 0.0));
+        assert!(hit.distance < 3.0);
+        assert!(world
+            .raycast(vec2(0.0, 0.5), vec2(1.0, 0.0), 10.0)
+            .is_none());
+    }
+
+    #[test]
+    fn overlap_circle_sorts_by_distance() {
+        let world = world(&[wall(1.5), wall(-0.5), wall(3.0)]);
+        assert_eq!(world.overlap_circle(vec2(0.0, 0.0), 2.0), vec![1, 0]);
+        assert!(world.overlap_circle(vec2(0.0, 0.0), 0.25).is_empty());
+    }
+}
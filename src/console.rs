@@ -0,0 +1,222 @@
+use super::*;
+
+type CommandHandler<T> = Box<dyn Fn(&mut T, &[&str]) -> Result<String, anyhow::Error>>;
+
+struct Command<T> {
+    usage: &'static str,
+    handler: CommandHandler<T>,
+}
+
+pub struct CommandRegistry<T> {
+    commands: std::collections::BTreeMap<String, Command<T>>,
+}
+
+impl<T> Default for CommandRegistry<T> {
+    fn default() -> Self {
+        Self {
+            commands: default(),
+        }
+    }
+}
+
+impl<T> CommandRegistry<T> {
+    pub fn register(
+        &mut self,
+        name: &str,
+        usage: &'static str,
+        handler: impl Fn(&mut T, &[&str]) -> Result<String, anyhow::Error> + 'static,
+    ) {
+        self.commands.insert(
+            name.to_owned(),
+            Command {
+                usage,
+                handler: Box::new(handler),
+            },
+        );
+    }
+    pub fn execute(&self, target: &mut T, line: &str) -> Result<String, anyhow::Error> {
+        let mut words = line.split_whitespace();
+        let name = match words.next() {
+            Some(name) => name,
+            None => return Ok(String::new()),
+        };
+        let args: Vec<&str> = words.collect();
+        if name == "help" {
+            return Ok(self
+                .commands
+                .values()
+                .map(|command| command.usage)
+                .collect::<Vec<_>>()
+                .join(", "));
+        }
+        match self.commands.get(name) {
+            Some(command) => (command.handler)(target, &args)
+                .with_context(|| format!("Usage: {}", command.usage)),
+            None => Err(anyhow!("Unknown command {:?}, try \"help\"", name)),
+        }
+    }
+}
+
+pub fn parse_arg<T: std::str::FromStr>(args: &[&str], index: usize) -> Result<T, anyhow::Error>
+where
+    T::Err: std::error::Error + Send + Sync + 'static,
+{
+    let arg = args
+        .get(index)
+        .ok_or_else(|| anyhow!("Missing argument #{}", index + 1))?;
+    Ok(arg.parse()?)
+}
+
+pub struct Console {
+    geng: Rc<Geng>,
+    pub visible: bool,
+    input: String,
+    history: Vec<String>,
+    history_index: usize,
+    output: std::collections::VecDeque<String>,
+}
+
+impl Console {
+    const MAX_OUTPUT: usize = 10;
+    const FONT_SIZE: f32 = 20.0;
+    pub fn new(geng: &Rc<Geng>) -> Self {
+        Self {
+            geng: geng.clone(),
+            visible: false,
+            input: String::new(),
+            history: Vec::new(),
+            history_index: 0,
+            output: default(),
+        }
+    }
+    pub fn toggle(&mut self) {
+        self.visible = !self.visible;
+    }
+    pub fn print(&mut self, line: impl Into<String>) {
+        self.output.push_back(line.into());
+        while self.output.len() > Self::MAX_OUTPUT {
+            self.output.pop_front();
+        }
+    }
+    pub fn handle_key(&mut self, key: geng::Key) -> Option<String> {
+        match key {
+            geng::Key::Enter => {
+                let line = mem::take(&mut self.input);
+                if line.trim().is_empty() {
+                    return None;
+                }
+                self.print(format!("> {}", line));
+                self.history.push(line.clone());
+                self.history_index = self.history.len();
+                return Some(line);
+            }
+            geng::Key::Backspace => {
+                self.input.pop();
+            }
+            geng::Key::Up => {
+                if self.history_index > 0 {
+                    self.history_index -= 1;
+                    self.input = self.history[self.history_index].clone();
+                }
+            }
+            geng::Key::Down => {
+                if self.history_index + 1 < self.history.len() {
+                    self.history_index += 1;
+                    self.input = self.history[self.history_index].clone();
+                } else {
+                    self.history_index = self.history.len();
+                    self.input.clear();
+                }
+            }
+            _ => {
+                let shift = self.geng.window().is_key_pressed(geng::Key::LShift)
+                    || self.geng.window().is_key_pressed(geng::Key::RShift);
+                if let Some(c) = key_char(key, shift) {
+                    self.input.push(c);
+                }
+            }
+        }
+        None
+    }
+    pub fn draw(&self, framebuffer: &mut ugli::Framebuffer) {
+        if !self.visible {
+            return;
+        }
+        let framebuffer_size = framebuffer.size().map(|x| x as f32);
+        let line_height = Self::FONT_SIZE * 1.2;
+        let height = line_height * (Self::MAX_OUTPUT + 1) as f32 + line_height / 2.0;
+        self.geng.draw_2d().quad(
+            framebuffer,
+            AABB::from_corners(vec2(0.0, framebuffer_size.y - height), framebuffer_size),
+            Color::rgba(0.0, 0.0, 0.0, 0.7),
+        );
+        let font = self.geng.default_font();
+        let mut y = framebuffer_size.y - line_height;
+        for line in &self.output {
+            font.draw(
+                framebuffer,
+                line,
+                vec2(10.0, y),
+                Self::FONT_SIZE,
+                Color::rgb(0.8, 0.8, 0.8),
+            );
+            y -= line_height;
+        }
+        let y = framebuffer_size.y - height + line_height / 2.0;
+        font.draw(
+            framebuffer,
+            &format!("> {}_", self.input),
+            vec2(10.0, y),
+            Self::FONT_SIZE,
+            Color::WHITE,
+        );
+    }
+}
+
+fn key_char(key: geng::Key, shift: bool) -> Option<char> {
+    use geng::Key::*;
+    let c = match key {
+        A => 'a',
+        B => 'b',
+        C => 'c',
+        D => 'd',
+        E => 'e',
+        F => 'f',
+        G => 'g',
+        H => 'h',
+        I => 'i',
+        J => 'j',
+        K => 'k',
+        L => 'l',
+        M => 'm',
+        N => 'n',
+        O => 'o',
+        P => 'p',
+        Q => 'q',
+        R => 'r',
+        S => 's',
+        T => 't',
+        U => 'u',
+        V => 'v',
+        W => 'w',
+        X => 'x',
+        Y => 'y',
+        Z => 'z',
+        Num0 => '0',
+        Num1 => '1',
+        Num2 => '2',
+        Num3 => '3',
+        Num4 => '4',
+        Num5 => '5',
+        Num6 => '6',
+        Num7 => '7',
+        Num8 => '8',
+        Num9 => '9',
+        Space => ' ',
+        // geng doesn't report punctuation keys, so these stand in for them
+        PageUp => '.',
+        PageDown => '-',
+        _ => return None,
+    };
+    Some(if shift { c.to_ascii_uppercase() } else { c })
+}
@@ -0,0 +1,220 @@
+use std::collections::VecDeque;
+
+use super::*;
+
+#[cfg(not(target_arch = "wasm32"))]
+const LOG_DIR: &str = "logs";
+// Logs of this many runs are kept, game.log is the current one
+#[cfg(not(target_arch = "wasm32"))]
+const LOG_FILES: usize = 5;
+// Left behind by a panic, its presence on the next launch brings up the notice
+#[cfg(not(target_arch = "wasm32"))]
+const CRASH_MARKER: &str = "crashed";
+const RECENT_EVENTS: usize = 20;
+const CRASH_TITLE: &str = "The game crashed last time";
+const CRASH_CONTINUE: &str = "Press any key to continue";
+const CRASH_TITLE_SIZE: f32 = 48.0;
+const CRASH_TEXT_SIZE: f32 = 24.0;
+
+// What the game was doing, written to the log when it panics
+#[derive(Default)]
+struct CrashContext {
+    level: String,
+    tick: u64,
+    events: VecDeque<String>,
+}
+
+thread_local! {
+    static CONTEXT: RefCell<CrashContext> = RefCell::new(default());
+}
+
+fn with_context(f: impl FnOnce(&mut CrashContext)) {
+    CONTEXT.with(|context| f(&mut context.borrow_mut()));
+}
+
+pub fn enter_level(name: &str) {
+    with_context(|context| {
+        context.level = name.to_owned();
+        context.events.clear();
+    });
+}
+
+pub fn tick() {
+    with_context(|context| context.tick += 1);
+}
+
+pub fn record_event(event: &GameEvent) {
+    with_context(|context| {
+        let entry = format!("#{} {:?}", context.tick, event);
+        context.events.push_back(entry);
+        if context.events.len() > RECENT_EVENTS {
+            context.events.pop_front();
+        }
+    });
+}
+
+// The panic might have happened mid-update, so a borrowed context is skipped
+fn context_report() -> String {
+    CONTEXT
+        .try_with(|context| {
+            let context = context.try_borrow().ok()?;
+            let mut report = format!("Level: {}, tick: {}", context.level, context.tick);
+            for event in &context.events {
+                report += "\n  ";
+                report += event;
+            }
+            Some(report)
+        })
+        .ok()
+        .flatten()
+        .unwrap_or_else(|| "Crash context unavailable".to_owned())
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+fn log_dir() -> std::path::PathBuf {
+    let exe = std::env::current_exe().unwrap_or_default();
+    exe.parent().unwrap_or(&exe).join(LOG_DIR)
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+fn log_path(index: usize) -> std::path::PathBuf {
+    log_dir().join(match index {
+        0 => "game.log".to_owned(),
+        _ => format!("game.{}.log", index),
+    })
+}
+
+// Unbuffered so whatever was logged before a crash is already on disk
+#[cfg(not(target_arch = "wasm32"))]
+struct FileLogger {
+    file: Mutex<std::fs::File>,
+    start: std::time::Instant,
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+impl log::Log for FileLogger {
+    fn enabled(&self, _: &log::Metadata) -> bool {
+        true
+    }
+    fn log(&self, record: &log::Record) {
+        if let Ok(mut file) = self.file.lock() {
+            let _ = writeln!(
+                file,
+                "[{:10.3}] {} {}: {}",
+                self.start.elapsed().as_secs_f64(),
+                record.level(),
+                record.target(),
+                record.args()
+            );
+        }
+    }
+    fn flush(&self) {
+        if let Ok(mut file) = self.file.lock() {
+            let _ = file.flush();
+        }
+    }
+}
+
+// Rotates the logs next to the executable and starts a new one. Returns the log
+// of the previous run if that run crashed
+#[cfg(not(target_arch = "wasm32"))]
+pub fn init() -> Option<std::path::PathBuf> {
+    match init_file() {
+        Ok(crashed) => crashed,
+        Err(e) => {
+            warn!("Failed to open the log file: {}", e);
+            None
+        }
+    }
+}
+
+#[cfg(target_arch = "wasm32")]
+pub fn init() -> Option<std::path::PathBuf> {
+    None
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+fn init_file() -> std::io::Result<Option<std::path::PathBuf>> {
+    std::fs::create_dir_all(log_dir())?;
+    let marker = log_dir().join(CRASH_MARKER);
+    let crashed = std::fs::remove_file(&marker).is_ok();
+    for index in (1..LOG_FILES).rev() {
+        let _ = std::fs::rename(log_path(index - 1), log_path(index));
+    }
+    let file = std::fs::File::create(log_path(0))?;
+    logger::add_logger(Box::new(FileLogger {
+        file: Mutex::new(file),
+        start: std::time::Instant::now(),
+    }));
+    // Runs before the logger's own hook, which adds the message and backtrace
+    let previous = std::panic::take_hook();
+    std::panic::set_hook(Box::new(move |info| {
+        error!("{}", context_report());
+        let _ = std::fs::write(&marker, "");
+        previous(info);
+    }));
+    Ok(crashed.then(|| log_path(1)))
+}
+
+// Shown once on the launch after a crash, then hands over to the game
+pub struct CrashNotice {
+    geng: Rc<Geng>,
+    log_path: String,
+    next: Option<Box<dyn geng::State>>,
+    dismissed: bool,
+}
+
+impl CrashNotice {
+    pub fn new(geng: &Rc<Geng>, log_path: &std::path::Path, next: Box<dyn geng::State>) -> Self {
+        Self {
+            geng: geng.clone(),
+            log_path: log_path.display().to_string(),
+            next: Some(next),
+            dismissed: false,
+        }
+    }
+}
+
+impl geng::State for CrashNotice {
+    fn draw(&mut self, framebuffer: &mut ugli::Framebuffer) {
+        ugli::clear(framebuffer, Some(Color::BLACK), None);
+        let size = framebuffer.size().map(|x| x as f32);
+        let font = self.geng.default_font();
+        let center = size.x / 2.0;
+        font.draw_aligned(
+            framebuffer,
+            CRASH_TITLE,
+            vec2(center, size.y * 0.6),
+            0.5,
+            CRASH_TITLE_SIZE,
+            Color::rgb(1.0, 0.4, 0.4),
+        );
+        font.draw_aligned(
+            framebuffer,
+            &format!("Log saved to {}", self.log_path),
+            vec2(center, size.y * 0.6 - CRASH_TITLE_SIZE),
+            0.5,
+            CRASH_TEXT_SIZE,
+            Color::WHITE,
+        );
+        font.draw_aligned(
+            framebuffer,
+            CRASH_CONTINUE,
+            vec2(center, size.y * 0.2),
+            0.5,
+            CRASH_TEXT_SIZE,
+            Color::rgba(1.0, 1.0, 1.0, 0.7),
+        );
+    }
+    fn handle_event(&mut self, event: geng::Event) {
+        if let geng::Event::KeyDown { .. } | geng::Event::MouseDown { .. } = event {
+            self.dismissed = true;
+        }
+    }
+    fn transition(&mut self) -> Option<geng::Transition> {
+        if !self.dismissed {
+            return None;
+        }
+        Some(geng::Transition::Switch(self.next.take()?))
+    }
+}
@@ -0,0 +1,601 @@
+use super::*;
+
+const EDITOR_HINTS: &str = "1 - tiles, 2 - segments, 3 - move, 4 - inspect, Shift+LMB - spawn, Ctrl+Z/Ctrl+Shift+Z - undo/redo, Ctrl+S - save, Ctrl+C/Ctrl+V - level code, F5 - playtest";
+const PICK_RADIUS: f32 = 1.0;
+const SEGMENT_SNAP: f32 = 0.5;
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum EditorTool {
+    Tiles,
+    Segments,
+    Move,
+    Inspect,
+}
+
+impl EditorTool {
+    fn name(self) -> &'static str {
+        match self {
+            Self::Tiles => "Tiles",
+            Self::Segments => "Segments",
+            Self::Move => "Move",
+            Self::Inspect => "Inspect",
+        }
+    }
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum EntityRef {
+    Spawn,
+    Goal,
+    Checkpoint(usize),
+    Collectible(usize),
+    Pickup(usize),
+    Hook(usize),
+    Switch(usize),
+    Sign(usize),
+    Door(usize),
+    Breakable(usize),
+    Hazard(usize),
+    Platform(usize),
+    Enemy(usize),
+    ForceZone(usize),
+    GravityZone(usize),
+    CameraZone(usize),
+    Water(usize),
+    Hint(usize),
+    // Level wide, has no position to pick or move
+    Palette,
+}
+
+type EntityList = fn(usize) -> EntityRef;
+
+impl EntityRef {
+    pub fn all(level: &Level) -> Vec<Self> {
+        let mut entities = vec![Self::Spawn];
+        if level.goal.is_some() {
+            entities.push(Self::Goal);
+        }
+        let lists: [(usize, EntityList); 16] = [
+            (level.checkpoints.len(), Self::Checkpoint),
+            (level.collectibles.len(), Self::Collectible),
+            (level.pickups.len(), Self::Pickup),
+            (level.hooks.len(), Self::Hook),
+            (level.switches.len(), Self::Switch),
+            (level.signs.len(), Self::Sign),
+            (level.doors.len(), Self::Door),
+            (level.breakables.len(), Self::Breakable),
+            (level.hazards.len(), Self::Hazard),
+            (level.platforms.len(), Self::Platform),
+            (level.enemies.len(), Self::Enemy),
+            (level.force_zones.len(), Self::ForceZone),
+            (level.gravity_zones.len(), Self::GravityZone),
+            (level.camera_zones.len(), Self::CameraZone),
+            (level.water.len(), Self::Water),
+            (level.hints.len(), Self::Hint),
+        ];
+        for (count, entity) in lists {
+            entities.extend((0..count).map(entity));
+        }
+        entities
+    }
+    // Closest entity handle under the cursor
+    pub fn pick(level: &Level, pos: Vec2<f32>) -> Option<Self> {
+        Self::all(level)
+            .into_iter()
+            .filter_map(|entity| Some((entity, (entity.anchor(level)? - pos).len())))
+            .filter(|&(_, distance)| distance < PICK_RADIUS)
+            .min_by(|a, b| a.1.partial_cmp(&b.1).unwrap())
+            .map(|(entity, _)| entity)
+    }
+    pub fn anchor(self, level: &Level) -> Option<Vec2<f32>> {
+        let half = vec2(0.5, 0.5);
+        Some(match self {
+            Self::Spawn => level.spawn_pos(),
+            Self::Goal => level.goal.as_ref()?.pos,
+            Self::Checkpoint(i) => level.checkpoints.get(i)?.pos,
+            Self::Collectible(i) => level.collectibles.get(i)?.pos,
+            Self::Pickup(i) => level.pickups.get(i)?.pos,
+            Self::Hook(i) => level.hooks.get(i)?.pos,
+            Self::Switch(i) => level.switches.get(i)?.pos,
+            Self::Sign(i) => level.signs.get(i)?.pos,
+            Self::Door(i) => {
+                let door = level.doors.get(i)?;
+                (door.p1 + door.p2) / 2.0
+            }
+            Self::Breakable(i) => *level.breakables.get(i)? + half,
+            Self::Hazard(i) => level.hazards.get(i)?.pos + half,
+            Self::Platform(i) => *level.platforms.get(i)?.tiles.first()? + half,
+            Self::Enemy(i) => *level.enemies.get(i)?.path.first()?,
+            Self::ForceZone(i) => level.force_zones.get(i)?.aabb.center(),
+            Self::GravityZone(i) => level.gravity_zones.get(i)?.aabb.center(),
+            Self::CameraZone(i) => level.camera_zones.get(i)?.aabb.center(),
+            Self::Water(i) => level.water.get(i)?.aabb.center(),
+            Self::Hint(i) => level.hints.get(i)?.aabb.center(),
+            Self::Palette => return None,
+        })
+    }
+    fn translate(self, level: &mut Level, delta: Vec2<f32>) -> bool {
+        fn shift(points: &mut [Vec2<f32>], delta: Vec2<f32>) {
+            for point in points {
+                *point += delta;
+            }
+        }
+        match self {
+            Self::Spawn => level.spawn = Some(level.spawn_pos() + delta),
+            Self::Goal => match &mut level.goal {
+                Some(goal) => goal.pos += delta,
+                None => return false,
+            },
+            Self::Checkpoint(i) => match level.checkpoints.get_mut(i) {
+                Some(checkpoint) => checkpoint.pos += delta,
+                None => return false,
+            },
+            Self::Collectible(i) => match level.collectibles.get_mut(i) {
+                Some(collectible) => collectible.pos += delta,
+                None => return false,
+            },
+            Self::Pickup(i) => match level.pickups.get_mut(i) {
+                Some(pickup) => pickup.pos += delta,
+                None => return false,
+            },
+            Self::Hook(i) => match level.hooks.get_mut(i) {
+                Some(hook) => hook.pos += delta,
+                None => return false,
+            },
+            Self::Switch(i) => match level.switches.get_mut(i) {
+                Some(switch) => switch.pos += delta,
+                None => return false,
+            },
+            Self::Sign(i) => match level.signs.get_mut(i) {
+                Some(sign) => sign.pos += delta,
+                None => return false,
+            },
+            Self::Door(i) => match level.doors.get_mut(i) {
+                Some(door) => {
+                    door.p1 += delta;
+                    door.p2 += delta;
+                }
+                None => return false,
+            },
+            Self::Breakable(i) => match level.breakables.get_mut(i) {
+                Some(pos) => *pos += delta,
+                None => return false,
+            },
+            Self::Hazard(i) => match level.hazards.get_mut(i) {
+                Some(hazard) => hazard.pos += delta,
+                None => return false,
+            },
+            Self::Platform(i) => match level.platforms.get_mut(i) {
+                Some(platform) => {
+                    shift(&mut platform.tiles, delta);
+                    shift(&mut platform.path, delta);
+                }
+                None => return false,
+            },
+            Self::Enemy(i) => match level.enemies.get_mut(i) {
+                Some(enemy) => shift(&mut enemy.path, delta),
+                None => return false,
+            },
+            Self::ForceZone(i) => match level.force_zones.get_mut(i) {
+                Some(zone) => zone.aabb = zone.aabb.translate(delta),
+                None => return false,
+            },
+            Self::GravityZone(i) => match level.gravity_zones.get_mut(i) {
+                Some(zone) => zone.aabb = zone.aabb.translate(delta),
+                None => return false,
+            },
+            Self::CameraZone(i) => match level.camera_zones.get_mut(i) {
+                Some(zone) => zone.aabb = zone.aabb.translate(delta),
+                None => return false,
+            },
+            Self::Water(i) => match level.water.get_mut(i) {
+                Some(water) => water.aabb = water.aabb.translate(delta),
+                None => return false,
+            },
+            Self::Hint(i) => match level.hints.get_mut(i) {
+                Some(hint) => hint.aabb = hint.aabb.translate(delta),
+                None => return false,
+            },
+            Self::Palette => return false,
+        }
+        true
+    }
+}
+
+// Every edit is a command with an exact inverse, which is what undo runs
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub enum EditorCommand {
+    SetTile {
+        pos: Vec2<f32>,
+        filled: bool,
+    },
+    AddSegment(Segment),
+    RemoveSegment(Segment),
+    Move {
+        entity: EntityRef,
+        delta: Vec2<f32>,
+    },
+    Replace {
+        entity: EntityRef,
+        before: EntityData,
+        after: EntityData,
+    },
+    ReplaceLevel {
+        before: Box<Level>,
+        after: Box<Level>,
+    },
+}
+
+impl EditorCommand {
+    // Returns false when nothing changed, such commands are not recorded
+    pub fn apply(&self, level: &mut Level) -> bool {
+        match self {
+            &Self::SetTile { pos, filled } => level.set_tile(pos, filled),
+            &Self::AddSegment(segment) => {
+                level.segments.push(segment);
+                true
+            }
+            Self::RemoveSegment(segment) => level.remove_segment(segment),
+            &Self::Move { entity, delta } => entity.translate(level, delta),
+            Self::Replace { entity, after, .. } => entity.set(level, after.clone()),
+            Self::ReplaceLevel { after, .. } => {
+                *level = (**after).clone();
+                true
+            }
+        }
+    }
+    pub fn inverse(&self) -> Self {
+        match self {
+            &Self::SetTile { pos, filled } => Self::SetTile {
+                pos,
+                filled: !filled,
+            },
+            &Self::AddSegment(segment) => Self::RemoveSegment(segment),
+            &Self::RemoveSegment(segment) => Self::AddSegment(segment),
+            &Self::Move { entity, delta } => Self::Move {
+                entity,
+                delta: -delta,
+            },
+            Self::Replace {
+                entity,
+                before,
+                after,
+            } => Self::Replace {
+                entity: *entity,
+                before: after.clone(),
+                after: before.clone(),
+            },
+            Self::ReplaceLevel { before, after } => Self::ReplaceLevel {
+                before: after.clone(),
+                after: before.clone(),
+            },
+        }
+    }
+}
+
+// Written next to the level after every edit and restored if the editor didn't exit cleanly
+#[derive(Serialize, Deserialize)]
+struct EditorSession {
+    base: Level,
+    // Each entry is one stroke, undone as a whole
+    done: Vec<Vec<EditorCommand>>,
+    undone: Vec<Vec<EditorCommand>>,
+}
+
+#[derive(Debug, Clone)]
+pub enum EditorAction {
+    Command(EditorCommand),
+    Undo,
+    Redo,
+    Save,
+    Playtest,
+    CloseInspector,
+    CopyCode,
+    PasteCode,
+}
+
+enum Drag {
+    Paint(bool),
+    Segment(Vec2<f32>),
+    Move(EntityRef, Vec2<f32>),
+}
+
+pub struct Editor {
+    pub path: std::path::PathBuf,
+    pub playtest: bool,
+    pub inspector: Option<Inspector>,
+    tool: EditorTool,
+    drag: Option<Drag>,
+    session: EditorSession,
+    // Commands land in the last stroke until the mouse is released
+    stroke_open: bool,
+}
+
+impl Editor {
+    pub fn new(path: std::path::PathBuf, level: &mut Level) -> Self {
+        let session = match std::fs::read_to_string(Self::session_path(&path))
+            .ok()
+            .and_then(|data| serde_json::from_str::<EditorSession>(&data).ok())
+        {
+            Some(session) => {
+                info!("Restoring unfinished editor session for {:?}", path);
+                *level = session.base.clone();
+                for command in session.done.iter().flatten() {
+                    command.apply(level);
+                }
+                session
+            }
+            None => EditorSession {
+                base: level.clone(),
+                done: Vec::new(),
+                undone: Vec::new(),
+            },
+        };
+        Self {
+            path,
+            playtest: false,
+            inspector: None,
+            tool: EditorTool::Tiles,
+            drag: None,
+            session,
+            stroke_open: false,
+        }
+    }
+    fn session_path(path: &std::path::Path) -> std::path::PathBuf {
+        path.with_extension("session.json")
+    }
+    fn save_session(&self) {
+        let result = serde_json::to_string(&self.session)
+            .map_err(anyhow::Error::from)
+            .and_then(|data| Ok(std::fs::write(Self::session_path(&self.path), data)?));
+        if let Err(e) = result {
+            warn!("Failed to save editor session: {}", e);
+        }
+    }
+    pub fn editing(&self) -> bool {
+        !self.playtest
+    }
+    pub fn execute(&mut self, level: &mut Level, command: EditorCommand) -> bool {
+        if !command.apply(level) {
+            return false;
+        }
+        match self.session.done.last_mut() {
+            Some(stroke) if self.stroke_open => stroke.push(command),
+            _ => self.session.done.push(vec![command]),
+        }
+        self.stroke_open = self.drag.is_some();
+        self.session.undone.clear();
+        self.save_session();
+        true
+    }
+    pub fn undo(&mut self, level: &mut Level) -> bool {
+        let stroke = match self.session.done.pop() {
+            Some(stroke) => stroke,
+            None => return false,
+        };
+        for command in stroke.iter().rev() {
+            command.inverse().apply(level);
+        }
+        self.session.undone.push(stroke);
+        self.save_session();
+        true
+    }
+    pub fn redo(&mut self, level: &mut Level) -> bool {
+        let stroke = match self.session.undone.pop() {
+            Some(stroke) => stroke,
+            None => return false,
+        };
+        for command in &stroke {
+            command.apply(level);
+        }
+        self.session.done.push(stroke);
+        self.save_session();
+        true
+    }
+    pub fn handle_event(
+        &mut self,
+        event: &geng::Event,
+        input: &InputState,
+        level: &Level,
+        cursor: Vec2<f32>,
+    ) -> Option<EditorAction> {
+        let cell = cursor.map(|x| x.floor());
+        let snapped = cursor.map(|x| (x / SEGMENT_SNAP).round() * SEGMENT_SNAP);
+        let ctrl = input.key(geng::Key::LCtrl) || input.key(geng::Key::RCtrl);
+        let shift = input.key(geng::Key::LShift) || input.key(geng::Key::RShift);
+        match *event {
+            geng::Event::KeyDown { key: geng::Key::F5 } => {
+                self.drag = None;
+                self.stroke_open = false;
+                Some(EditorAction::Playtest)
+            }
+            _ if self.playtest => None,
+            geng::Event::KeyDown {
+                key: geng::Key::Escape,
+            } if self.inspector.is_some() => Some(EditorAction::CloseInspector),
+            geng::Event::KeyDown { key } if !ctrl && self.inspector.is_some() => self
+                .inspector
+                .as_mut()
+                .unwrap()
+                .handle_key(key, level)
+                .map(EditorAction::Command),
+            geng::Event::KeyDown { key } if ctrl => match key {
+                geng::Key::S => Some(EditorAction::Save),
+                geng::Key::C => Some(EditorAction::CopyCode),
+                geng::Key::V => Some(EditorAction::PasteCode),
+                geng::Key::Z if shift => Some(EditorAction::Redo),
+                geng::Key::Z => Some(EditorAction::Undo),
+                _ => None,
+            },
+            geng::Event::KeyDown { key } => {
+                self.tool = match key {
+                    geng::Key::Num1 => EditorTool::Tiles,
+                    geng::Key::Num2 => EditorTool::Segments,
+                    geng::Key::Num3 => EditorTool::Move,
+                    geng::Key::Num4 => EditorTool::Inspect,
+                    _ => return None,
+                };
+                self.drag = None;
+                self.inspector = None;
+                None
+            }
+            geng::Event::MouseDown {
+                button: geng::MouseButton::Middle,
+                ..
+            } => None,
+            geng::Event::MouseDown { button, .. } => {
+                self.stroke_open = false;
+                let left = button == geng::MouseButton::Left;
+                if left && shift {
+                    let delta = cursor - level.spawn_pos();
+                    return Some(EditorAction::Command(EditorCommand::Move {
+                        entity: EntityRef::Spawn,
+                        delta,
+                    }));
+                }
+                match (self.tool, left) {
+                    (EditorTool::Tiles, _) => {
+                        self.drag = Some(Drag::Paint(left));
+                        Some(EditorAction::Command(EditorCommand::SetTile {
+                            pos: cell,
+                            filled: left,
+                        }))
+                    }
+                    (EditorTool::Segments, true) => {
+                        self.drag = Some(Drag::Segment(snapped));
+                        None
+                    }
+                    (EditorTool::Segments, false) => {
+                        let segment = level
+                            .segments
+                            .iter()
+                            .map(|segment| (segment, segment_distance(segment, cursor)))
+                            .filter(|&(_, distance)| distance < PICK_RADIUS)
+                            .min_by(|a, b| a.1.partial_cmp(&b.1).unwrap())?
+                            .0;
+                        Some(EditorAction::Command(EditorCommand::RemoveSegment(
+                            *segment,
+                        )))
+                    }
+                    (EditorTool::Move, true) => {
+                        self.drag = Some(Drag::Move(EntityRef::pick(level, cursor)?, cursor));
+                        None
+                    }
+                    (EditorTool::Move, false) => None,
+                    (EditorTool::Inspect, true) => {
+                        let entity = EntityRef::pick(level, cursor).unwrap_or(EntityRef::Palette);
+                        self.inspector = Some(Inspector::new(entity));
+                        None
+                    }
+                    (EditorTool::Inspect, false) => {
+                        self.inspector = None;
+                        None
+                    }
+                }
+            }
+            geng::Event::MouseUp { .. } => {
+                self.stroke_open = false;
+                match self.drag.take() {
+                    Some(Drag::Segment(start)) if (snapped - start).len() > EPS => {
+                        Some(EditorAction::Command(EditorCommand::AddSegment(
+                            Segment::new(start, snapped),
+                        )))
+                    }
+                    _ => None,
+                }
+            }
+            geng::Event::MouseMove { .. } => match &mut self.drag {
+                Some(Drag::Paint(filled)) => Some(EditorAction::Command(EditorCommand::SetTile {
+                    pos: cell,
+                    filled: *filled,
+                })),
+                Some(Drag::Move(entity, last)) => {
+                    let delta = cursor - *last;
+                    *last = cursor;
+                    Some(EditorAction::Command(EditorCommand::Move {
+                        entity: *entity,
+                        delta,
+                    }))
+                }
+                _ => None,
+            },
+            _ => None,
+        }
+    }
+    pub fn draw(
+        &self,
+        framebuffer: &mut ugli::Framebuffer,
+        line_renderer: &LineRenderer,
+        camera: &Camera,
+        level: &Level,
+        cursor: Vec2<f32>,
+    ) {
+        line_renderer.begin();
+        let cross = |pos: Vec2<f32>, size: f32| {
+            [
+                pos - vec2(size, 0.0),
+                pos + vec2(size, 0.0),
+                pos - vec2(0.0, size),
+                pos + vec2(0.0, size),
+            ]
+        };
+        match self.tool {
+            EditorTool::Tiles => {
+                let cell = AABB::pos_size(cursor.map(|x| x.floor()), vec2(1.0, 1.0));
+                line_renderer.add_strip(
+                    Color::rgba(1.0, 1.0, 1.0, 0.5),
+                    [
+                        cell.bottom_left(),
+                        cell.bottom_right(),
+                        cell.top_right(),
+                        cell.top_left(),
+                        cell.bottom_left(),
+                    ],
+                );
+            }
+            EditorTool::Segments => {
+                let snapped = cursor.map(|x| (x / SEGMENT_SNAP).round() * SEGMENT_SNAP);
+                line_renderer.add_lines(Color::rgba(1.0, 1.0, 1.0, 0.5), cross(snapped, 0.2));
+                if let Some(Drag::Segment(start)) = self.drag {
+                    line_renderer.add_lines(Color::rgb(1.0, 1.0, 0.0), [start, snapped]);
+                }
+            }
+            EditorTool::Move | EditorTool::Inspect => {
+                for entity in EntityRef::all(level) {
+                    if let Some(anchor) = entity.anchor(level) {
+                        line_renderer
+                            .add_lines(Color::rgba(1.0, 0.5, 0.0, 0.7), cross(anchor, 0.3));
+                    }
+                }
+            }
+        }
+        line_renderer.add_lines(Color::rgb(0.0, 1.0, 0.0), cross(level.spawn_pos(), 0.5));
+        line_renderer.flush(framebuffer, camera);
+    }
+    pub fn hints(&self) -> String {
+        format!(
+            "{}: {}  [{}]",
+            self.tool.name(),
+            EDITOR_HINTS,
+            self.path.display()
+        )
+    }
+}
+
+impl Drop for Editor {
+    // A panic leaves the session behind so the next start can restore it
+    fn drop(&mut self) {
+        if !std::thread::panicking() {
+            let _ = std::fs::remove_file(Self::session_path(&self.path));
+        }
+    }
+}
+
+fn segment_distance(segment: &Segment, pos: Vec2<f32>) -> f32 {
+    let dir = segment.p2 - segment.p1;
+    let t = clamp(
+        Vec2::dot(pos - segment.p1, dir) / dir.len().powi(2).max(EPS),
+        0.0..=1.0,
+    );
+    (segment.p1 + dir * t - pos).len()
+}
@@ -0,0 +1,103 @@
+use super::*;
+
+// Slow enough to save power, fast enough for the fixed timestep to catch up each frame
+const BACKGROUND_FPS: f64 = 10.0;
+
+#[derive(Debug, Copy, Clone, Default)]
+pub struct FrameLimit {
+    pub max_fps: Option<u32>,
+    pub throttle_unfocused: bool,
+}
+
+// Root state that caps how often the current state runs.
+// Native builds sleep between frames, the web skips them since it can't block.
+pub struct FrameLimiter {
+    states: geng::StateManager,
+    limit: Rc<Cell<FrameLimit>>,
+    #[cfg(not(target_arch = "wasm32"))]
+    last_frame: std::time::Instant,
+    #[cfg(target_arch = "wasm32")]
+    pending_time: f64,
+    #[cfg(target_arch = "wasm32")]
+    frame_due: bool,
+}
+
+impl FrameLimiter {
+    pub fn new(state: impl geng::State, limit: Rc<Cell<FrameLimit>>) -> Self {
+        let mut states = geng::StateManager::new();
+        states.push(Box::new(state));
+        Self {
+            states,
+            limit,
+            #[cfg(not(target_arch = "wasm32"))]
+            last_frame: std::time::Instant::now(),
+            #[cfg(target_arch = "wasm32")]
+            pending_time: 0.0,
+            #[cfg(target_arch = "wasm32")]
+            frame_due: true,
+        }
+    }
+    // Native windows don't report focus through geng
+    fn focused() -> bool {
+        #[cfg(target_arch = "wasm32")]
+        return web_sys::window()
+            .and_then(|window| window.document())
+            .map_or(true, |document| document.has_focus().unwrap_or(true));
+        #[cfg(not(target_arch = "wasm32"))]
+        true
+    }
+    fn frame_time(&self) -> Option<f64> {
+        let limit = self.limit.get();
+        let fps = limit.max_fps.map(|fps| fps as f64);
+        let fps = if limit.throttle_unfocused && !Self::focused() {
+            Some(fps.map_or(BACKGROUND_FPS, |fps| fps.min(BACKGROUND_FPS)))
+        } else {
+            fps
+        };
+        fps.map(|fps| 1.0 / fps.max(1.0))
+    }
+}
+
+impl geng::State for FrameLimiter {
+    fn update(&mut self, delta_time: f64) {
+        #[cfg(target_arch = "wasm32")]
+        {
+            self.pending_time += delta_time;
+            self.frame_due = match self.frame_time() {
+                Some(frame_time) => self.pending_time >= frame_time,
+                None => true,
+            };
+            if !self.frame_due {
+                return;
+            }
+            let delta_time = mem::take(&mut self.pending_time);
+            self.states.update(delta_time);
+        }
+        #[cfg(not(target_arch = "wasm32"))]
+        self.states.update(delta_time);
+    }
+    fn draw(&mut self, framebuffer: &mut ugli::Framebuffer) {
+        #[cfg(target_arch = "wasm32")]
+        if !self.frame_due {
+            return;
+        }
+        self.states.draw(framebuffer);
+        #[cfg(not(target_arch = "wasm32"))]
+        {
+            if let Some(frame_time) = self.frame_time() {
+                let frame_time = std::time::Duration::from_secs_f64(frame_time);
+                let elapsed = self.last_frame.elapsed();
+                if elapsed < frame_time {
+                    std::thread::sleep(frame_time - elapsed);
+                }
+            }
+            self.last_frame = std::time::Instant::now();
+        }
+    }
+    fn handle_event(&mut self, event: geng::Event) {
+        self.states.handle_event(event);
+    }
+    fn transition(&mut self) -> Option<geng::Transition> {
+        None
+    }
+}
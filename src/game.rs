@@ -2,98 +2,546 @@ use std::{cell, collections::VecDeque};
 
 use super::*;
 
-const EPS: f32 = 1e-5;
-const GRAVITY: f32 = 50.0;
-const BALL_SWING_DISTANCE: f32 = 0.8;
+mod ambience;
+mod entities;
+mod events;
+mod rewind;
+mod signs;
+mod simulation;
+mod spectator;
+mod stats;
+mod tuning;
+mod tutorial;
+mod versus;
+
+use ambience::lerp_color;
+pub use entities::*;
+pub use events::*;
+use rewind::*;
+pub use simulation::*;
+pub use spectator::*;
+pub use tuning::*;
+pub use versus::*;
+
+const STAND_BOUNCE_SPEED: f32 = 2.0;
+const NOCLIP_SPEED: f32 = 20.0;
+const FIXED_DELTA_TIME: f32 = 1.0 / 240.0;
+const ROPE_POINTS: usize = 16;
+const ROPE_ITERATIONS: usize = 8;
+// Share of the rope's swing kept after a second
+const ROPE_DAMPING: f32 = 0.3;
+const ROPE_RADIUS: f32 = 0.1;
+const CHAIN_WIDTH: f32 = 0.5;
+const CHAIN_LINK_LENGTH: f32 = 0.5;
+const CHAIN_LINE_WIDTH: f32 = 0.1;
+const AIM_PREVIEW_DOT_SIZE: f32 = 0.15;
+const AIM_PREVIEW_DOT_SPACING: f32 = 0.4;
+const TRAIL_LENGTH: usize = 32;
+const TRAIL_FADE_TIME: f32 = 0.4;
+const TRAIL_OPACITY: f32 = 0.5;
+const GHOST_OPACITY: f32 = 0.35;
+const CULL_MARGIN: f32 = 2.0;
+const FPS_SMOOTHING: f32 = 0.05;
+const DEBUG_VELOCITY_SCALE: f32 = 0.1;
+const DEBUG_CIRCLE_SEGMENTS: usize = 48;
+// Views wider or narrower than this get black bars, so every screen sees about as much
+const MIN_VIEW_ASPECT: f32 = 4.0 / 3.0;
+const MAX_VIEW_ASPECT: f32 = 21.0 / 9.0;
+const ANIMATION_FRAME_DURATION: f32 = 0.1;
+const WALK_ANIMATION_SPEED: f32 = 1.0;
+const MAX_LEAN: f32 = 0.35;
+// Getting slammed into a wall by the chain faster than this knocks the character over
+const TUMBLE_SPEED: f32 = 15.0;
+const TUMBLE_RESTITUTION: f32 = 0.4;
+const TUMBLE_DURATION: f32 = 1.0;
+// Landing only ends a tumble after this long, so the slam itself doesn't
+const TUMBLE_MIN_DURATION: f32 = 0.25;
+const TUMBLE_SPIN_SPEED: f32 = 12.0;
+const TENSION_SMOOTHING: f32 = 20.0;
+// How much longer the chain links get right before snapping
+const CHAIN_MAX_STRETCH: f32 = 0.5;
+const LEAN_SHARPNESS: f32 = 10.0;
+const CAMERA_BALL_WEIGHT: f32 = 0.3;
+const FREE_CAMERA_ZOOM: f32 = 1.002;
+const IMPACT_SHAKE_SPEED: f32 = 20.0;
+const IMPACT_SHAKE: f32 = 0.02;
+const IMPACT_SOUND_SPEED: f32 = 8.0;
+const IMPACT_SOUND_FULL_SPEED: f32 = 30.0;
+const LANDING_SOUND_SPEED: f32 = 5.0;
+const CHAIN_LOOP_SPIN_SPEED: f32 = 15.0;
+const CHAIN_LOOP_REEL_SPEED: f32 = 10.0;
+const IMPACT_ABERRATION: f32 = 0.002;
+const BREAK_SHAKE: f32 = 0.3;
+const DEATH_SHAKE: f32 = 0.6;
+const KILL_PLANE_MARGIN: f32 = 20.0;
+const BULLET_TIME_SCALE: f32 = 0.3;
+const BULLET_TIME_DURATION: f32 = 0.4;
+const CHARGE_METER_SIZE: Vec2<f32> = vec2(200.0, 16.0);
+const WHEEL_CHAIN_STEP: f32 = 0.01;
+const INPUT_BUFFER_TIME: f32 = 0.1;
+const LEVEL_COMPLETE_DELAY: f32 = 1.5;
+const WATER_DRAG: f32 = 3.0;
+const CHARACTER_DENSITY: f32 = 0.8;
+const BREAK_SPEED: f32 = 15.0;
+const SWEEP_ITERATIONS: usize = 3;
+// Share of the smallest body's radius anything may move in one substep
+const SUBSTEP_TRAVEL: f32 = 0.5;
+// Length of the link between the two balls of the double ball mutator
+const SECOND_BALL_CHAIN: f32 = 1.5;
+// Zipping pulls the character along the taut chain to an anchored ball
+const ZIP_SPEED: f32 = 40.0;
+// Gives up when something blocks the rail for this long
+const ZIP_MAX_TIME: f32 = 0.6;
+const DASH_COOLDOWN: f32 = 1.0;
+// A solid ball rolling without slipping gets 5/7 of the slope's pull
+const ROLL_ACCELERATION: f32 = 5.0 / 7.0;
+// Rolling resistance relative to gravity and ground friction, this is also
+// the steepest slope a ball can rest on
+const ROLLING_FRICTION: f32 = 0.3;
+// How far below a rolling ball the ground is still looked for
+const ROLL_GROUND_PROBE: f32 = 0.05;
+const DEBRIS_PARTICLES: usize = 12;
+const DEBRIS_LIFETIME: f32 = 0.8;
+const ZIP_PARTICLES: usize = 10;
+const ZIP_KICK: f32 = 0.6;
+const ENEMY_DEFEAT_SPEED: f32 = 12.0;
+const ONE_WAY_WIDTH: f32 = 0.2;
+const DOOR_WIDTH: f32 = 0.3;
+const DOOR_SPEED: f32 = 3.0;
+const FORCE_ZONE_STREAKS: f32 = 0.5;
+const AIM_PREVIEW_TIME: f32 = 2.0;
+const AIM_PREVIEW_STEP: f32 = 1.0 / 30.0;
+const LOW_RES_SIZE: Vec2<usize> = vec2(480, 270);
+const PARALLAX_LAYERS: [ParallaxLayer; 3] = [
+    ParallaxLayer {
+        factor: 0.9,
+        bottom: -5.0,
+        size: vec2(80.0, 40.0),
+        opacity: 0.3,
+    },
+    ParallaxLayer {
+        factor: 0.7,
+        bottom: -10.0,
+        size: vec2(60.0, 30.0),
+        opacity: 0.5,
+    },
+    ParallaxLayer {
+        factor: 0.5,
+        bottom: -15.0,
+        size: vec2(40.0, 20.0),
+        opacity: 0.7,
+    },
+];
 
 struct Collision {
     normal: Vec2<f32>,
     penetration: f32,
 }
 
+fn collide_circle(
+    pos: Vec2<f32>,
+    radius: f32,
+    &Segment { p1, p2, .. }: &Segment,
+) -> Option<Collision> {
+    let v = p2 - p1;
+    if Vec2::dot(v, pos - p1) < 0.0 {
+        let n = pos - p1;
+        let penetration = radius - n.len();
+        if penetration > 0.0 {
+            return Some(Collision {
+                normal: n.normalize(),
+                penetration,
+            });
+        } else {
+            return None;
+        }
+    }
+    if Vec2::dot(-v, pos - p2) < 0.0 {
+        let n = pos - p2;
+        let penetration = radius - n.len();
+        if penetration > 0.0 {
+            return Some(Collision {
+                normal: n.normalize(),
+                penetration,
+            });
+        } else {
+            return None;
+        }
+    }
+    let n = Vec2::rotate_90(v.normalize());
+    let distance = Vec2::dot(n, pos - p1);
+    if distance > 0.0 && distance < radius {
+        return Some(Collision {
+            normal: n,
+            penetration: radius - distance,
+        });
+    }
+    if distance < 0.0 && distance > -radius {
+        let n = -n;
+        let distance = -distance;
+        return Some(Collision {
+            normal: n,
+            penetration: radius - distance,
+        });
+    }
+    None
+}
+
 #[derive(Clone)]
 struct Ball {
     pos: Vec2<f32>,
     vel: Vec2<f32>,
     size: f32,
+    density: f32,
     stand: bool,
+    // Only the ball rolls, the character stays put when standing
+    rolls: bool,
+    angle: f32,
+    angular_vel: f32,
+    ground_normal: Vec2<f32>,
+    ground_material: Material,
+    up: Vec2<f32>,
+    grounded: bool,
+    ground_vel: Vec2<f32>,
+    hurt: bool,
+    impact: f32,
+    kind: BallKind,
+    sticky: bool,
+    anchor: Option<Vec2<f32>>,
+    impacts: Vec<(Vec2<f32>, f32)>,
 }
 
 impl Ball {
-    fn new(pos: Vec2<f32>, size: f32) -> Self {
+    fn new(pos: Vec2<f32>, size: f32, density: f32) -> Self {
         Self {
             pos,
             size,
+            density,
             vel: vec2(0.0, 0.0),
             stand: false,
+            rolls: false,
+            angle: 0.0,
+            angular_vel: 0.0,
+            ground_normal: vec2(0.0, 1.0),
+            ground_material: default(),
+            up: vec2(0.0, 1.0),
+            grounded: false,
+            ground_vel: vec2(0.0, 0.0),
+            hurt: false,
+            impact: 0.0,
+            kind: default(),
+            sticky: false,
+            anchor: None,
+            impacts: Vec::new(),
         }
     }
-    fn collide(&self, &[p1, p2]: &Segment) -> Option<Collision> {
-        let v = p2 - p1;
-        if Vec2::dot(v, self.pos - p1) < 0.0 {
-            let n = self.pos - p1;
-            let penetration = self.size - n.len();
-            if penetration > 0.0 {
-                return Some(Collision {
-                    normal: n.normalize(),
-                    penetration,
-                });
-            } else {
-                return None;
-            }
+    fn passes_through(&self, normal: Vec2<f32>, segment: &Segment) -> bool {
+        segment.one_way && (normal.y <= 0.0 || self.vel.y - segment.vel.y > 0.0)
+    }
+    fn collide(&self, segment: &Segment) -> Option<Collision> {
+        collide_circle(self.pos, self.size, segment)
+            .filter(|collision| !self.passes_through(collision.normal, segment))
+    }
+    fn hit(&mut self, normal: Vec2<f32>, segment: &Segment) {
+        let material = segment.material;
+        if material.hazard() {
+            self.hurt = true;
         }
-        if Vec2::dot(-v, self.pos - p2) < 0.0 {
-            let n = self.pos - p2;
-            let penetration = self.size - n.len();
-            if penetration > 0.0 {
-                return Some(Collision {
-                    normal: n.normalize(),
-                    penetration,
-                });
+        let vel = self.vel - segment.vel;
+        let relative_vel = Vec2::dot(normal, vel);
+        if relative_vel < 0.0 {
+            self.impact = partial_max(self.impact, -relative_vel);
+            if material == Material::Breakable {
+                self.impacts
+                    .push((self.pos - normal * self.size, -relative_vel));
+            }
+            let bounce = -relative_vel * material.bounciness().max(self.kind.restitution());
+            let flat = Vec2::dot(normal, self.up) > Vec2::skew(self.up, normal).abs() * 2.0;
+            if flat && bounce < STAND_BOUNCE_SPEED {
+                self.grounded = true;
+                self.ground_vel = segment.vel;
+            }
+            let moving = segment.vel.len() > EPS;
+            if !moving && (self.sticky || self.kind.grapples()) && self.anchor.is_none() {
+                self.anchor = Some(normal);
+            }
+            if !moving
+                && (self.anchor.is_some()
+                    || material.sticky()
+                    || (self.grounded && material.friction() > 0.0))
+            {
+                self.stand = true;
+                self.ground_normal = normal;
+                self.ground_material = material;
+            }
+            let tangent = vel - relative_vel * normal;
+            let tangent_len = tangent.len();
+            // Grippy surfaces bring the spin in line with rolling along them
+            let roll = Vec2::dot(tangent, Vec2::rotate_90(normal)) / self.size;
+            self.angular_vel += (roll - self.angular_vel) * material.friction().min(1.0);
+            let tangent = if tangent_len > EPS {
+                tangent
+                    * ((tangent_len + relative_vel * material.friction()).max(0.0) / tangent_len)
             } else {
-                return None;
+                tangent
+            };
+            self.vel = tangent + normal * bounce + segment.vel;
+        }
+    }
+    // The rest of the movement slides along whatever was hit,
+    // so resting on the ground doesn't block walking along it
+    fn sweep(&mut self, level: &CollisionWorld, mut movement: Vec2<f32>) {
+        for _ in 0..SWEEP_ITERATIONS {
+            let target = self.pos + movement;
+            let aabb = AABB::from_corners(self.pos, target).add_padding(self.size);
+            let mut first_hit: Option<(f32, Vec2<f32>, Segment)> = None;
+            for segment in level.query(aabb) {
+                if let Some((t, normal)) = sweep_circle(self.pos, target, self.size, segment) {
+                    if self.passes_through(normal, segment) {
+                        continue;
+                    }
+                    if !matches!(first_hit, Some((best, _, _)) if best <= t) {
+                        first_hit = Some((t, normal, *segment));
+                    }
+                }
+            }
+            match first_hit {
+                Some((t, normal, segment)) => {
+                    self.pos += movement * t;
+                    self.hit(normal, &segment);
+                    let rest = movement * (1.0 - t);
+                    movement = rest - normal * Vec2::dot(rest, normal).min(0.0);
+                }
+                None => {
+                    self.pos = target;
+                    return;
+                }
             }
         }
-        let n = Vec2::rotate_90(v.normalize());
-        let distance = Vec2::dot(n, self.pos - p1);
-        if distance > 0.0 && distance < self.size {
-            return Some(Collision {
-                normal: n,
-                penetration: self.size - distance,
-            });
+    }
+    fn update(&mut self, env: &Environment, delta_time: f32) {
+        let level = env.level;
+        let gravity = env.gravity_at(self.pos) * self.kind.gravity_scale();
+        let acceleration = env.force_at(self.pos);
+        let submersion = env.submersion(self.pos, self.size);
+        let up = if gravity.len() > EPS {
+            -gravity.normalize()
+        } else {
+            self.up
+        };
+        if self.anchor.is_none()
+            && (submersion > 0.0 || acceleration != vec2(0.0, 0.0) || up != self.up)
+        {
+            self.stand = false;
         }
-        if distance < 0.0 && distance > -self.size {
-            let n = -n;
-            let distance = -distance;
-            return Some(Collision {
-                normal: n,
-                penetration: self.size - distance,
-            });
+        self.up = up;
+        self.grounded = self.stand;
+        if !self.grounded {
+            self.ground_vel = vec2(0.0, 0.0);
         }
-        None
-    }
-    fn update(&mut self, level: &[Segment], delta_time: f32) {
         if !self.stand {
-            self.vel.y -= GRAVITY * delta_time;
-            self.pos += self.vel * delta_time;
+            let buoyancy = -gravity * submersion / self.density;
+            self.vel += (gravity + buoyancy + acceleration) * delta_time;
+            self.vel *= (1.0 - WATER_DRAG * submersion * delta_time).max(0.0);
+            self.sweep(level, self.vel * delta_time);
+        } else if self.rolls && self.anchor.is_none() && !self.ground_material.sticky() {
+            self.roll(level, gravity, delta_time);
         } else {
             self.vel = vec2(0.0, 0.0);
+            self.angular_vel = 0.0;
         }
-        for segment in level {
+        self.angle += self.angular_vel * delta_time;
+        let aabb = AABB::pos_size(self.pos, vec2(0.0, 0.0)).add_padding(self.size);
+        for segment in level.query(aabb) {
             if let Some(collision) = self.collide(segment) {
                 self.pos += collision.normal * collision.penetration;
-                let relative_vel = Vec2::dot(collision.normal, self.vel);
-                if relative_vel < 0.0 {
-                    if collision.normal.y > collision.normal.x.abs() * 2.0 {
-                        self.stand = true;
+                self.hit(collision.normal, segment);
+            }
+        }
+    }
+    // Moves a standing ball along the ground it rests on. Slopes steeper than the
+    // rolling friction get it going, flatter ones slow it down until it stops
+    fn roll(&mut self, level: &CollisionWorld, gravity: Vec2<f32>, delta_time: f32) {
+        let tangent = Vec2::rotate_90(self.ground_normal);
+        let mut speed = Vec2::dot(self.vel - self.ground_vel, tangent)
+            + Vec2::dot(gravity, tangent) * ROLL_ACCELERATION * delta_time;
+        let friction =
+            ROLLING_FRICTION * gravity.len() * self.ground_material.friction() * delta_time;
+        speed = speed.signum() * (speed.abs() - friction).max(0.0);
+        self.vel = tangent * speed;
+        self.angular_vel = speed / self.size;
+        if speed == 0.0 {
+            return;
+        }
+        self.sweep(level, self.vel * delta_time);
+        // Follow the ground over bumps and convex corners, rolling off a ledge
+        // leaves the ball falling with its current velocity
+        let probe = self.size + ROLL_GROUND_PROBE;
+        let aabb = AABB::pos_size(self.pos, vec2(0.0, 0.0)).add_padding(probe);
+        let ground = level
+            .query(aabb)
+            .filter_map(|segment| {
+                let collision = collide_circle(self.pos, probe, segment)?;
+                let flat = Vec2::dot(collision.normal, self.up)
+                    > Vec2::skew(self.up, collision.normal).abs() * 2.0;
+                (flat && !self.passes_through(collision.normal, segment))
+                    .then_some((collision, segment.material))
+            })
+            .max_by(|(a, _), (b, _)| a.penetration.partial_cmp(&b.penetration).unwrap());
+        match ground {
+            Some((collision, material)) => {
+                self.pos -= collision.normal * (ROLL_GROUND_PROBE - collision.penetration);
+                self.ground_normal = collision.normal;
+                self.ground_material = material;
+            }
+            None => self.stand = false,
+        }
+    }
+    fn interpolate(&self, prev_pos: Vec2<f32>, alpha: f32) -> Self {
+        Self {
+            pos: prev_pos + (self.pos - prev_pos) * alpha,
+            ..self.clone()
+        }
+    }
+}
+
+#[derive(Clone)]
+struct Rope {
+    points: Vec<Vec2<f32>>,
+    prev_points: Vec<Vec2<f32>>,
+}
+
+impl Rope {
+    fn new(from: Vec2<f32>, to: Vec2<f32>) -> Self {
+        let points: Vec<Vec2<f32>> = (0..ROPE_POINTS)
+            .map(|i| from + (to - from) * (i as f32 / (ROPE_POINTS - 1) as f32))
+            .collect();
+        Self {
+            prev_points: points.clone(),
+            points,
+        }
+    }
+    fn update(
+        &mut self,
+        from: Vec2<f32>,
+        to: Vec2<f32>,
+        length: f32,
+        env: &Environment,
+        delta_time: f32,
+    ) {
+        let n = self.points.len();
+        let damping = ROPE_DAMPING.powf(delta_time);
+        for i in 1..n - 1 {
+            let pos = self.points[i];
+            let vel = (pos - self.prev_points[i]) * damping;
+            self.prev_points[i] = pos;
+            self.points[i] = pos + vel + env.gravity_at(pos) * delta_time * delta_time;
+        }
+        self.points[0] = from;
+        self.points[n - 1] = to;
+        let link_len = length / (n - 1) as f32;
+        for _ in 0..ROPE_ITERATIONS {
+            for i in 0..n - 1 {
+                let delta = self.points[i + 1] - self.points[i];
+                let len = delta.len();
+                if len <= link_len {
+                    continue;
+                }
+                let w1 = if i == 0 { 0.0 } else { 1.0 };
+                let w2 = if i + 1 == n - 1 { 0.0 } else { 1.0 };
+                if w1 + w2 == 0.0 {
+                    continue;
+                }
+                let correction = delta * ((len - link_len) / len / (w1 + w2));
+                self.points[i] += correction * w1;
+                self.points[i + 1] -= correction * w2;
+            }
+            for point in &mut self.points[1..n - 1] {
+                let aabb = AABB::pos_size(*point, vec2(0.0, 0.0)).add_padding(ROPE_RADIUS);
+                for segment in env.level.query(aabb).filter(|segment| !segment.one_way) {
+                    if let Some(collision) = collide_circle(*point, ROPE_RADIUS, segment) {
+                        *point += collision.normal * collision.penetration;
                     }
-                    self.vel -= relative_vel * collision.normal;
                 }
             }
         }
     }
-    fn matrix(&self) -> Mat4<f32> {
-        Mat4::translate(self.pos.extend(0.0)) * Mat4::scale_uniform(self.size)
+}
+
+#[derive(Debug, Copy, Clone)]
+struct ChainWrap {
+    pos: Vec2<f32>,
+    side: f32,
+}
+
+fn point_in_triangle(p: Vec2<f32>, a: Vec2<f32>, b: Vec2<f32>, c: Vec2<f32>) -> bool {
+    let s1 = Vec2::skew(b - a, p - a);
+    let s2 = Vec2::skew(c - b, p - b);
+    let s3 = Vec2::skew(a - c, p - c);
+    (s1 > EPS && s2 > EPS && s3 > EPS) || (s1 < -EPS && s2 < -EPS && s3 < -EPS)
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct SimulationConfig {
+    // Advance everything that affects physics only in fixed steps,
+    // so recorded inputs replay to identical results
+    pub deterministic: bool,
+}
+
+struct Environment<'a> {
+    level: &'a CollisionWorld,
+    entities: &'a [Entity],
+    gravity: f32,
+}
+
+impl Environment<'_> {
+    fn gravity_at(&self, pos: Vec2<f32>) -> Vec2<f32> {
+        let direction = self
+            .areas()
+            .rev()
+            .filter(|(area, _)| area.aabb.contains(pos))
+            .find_map(|(_, behavior)| match behavior {
+                Behavior::GravityZone { direction } => Some(*direction),
+                _ => None,
+            })
+            .unwrap_or(GravityDirection::Down);
+        direction.vec() * self.gravity
+    }
+    fn force_at(&self, pos: Vec2<f32>) -> Vec2<f32> {
+        self.areas()
+            .filter(|(area, _)| area.aabb.contains(pos))
+            .fold(vec2(0.0, 0.0), |sum, (_, behavior)| match behavior {
+                Behavior::ForceZone { acceleration } => sum + *acceleration,
+                _ => sum,
+            })
+    }
+    fn submersion(&self, pos: Vec2<f32>, radius: f32) -> f32 {
+        self.areas()
+            .filter(|(_, behavior)| matches!(behavior, Behavior::Water))
+            .map(|(area, _)| WaterVolume { aabb: area.aabb }.submersion(pos, radius))
+            .fold(0.0, f32::max)
+    }
+    fn areas(&self) -> impl DoubleEndedIterator<Item = (&Area, &Behavior)> {
+        self.entities
+            .iter()
+            .filter_map(|entity| Some((entity.area.as_ref()?, &entity.behavior)))
+    }
+}
+
+#[derive(Debug, Copy, Clone, Default)]
+struct BufferedInput {
+    time_left: f32,
+}
+
+impl BufferedInput {
+    fn press(&mut self) {
+        self.time_left = INPUT_BUFFER_TIME;
+    }
+    fn update(&mut self, delta_time: f32) {
+        self.time_left = (self.time_left - delta_time).max(0.0);
+    }
+    fn take(&mut self) -> bool {
+        mem::take(&mut self.time_left) > 0.0
     }
 }
 
@@ -103,39 +551,575 @@ struct Player {
     ball: Ball,
     ball_in_hands: bool,
     chain_len: f32,
+    chain_adjust: f32,
+    chain_wraps: Vec<ChainWrap>,
+    rope: Rope,
+    reel: bool,
+    reel_speed: f32,
+    walk: f32,
+    jump: BufferedInput,
+    jump_held: bool,
+    jumping: bool,
+    coyote_time: f32,
+    throw: BufferedInput,
+    throw_vel: Vec2<f32>,
+    // How fast the ball was spinning around in hands when thrown
+    throw_spin: f32,
+    noclip: bool,
+    animation: AnimationController,
+    lean: f32,
+    // Time spent tumbling, input is ignored until the character recovers
+    tumble: Option<f32>,
+    tumble_angle: f32,
+    tumble_spin: f32,
+    // Set on the step a tumble starts, with the slam speed
+    tumbled: Option<f32>,
+    // How fast the character is being dragged past the chain length, smoothed
+    tension: f32,
+    chain_snapping: bool,
+    // The ball lies loose until the character walks over it
+    chain_snapped: bool,
+    // Set on the step the chain snaps
+    snapped: bool,
+    second_ball: Option<Ball>,
+    dash: BufferedInput,
+    dash_cooldown: f32,
+    // Time spent zipping towards the anchored ball
+    zip: Option<f32>,
+    // Set on the step a zip starts, with its direction
+    zipped: Option<Vec2<f32>>,
+    tuning: Tuning,
 }
 
 impl Player {
-    fn new() -> Self {
+    fn new(spawn: Vec2<f32>, tuning: Tuning) -> Self {
         Self {
-            character: Ball::new(vec2(0.0, 0.0), 1.0),
-            ball: Ball::new(vec2(0.0, 0.0), 0.5),
+            character: Ball::new(spawn, 1.0, CHARACTER_DENSITY),
+            ball: Ball {
+                rolls: true,
+                ..Ball::new(spawn, BallKind::Normal.size(), BallKind::Normal.density())
+            },
             ball_in_hands: true,
             chain_len: 1.0,
+            chain_adjust: 0.0,
+            chain_wraps: Vec::new(),
+            rope: Rope::new(spawn, spawn),
+            reel: false,
+            reel_speed: 0.0,
+            walk: 0.0,
+            jump: default(),
+            jump_held: false,
+            jumping: false,
+            coyote_time: 0.0,
+            throw: default(),
+            throw_vel: vec2(0.0, 0.0),
+            throw_spin: 0.0,
+            noclip: false,
+            animation: AnimationController::new(ANIMATION_FRAME_DURATION),
+            lean: 0.0,
+            tumble: None,
+            tumble_angle: 0.0,
+            tumble_spin: 0.0,
+            tumbled: None,
+            tension: 0.0,
+            chain_snapping: false,
+            chain_snapped: false,
+            snapped: false,
+            second_ball: None,
+            dash: default(),
+            dash_cooldown: 0.0,
+            zip: None,
+            zipped: None,
+            tuning,
         }
     }
-    fn update(&mut self, level: &[Segment], delta_time: f32) {
+    fn add_second_ball(&mut self) {
+        self.second_ball = Some(Ball {
+            rolls: true,
+            ..Ball::new(
+                self.ball.pos,
+                BallKind::Normal.size(),
+                BallKind::Normal.density(),
+            )
+        });
+    }
+    // The second ball follows the first one on a stiff link, sharing the
+    // correction by mass unless the first ball is held in place
+    fn update_second_ball(&mut self, env: &Environment, delta_time: f32) {
+        let second = match &mut self.second_ball {
+            Some(second) => second,
+            None => return,
+        };
         if self.ball_in_hands {
-            self.ball.pos = self.character.pos + self.ball.vel.normalize() * BALL_SWING_DISTANCE;
+            second.pos = self.ball.pos;
+            second.vel = self.ball.vel;
+            return;
+        }
+        second.update(env, delta_time);
+        let delta_pos = second.pos - self.ball.pos;
+        let len = delta_pos.len();
+        if len <= SECOND_BALL_CHAIN {
+            return;
+        }
+        let dir = delta_pos / len;
+        let mass = |ball: &Ball| ball.density * ball.size * ball.size;
+        let pinned = self.ball.stand || self.ball.anchor.is_some();
+        let share = if pinned {
+            1.0
+        } else {
+            mass(&self.ball) / (mass(&self.ball) + mass(second))
+        };
+        let pull = len - SECOND_BALL_CHAIN;
+        let relative = Vec2::dot(second.vel - self.ball.vel, dir).max(0.0);
+        second.pos -= dir * pull * share;
+        second.vel -= dir * relative * share;
+        if !pinned {
+            self.ball.pos += dir * pull * (1.0 - share);
+            self.ball.vel += dir * relative * (1.0 - share);
+        }
+    }
+    fn can_zip(&self) -> bool {
+        self.chained()
+            && self.ball.anchor.is_some()
+            && self.zip.is_none()
+            && self.tumble.is_none()
+            && self.dash_cooldown <= 0.0
+    }
+    fn start_zip(&mut self) {
+        self.zip = Some(0.0);
+        self.dash_cooldown = DASH_COOLDOWN;
+        self.zipped = Some((self.chain_anchor() - self.character.pos).normalize());
+        self.reel_speed = 0.0;
+        self.chain_adjust = 0.0;
+        self.character.stand = false;
+    }
+    // The chain turns into a stiff rail, reaching the ball uses up its anchor
+    fn update_zip(&mut self, delta_time: f32) {
+        let time = match &mut self.zip {
+            Some(time) => {
+                *time += delta_time;
+                *time
+            }
+            None => return,
+        };
+        let reach = self.character.size + self.ball.size;
+        if (self.ball.pos - self.character.pos).len() < reach || time > ZIP_MAX_TIME {
+            self.ball.anchor = None;
+            self.release_ball();
+            return;
+        }
+        let delta_pos = self.chain_anchor() - self.character.pos;
+        if delta_pos.len() > EPS {
+            self.character.vel = delta_pos.normalize() * ZIP_SPEED;
+        }
+        self.chain_len = self.wrapped_chain_len() + delta_pos.len();
+    }
+    fn chain_anchor(&self) -> Vec2<f32> {
+        self.chain_wraps
+            .last()
+            .map_or(self.ball.pos, |wrap| wrap.pos)
+    }
+    fn wrapped_chain_len(&self) -> f32 {
+        let mut len = 0.0;
+        let mut prev = self.ball.pos;
+        for wrap in &self.chain_wraps {
+            len += (wrap.pos - prev).len();
+            prev = wrap.pos;
+        }
+        len
+    }
+    fn throw_ball(&mut self) {
+        self.ball_in_hands = false;
+        self.ball.pos =
+            self.character.pos + self.throw_vel.normalize() * self.tuning.ball_swing_distance;
+        self.ball.vel = self.throw_vel;
+        self.ball.angular_vel = self.throw_spin;
+        self.ball.stand = false;
+        self.ball.anchor = None;
+        self.chain_len = 2.0;
+    }
+    fn set_ball_kind(&mut self, kind: BallKind) {
+        self.ball.kind = kind;
+        self.ball.size = kind.size();
+        self.ball.density = kind.density();
+        self.ball.anchor = None;
+    }
+    fn chained(&self) -> bool {
+        !self.ball_in_hands && !self.chain_snapped
+    }
+    // Zero at rest, one when about to snap
+    fn strain(&self) -> f32 {
+        if !self.chain_snapping || self.tuning.chain_break_tension <= 0.0 {
+            return 0.0;
+        }
+        clamp(self.tension / self.tuning.chain_break_tension, 0.0..=1.0)
+    }
+    fn snap_chain(&mut self) {
+        self.chain_snapped = true;
+        self.snapped = true;
+        self.zip = None;
+        self.chain_wraps.clear();
+        self.chain_adjust = 0.0;
+        self.reel_speed = 0.0;
+        self.tension = 0.0;
+    }
+    fn release_ball(&mut self) {
+        self.ball_in_hands = true;
+        self.zip = None;
+        self.chain_snapped = false;
+        self.tension = 0.0;
+        self.chain_wraps.clear();
+        self.chain_len = 1.0;
+        self.chain_adjust = 0.0;
+        self.reel_speed = 0.0;
+    }
+    fn update_reel(&mut self, delta_time: f32) {
+        if !self.reel {
+            self.reel_speed = 0.0;
+            return;
+        }
+        let max_speed = self.tuning.max_reel_speed * self.ball.kind.reel_speed();
+        self.reel_speed =
+            (self.reel_speed + self.tuning.reel_acceleration * delta_time).min(max_speed);
+        self.chain_len =
+            (self.chain_len - self.reel_speed * delta_time).max(self.wrapped_chain_len());
+        let delta_pos = self.chain_anchor() - self.character.pos;
+        if delta_pos.len() > EPS {
+            let dir = delta_pos.normalize();
+            let along = Vec2::dot(self.character.vel, dir);
+            if along < self.reel_speed {
+                self.character.vel +=
+                    dir * (self.reel_speed - along).min(self.tuning.reel_force * delta_time);
+            }
+            self.character.stand = false;
+        }
+    }
+    fn adjust_chain(&mut self, amount: f32) {
+        if !self.chained() {
+            return;
+        }
+        self.chain_adjust = clamp(
+            self.chain_adjust + amount,
+            -self.tuning.max_chain_len..=self.tuning.max_chain_len,
+        );
+    }
+    fn update_chain_adjust(&mut self, delta_time: f32) {
+        let max_change = self.tuning.chain_adjust_speed * delta_time;
+        let change = clamp(self.chain_adjust, -max_change..=max_change);
+        if change == 0.0 {
+            return;
+        }
+        self.chain_adjust -= change;
+        self.chain_len = (self.chain_len + change)
+            .min(self.tuning.max_chain_len)
+            .max(self.wrapped_chain_len().max(self.tuning.min_chain_len));
+    }
+    fn update_controls(&mut self, delta_time: f32) {
+        let grounded = self.character.grounded;
+        let ground_vel = self.character.ground_vel;
+        if grounded {
+            self.coyote_time = self.tuning.coyote_time;
         } else {
-            self.ball.update(level, delta_time);
-            if self.ball.stand {
-                self.chain_len -= 5.0 * delta_time;
-                if self.chain_len < 0.1 {
-                    self.chain_len = 0.1;
-                    self.ball_in_hands = true;
+            self.coyote_time = (self.coyote_time - delta_time).max(0.0);
+        }
+        let up = self.character.up;
+        let right = vec2(up.y, -up.x);
+        let mut side = Vec2::dot(self.character.vel, right);
+        let mut vertical = Vec2::dot(self.character.vel, up);
+        if grounded || self.walk != 0.0 {
+            let acceleration = if grounded {
+                self.tuning.walk_acceleration
+            } else {
+                self.tuning.air_acceleration
+            } * delta_time;
+            side += clamp(
+                Vec2::dot(ground_vel, right) + self.walk * self.tuning.walk_speed - side,
+                -acceleration..=acceleration,
+            );
+        }
+        if self.coyote_time > 0.0 && self.jump.take() {
+            vertical = self.tuning.jump_speed;
+            self.jumping = true;
+            self.coyote_time = 0.0;
+        }
+        if self.jumping && !self.jump_held && vertical > 0.0 {
+            vertical *= self.tuning.jump_cut;
+            self.jumping = false;
+        }
+        if vertical <= 0.0 {
+            self.jumping = false;
+        }
+        self.character.vel = right * side + up * vertical;
+    }
+    fn update_chain_wraps(&mut self, level: &CollisionWorld, prev_pos: Vec2<f32>) {
+        while let Some(wrap) = self.chain_wraps.last().copied() {
+            let prev_anchor = match self.chain_wraps.len() {
+                1 => self.ball.pos,
+                len => self.chain_wraps[len - 2].pos,
+            };
+            let side = Vec2::skew(wrap.pos - prev_anchor, self.character.pos - wrap.pos);
+            if side * wrap.side < 0.0 {
+                self.chain_wraps.pop();
+            } else {
+                break;
+            }
+        }
+        let anchor = self.chain_anchor();
+        let old_dir = prev_pos - anchor;
+        let sweep = Vec2::skew(old_dir, self.character.pos - anchor);
+        if sweep.abs() < EPS {
+            return;
+        }
+        let mut best: Option<(f32, Vec2<f32>)> = None;
+        let pos = self.character.pos;
+        let aabb = AABB::from_corners(
+            vec2(
+                anchor.x.min(prev_pos.x).min(pos.x),
+                anchor.y.min(prev_pos.y).min(pos.y),
+            ),
+            vec2(
+                anchor.x.max(prev_pos.x).max(pos.x),
+                anchor.y.max(prev_pos.y).max(pos.y),
+            ),
+        );
+        for segment in level.query(aabb).filter(|segment| !segment.one_way) {
+            for &p in &[segment.p1, segment.p2] {
+                if !point_in_triangle(p, anchor, prev_pos, self.character.pos) {
+                    continue;
                 }
+                let dir = p - anchor;
+                let angle = Vec2::skew(old_dir, dir)
+                    .atan2(Vec2::dot(old_dir, dir))
+                    .abs();
+                let better = match best {
+                    Some((best_angle, _)) => angle < best_angle,
+                    None => true,
+                };
+                if better {
+                    best = Some((angle, p));
+                }
+            }
+        }
+        if let Some((_, pos)) = best {
+            self.chain_wraps.push(ChainWrap {
+                pos,
+                side: sweep.signum(),
+            });
+        }
+    }
+    fn start_tumble(&mut self, bounce: Vec2<f32>, speed: f32) {
+        self.tumble = Some(0.0);
+        self.tumbled = Some(speed);
+        self.character.vel = bounce * speed * TUMBLE_RESTITUTION;
+        let right = vec2(self.character.up.y, -self.character.up.x);
+        self.tumble_spin = -Vec2::dot(bounce, right).signum() * TUMBLE_SPIN_SPEED;
+    }
+    fn update_tumble(&mut self, delta_time: f32) {
+        let time = match &mut self.tumble {
+            Some(time) => time,
+            None => return,
+        };
+        *time += delta_time;
+        self.tumble_angle += self.tumble_spin * delta_time;
+        let landed = self.character.grounded && *time > TUMBLE_MIN_DURATION;
+        if landed || *time > TUMBLE_DURATION {
+            self.tumble = None;
+            self.tumble_angle = 0.0;
+        }
+    }
+    fn update(&mut self, env: &Environment, delta_time: f32) {
+        let prev_pos = self.character.pos;
+        let tumbling = self.tumble.is_some();
+        // How fast the chain drags the character this step
+        let mut yank = 0.0;
+        if tumbling {
+            self.throw.take();
+        }
+        if self.ball_in_hands && self.throw.take() {
+            self.throw_ball();
+        }
+        self.jump.update(delta_time);
+        self.throw.update(delta_time);
+        self.dash.update(delta_time);
+        self.dash_cooldown = (self.dash_cooldown - delta_time).max(0.0);
+        if self.can_zip() && self.dash.take() {
+            self.start_zip();
+        }
+        if self.ball_in_hands {
+            self.chain_wraps.clear();
+            self.ball.pos =
+                self.character.pos + self.ball.vel.normalize() * self.tuning.ball_swing_distance;
+        } else if self.chain_snapped {
+            self.ball.update(env, delta_time);
+            let reach = self.character.size + self.ball.size;
+            if (self.ball.pos - self.character.pos).len() < reach {
+                self.release_ball();
+            }
+        } else {
+            self.ball.update(env, delta_time);
+            if self.zip.is_some() {
+                self.update_zip(delta_time);
+            } else if !tumbling {
+                self.update_reel(delta_time);
+                yank += self.reel_speed;
+            }
+            self.update_chain_adjust(delta_time);
+            if self.chain_len < self.tuning.min_chain_len {
+                self.release_ball();
+            }
+            let free_len = (self.chain_len - self.wrapped_chain_len()).max(0.0);
+            let delta_pos = self.chain_anchor() - self.character.pos;
+            let mut stretch = 0.0;
+            if delta_pos.len() > free_len {
+                let pull = delta_pos.len() - free_len;
+                self.character.pos += delta_pos.normalize() * pull;
+                stretch = pull / delta_time;
+                yank += stretch;
+            }
+            self.tension += (stretch - self.tension) * (TENSION_SMOOTHING * delta_time).min(1.0);
+            if self.chain_snapping && self.tension > self.tuning.chain_break_tension {
+                self.snap_chain();
+            }
+        }
+        self.update_second_ball(env, delta_time);
+        if self.noclip {
+            self.character.vel = vec2(0.0, 0.0);
+        } else {
+            if !tumbling && self.zip.is_none() {
+                self.update_controls(delta_time);
+            }
+            self.character.stand = false;
+            // Whatever the collisions push back is how hard the character got slammed
+            let expected = self.character.pos + self.character.vel * delta_time;
+            let prev_impact = mem::take(&mut self.character.impact);
+            self.character.update(env, delta_time);
+            let pushed = self.character.pos - expected;
+            let slam = partial_max(pushed.len() / delta_time, self.character.impact);
+            self.character.impact = partial_max(prev_impact, self.character.impact);
+            if !tumbling && yank > TUMBLE_SPEED && slam > TUMBLE_SPEED && pushed.len() > EPS {
+                self.start_tumble(pushed.normalize(), slam);
+            }
+        }
+        self.update_tumble(delta_time);
+        if self.chained() {
+            self.update_chain_wraps(env.level, prev_pos);
+        }
+    }
+    // Fast bodies get split into substeps so the chain and contacts keep up,
+    // past the cap the swept collision still stops them tunneling through walls
+    fn substeps(&self, delta_time: f32) -> usize {
+        let mut speed = self.character.vel.len();
+        let mut size = self.character.size;
+        if !self.ball_in_hands {
+            speed = partial_max(speed, self.ball.vel.len());
+            size = partial_min(size, self.ball.size);
+        }
+        let travel = speed * delta_time / (size * SUBSTEP_TRAVEL).max(EPS);
+        clamp(travel.ceil() as usize, 1..=self.tuning.max_substeps.max(1))
+    }
+    // Returns how many substeps it took
+    fn step(&mut self, env: &Environment, delta_time: f32) -> usize {
+        let substeps = self.substeps(delta_time);
+        for _ in 0..substeps {
+            self.update(env, delta_time / substeps as f32);
+        }
+        substeps
+    }
+    fn update_animation(&mut self, spin: bool, spin_angle: f32, delta_time: f32) {
+        let character = &self.character;
+        let right = vec2(character.up.y, -character.up.x);
+        let side_vel = Vec2::dot(character.vel, right);
+        // Lean into the swing, or against a taut chain
+        let toward_ball = if self.ball_in_hands {
+            if spin {
+                Vec2::rotated(vec2(1.0, 0.0), spin_angle)
+            } else {
+                vec2(0.0, 0.0)
             }
-            let delta_pos = self.ball.pos - self.character.pos;
-            if delta_pos.len() > self.chain_len {
-                self.character.pos += delta_pos.normalize() * (delta_pos.len() - self.chain_len);
+        } else if self.chain_snapped {
+            vec2(0.0, 0.0)
+        } else {
+            let offset = self.chain_anchor() - character.pos;
+            let slack = self.chain_len - self.wrapped_chain_len() - offset.len();
+            if slack < CHAIN_LINK_LENGTH && offset.len() > EPS {
+                offset.normalize()
+            } else {
+                vec2(0.0, 0.0)
             }
+        };
+        let lean = -Vec2::dot(toward_ball, right) * MAX_LEAN;
+        self.lean += (lean - self.lean) * (LEAN_SHARPNESS * delta_time).min(1.0);
+        if side_vel.abs() > WALK_ANIMATION_SPEED {
+            self.animation.facing_left = side_vel < 0.0;
+        }
+        let state = if spin && self.ball_in_hands {
+            CharacterAnimation::SpinUp
+        } else if self.reel && self.chained() {
+            CharacterAnimation::Reeling
+        } else if !character.grounded {
+            CharacterAnimation::Flying
+        } else if side_vel.abs() > WALK_ANIMATION_SPEED {
+            CharacterAnimation::Walk
+        } else {
+            CharacterAnimation::Idle
+        };
+        self.animation.set_state(state);
+        self.animation.playback.speed = match state {
+            CharacterAnimation::Walk => side_vel.abs() / self.tuning.walk_speed,
+            _ => 1.0,
+        };
+        self.animation.update(delta_time);
+    }
+    fn update_rope(&mut self, env: &Environment, delta_time: f32) {
+        if self.ball_in_hands {
+            self.rope = Rope::new(self.character.pos, self.ball.pos);
+        } else {
+            self.rope.update(
+                self.character.pos,
+                self.ball.pos,
+                self.chain_len,
+                env,
+                delta_time,
+            );
         }
-        self.character.update(level, delta_time);
     }
 }
 
-type Segment = [Vec2<f32>; 2];
+struct Particle {
+    pos: Vec2<f32>,
+    vel: Vec2<f32>,
+    life: f32,
+    color: Color<f32>,
+}
+
+#[derive(Debug, Copy, Clone)]
+enum TransitionAction {
+    Respawn,
+    NextLevel,
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+enum InputDevice {
+    KeyboardMouse,
+    Gamepad,
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+enum PlayerBody {
+    Character,
+    Ball,
+}
+
+#[derive(Debug, Copy, Clone)]
+enum LevelState {
+    Playing,
+    Complete {
+        time: f32,
+        timer: f32,
+        split: f32,
+        pb_delta: Option<f32>,
+    },
+}
 
 pub struct Game {
     time: f32,
@@ -143,249 +1127,2625 @@ pub struct Game {
     assets: Rc<Assets>,
     renderer: Renderer,
     line_renderer: LineRenderer,
+    tile_renderer: TileRenderer,
+    parallax_renderer: ParallaxRenderer,
+    chain_renderer: ChainRenderer,
+    light_renderer: LightRenderer,
+    lighting: Option<Lighting>,
+    palette: LevelPalette,
+    ambience: Option<Ambience>,
+    level_data: Level,
+    post_process: PostProcess,
+    effects: Effects,
+    world_target: Option<ugli::Texture>,
+    // Second view following the ball in split-screen co-op
+    ball_camera: Option<Camera>,
+    ball_target: Option<ugli::Texture>,
+    pixel_perfect: bool,
+    tile_chunks: Vec<TileBatch>,
+    platform_batches: Vec<TileBatch>,
+    transition_renderer: TransitionRenderer,
+    transition: Option<ScreenTransition>,
+    transition_action: Option<TransitionAction>,
     camera: Camera,
+    themes: Themes,
+    tuning: Tuning,
+    tuning_panel: Option<TuningPanel>,
+    infinite_chain: bool,
     player: Player,
-    save: Option<Player>,
-    level: Vec<Segment>,
+    save: Option<Snapshot>,
+    rewind: Rewind,
+    rewind_config: RewindConfig,
+    active_checkpoint: Option<usize>,
+    debug: bool,
+    debug_draw: bool,
+    fps: f32,
+    substeps: usize,
+    level_index: usize,
+    level_state: LevelState,
+    level_time: f32,
+    timer_running: bool,
+    run_time: f32,
+    ghost: Option<AutoSave<Ghost>>,
+    ghost_recording: Vec<GhostFrame>,
+    race: Option<Race>,
+    leaderboard: Option<LeaderboardRequest>,
+    level: CollisionWorld,
     tiles: Vec<Vec2<f32>>,
+    // Sign entity whose popup is open
+    reading: Option<usize>,
+    entities: Vec<Entity>,
+    collected: HashMap<usize, usize>,
+    particles: Vec<Particle>,
+    // Filled during a fixed step, drained by process_events at its end
+    events: Vec<GameEvent>,
     framebuffer_size: Vec2<usize>,
     spin: bool,
+    spin_angle: f32,
+    aim_time: f32,
+    gravity: f32,
+    time_scale: f32,
+    simulation: SimulationConfig,
+    free_camera: bool,
+    spectator: Option<Spectator>,
+    align_camera: bool,
+    last_mouse_pos: Vec2<f32>,
+    bullet_time: bool,
+    bounds: Option<AABB<f32>>,
+    kill_margin: f32,
+    bullet_time_left: f32,
+    ball_trail: VecDeque<(Vec2<f32>, f32)>,
+    trail_length: usize,
+    trail_fade: f32,
+    accumulator: f32,
+    prev_character_pos: Vec2<f32>,
+    prev_ball_pos: Vec2<f32>,
+    console: Console,
+    commands: Rc<CommandRegistry<Game>>,
+    settings: AutoSave<Settings>,
+    progress: AutoSave<SaveData>,
+    // Set by the pause and results scenes pushed on top, handled on getting focus back
+    pause_action: Rc<Cell<Option<PauseAction>>>,
+    results_action: Rc<Cell<Option<ResultsAction>>>,
+    stats: RunStats,
+    // Not yet added to the save
+    lifetime_stats: LifetimeStats,
+    toasts: AchievementToasts,
+    state_transition: Option<SceneTransition>,
+    audio: AudioPlayer,
+    chain_loop: ChainLoop,
+    prev_chain_len: f32,
+    chain_taut: bool,
+    input: InputState,
+    gamepad: Gamepad,
+    pad_spin: bool,
+    coop: bool,
+    tick: u64,
+    input_recorder: Option<InputRecorder>,
+    rng: StdRng,
+    // Loaded from a file instead of the level list, restarts in place of advancing
+    custom_level: Option<Level>,
+    // Generated levels, completing one moves on to the next seed
+    endless_seed: Option<u64>,
+    editor: Option<Editor>,
+    level_issues: Vec<LevelIssue>,
+    #[cfg(not(target_arch = "wasm32"))]
+    hot_reload: Option<HotReload>,
 }
 
 impl Game {
-    pub fn new(geng: &Rc<Geng>, assets: &Rc<Assets>) -> Self {
-        // let framebuffer = ugli::FramebufferRead::new_color(
-        //     geng.ugli(),
-        //     ugli::ColorAttachmentRead::Texture(&assets.level),
-        // );
-        // let data = framebuffer.read_color();
-        // let cell_size = 20;
-        // assert!(assets.level.size().x % cell_size == 0);
-        // assert!(assets.level.size().y % cell_size == 0);
-        // let mut values = Vec::new();
-        // for x in (0..assets.level.size().x).step_by(cell_size) {
-        //     let mut row = Vec::new();
-        //     for y in (0..assets.level.size().y).step_by(cell_size) {
-        //         let mut sum = 0.0;
-        //         for dx in 0..cell_size {
-        //             for dy in 0..cell_size {
-        //                 let color = data.get(x + dx, y + dy);
-        //                 let color: Color<f32> = color.convert();
-        //                 sum += color.a;
-        //             }
-        //         }
-        //         sum /= (cell_size * cell_size) as f32;
-        //         row.push(sum);
-        //     }
-        //     row.reverse();
-        //     values.push(row);
-        // }
-        // let mut level = Vec::new();
-        // let mut help = |p: [(Vec2<f32>, f32); 3]| {
-        //     let mut zeros = Vec::new();
-        //     for i in 0..3 {
-        //         let (p1, v1) = p[i];
-        //         let (p2, v2) = p[(i + 1) % 3];
-        //         if v1 == 0.5 && v2 == 0.5 {
-        //             zeros.push(p1);
-        //             zeros.push(p2);
-        //         }
-        //         if (v1 < 0.5 && v2 > 0.5) || (v1 > 0.5 && v2 < 0.5) {
-        //             // println!("{:?}", p);
-        //             // v1 + (v2 - v1) * t = 0.5
-        //             let t = (0.5 - v1) / (v2 - v1);
-        //             let p = p1 + (p2 - p1) * t;
-        //             // println!("{:?}", p);
-        //             zeros.push(p);
-        //         }
-        //     }
-        //     for p in &mut zeros {
-        //         *p /= 5.0;
-        //     }
-        //     if zeros.len() == 2 {
-        //         level.push([zeros[0], zeros[1]]);
-        //     }
-        // };
-        // let get = |x: usize, y: usize| (vec2(x as f32, y as f32), values[x][y]);
-        // for x in 0..values.len() {
-        //     for y in 0..values[x].len() {
-        //         if values[x][y] > 0.5 {
-        //             let tile_pos = vec2(x as f32, y as f32);
-        //             level.push([tile_pos, tile_pos + vec2(1.0, 0.0)]);
-        //             level.push([tile_pos, tile_pos + vec2(0.0, 1.0)]);
-        //             level.push([tile_pos + vec2(1.0, 1.0), tile_pos + vec2(1.0, 0.0)]);
-        //             level.push([tile_pos + vec2(1.0, 1.0), tile_pos + vec2(0.0, 1.0)]);
-        //         }
-        //     }
-        // }
-        let (level, tiles) = serde_json::from_str(&assets.level).unwrap();
-        Self {
+    pub fn new(
+        geng: &Rc<Geng>,
+        assets: &Rc<Assets>,
+        settings: AutoSave<Settings>,
+        level: usize,
+    ) -> Self {
+        let tile_renderer = TileRenderer::new(geng, &assets.shaders);
+        let debug_draw = settings.launch.debug_draw;
+        let rng = StdRng::seed_from_u64(
+            settings
+                .launch
+                .fixed_seed
+                .unwrap_or_else(|| global_rng().gen()),
+        );
+        let tuning = Tuning::parse(&assets.config).unwrap_or_else(|e| {
+            warn!("Invalid {}: {}", Tuning::PATH, e);
+            default()
+        });
+        let mut game = Self {
             time: 0.0,
             geng: geng.clone(),
             assets: assets.clone(),
             camera: Camera::new(30.0),
-            player: Player::new(),
-            // tiles: Vec::new(),
-            renderer: Renderer::new(geng),
-            line_renderer: LineRenderer::new(geng),
-            // level: Vec::new(),
-            level,
-            tiles,
+            themes: assets.themes.clone(),
+            tuning,
+            tuning_panel: None,
+            infinite_chain: false,
+            player: Player::new(vec2(0.0, 0.0), tuning),
+            level_data: default(),
+            renderer: Renderer::new(geng, &assets.shaders),
+            line_renderer: LineRenderer::new(geng, &assets.shaders),
+            tile_chunks: Vec::new(),
+            tile_renderer,
+            parallax_renderer: ParallaxRenderer::new(geng, &assets.shaders),
+            chain_renderer: ChainRenderer::new(geng, &assets.shaders),
+            light_renderer: LightRenderer::new(geng, &assets.shaders),
+            lighting: None,
+            palette: default(),
+            ambience: None,
+            post_process: PostProcess::new(geng, &assets.shaders),
+            effects: default(),
+            world_target: None,
+            ball_camera: None,
+            ball_target: None,
+            pixel_perfect: false,
+            platform_batches: Vec::new(),
+            transition_renderer: TransitionRenderer::new(geng, &assets.shaders),
+            transition: None,
+            transition_action: None,
+            level: CollisionWorld::new(&[]),
+            tiles: Vec::new(),
+            reading: None,
+            entities: Vec::new(),
+            collected: HashMap::new(),
+            particles: Vec::new(),
+            events: Vec::new(),
             spin: false,
-            // level_size: (assets.level.size() / cell_size).map(|x| x as f32),
+            spin_angle: 0.0,
+            aim_time: 0.0,
             save: None,
+            rewind: default(),
+            rewind_config: default(),
+            active_checkpoint: None,
+            debug: cfg!(debug_assertions),
+            debug_draw,
+            fps: 0.0,
+            substeps: 1,
+            level_index: 0,
+            level_state: LevelState::Playing,
+            level_time: 0.0,
+            timer_running: false,
+            run_time: 0.0,
+            ghost: None,
+            ghost_recording: Vec::new(),
+            race: None,
+            leaderboard: None,
             framebuffer_size: vec2(1, 1),
+            gravity: tuning.gravity,
+            time_scale: 1.0,
+            simulation: default(),
+            free_camera: false,
+            spectator: None,
+            align_camera: false,
+            last_mouse_pos: vec2(0.0, 0.0),
+            bullet_time: true,
+            bounds: None,
+            kill_margin: KILL_PLANE_MARGIN,
+            bullet_time_left: 0.0,
+            ball_trail: VecDeque::new(),
+            trail_length: TRAIL_LENGTH,
+            trail_fade: TRAIL_FADE_TIME,
+            accumulator: 0.0,
+            prev_character_pos: vec2(0.0, 0.0),
+            prev_ball_pos: vec2(0.0, 0.0),
+            console: Console::new(geng),
+            commands: Rc::new(Self::commands()),
+            audio: AudioPlayer {
+                volume: settings.sfx(),
+                ..default()
+            },
+            settings,
+            progress: SaveData::load(&assets.levels),
+            pause_action: default(),
+            results_action: default(),
+            stats: default(),
+            lifetime_stats: default(),
+            toasts: default(),
+            state_transition: None,
+            chain_loop: ChainLoop::new(assets),
+            prev_chain_len: 1.0,
+            chain_taut: false,
+            input: default(),
+            gamepad: Gamepad::new(),
+            pad_spin: false,
+            coop: false,
+            tick: 0,
+            input_recorder: None,
+            rng,
+            custom_level: None,
+            endless_seed: None,
+            editor: None,
+            level_issues: Vec::new(),
+            #[cfg(not(target_arch = "wasm32"))]
+            hot_reload: HotReload::new(&HotReload::default_paths())
+                .map_err(|e| warn!("Hot reloading disabled: {}", e))
+                .ok(),
+        };
+        game.start_level(level);
+        game
+    }
+    #[cfg(not(target_arch = "wasm32"))]
+    fn update_hot_reload(&mut self) {
+        let changed = match &self.hot_reload {
+            Some(hot_reload) => hot_reload.changed(),
+            None => return,
+        };
+        for path in changed {
+            match self.reload_file(&path) {
+                Ok(true) => info!("Reloaded {:?}", path),
+                Ok(false) => {}
+                Err(e) => warn!("Failed to reload {:?}: {}", path, e),
+            }
         }
     }
-}
-
-impl geng::State for Game {
-    fn update(&mut self, delta_time: f64) {
-        let delta_time = delta_time as f32;
-        self.time += delta_time;
-        if self.geng.window().is_key_pressed(geng::Key::S) {
-            self.player.chain_len = (self.player.chain_len - 2.0 * delta_time).max(0.05);
+    #[cfg(not(target_arch = "wasm32"))]
+    fn recompile_shaders(&mut self) -> anyhow::Result<()> {
+        let Programs {
+            renderer,
+            line,
+            line_thick,
+            tile,
+            parallax,
+            chain,
+            light,
+            post_process,
+            transition,
+        } = self.assets.shaders.recompile(&self.geng)?;
+        self.renderer.program = renderer;
+        self.line_renderer.program = line;
+        self.line_renderer.thick_program = line_thick;
+        self.tile_renderer.program = tile;
+        self.parallax_renderer.program = parallax;
+        self.chain_renderer.program = chain;
+        self.light_renderer.program = light;
+        self.post_process.program = post_process;
+        self.transition_renderer.program = transition;
+        Ok(())
+    }
+    #[cfg(not(target_arch = "wasm32"))]
+    fn reload_file(&mut self, path: &std::path::Path) -> anyhow::Result<bool> {
+        let name = |path: Option<&std::path::Path>| {
+            path.and_then(|path| path.file_stem())
+                .and_then(|name| name.to_str())
+                .unwrap_or("")
+                .to_owned()
+        };
+        let (dir, file) = (name(path.parent()), name(Some(path)));
+        match path.extension().and_then(|ext| ext.to_str()) {
+            Some("glsl") if dir == "shaders" => {
+                if !self
+                    .assets
+                    .shaders
+                    .set_source(&file, std::fs::read_to_string(path)?)
+                {
+                    return Ok(false);
+                }
+                self.recompile_shaders()?;
+            }
+            Some("png") => {
+                let assets = &self.assets;
+                let texture = |geng: &Geng| -> anyhow::Result<ugli::Texture> {
+                    Ok(ugli::Texture::from_image_image(
+                        geng.ugli(),
+                        image::open(path)?.to_rgba8(),
+                    ))
+                };
+                match (dir.as_str(), file.as_str()) {
+                    (_, "atlas") => *assets.atlas.texture.borrow_mut() = texture(&self.geng)?,
+                    ("background", layer) => {
+                        let target = match layer {
+                            "far" => &assets.background_far,
+                            "mid" => &assets.background_mid,
+                            "near" => &assets.background_near,
+                            _ => return Ok(false),
+                        };
+                        let mut texture = texture(&self.geng)?;
+                        texture.set_wrap_mode(ugli::WrapMode::Repeat);
+                        *target.borrow_mut() = texture;
+                    }
+                    (_, animation) => {
+                        let target = match animation {
+                            "player_idle" => &assets.player_idle,
+                            "player_walk" => &assets.player_walk,
+                            "player_spin" => &assets.player_spin,
+                            "player_fly" => &assets.player_fly,
+                            "player_reel" => &assets.player_reel,
+                            _ => return Ok(false),
+                        };
+                        *target.borrow_mut() =
+                            Animation::from_png(&self.geng, &std::fs::read(path)?)?;
+                    }
+                }
+            }
+            Some("json") => match (dir.as_str(), file.as_str()) {
+                (_, "themes") => {
+                    self.themes = Themes::parse(&std::fs::read_to_string(path)?)?;
+                }
+                (_, "config") => self.set_tuning(Tuning::parse(&std::fs::read_to_string(path)?)?),
+                ("levels", name) => {
+                    let index = match self.assets.levels.find(name) {
+                        Some(index) => index,
+                        None => return Ok(false),
+                    };
+                    let level = Level::from_json(&std::fs::read_to_string(path)?)?;
+                    *self.assets.levels.levels[index].1.borrow_mut() = level;
+                    if index == self.level_index {
+                        self.start_level(index);
+                    }
+                }
+                _ => return Ok(false),
+            },
+            _ => return Ok(false),
         }
-        const STEPS: usize = 100;
-        for _ in 0..STEPS {
-            self.player.update(&self.level, delta_time / STEPS as f32);
+        Ok(true)
+    }
+    fn player_tuning(&self) -> Tuning {
+        Tuning {
+            max_chain_len: if self.infinite_chain {
+                f32::INFINITY
+            } else {
+                self.tuning.max_chain_len
+            },
+            ..self.tuning
         }
-        if self.player.ball_in_hands {
-            self.player.ball.vel = Vec2::rotated(vec2(25.0, 0.0), self.time * 15.0);
+    }
+    fn set_tuning(&mut self, tuning: Tuning) {
+        self.tuning = tuning;
+        self.player.tuning = self.player_tuning();
+        self.gravity = tuning.gravity;
+    }
+    fn apply_tuning_action(&mut self, action: TuningAction) {
+        match action {
+            TuningAction::Changed(tuning) => self.set_tuning(tuning),
+            TuningAction::Teleport => {
+                self.player.release_ball();
+                self.player.character.pos = self.cursor_world_pos();
+                self.player.character.vel = vec2(0.0, 0.0);
+                self.prev_character_pos = self.player.character.pos;
+            }
+            TuningAction::ToggleInfiniteChain => {
+                self.infinite_chain = !self.infinite_chain;
+                self.set_tuning(self.tuning);
+            }
+            TuningAction::Save => {
+                #[cfg(not(target_arch = "wasm32"))]
+                match self.tuning.save() {
+                    Ok(()) => info!("Saved {}", Tuning::PATH),
+                    Err(e) => warn!("Failed to save {}: {}", Tuning::PATH, e),
+                }
+                #[cfg(target_arch = "wasm32")]
+                warn!("Saving {} is not supported on the web", Tuning::PATH);
+            }
         }
     }
-    fn draw(&mut self, framebuffer: &mut ugli::Framebuffer) {
-        self.framebuffer_size = framebuffer.size();
-        ugli::clear(framebuffer, Some(Color::rgb(0.8, 0.8, 1.0)), None);
-        // self.renderer.draw(
-        //     framebuffer,
-        //     &self.camera,
-        //     Mat4::scale(self.level_size.extend(1.0)),
-        //     &self.assets.level,
-        //     Color::WHITE,
-        // );
-        for &tile in &self.tiles {
-            self.renderer.draw(
-                framebuffer,
-                &self.camera,
-                Mat4::translate(tile.extend(0.0)),
-                &self.assets.block,
-                Color::WHITE,
-            );
+    #[cfg(not(target_arch = "wasm32"))]
+    fn reload_tuning(&mut self) {
+        match self.reload_file(std::path::Path::new(Tuning::PATH)) {
+            Ok(_) => info!("Reloaded {}", Tuning::PATH),
+            Err(e) => warn!("Failed to reload {}: {}", Tuning::PATH, e),
         }
-        if !self.player.ball_in_hands {
-            self.line_renderer.draw_strip(
-                framebuffer,
-                &self.camera,
-                Color::BLACK,
-                vec![self.player.character.pos, self.player.ball.pos],
-            );
-            let e1 = self.player.ball.pos - self.player.character.pos;
-            let e2 = Vec2::rotate_90(e1).normalize();
-            self.renderer.draw(
-                framebuffer,
-                &self.camera,
-                Mat4::translate(self.player.character.pos.extend(0.0))
-                    * Mat4::from_orts(e2.extend(0.0), e1.extend(0.0), vec3(0.0, 0.0, 1.0))
-                    * Mat4::translate(vec3(-1.0, 0.0, 0.0))
-                    * Mat4::scale(vec3(2.0, 1.0, 1.0)),
-                &self.assets.chain,
-                Color::WHITE,
-            );
+    }
+    pub fn with_input_recorder(mut self, recorder: InputRecorder) -> Self {
+        self.simulation.deterministic = true;
+        self.input_recorder = Some(recorder);
+        self
+    }
+    pub fn with_custom_level(mut self, level: Level) -> Self {
+        self.custom_level = Some(level.clone());
+        self.load_level(level);
+        if cfg!(debug_assertions) {
+            self.validate_level();
         }
-        self.renderer.draw(
-            framebuffer,
-            &self.camera,
-            self.player.character.matrix()
-                * Mat4::translate(vec3(-1.0, -1.0, 0.0))
-                * Mat4::scale_uniform(2.0),
-            &self.assets.player,
-            Color::WHITE,
-        );
-        if !self.spin && self.player.ball_in_hands {
-            self.player.ball.pos = self.player.character.pos + vec2(0.0, 1.0);
+        self
+    }
+    pub fn with_race(mut self, race: Race) -> Self {
+        self.race = Some(race);
+        self
+    }
+    fn back_to_lobby(&mut self) {
+        let race = match self.race.take() {
+            Some(race) => race,
+            None => return,
+        };
+        self.settings.save();
+        let id = race.id;
+        let (connection, players) = race.into_lobby();
+        self.state_transition = Some(SceneTransition::Replace(Box::new(
+            RaceLobby::new(
+                &self.geng,
+                &self.assets,
+                Settings::reload(&self.settings.launch),
+                Some(id),
+                connection,
+            )
+            .with_players(players),
+        )));
+    }
+    fn pause(&mut self) {
+        self.settings.save();
+        self.state_transition = Some(SceneTransition::Push(Box::new(Pause::new(
+            &self.geng,
+            &self.assets,
+            Settings::reload(&self.settings.launch),
+            self.theme(),
+            &self.pause_action,
+        ))));
+    }
+    fn show_results(&mut self) {
+        self.state_transition = Some(SceneTransition::Push(Box::new(LevelComplete::new(
+            &self.geng,
+            &self.assets,
+            self.theme(),
+            self.results_lines(),
+            self.leaderboard.take(),
+            &self.results_action,
+        ))));
+    }
+    pub fn with_coop(mut self) -> Self {
+        self.coop = true;
+        self.ball_camera = Some(Camera::new(self.camera.fov));
+        self
+    }
+    // In co-op the keyboard and mouse drive the character and the gamepad drives the ball
+    fn controls(&self, device: InputDevice, body: PlayerBody) -> bool {
+        !self.coop || (device == InputDevice::Gamepad) == (body == PlayerBody::Ball)
+    }
+    pub fn with_endless(mut self, seed: u64) -> Self {
+        self.endless_seed = Some(seed);
+        self.with_custom_level(generate_level(seed))
+    }
+    fn validate_level(&mut self) {
+        self.level_issues = validate_level(&self.level_data);
+        for issue in &self.level_issues {
+            warn!("Level issue at {:?}: {}", issue.pos, issue.message);
         }
-        self.renderer.draw(
-            framebuffer,
-            &self.camera,
-            self.player.ball.matrix()
-                * Mat4::translate(vec3(-1.0, -1.0, 0.0))
-                * Mat4::scale_uniform(2.0),
-            &self.assets.ball,
-            Color::WHITE,
-        );
-        // self.line_renderer.draw(
-        //     framebuffer,
-        //     &self.camera,
-        //     Color::WHITE,
-        //     self.level
-        //         .iter()
-        //         .flat_map(|&[p1, p2]| std::iter::once(p1).chain(std::iter::once(p2))),
-        // );
     }
-    fn handle_event(&mut self, event: geng::Event) {
-        match event {
-            // geng::Event::MouseDown {
-            //     position,
-            //     button: geng::MouseButton::Right,
-            // } => {
-            //     let world_pos = self.camera.screen_to_world(
-            //         self.framebuffer_size.map(|x| x as f32),
-            //         position.map(|x| x as f32),
-            //     );
-            //     let tile_pos = world_pos.map(|x| x.floor());
-            //     self.tiles.push(tile_pos);
-            //     self.level.push([tile_pos, tile_pos + vec2(1.0, 0.0)]);
-            //     self.level.push([tile_pos, tile_pos + vec2(0.0, 1.0)]);
-            //     self.level
-            //         .push([tile_pos + vec2(1.0, 1.0), tile_pos + vec2(1.0, 0.0)]);
-            //     self.level
-            //         .push([tile_pos + vec2(1.0, 1.0), tile_pos + vec2(0.0, 1.0)]);
-            // }
-            geng::Event::MouseDown {
-                button: geng::MouseButton::Left,
+    pub fn with_editor(mut self, path: std::path::PathBuf) -> Self {
+        self.editor = Some(Editor::new(path, &mut self.level_data));
+        self.free_camera = true;
+        self.reload_edited_level();
+        self
+    }
+    fn apply_editor_action(&mut self, action: EditorAction) {
+        let editor = self.editor.as_mut().unwrap();
+        let changed = match action {
+            EditorAction::Command(command) => editor.execute(&mut self.level_data, command),
+            EditorAction::Undo => editor.undo(&mut self.level_data),
+            EditorAction::Redo => editor.redo(&mut self.level_data),
+            EditorAction::Save => {
+                let path = &editor.path;
+                match std::fs::write(path, self.level_data.to_json()) {
+                    Ok(()) => self.console.print(format!("Saved {}", path.display())),
+                    Err(e) => self.console.print(format!("Failed to save: {}", e)),
+                }
+                self.validate_level();
+                if !self.level_issues.is_empty() {
+                    self.console
+                        .print(format!("{} level issues found", self.level_issues.len()));
+                }
+                return;
+            }
+            EditorAction::CloseInspector => {
+                editor.inspector = None;
+                false
+            }
+            EditorAction::CopyCode => {
+                let message = match copy_to_clipboard(&encode_level(&self.level_data)) {
+                    Ok(()) => "Level code copied".to_owned(),
+                    Err(e) => format!("Failed to copy level code: {}", e),
+                };
+                self.console.print(message);
+                false
+            }
+            EditorAction::PasteCode => {
+                match paste_from_clipboard().and_then(|code| decode_level(&code)) {
+                    Ok(level) => {
+                        let before = Box::new(self.level_data.clone());
+                        editor.execute(
+                            &mut self.level_data,
+                            EditorCommand::ReplaceLevel {
+                                before,
+                                after: Box::new(level),
+                            },
+                        )
+                    }
+                    Err(e) => {
+                        self.console
+                            .print(format!("Failed to paste level code: {}", e));
+                        false
+                    }
+                }
+            }
+            EditorAction::Playtest => {
+                editor.playtest = !editor.playtest;
+                self.free_camera = !editor.playtest;
+                if editor.playtest {
+                    self.load_level(self.level_data.clone());
+                    return;
+                }
+                true
+            }
+        };
+        if changed {
+            self.reload_edited_level();
+        }
+    }
+    // Rebuilds the level after an edit without moving the view
+    fn reload_edited_level(&mut self) {
+        let (center, fov) = (self.camera.center, self.camera.fov);
+        self.custom_level = Some(self.level_data.clone());
+        self.load_level(self.level_data.clone());
+        self.camera.center = center;
+        self.camera.target_position = center;
+        self.camera.fov = fov;
+        self.camera.target_fov = fov;
+        self.camera.bounds = None;
+    }
+    fn start_level(&mut self, index: usize) {
+        self.save_stats();
+        if let Some(level) = &self.custom_level {
+            self.load_level(level.clone());
+            self.restart_spectator();
+            return;
+        }
+        self.level_index = index;
+        let level = self.assets.levels.levels[index].1.borrow().clone();
+        self.load_level(level);
+        let name = &self.assets.levels.levels[index].0;
+        self.ghost = if self.settings.ghosts {
+            Some(AutoSave::load(&Ghost::path(name)))
+        } else {
+            None
+        };
+        self.restart_spectator();
+        if cfg!(debug_assertions) {
+            self.validate_level();
+        }
+    }
+    // Menu picks and whatever the level forces
+    fn mutators(&self) -> Mutators {
+        self.settings.mutators.union(self.level_data.mutators)
+    }
+    fn gravity(&self) -> f32 {
+        self.gravity * self.mutators().gravity_scale()
+    }
+    fn new_player(&self) -> Player {
+        let mut player = Player::new(self.level_data.spawn_pos(), self.player_tuning());
+        if self.mutators().double_ball {
+            player.add_second_ball();
+        }
+        player
+    }
+    fn load_level(&mut self, level: Level) {
+        self.level_data = level.clone();
+        let mut world = CollisionWorld::new(&optimize_segments(&level.segments));
+        let mut hazard_tiles: HashMap<Material, Vec<Vec2<f32>>> = HashMap::new();
+        for hazard in &level.hazards {
+            hazard_tiles
+                .entry(hazard.material)
+                .or_default()
+                .push(hazard.pos);
+        }
+        for (material, tiles) in hazard_tiles {
+            for segment in tile_outline(tiles) {
+                world.insert(Segment {
+                    material,
+                    ..segment
+                });
+            }
+        }
+        self.particles.clear();
+        self.entities = level_entities(&self.level_data, self.mutators());
+        insert_bodies(&mut self.entities, &mut world);
+        self.collected.insert(self.level_index, 0);
+        self.active_checkpoint = None;
+        self.reading = None;
+        crash_log::enter_level(
+            self.level_data
+                .metadata
+                .name
+                .as_deref()
+                .unwrap_or(&self.assets.levels.levels[self.level_index].0),
+        );
+        self.lighting = level.lighting;
+        self.palette = level.palette;
+        self.ambience = level.ambience;
+        self.level_state = LevelState::Playing;
+        self.stats = default();
+        self.level_time = 0.0;
+        self.timer_running = false;
+        self.ghost = None;
+        self.ghost_recording.clear();
+        self.leaderboard = None;
+        self.camera = Camera::new(30.0);
+        self.camera.mirror = self.mutators().mirror;
+        if let Some(camera) = &mut self.ball_camera {
+            *camera = Camera::new(30.0);
+            camera.mirror = self.camera.mirror;
+        }
+        self.bounds = world.bounds();
+        self.camera.bounds = self.bounds;
+        self.level = world;
+        self.tiles = level.tiles;
+        let block_uv = self.assets.atlas.uv("block");
+        self.tile_chunks = self.tile_renderer.chunks(&self.tiles, block_uv);
+        self.platform_batches = self
+            .level_data
+            .platforms
+            .iter()
+            .map(|platform| self.tile_renderer.batch(&platform.tiles, block_uv))
+            .collect();
+        self.player = self.new_player();
+        self.save = None;
+        self.rewind = default();
+        self.rewind_config = self.level_data.rewind.clone();
+    }
+    fn commands() -> CommandRegistry<Self> {
+        let mut commands = CommandRegistry::default();
+        commands.register("tp", "tp <x> <y>", |game: &mut Self, args| {
+            let pos = vec2(parse_arg(args, 0)?, parse_arg(args, 1)?);
+            game.player.character.pos = pos;
+            game.player.character.vel = vec2(0.0, 0.0);
+            Ok(format!("Teleported to {}", pos))
+        });
+        commands.register("load", "load <level>", |game: &mut Self, args| {
+            let name: String = parse_arg(args, 0)?;
+            if let Some(index) = game.assets.levels.find(&name) {
+                game.start_level(index);
+                return Ok(format!("Loaded {:?}", name));
+            }
+            #[cfg(target_arch = "wasm32")]
+            {
+                Err(anyhow!("Can not load {:?} in a web build", name))
+            }
+            #[cfg(not(target_arch = "wasm32"))]
+            {
+                let data = std::fs::read_to_string(format!("{}.json", name))?;
+                game.load_level(Level::from_json(&data)?);
+                Ok(format!("Loaded {:?}", name))
+            }
+        });
+        commands.register("save", "save <level>", |game: &mut Self, args| {
+            let name: String = parse_arg(args, 0)?;
+            #[cfg(target_arch = "wasm32")]
+            {
+                Err(anyhow!("Can not save {:?} in a web build", name))
+            }
+            #[cfg(not(target_arch = "wasm32"))]
+            {
+                std::fs::write(format!("{}.json", name), game.level_data.to_json())?;
+                Ok(format!("Saved {:?}", name))
+            }
+        });
+        commands.register("copy_level", "copy_level", |game: &mut Self, _| {
+            let code = encode_level(&game.level_data);
+            copy_to_clipboard(&code)?;
+            Ok(format!("Copied a {} character level code", code.len()))
+        });
+        commands.register(
+            "paste_level",
+            "paste_level [code]",
+            |game: &mut Self, args| {
+                let code = match args.first() {
+                    Some(code) => code.to_string(),
+                    None => paste_from_clipboard()?,
+                };
+                let level = decode_level(&code)?;
+                game.custom_level = Some(level.clone());
+                game.load_level(level);
+                Ok("Loaded level from code".to_owned())
+            },
+        );
+        commands.register(
+            "set",
+            "set gravity|bullet_time|kill_margin|deterministic|align_camera|trail_length|trail_fade|vignette|pixel_perfect|master_volume|music_volume|sfx_volume|show_timer|ghosts|leaderboard|leaderboard_url|player_name|fullscreen|vsync|max_fps|throttle_unfocused <value>",
+            |game: &mut Self, args| {
+                let name: String = parse_arg(args, 0)?;
+                match name.as_str() {
+                    "gravity" => game.gravity = parse_arg(args, 1)?,
+                    "bullet_time" => game.bullet_time = parse_arg(args, 1)?,
+                    "kill_margin" => game.kill_margin = parse_arg(args, 1)?,
+                    "deterministic" => game.simulation.deterministic = parse_arg(args, 1)?,
+                    "align_camera" => game.align_camera = parse_arg(args, 1)?,
+                    "trail_length" => game.trail_length = parse_arg(args, 1)?,
+                    "trail_fade" => game.trail_fade = parse_arg(args, 1)?,
+                    "vignette" => game.effects.vignette = parse_arg(args, 1)?,
+                    "pixel_perfect" => {
+                        game.pixel_perfect = parse_arg(args, 1)?;
+                        game.world_target = None;
+                        game.ball_target = None;
+                    }
+                    "master_volume" => game.settings.master_volume = parse_arg(args, 1)?,
+                    "music_volume" => game.settings.music_volume = parse_arg(args, 1)?,
+                    "sfx_volume" => game.settings.sfx_volume = parse_arg(args, 1)?,
+                    "show_timer" => game.settings.show_timer = parse_arg(args, 1)?,
+                    "ghosts" => game.settings.ghosts = parse_arg(args, 1)?,
+                    "leaderboard" => game.settings.leaderboard = parse_arg(args, 1)?,
+                    "leaderboard_url" => game.settings.leaderboard_url = parse_arg(args, 1)?,
+                    "player_name" => game.settings.player_name = parse_arg(args, 1)?,
+                    "letterbox" => game.settings.letterbox = parse_arg(args, 1)?,
+                    "fullscreen" => {
+                        game.settings.fullscreen = parse_arg(args, 1)?;
+                        game.geng.window().set_fullscreen(game.settings.fullscreen);
+                    }
+                    "vsync" => {
+                        game.settings.vsync = parse_arg(args, 1)?;
+                        return Ok(format!("vsync = {}, applies after restart", args[1]));
+                    }
+                    // 0 removes the cap
+                    "max_fps" => {
+                        game.settings.max_fps = Some(parse_arg(args, 1)?).filter(|&fps| fps > 0);
+                        game.settings.apply_frame_limit();
+                    }
+                    "throttle_unfocused" => {
+                        game.settings.throttle_unfocused = parse_arg(args, 1)?;
+                        game.settings.apply_frame_limit();
+                    }
+                    _ => anyhow::bail!("Unknown variable {:?}", name),
+                }
+                game.audio.volume = game.settings.sfx();
+                Ok(format!("{} = {}", name, args[1]))
+            },
+        );
+        commands.register("bind", "bind <action> <key>", |game: &mut Self, args| {
+            let action: String = parse_arg(args, 0)?;
+            let key: KeyBinding = parse_arg::<String>(args, 1)?.parse()?;
+            *game
+                .settings
+                .keys
+                .get_mut(&action)
+                .ok_or_else(|| anyhow!("Unknown action {:?}", action))? = key;
+            Ok(format!("{} = {:?}", action, key.0))
+        });
+        commands.register("give", "give <powerup>", |game: &mut Self, args| {
+            let name: String = parse_arg(args, 0)?;
+            match name.as_str() {
+                "sticky" => {
+                    let ball = &mut game.player.ball;
+                    ball.sticky = !ball.sticky;
+                    ball.anchor = None;
+                    Ok(format!(
+                        "Sticky ball {}",
+                        if ball.sticky { "on" } else { "off" }
+                    ))
+                }
+                _ => Err(anyhow!("Unknown powerup {:?}", name)),
+            }
+        });
+        commands.register("timescale", "timescale <value>", |game: &mut Self, args| {
+            game.time_scale = parse_arg::<f32>(args, 0)?.max(0.0);
+            Ok(format!("Time scale is {}", game.time_scale))
+        });
+        commands.register("debug", "debug", |game: &mut Self, _args| {
+            game.debug = !game.debug;
+            Ok(format!("Debug {}", if game.debug { "on" } else { "off" }))
+        });
+        commands.register("spectate", "spectate", |game: &mut Self, _args| {
+            let name = game.assets.levels.levels[game.level_index].0.clone();
+            let ghost = Ghost::clone(&AutoSave::load(&Ghost::path(&name)));
+            if ghost.frames.is_empty() {
+                return Err(anyhow!("No ghost recorded for {:?}", name));
+            }
+            game.start_level(game.level_index);
+            game.spectate(SpectatorSource::Ghost(ghost));
+            Ok(format!("Watching the best run of {:?}", name))
+        });
+        commands.register("noclip", "noclip", |game: &mut Self, _args| {
+            game.player.noclip = !game.player.noclip;
+            Ok(format!(
+                "Noclip {}",
+                if game.player.noclip { "on" } else { "off" }
+            ))
+        });
+        commands
+    }
+    fn fixed_update(&mut self, delta_time: f32) {
+        crash_log::tick();
+        self.replay_input();
+        self.prev_character_pos = self.player.character.pos;
+        self.prev_ball_pos = self.player.ball.pos;
+        if self.simulation.deterministic {
+            self.update_aim(delta_time);
+            self.update_level_state(delta_time);
+        }
+        if self.update_rewind() {
+            return;
+        }
+        update_physics(&mut self.entities, &mut self.level, delta_time);
+        if self.update_spectator(delta_time) {
+            self.update_hazards();
+            let env = Environment {
+                level: &self.level,
+                entities: &self.entities,
+                gravity: self.gravity(),
+            };
+            self.player.update_rope(&env, delta_time);
+            return;
+        }
+        if self.player.noclip && !self.console.visible {
+            let input = &self.input;
+            let mut direction = vec2(0.0, 0.0);
+            if input.key(geng::Key::Left) {
+                direction.x -= 1.0;
+            }
+            if input.key(geng::Key::Right) {
+                direction.x += 1.0;
+            }
+            if input.key(geng::Key::Down) {
+                direction.y -= 1.0;
+            }
+            if input.key(geng::Key::Up) {
+                direction.y += 1.0;
+            }
+            self.player.character.pos += direction * NOCLIP_SPEED * delta_time;
+        }
+        let keys_character = self.controls(InputDevice::KeyboardMouse, PlayerBody::Character);
+        let keys_ball = self.controls(InputDevice::KeyboardMouse, PlayerBody::Ball);
+        let pad_character = self.controls(InputDevice::Gamepad, PlayerBody::Character);
+        let pad_ball = self.controls(InputDevice::Gamepad, PlayerBody::Ball);
+        let keys = &self.settings.keys;
+        let input = &self.input;
+        if keys_ball && input.key(keys.shorten.0) && !self.console.visible {
+            self.player.chain_len = (self.player.chain_len - 2.0 * delta_time).max(0.05);
+        }
+        let pad = &self.gamepad;
+        let controls = !self.console.visible && !self.player.noclip;
+        self.player.walk = 0.0;
+        if controls {
+            if keys_character {
+                if input.key(keys.left.0) || input.key(geng::Key::Left) {
+                    self.player.walk -= 1.0;
+                }
+                if input.key(keys.right.0) || input.key(geng::Key::Right) {
+                    self.player.walk += 1.0;
+                }
+            }
+            // While spinning the stick aims instead
+            if let Some(stick) = pad.stick().filter(|_| pad_character && !self.pad_spin) {
+                self.player.walk = clamp(self.player.walk + stick.x, -1.0..=1.0);
+            }
+            // Input stays relative to the screen when the picture is mirrored
+            if self.camera.mirror {
+                self.player.walk = -self.player.walk;
+            }
+            if pad_ball && pad.held(PadButton::LeftTrigger) {
+                self.player
+                    .adjust_chain(self.tuning.chain_adjust_speed * delta_time);
+            }
+        }
+        self.player.jump_held = controls
+            && ((keys_character && input.key(keys.jump.0))
+                || (pad_character && pad.held(PadButton::South)));
+        self.player.reel = !self.console.visible
+            && ((keys_ball && (input.button(geng::MouseButton::Right) || input.key(keys.reel.0)))
+                || (pad_ball && pad.held(PadButton::RightTrigger)));
+        if self.player.ball_in_hands {
+            self.player.ball.vel = if self.spin {
+                Vec2::rotated(vec2(1.0, 0.0), self.spin_angle)
+            } else {
+                vec2(0.0, 1.0)
+            };
+        }
+        let env = Environment {
+            level: &self.level,
+            entities: &self.entities,
+            gravity: self.gravity(),
+        };
+        let was_in_hands = self.player.ball_in_hands;
+        let was_grounded = self.player.character.grounded;
+        self.player.chain_snapping =
+            self.settings.accessibility.chain_snapping && !self.mutators().unbreakable_chain;
+        self.substeps = self.player.step(&env, delta_time);
+        self.player.update_rope(&env, delta_time);
+        if was_in_hands && !self.player.ball_in_hands {
+            self.events.push(GameEvent::Throw {
+                pos: self.player.character.pos,
+            });
+        }
+        if !self.player.ball_in_hands {
+            let speed = self.player.ball.vel.len();
+            self.lifetime_stats.distance_swung += speed * delta_time;
+            if matches!(self.level_state, LevelState::Playing) {
+                self.stats.max_ball_speed = self.stats.max_ball_speed.max(speed);
+            }
+        }
+        if let Some(speed) = self.player.tumbled.take() {
+            self.events.push(GameEvent::Tumble {
+                speed,
+                pos: self.player.character.pos,
+            });
+        }
+        if let Some(dir) = self.player.zipped.take() {
+            self.events.push(GameEvent::Zip {
+                pos: self.player.character.pos,
+                dir,
+            });
+        }
+        if mem::take(&mut self.player.snapped) {
+            self.events.push(GameEvent::ChainSnapped {
+                pos: self.player.ball.pos,
+            });
+        }
+        let landing = mem::take(&mut self.player.character.impact);
+        if !was_grounded && self.player.character.grounded {
+            self.events.push(GameEvent::Landing {
+                speed: landing,
+                pos: self.player.character.pos,
+            });
+        }
+        let player = &self.player;
+        let chain_taut = player.chained()
+            && player.wrapped_chain_len() + (player.character.pos - player.chain_anchor()).len()
+                >= player.chain_len - EPS;
+        if chain_taut && !self.chain_taut {
+            self.events.push(GameEvent::ChainTaut {
+                pos: player.character.pos,
+            });
+        }
+        self.chain_taut = chain_taut;
+        self.player.character.impacts.clear();
+        if self.ghost.is_some()
+            && self.timer_running
+            && matches!(self.level_state, LevelState::Playing)
+        {
+            self.ghost_recording.push(GhostFrame {
+                time: self.level_time,
+                character: self.player.character.pos,
+                ball: self.player.ball.pos,
+                ball_size: self.player.ball.size,
+            });
+        }
+        for (point, speed) in mem::take(&mut self.player.ball.impacts) {
+            if speed > BREAK_SPEED {
+                self.break_block(point);
+            }
+        }
+        self.update_triggers();
+        self.update_hooks(delta_time);
+        self.update_hazards();
+        if let Some(bounds) = self.bounds {
+            let bounds = bounds.add_padding(self.kill_margin);
+            if !bounds.contains(self.player.character.pos) {
+                self.player.character.hurt = true;
+            } else if !self.player.ball_in_hands && !bounds.contains(self.player.ball.pos) {
+                self.player.release_ball();
+            }
+        }
+        let impact = mem::take(&mut self.player.ball.impact);
+        if impact > 0.0 {
+            self.events.push(GameEvent::Impact {
+                speed: impact,
+                size: self.player.ball.size,
+                pos: self.player.ball.pos,
+            });
+        }
+        if mem::take(&mut self.player.character.hurt) && !self.player.noclip {
+            if self.transition.is_none() {
+                self.events.push(GameEvent::Death);
+            }
+            self.start_transition(self.tuning.transitions.death, TransitionAction::Respawn);
+        }
+        self.update_hints();
+        self.update_signs();
+        self.process_events();
+        self.check_achievements(false);
+    }
+    fn complete_level(&mut self) {
+        let name = &self.assets.levels.levels[self.level_index].0;
+        let best = self
+            .progress
+            .record(name)
+            .and_then(|record| record.best_time)
+            .filter(|_| self.custom_level.is_none());
+        self.run_time += self.level_time;
+        self.level_state = LevelState::Complete {
+            time: self.level_time,
+            timer: 0.0,
+            split: self.run_time,
+            pb_delta: best.map(|best| self.level_time - best),
+        };
+        if let Some(ghost) = &mut self.ghost {
+            if ghost.beaten_by(self.level_time) {
+                **ghost = Ghost {
+                    time: Some(self.level_time),
+                    frames: mem::take(&mut self.ghost_recording),
+                };
+            }
+        }
+        self.events.push(GameEvent::LevelComplete {
+            time: self.level_time,
+        });
+        if self.custom_level.is_none() {
+            let collected = self.collected.get(&self.level_index).copied();
+            self.progress.complete(
+                &self.assets.levels,
+                self.level_index,
+                self.level_time,
+                collected.unwrap_or(0),
+            );
+            self.progress.settings = (*self.settings).clone();
+        }
+    }
+    fn cursor_world_pos(&self) -> Vec2<f32> {
+        if let Some(cursor) = self.input.replay_cursor {
+            return cursor;
+        }
+        self.camera.screen_to_world(
+            self.world_framebuffer_size(),
+            self.window_to_world_screen(self.input.mouse_pos),
+        )
+    }
+    fn replay_input(&mut self) {
+        let recorder = match &mut self.input_recorder {
+            Some(recorder) => recorder,
+            None => return,
+        };
+        if recorder.replaying() {
+            for (event, cursor) in recorder.due(self.tick) {
+                self.input.replay_cursor = Some(cursor);
+                if let Some(event) = event {
+                    self.handle_input(event);
+                }
+            }
+        } else {
+            let cursor = self.cursor_world_pos();
+            if let Some(recorder) = &mut self.input_recorder {
+                recorder.capture_cursor(self.tick, cursor);
+            }
+        }
+        self.tick += 1;
+    }
+    fn throw_velocity(&self) -> Vec2<f32> {
+        let direction = if self.pad_spin {
+            Vec2::rotated(vec2(1.0, 0.0), self.spin_angle)
+        } else {
+            self.cursor_world_pos() - self.player.character.pos
+        };
+        let direction = if direction.len() > EPS {
+            direction.normalize()
+        } else {
+            vec2(0.0, 1.0)
+        };
+        let tuning = &self.tuning;
+        direction
+            * (tuning.min_throw_speed
+                + (tuning.max_throw_speed - tuning.min_throw_speed) * self.throw_power())
+    }
+    fn bullet_time_scale(&self) -> f32 {
+        let t = 1.0 - self.bullet_time_left / BULLET_TIME_DURATION;
+        BULLET_TIME_SCALE + (1.0 - BULLET_TIME_SCALE) * t * t
+    }
+    fn throw_power(&self) -> f32 {
+        clamp(self.aim_time / self.tuning.throw_charge_time, 0.0..=1.0)
+    }
+    // Flies a copy of the ball with coarse steps, so the arc bends through force
+    // zones and water the same way the throw will, and ends where it first hits
+    fn aim_preview(&self) -> Vec<Vec2<f32>> {
+        let vel = self.throw_velocity();
+        let mut ball = self.player.ball.clone();
+        ball.pos = self.player.character.pos + vel.normalize() * self.tuning.ball_swing_distance;
+        ball.vel = vel;
+        ball.stand = false;
+        ball.anchor = None;
+        ball.impact = 0.0;
+        ball.hurt = false;
+        let env = Environment {
+            level: &self.level,
+            entities: &self.entities,
+            gravity: self.gravity(),
+        };
+        let mut points = vec![ball.pos];
+        let steps = (AIM_PREVIEW_TIME / AIM_PREVIEW_STEP) as usize;
+        for _ in 0..steps {
+            ball.update(&env, AIM_PREVIEW_STEP);
+            points.push(ball.pos);
+            if ball.impact > 0.0 || ball.stand || ball.hurt {
+                break;
+            }
+        }
+        points
+    }
+    fn execute_command(&mut self, line: &str) {
+        let commands = self.commands.clone();
+        match commands.execute(self, line) {
+            Ok(output) => {
+                if !output.is_empty() {
+                    self.console.print(output);
+                }
+            }
+            Err(e) => self.console.print(format!("Error: {:#}", e)),
+        }
+    }
+    fn update_chain_loop(&mut self, delta_time: f32) {
+        let player = &self.player;
+        let angular_speed = if player.ball_in_hands {
+            if self.spin {
+                self.throw_power() * self.tuning.max_spin_speed
+            } else {
+                0.0
+            }
+        } else if player.chain_snapped {
+            0.0
+        } else {
+            let offset = player.ball.pos - player.chain_anchor();
+            let vel = player.ball.vel - player.character.vel;
+            if offset.len() > EPS {
+                Vec2::skew(offset, vel).abs() / offset.len().powi(2)
+            } else {
+                0.0
+            }
+        };
+        let reel_speed = if delta_time > 0.0 {
+            (player.chain_len - self.prev_chain_len).abs() / delta_time
+        } else {
+            0.0
+        };
+        self.prev_chain_len = player.chain_len;
+        let intensity = partial_max(
+            angular_speed / CHAIN_LOOP_SPIN_SPEED,
+            reel_speed / CHAIN_LOOP_REEL_SPEED,
+        );
+        self.chain_loop
+            .update(intensity, self.audio.volume, delta_time);
+    }
+    fn draw_zone(
+        &self,
+        framebuffer: &mut ugli::Framebuffer,
+        aabb: AABB<f32>,
+        flow: Vec2<f32>,
+        color: Color<f32>,
+    ) {
+        self.renderer
+            .draw_rect(framebuffer, &self.camera, aabb, color);
+        if flow.len() < EPS {
+            return;
+        }
+        let size = aabb.size();
+        let direction = flow.normalize();
+        let offset = flow * self.time;
+        let streaks = (size.x * size.y / 4.0).ceil() as usize;
+        for i in 0..streaks {
+            let seed = vec2(i as f32 * 0.618034, i as f32 * 0.381966 + 0.5);
+            let pos = seed.map(|x| x.fract()) * size + offset;
+            let pos = vec2(pos.x.rem_euclid(size.x), pos.y.rem_euclid(size.y));
+            let pos = aabb.bottom_left() + pos;
+            self.renderer.draw_rect(
+                framebuffer,
+                &self.camera,
+                AABB::from_corners(pos, pos + direction * 0.5).add_padding(0.05),
+                color,
+            );
+        }
+    }
+    fn draw_ui_text(
+        &self,
+        framebuffer: &mut ugli::Framebuffer,
+        text: &str,
+        pos: Vec2<f32>,
+        align: f32,
+        size: f32,
+        color: Color<f32>,
+    ) {
+        draw_ui_text(
+            framebuffer,
+            &self.assets.font,
+            text,
+            pos,
+            align,
+            size,
+            color,
+        );
+    }
+    fn next_medal(&self) -> Option<(Medal, f32)> {
+        if !matches!(self.level_state, LevelState::Playing) {
+            return None;
+        }
+        let medals = self.level_data.medals.as_ref()?;
+        Medal::ALL
+            .iter()
+            .map(|&medal| (medal, medals.time(medal)))
+            .find(|&(_, time)| self.level_time <= time)
+    }
+    fn results_lines(&self) -> Vec<String> {
+        let (time, split, pb_delta) = match self.level_state {
+            LevelState::Complete {
+                time,
+                split,
+                pb_delta,
+                ..
+            } => (time, split, pb_delta),
+            LevelState::Playing => return Vec::new(),
+        };
+        let mut lines = vec![match pb_delta {
+            Some(delta) => trf(
+                "results.time_best",
+                &[
+                    &format_time(time),
+                    &format_time(time - delta),
+                    &format_delta(delta),
+                ],
+            ),
+            None => trf("results.time", &[&format_time(time)]),
+        }];
+        let mut splits = vec![trf("results.split", &[&format_time(split)])];
+        if let Some(sum) = self.progress.sum_of_best(&self.assets.levels) {
+            splits.push(trf("results.sum_of_best", &[&format_time(sum)]));
+        }
+        lines.push(splits.join("  "));
+        if let Some(medals) = &self.level_data.medals {
+            lines.push(match medals.medal(time) {
+                Some(medal) => trf("results.medal", &[&medal.name()]),
+                None => trf(
+                    "results.no_medal",
+                    &[&Medal::Bronze.name(), &format_time(medals.bronze)],
+                ),
+            });
+        }
+        if self.collectible_count() != 0 {
+            lines.push(trf("hud.collected", &[&self.collected_text()]));
+        }
+        lines.push(trf(
+            "results.stats",
+            &[
+                &self.stats.throws,
+                &self.stats.deaths,
+                &format!("{:.1}", self.stats.max_ball_speed),
+            ],
+        ));
+        lines
+    }
+    fn collected_text(&self) -> String {
+        format!(
+            "{}/{}",
+            self.collected.get(&self.level_index).copied().unwrap_or(0),
+            self.collectible_count(),
+        )
+    }
+    fn break_block(&mut self, point: Vec2<f32>) {
+        let entity = match self.entities.iter_mut().find(|entity| {
+            matches!(entity.behavior, Behavior::Breakable)
+                && entity.body.is_some()
+                && AABB::pos_size(entity.pos - vec2(0.5, 0.5), vec2(1.0, 1.0))
+                    .add_padding(0.1)
+                    .contains(point)
+        }) {
+            Some(entity) => entity,
+            None => return,
+        };
+        if let Some(mut body) = entity.body.take() {
+            body.remove(&mut self.level);
+        }
+        entity.sprite = None;
+        self.events.push(GameEvent::BlockBroken { pos: entity.pos });
+    }
+    fn spawn_debris(&mut self, center: Vec2<f32>, color: Color<f32>) {
+        let rng = &mut self.rng;
+        for _ in 0..DEBRIS_PARTICLES {
+            self.particles.push(Particle {
+                pos: center + vec2(rng.gen_range(-0.5..0.5), rng.gen_range(-0.5..0.5)),
+                vel: Vec2::rotated(
+                    vec2(rng.gen_range(2.0..8.0), 0.0),
+                    rng.gen_range(0.0..2.0 * f32::PI),
+                ),
+                life: DEBRIS_LIFETIME,
+                color,
+            });
+        }
+    }
+    // Streaks left behind by a zip, thrown opposite to where it goes
+    fn spawn_zip_streaks(&mut self, pos: Vec2<f32>, dir: Vec2<f32>, color: Color<f32>) {
+        let rng = &mut self.rng;
+        for _ in 0..ZIP_PARTICLES {
+            self.particles.push(Particle {
+                pos: pos + vec2(rng.gen_range(-0.3..0.3), rng.gen_range(-0.3..0.3)),
+                vel: Vec2::rotated(-dir * rng.gen_range(4.0..12.0), rng.gen_range(-0.4..0.4)),
+                life: DEBRIS_LIFETIME * 0.5,
+                color,
+            });
+        }
+    }
+    fn update_trail(&mut self) {
+        let ball = &self.player.ball;
+        if self.player.ball_in_hands {
+            self.ball_trail.clear();
+            return;
+        }
+        if !ball.stand {
+            self.ball_trail.push_back((ball.pos, self.time));
+        }
+        while self.ball_trail.len() > self.trail_length {
+            self.ball_trail.pop_front();
+        }
+        while let Some(&(_, time)) = self.ball_trail.front() {
+            if self.time - time < self.trail_fade {
+                break;
+            }
+            self.ball_trail.pop_front();
+        }
+    }
+    fn update_particles(&mut self, delta_time: f32) {
+        let gravity = self.gravity();
+        for particle in &mut self.particles {
+            particle.vel.y -= gravity * delta_time;
+            particle.pos += particle.vel * delta_time;
+            particle.life -= delta_time;
+        }
+        self.particles.retain(|particle| particle.life > 0.0);
+    }
+    fn next_level(&mut self) {
+        if let Some(seed) = &mut self.endless_seed {
+            *seed += 1;
+            self.custom_level = Some(generate_level(*seed));
+        }
+        self.start_level((self.level_index + 1) % self.assets.levels.levels.len());
+    }
+    fn update_aim(&mut self, delta_time: f32) {
+        if self.spin {
+            self.aim_time += delta_time;
+            if !self.pad_spin {
+                self.spin_angle += self.throw_power() * self.tuning.max_spin_speed * delta_time;
+            } else if let Some(stick) = self.aim_stick() {
+                self.spin_angle = stick.arg();
+            }
+        }
+    }
+    fn throw_spin(&mut self) {
+        self.player.throw_vel = self.throw_velocity();
+        self.player.throw_spin = if self.pad_spin {
+            0.0
+        } else {
+            self.throw_power() * self.tuning.max_spin_speed
+        };
+        self.player.throw.press();
+        self.spin = false;
+        self.pad_spin = false;
+    }
+    fn aim_stick(&self) -> Option<Vec2<f32>> {
+        let stick = self.gamepad.stick()?;
+        Some(if self.camera.mirror {
+            vec2(-stick.x, stick.y)
+        } else {
+            stick
+        })
+    }
+    fn update_gamepad(&mut self) {
+        if let Some(recorder) = &self.input_recorder {
+            if recorder.replaying() {
+                return;
+            }
+        }
+        let events = self.gamepad.poll();
+        if self.console.visible {
+            return;
+        }
+        let character = self.controls(InputDevice::Gamepad, PlayerBody::Character);
+        let ball = self.controls(InputDevice::Gamepad, PlayerBody::Ball);
+        let toggle_spin = self.settings.accessibility.toggle_spin;
+        for PadEvent { button, pressed } in events {
+            match (button, pressed) {
+                (PadButton::Start, true) => self.pause(),
+                (PadButton::South, true) if character => {
+                    self.timer_running = true;
+                    self.player.jump.press();
+                }
+                (PadButton::West, true) if ball => self.player.release_ball(),
+                (PadButton::North, true) if ball => self.player.dash.press(),
+                (PadButton::East, true) if character => self.toggle_sign(),
+                (PadButton::RightTrigger2, true) if self.pad_spin && toggle_spin => {
+                    self.throw_spin()
+                }
+                (PadButton::RightTrigger2, true) if ball => {
+                    self.timer_running = true;
+                    self.spin = true;
+                    self.pad_spin = true;
+                    self.aim_time = 0.0;
+                    self.spin_angle = self.aim_stick().unwrap_or(vec2(0.0, 1.0)).arg();
+                }
+                (PadButton::RightTrigger2, false) if self.pad_spin && !toggle_spin => {
+                    self.throw_spin()
+                }
+                _ => {}
+            }
+        }
+    }
+    fn update_level_state(&mut self, delta_time: f32) {
+        match &mut self.level_state {
+            LevelState::Playing if self.timer_running => self.level_time += delta_time,
+            LevelState::Playing => {}
+            LevelState::Complete { timer, .. } => {
+                *timer += delta_time;
+                // Races stay on the results until the player heads back to the lobby
+                if *timer > LEVEL_COMPLETE_DELAY
+                    && self.race.is_none()
+                    && self.transition.is_none()
+                    && self.state_transition.is_none()
+                {
+                    self.show_results();
+                }
+            }
+        }
+    }
+    fn save_point(&mut self) {
+        self.save = Some(self.snapshot());
+    }
+    fn respawn(&mut self) {
+        match self.save.clone() {
+            Some(save) => self.restore(&save),
+            None => {
+                self.restore_world(&self.initial_world_state());
+                self.player = self.new_player();
+                self.prev_character_pos = self.player.character.pos;
+                self.prev_ball_pos = self.player.ball.pos;
+                self.ball_trail.clear();
+            }
+        }
+    }
+    fn start_transition(&mut self, kind: TransitionKind, action: TransitionAction) {
+        if self.transition.is_some() {
+            return;
+        }
+        self.transition = Some(ScreenTransition::new(kind, &self.tuning.transitions));
+        self.transition_action = Some(action);
+    }
+    fn update_transition(&mut self, delta_time: f32) {
+        let (covered, finished) = match &mut self.transition {
+            Some(transition) => (transition.update(delta_time), transition.finished()),
+            None => return,
+        };
+        if covered {
+            match self.transition_action.take() {
+                Some(TransitionAction::Respawn) => self.respawn(),
+                Some(TransitionAction::NextLevel) => self.next_level(),
+                None => {}
+            }
+        }
+        if finished {
+            self.transition = None;
+        }
+    }
+    fn draw_world(&self, framebuffer: &mut ugli::Framebuffer, camera: &Camera) {
+        let theme = self.theme();
+        let alpha = self.accumulator / FIXED_DELTA_TIME;
+        let character = self
+            .player
+            .character
+            .interpolate(self.prev_character_pos, alpha);
+        let mut ball = self.player.ball.interpolate(self.prev_ball_pos, alpha);
+        if self.player.ball_in_hands {
+            ball.pos = if self.spin {
+                character.pos
+                    + Vec2::rotated(vec2(1.0, 0.0), self.spin_angle)
+                        * self.tuning.ball_swing_distance
+            } else {
+                character.pos + vec2(0.0, 1.0)
+            };
+            if self.spin {
+                ball.angle = self.spin_angle;
+            }
+        }
+        ugli::clear(framebuffer, Some(theme.background.rgba()), None);
+        self.draw_ambience(framebuffer, camera);
+        for (layer, texture) in PARALLAX_LAYERS.iter().zip([
+            &self.assets.background_far.borrow(),
+            &self.assets.background_mid.borrow(),
+            &self.assets.background_near.borrow(),
+        ]) {
+            self.parallax_renderer
+                .draw(framebuffer, camera, layer, texture, theme.parallax.rgba());
+        }
+        // self.renderer.draw(
+        //     framebuffer,
+        //     &self.camera,
+        //     Mat4::scale(self.level_size.extend(1.0)),
+        //     &self.assets.level,
+        //     Color::WHITE,
+        // );
+        let view = camera.view_bounds(framebuffer.size().map(|x| x as f32));
+        let visible = |pos: Vec2<f32>| view.add_padding(CULL_MARGIN).contains(pos);
+        for chunk in self
+            .tile_chunks
+            .iter()
+            .filter(|chunk| chunk.bounds.intersects(&view))
+        {
+            self.tile_renderer.draw(
+                framebuffer,
+                camera,
+                chunk,
+                Mat4::identity(),
+                &self.assets.atlas.texture.borrow(),
+                theme.tiles.rgba(),
+            );
+        }
+        for particle in self
+            .particles
+            .iter()
+            .filter(|particle| visible(particle.pos))
+        {
+            let mut color = particle.color;
+            color.a *= particle.life / DEBRIS_LIFETIME;
+            self.renderer.draw_rect(
+                framebuffer,
+                camera,
+                AABB::pos_size(particle.pos, vec2(0.0, 0.0)).add_padding(0.1),
+                color,
+            );
+        }
+        for entity in &self.entities {
+            let aabb = match &entity.area {
+                Some(area) => area.aabb,
+                None => continue,
+            };
+            match entity.behavior {
+                Behavior::Water => {
+                    self.renderer
+                        .draw_rect(framebuffer, camera, aabb, theme.water.rgba());
+                }
+                Behavior::ForceZone { acceleration } => self.draw_zone(
+                    framebuffer,
+                    aabb,
+                    acceleration * FORCE_ZONE_STREAKS,
+                    theme.force_zone.rgba(),
+                ),
+                Behavior::GravityZone { direction } => self.draw_zone(
+                    framebuffer,
+                    aabb,
+                    direction.vec() * self.gravity() * FORCE_ZONE_STREAKS,
+                    theme.gravity_zone.rgba(),
+                ),
+                _ => {}
+            }
+        }
+        self.draw_sprites(framebuffer, camera, view, &theme);
+        self.draw_doors(framebuffer, camera, &theme);
+        for (_, segment) in self.level.segments().filter(|(_, segment)| segment.one_way) {
+            self.renderer.draw_bar(
+                framebuffer,
+                camera,
+                segment.p1,
+                segment.p2,
+                ONE_WAY_WIDTH,
+                &self.assets.atlas.texture.borrow(),
+                Some(self.assets.atlas.uv("block")),
+                theme.tiles.rgba(),
+            );
+        }
+        for entity in &self.entities {
+            let batch = match entity.behavior {
+                Behavior::Platform { batch } => &self.platform_batches[batch],
+                _ => continue,
+            };
+            let offset = entity.interpolated_pos(alpha);
+            if !batch.bounds.translate(offset).intersects(&view) {
+                continue;
+            }
+            self.tile_renderer.draw(
+                framebuffer,
+                camera,
+                batch,
+                Mat4::translate(offset.extend(0.0)),
+                &self.assets.atlas.texture.borrow(),
+                theme.tiles.rgba(),
+            );
+        }
+        if self.spin && self.player.ball_in_hands {
+            let preview = self.aim_preview();
+            let mut dots = Vec::new();
+            let mut until_dot = 0.0;
+            for (&a, &b) in preview.iter().zip(preview.iter().skip(1)) {
+                let len = (b - a).len();
+                let mut along = until_dot;
+                while along < len {
+                    dots.push(a + (b - a) * (along / len));
+                    along += AIM_PREVIEW_DOT_SPACING;
+                }
+                until_dot = along - len;
+            }
+            dots.extend(preview.last());
+            for pos in dots {
+                self.renderer.draw_sprite(
+                    framebuffer,
+                    camera,
+                    &self.assets.atlas.texture.borrow(),
+                    Some(self.assets.atlas.uv("ball")),
+                    pos,
+                    vec2(AIM_PREVIEW_DOT_SIZE, AIM_PREVIEW_DOT_SIZE),
+                    0.0,
+                    false,
+                    theme.chain_line.rgba(),
+                    vec2(0.5, 0.5),
+                );
+            }
+        }
+        if self.ball_trail.len() > 1 {
+            let color = theme.ball.rgba();
+            let time = self.time;
+            let fade = self.trail_fade;
+            self.line_renderer.draw_ribbon(
+                framebuffer,
+                camera,
+                Color::WHITE,
+                self.ball_trail.iter().map(|&(pos, created)| {
+                    let life = (1.0 - (time - created) / fade).max(0.0);
+                    LinePoint {
+                        pos,
+                        width: ball.size * 2.0 * life,
+                        color: Color {
+                            a: color.a * TRAIL_OPACITY * life,
+                            ..color
+                        },
+                    }
+                }),
+                LineJoin::Round,
+            );
+        }
+        if self.player.chained() {
+            let mut rope = self.player.rope.points.clone();
+            rope[0] = character.pos;
+            *rope.last_mut().unwrap() = ball.pos;
+            // Strain shows up in the middle of the chain first, thinning it and
+            // shifting it towards the hazard color
+            let strain = self.player.strain();
+            let (base, strained) = (theme.chain_line.rgba(), theme.hazard.rgba());
+            let last = (rope.len() - 1).max(1) as f32;
+            self.line_renderer.draw_ribbon(
+                framebuffer,
+                camera,
+                Color::WHITE,
+                rope.iter().enumerate().map(|(index, &pos)| {
+                    let middle = 1.0 - (index as f32 / last * 2.0 - 1.0).powi(2);
+                    let t = strain * middle;
+                    LinePoint {
+                        pos,
+                        width: CHAIN_LINE_WIDTH * (1.0 - t * 0.5),
+                        color: lerp_color(base, strained, t),
+                    }
+                }),
+                LineJoin::Round,
+            );
+            self.chain_renderer.draw(
+                framebuffer,
+                camera,
+                &rope,
+                vec2(
+                    CHAIN_WIDTH * (1.0 - strain * 0.3),
+                    CHAIN_LINK_LENGTH * (1.0 + strain * CHAIN_MAX_STRETCH),
+                ),
+                &self.assets.atlas.texture.borrow(),
+                self.assets.atlas.uv("chain"),
+                theme.chain.rgba(),
+            );
+        }
+        let ghost_color = Color {
+            a: GHOST_OPACITY,
+            ..theme.player.rgba()
+        };
+        if let Some(frame) = self
+            .ghost
+            .as_ref()
+            .filter(|_| self.timer_running)
+            .and_then(|ghost| ghost.sample(self.level_time))
+        {
+            self.draw_ghost(framebuffer, camera, &frame, character.size, ghost_color);
+        }
+        if let Some(race) = &self.race {
+            for frame in race.remote.values() {
+                self.draw_ghost(framebuffer, camera, frame, character.size, ghost_color);
+            }
+        }
+        self.draw_rewind_ghosts(framebuffer, camera, ghost_color);
+        self.renderer.draw_sprite(
+            framebuffer,
+            camera,
+            &self.player.animation.frame(&self.assets),
+            None,
+            character.pos,
+            vec2(character.size, character.size) * 2.0,
+            self.player.lean + self.player.tumble_angle,
+            self.player.animation.facing_left,
+            theme.player.rgba(),
+            vec2(0.5, 0.5),
+        );
+        self.renderer.draw_sprite(
+            framebuffer,
+            camera,
+            &self.assets.atlas.texture.borrow(),
+            Some(self.assets.atlas.uv("ball")),
+            ball.pos,
+            vec2(ball.size, ball.size) * 2.0,
+            ball.angle,
+            false,
+            if ball.sticky {
+                theme.pickup.rgba()
+            } else {
+                theme.ball.rgba()
+            },
+            vec2(0.5, 0.5),
+        );
+        if let Some(second) = &self.player.second_ball {
+            let second_pos = if self.player.ball_in_hands {
+                ball.pos
+            } else {
+                second.pos
+            };
+            self.chain_renderer.draw(
+                framebuffer,
+                camera,
+                &[ball.pos, second_pos],
+                vec2(CHAIN_WIDTH, CHAIN_LINK_LENGTH),
+                &self.assets.atlas.texture.borrow(),
+                self.assets.atlas.uv("chain"),
+                theme.chain.rgba(),
+            );
+            self.renderer.draw_sprite(
+                framebuffer,
+                camera,
+                &self.assets.atlas.texture.borrow(),
+                Some(self.assets.atlas.uv("ball")),
+                second_pos,
+                vec2(second.size, second.size) * 2.0,
+                second.angle,
+                false,
+                theme.ball.rgba(),
+                vec2(0.5, 0.5),
+            );
+        }
+        if self.debug_draw {
+            self.draw_debug(framebuffer, camera);
+        }
+        if self.show_level_issues() {
+            self.line_renderer.begin();
+            for pos in self.level_issues.iter().filter_map(|issue| issue.pos) {
+                self.line_renderer.add_lines(
+                    Color::rgb(1.0, 0.0, 0.0),
+                    [
+                        pos - vec2(0.5, 0.5),
+                        pos + vec2(0.5, 0.5),
+                        pos - vec2(0.5, -0.5),
+                        pos + vec2(0.5, -0.5),
+                    ],
+                );
+            }
+            self.line_renderer.flush(framebuffer, camera);
+        }
+        if let Some(editor) = self.editor.as_ref().filter(|editor| editor.editing()) {
+            editor.draw(
+                framebuffer,
+                &self.line_renderer,
+                camera,
+                &self.level_data,
+                self.cursor_world_pos(),
+            );
+        }
+    }
+    fn draw_ghost(
+        &self,
+        framebuffer: &mut ugli::Framebuffer,
+        camera: &Camera,
+        frame: &GhostFrame,
+        character_size: f32,
+        color: Color<f32>,
+    ) {
+        self.renderer.draw_sprite(
+            framebuffer,
+            camera,
+            &self.assets.player_idle.borrow()[0],
+            None,
+            frame.character,
+            vec2(character_size, character_size) * 2.0,
+            0.0,
+            false,
+            color,
+            vec2(0.5, 0.5),
+        );
+        self.renderer.draw_sprite(
+            framebuffer,
+            camera,
+            &self.assets.atlas.texture.borrow(),
+            Some(self.assets.atlas.uv("ball")),
+            frame.ball,
+            vec2(frame.ball_size, frame.ball_size) * 2.0,
+            0.0,
+            false,
+            color,
+            vec2(0.5, 0.5),
+        );
+    }
+    fn draw_debug(&self, framebuffer: &mut ugli::Framebuffer, camera: &Camera) {
+        self.line_renderer.begin();
+        for cell in self.level.cells() {
+            self.line_renderer.add_strip(
+                Color::rgba(1.0, 1.0, 1.0, 0.2),
+                [
+                    cell.bottom_left(),
+                    cell.bottom_right(),
+                    cell.top_right(),
+                    cell.top_left(),
+                    cell.bottom_left(),
+                ],
+            );
+        }
+        self.line_renderer.add_lines(
+            Color::rgb(0.0, 1.0, 0.0),
+            self.level
+                .segments()
+                .flat_map(|(_, segment)| [segment.p1, segment.p2]),
+        );
+        let player = &self.player;
+        for ball in [&player.character, &player.ball] {
+            if ball.stand {
+                let contact = ball.pos - ball.up * ball.size;
+                self.line_renderer
+                    .add_lines(Color::rgb(1.0, 0.0, 1.0), [contact, contact + ball.up]);
+            }
+            self.line_renderer.add_lines(
+                Color::rgb(1.0, 1.0, 0.0),
+                [ball.pos, ball.pos + ball.vel * DEBUG_VELOCITY_SCALE],
+            );
+        }
+        for zone in &self.level_data.camera_zones {
+            let aabb = zone.aabb;
+            self.line_renderer.add_strip(
+                Color::rgb(1.0, 0.5, 0.0),
+                [
+                    aabb.bottom_left(),
+                    aabb.bottom_right(),
+                    aabb.top_right(),
+                    aabb.top_left(),
+                    aabb.bottom_left(),
+                ],
+            );
+        }
+        if player.chained() {
+            let center = player.chain_anchor();
+            let radius = player.chain_len - player.wrapped_chain_len();
+            self.line_renderer.add_strip(
+                Color::rgb(0.0, 1.0, 1.0),
+                (0..=DEBUG_CIRCLE_SEGMENTS).map(|i| {
+                    let angle = i as f32 / DEBUG_CIRCLE_SEGMENTS as f32 * 2.0 * f32::PI;
+                    center + vec2(angle.cos(), angle.sin()) * radius
+                }),
+            );
+        }
+        self.line_renderer.flush(framebuffer, camera);
+    }
+    fn show_level_issues(&self) -> bool {
+        self.debug_draw || self.editor.as_ref().is_some_and(|editor| editor.editing())
+    }
+    fn theme(&self) -> Theme {
+        let theme = self.themes.level_theme(&self.palette);
+        if self.settings.accessibility.high_contrast {
+            theme.high_contrast()
+        } else {
+            theme
+        }
+    }
+    fn point_lights(&self, theme: &Theme) -> Vec<PointLight> {
+        let lighting = match &self.lighting {
+            Some(lighting) => lighting,
+            None => return Vec::new(),
+        };
+        let mut lights = Vec::new();
+        for light in &lighting.lights {
+            let anchors: Vec<Vec2<f32>> = match light.anchor {
+                LightAnchor::World => vec![vec2(0.0, 0.0)],
+                LightAnchor::Player => vec![self.player.character.pos],
+                LightAnchor::Goal => {
+                    self.entity_positions(|behavior| matches!(behavior, Behavior::Goal))
+                }
+                LightAnchor::Hazards => {
+                    self.entity_positions(|behavior| matches!(behavior, Behavior::HazardTile))
+                }
+            };
+            lights.extend(anchors.into_iter().map(|anchor| PointLight {
+                pos: anchor + light.pos,
+                radius: light.radius,
+                color: light.color.unwrap_or_else(|| theme.light.rgba()),
+            }));
+        }
+        lights
+    }
+    // Window areas of the views, side by side in split-screen co-op
+    fn view_areas(&self) -> Vec<AABB<f32>> {
+        let window = AABB::pos_size(vec2(0.0, 0.0), self.framebuffer_size.map(|x| x as f32));
+        if self.ball_camera.is_none() {
+            return vec![window];
+        }
+        let middle = window.center().x.floor();
+        vec![
+            AABB {
+                x_max: middle,
+                ..window
+            },
+            AABB {
+                x_min: middle,
+                ..window
+            },
+        ]
+    }
+    fn world_framebuffer_size(&self) -> Vec2<f32> {
+        if self.pixel_perfect {
+            LOW_RES_SIZE.map(|x| x as f32)
+        } else {
+            self.letterboxed(self.view_areas()[0]).size()
+        }
+    }
+    // Split-screen halves are narrow by design and keep their whole area
+    fn letterboxed(&self, area: AABB<f32>) -> AABB<f32> {
+        if !self.settings.letterbox || self.ball_camera.is_some() {
+            return area;
+        }
+        let aspect = area.width() / area.height();
+        let size = if aspect > MAX_VIEW_ASPECT {
+            vec2(area.height() * MAX_VIEW_ASPECT, area.height())
+        } else if aspect < MIN_VIEW_ASPECT {
+            vec2(area.width(), area.width() / MIN_VIEW_ASPECT)
+        } else {
+            return area;
+        };
+        let size = size.map(f32::floor);
+        AABB::pos_size(
+            area.bottom_left() + ((area.size() - size) / 2.0).map(f32::floor),
+            size,
+        )
+    }
+    // Where a view is shown in its area, integer scaled and letterboxed in pixel perfect mode
+    fn viewport_in(&self, area: AABB<f32>) -> AABB<f32> {
+        if !self.pixel_perfect {
+            return self.letterboxed(area);
+        }
+        let world_size = self.world_framebuffer_size();
+        let fit = partial_min(area.width() / world_size.x, area.height() / world_size.y);
+        let scale = if fit >= 1.0 { fit.floor() } else { fit };
+        let size = world_size * scale;
+        AABB::pos_size(
+            area.bottom_left() + ((area.size() - size) / 2.0).map(f32::floor),
+            size,
+        )
+    }
+    // The mouse player's view
+    fn world_viewport(&self) -> AABB<f32> {
+        self.viewport_in(self.view_areas()[0])
+    }
+    fn render_scene(&self, target: Option<ugli::Texture>, camera: &Camera) -> ugli::Texture {
+        let size = self.world_framebuffer_size().map(|x| x as usize);
+        let mut scene = match target {
+            Some(texture) if texture.size() == size => texture,
+            _ => {
+                let mut texture = ugli::Texture::new_uninitialized(self.geng.ugli(), size);
+                if self.pixel_perfect {
+                    texture.set_filter(ugli::Filter::Nearest);
+                }
+                texture
+            }
+        };
+        self.draw_world(
+            &mut ugli::Framebuffer::new_color(
+                self.geng.ugli(),
+                ugli::ColorAttachment::Texture(&mut scene),
+            ),
+            camera,
+        );
+        scene
+    }
+    fn window_to_world_screen(&self, pos: Vec2<f32>) -> Vec2<f32> {
+        let viewport = self.world_viewport();
+        let world_size = self.world_framebuffer_size();
+        let pos = pos - viewport.bottom_left();
+        vec2(
+            pos.x / viewport.width() * world_size.x,
+            pos.y / viewport.height() * world_size.y,
+        )
+    }
+    fn world_screen_to_window(&self, pos: Vec2<f32>) -> Vec2<f32> {
+        let viewport = self.world_viewport();
+        let world_size = self.world_framebuffer_size();
+        viewport.bottom_left()
+            + vec2(
+                pos.x / world_size.x * viewport.width(),
+                pos.y / world_size.y * viewport.height(),
+            )
+    }
+}
+
+impl geng::State for Game {
+    fn update(&mut self, delta_time: f64) {
+        #[cfg(not(target_arch = "wasm32"))]
+        self.update_hot_reload();
+        // The web canvas follows the page size, so this can change on any frame
+        self.framebuffer_size = self.geng.window().size();
+        self.update_gamepad();
+        let real_delta_time = delta_time as f32;
+        if self.editor.as_ref().is_some_and(|editor| editor.editing()) {
+            self.camera.update(real_delta_time);
+            return;
+        }
+        if real_delta_time > 0.0 {
+            self.fps += (1.0 / real_delta_time - self.fps) * FPS_SMOOTHING;
+        }
+        // geng sounds have no playback rate control, so audio isn't slowed down
+        let delta_time = real_delta_time
+            * self.time_scale
+            * self.settings.accessibility.game_speed
+            * self.bullet_time_scale();
+        let delta_time = self.spectator_delta_time(delta_time);
+        self.bullet_time_left = (self.bullet_time_left - real_delta_time).max(0.0);
+        self.time += delta_time;
+        if !self.simulation.deterministic {
+            self.update_aim(delta_time);
+            self.update_level_state(delta_time);
+        }
+        self.update_transition(delta_time);
+        self.update_particles(delta_time);
+        self.effects.update(real_delta_time);
+        self.toasts.update(real_delta_time);
+        self.update_animations(delta_time);
+        self.player
+            .update_animation(self.spin, self.spin_angle, delta_time);
+        self.accumulator += delta_time;
+        let mut steps = 0;
+        while self.accumulator >= FIXED_DELTA_TIME {
+            if steps == self.tuning.max_fixed_steps {
+                self.accumulator = 0.0;
+                break;
+            }
+            self.fixed_update(FIXED_DELTA_TIME);
+            self.accumulator -= FIXED_DELTA_TIME;
+            steps += 1;
+        }
+        self.update_trail();
+        if let Some(leaderboard) = &mut self.leaderboard {
+            leaderboard.poll();
+        }
+        if let Some(race) = &mut self.race {
+            let frame = GhostFrame {
+                time: self.level_time,
+                character: self.player.character.pos,
+                ball: self.player.ball.pos,
+                ball_size: self.player.ball.size,
+            };
+            race.update(frame, real_delta_time);
+        }
+        self.update_chain_loop(real_delta_time);
+        self.audio.listener = self.camera.center;
+        if self.free_camera {
+            self.camera.clear_zone();
+            self.camera.update(delta_time);
+            return;
+        }
+        let character = &self.player.character;
+        let ball = &self.player.ball;
+        let zone = self
+            .level_data
+            .camera_zones
+            .iter()
+            .filter(|zone| zone.aabb.contains(character.pos))
+            .max_by_key(|zone| zone.priority);
+        self.camera.set_zone(zone, delta_time);
+        self.camera.target_rotation = if self.align_camera {
+            character.up.arg() - f32::PI / 2.0
+        } else {
+            0.0
+        };
+        self.camera
+            .fit(&[character.pos, ball.pos], self.world_framebuffer_size());
+        self.camera.follow(
+            character.pos + (ball.pos - character.pos) * CAMERA_BALL_WEIGHT,
+            character.vel,
+            delta_time,
+        );
+        self.camera.clamp_to_bounds(self.world_framebuffer_size());
+        let framebuffer_size = self.world_framebuffer_size();
+        if let Some(camera) = &mut self.ball_camera {
+            camera.bounds = self.camera.bounds;
+            camera.target_rotation = self.camera.target_rotation;
+            camera.follow(ball.pos, ball.vel, delta_time);
+            camera.clamp_to_bounds(framebuffer_size);
+        }
+    }
+    fn draw(&mut self, framebuffer: &mut ugli::Framebuffer) {
+        self.framebuffer_size = framebuffer.size();
+        let theme = self.theme();
+        let size = self.world_framebuffer_size().map(|x| x as usize);
+        let lights = self.point_lights(&theme);
+        // Bars around letterboxed views
+        ugli::clear(framebuffer, Some(Color::BLACK), None);
+        let areas = self.view_areas();
+        for (index, &area) in areas.iter().enumerate() {
+            let (camera, target) = match index {
+                0 => (&self.camera, self.world_target.take()),
+                _ => (self.ball_camera.as_ref().unwrap(), self.ball_target.take()),
+            };
+            let scene = self.render_scene(target, camera);
+            let viewport = self.viewport_in(area);
+            let light_map = match &self.lighting {
+                Some(lighting) => Some(self.light_renderer.render(
+                    camera,
+                    size,
+                    lighting.ambient,
+                    &lights,
+                    &self.level,
+                )),
+                None => None,
+            };
+            self.post_process
+                .apply(framebuffer, &scene, light_map, viewport, &self.effects);
+            match index {
+                0 => self.world_target = Some(scene),
+                _ => self.ball_target = Some(scene),
+            }
+        }
+        self.draw_hints(framebuffer, &theme);
+        self.draw_signs(framebuffer, &theme);
+        let character = self
+            .player
+            .character
+            .interpolate(self.prev_character_pos, self.accumulator / FIXED_DELTA_TIME);
+        let ui_size = UiCamera::size(self.framebuffer_size.map(|x| x as f32));
+        if self.debug_draw {
+            self.draw_ui_text(
+                framebuffer,
+                &format!("FPS: {:.0}", self.fps),
+                vec2(ui_size.x - 20.0, 20.0),
+                1.0,
+                24.0,
+                Color::rgb(0.0, 1.0, 0.0),
+            );
+            self.draw_ui_text(
+                framebuffer,
+                &format!("Substeps: {}", self.substeps),
+                vec2(ui_size.x - 20.0, 50.0),
+                1.0,
+                24.0,
+                Color::rgb(0.0, 1.0, 0.0),
+            );
+        }
+        draw_hud(
+            framebuffer,
+            &self.assets.font,
+            &HudInfo {
+                level_name: self
+                    .level_data
+                    .metadata
+                    .name
+                    .as_deref()
+                    .unwrap_or(&self.assets.levels.levels[self.level_index].0),
+                time: self.level_time,
+                show_timer: self.settings.show_timer,
+                text_scale: self.settings.accessibility.text_scale(),
+                collected: if self.collectible_count() == 0 {
+                    None
+                } else {
+                    Some(self.collected_text())
+                },
+                next_medal: self.next_medal(),
+            },
+            theme.hud.rgba(),
+        );
+        self.draw_spectator(framebuffer, theme.hud.rgba());
+        let scale = UiCamera::scale(self.framebuffer_size.map(|x| x as f32));
+        if areas.len() > 1 {
+            for (area, label) in areas.iter().zip(["hud.character", "hud.ball"]) {
+                self.draw_ui_text(
+                    framebuffer,
+                    &tr(label),
+                    vec2(area.center().x / scale, 20.0),
+                    0.5,
+                    24.0,
+                    theme.hud.rgba(),
+                );
+            }
+        }
+        if self.spin && self.player.ball_in_hands {
+            // Shown in the view of whoever throws the ball
+            let center = areas.last().unwrap().center().x / scale;
+            let pos = vec2(center, 40.0) - CHARGE_METER_SIZE / 2.0;
+            let hud = theme.hud.rgba();
+            self.renderer.draw_ui_rect(
+                framebuffer,
+                AABB::pos_size(pos, CHARGE_METER_SIZE).add_padding(2.0),
+                Color {
+                    a: hud.a * 0.3,
+                    ..hud
+                },
+            );
+            self.renderer.draw_ui_rect(
+                framebuffer,
+                AABB::pos_size(
+                    pos,
+                    vec2(
+                        CHARGE_METER_SIZE.x * self.throw_power(),
+                        CHARGE_METER_SIZE.y,
+                    ),
+                ),
+                hud,
+            );
+            self.draw_ui_text(
+                framebuffer,
+                &tr("hud.power"),
+                pos + vec2(CHARGE_METER_SIZE.x / 2.0, CHARGE_METER_SIZE.y + 8.0),
+                0.5,
+                24.0,
+                hud,
+            );
+        }
+        // The results scene covers this once it shows up, races never get one
+        let results_shown = self.race.is_none()
+            && matches!(self.level_state, LevelState::Complete { timer, .. } if timer > LEVEL_COMPLETE_DELAY);
+        if let (
+            LevelState::Complete {
+                time,
+                split,
+                pb_delta,
+                ..
+            },
+            false,
+        ) = (self.level_state, results_shown)
+        {
+            self.draw_ui_text(
+                framebuffer,
+                &tr("results.title"),
+                ui_size / 2.0 + vec2(0.0, 40.0),
+                0.5,
+                64.0,
+                theme.goal.rgba(),
+            );
+            self.draw_ui_text(
+                framebuffer,
+                &if self.collectible_count() == 0 {
+                    trf("results.time", &[&format_time(time)])
+                } else {
+                    trf(
+                        "results.time_collected",
+                        &[&format_time(time), &self.collected_text()],
+                    )
+                },
+                ui_size / 2.0 - vec2(0.0, 20.0),
+                0.5,
+                40.0,
+                theme.goal.rgba(),
+            );
+            let mut details = vec![trf("results.split", &[&format_time(split)])];
+            if let Some(delta) = pb_delta {
+                details.push(trf("results.pb", &[&format_delta(delta)]));
+            }
+            if let Some(sum) = self.progress.sum_of_best(&self.assets.levels) {
+                details.push(trf("results.sum_of_best", &[&format_time(sum)]));
+            }
+            self.draw_ui_text(
+                framebuffer,
+                &details.join("  "),
+                ui_size / 2.0 - vec2(0.0, 70.0),
+                0.5,
+                28.0,
+                theme.goal.rgba(),
+            );
+            if let Some(leaderboard) = &self.leaderboard {
+                for (index, line) in leaderboard.lines().iter().enumerate() {
+                    self.draw_ui_text(
+                        framebuffer,
+                        line,
+                        ui_size / 2.0 - vec2(0.0, 120.0 + index as f32 * 26.0),
+                        0.5,
+                        22.0,
+                        theme.goal.rgba(),
+                    );
+                }
+            }
+        }
+        if let Some(race) = &self.race {
+            let mut lines = vec![tr("race.title")];
+            for (place, result) in race.results.iter().enumerate() {
+                lines.push(match result.time {
+                    Some(time) => format!("{}. {} {}", place + 1, result.name, format_time(time)),
+                    None => trf("race.racing", &[&result.name]),
+                });
+            }
+            if matches!(self.level_state, LevelState::Complete { .. }) {
+                lines.push(tr("race.back_to_lobby"));
+            }
+            for (index, line) in lines.iter().enumerate() {
+                self.draw_ui_text(
+                    framebuffer,
+                    line,
+                    vec2(ui_size.x - 20.0, ui_size.y - 100.0 - index as f32 * 28.0),
+                    1.0,
+                    24.0,
+                    theme.hud.rgba(),
+                );
+            }
+        }
+        if let Some(transition) = &self.transition {
+            let center = self
+                .camera
+                .world_to_screen(self.world_framebuffer_size(), character.pos);
+            let center = self.world_screen_to_window(center);
+            self.transition_renderer
+                .draw(framebuffer, transition, center, theme.transition.rgba());
+        }
+        if self.show_level_issues() {
+            let scale = UiCamera::scale(self.framebuffer_size.map(|x| x as f32));
+            let mut unplaced = 0;
+            for issue in &self.level_issues {
+                let pos = match issue.pos {
+                    Some(pos) => {
+                        let screen = self
+                            .camera
+                            .world_to_screen(self.world_framebuffer_size(), pos);
+                        self.world_screen_to_window(screen) / scale + vec2(0.0, 16.0)
+                    }
+                    None => {
+                        unplaced += 1;
+                        vec2(20.0, ui_size.y - 60.0 - unplaced as f32 * 24.0)
+                    }
+                };
+                let align = if issue.pos.is_some() { 0.5 } else { 0.0 };
+                self.draw_ui_text(
+                    framebuffer,
+                    &issue.message,
+                    pos,
+                    align,
+                    20.0,
+                    Color::rgb(1.0, 0.3, 0.3),
+                );
+            }
+        }
+        if let Some(editor) = &self.editor {
+            self.draw_ui_text(
+                framebuffer,
+                &editor.hints(),
+                vec2(ui_size.x / 2.0, ui_size.y - 30.0),
+                0.5,
+                20.0,
+                theme.hud.rgba(),
+            );
+            if let Some(inspector) = editor.inspector.as_ref().filter(|_| editor.editing()) {
+                inspector.draw(
+                    framebuffer,
+                    &self.renderer,
+                    &self.assets.font,
+                    &self.level_data,
+                    theme.hud.rgba(),
+                );
+            }
+        }
+        if let Some(panel) = &self.tuning_panel {
+            panel.draw(
+                framebuffer,
+                &self.renderer,
+                &self.assets.font,
+                &self.tuning,
+                self.infinite_chain,
+                theme.hud.rgba(),
+            );
+        }
+        self.toasts
+            .draw(framebuffer, &self.renderer, &self.assets.font, &theme);
+        self.console.draw(framebuffer);
+    }
+    fn handle_event(&mut self, event: geng::Event) {
+        if self.settings.handle_fullscreen_key(&self.geng, &event) {
+            return;
+        }
+        if self.spectator.is_some() && !self.console.visible {
+            if matches!(event, geng::Event::KeyDown { key: geng::Key::F1 }) {
+                self.console.toggle();
+            } else {
+                self.handle_spectator_input(event);
+            }
+            return;
+        }
+        let cursor = self.cursor_world_pos();
+        match &mut self.input_recorder {
+            Some(recorder) if recorder.replaying() => return,
+            Some(recorder) => recorder.capture(self.tick, &event, cursor),
+            None => {}
+        }
+        self.handle_input(event);
+    }
+}
+
+impl Scene for Game {
+    fn focus(&mut self) {
+        // Whatever was held or pressed while covered stays with the scene on top
+        self.input.keys.clear();
+        self.input.buttons.clear();
+        self.gamepad.poll();
+        if let Some(action) = self.pause_action.take() {
+            // The pause menu saved its changes, this copy would save over them when dropped
+            self.settings = Settings::reload(&self.settings.launch);
+            self.settings.save();
+            self.audio.volume = self.settings.sfx();
+            match action {
+                PauseAction::Resume => {}
+                PauseAction::Restart => self.start_level(self.level_index),
+                PauseAction::BackToMenu => self.state_transition = Some(SceneTransition::Pop),
+            }
+        }
+        match self.results_action.take() {
+            Some(ResultsAction::NextLevel) => self.start_transition(
+                self.tuning.transitions.level_change,
+                TransitionAction::NextLevel,
+            ),
+            Some(ResultsAction::Retry) => self.start_level(self.level_index),
+            None => {}
+        }
+    }
+    fn blur(&mut self) {
+        self.chain_loop.silence();
+        self.save_stats();
+    }
+    fn scene_transition(&mut self) -> Option<SceneTransition> {
+        self.state_transition.take()
+    }
+    fn presence(&self) -> Option<Presence> {
+        let editing = self.editor.as_ref().is_some_and(|editor| editor.editing());
+        let mode = if editing {
+            "presence.editing"
+        } else if self.race.is_some() {
+            "presence.racing"
+        } else if self.endless_seed.is_some() {
+            "presence.endless"
+        } else {
+            "presence.playing"
+        };
+        let level = self
+            .level_data
+            .metadata
+            .name
+            .as_deref()
+            .unwrap_or(&self.assets.levels.levels[self.level_index].0);
+        Some(Presence {
+            details: tr(mode),
+            state: Some(trf("hud.level", &[&level])),
+            elapsed: (!editing && self.timer_running).then_some(self.level_time),
+        })
+    }
+}
+
+impl Game {
+    fn handle_input(&mut self, event: geng::Event) {
+        self.input.handle_event(&event);
+        let framebuffer_size = self.framebuffer_size.map(|x| x as f32);
+        if let Some(panel) = self
+            .tuning_panel
+            .as_mut()
+            .filter(|panel| panel.captures(&event, framebuffer_size))
+        {
+            if let Some(action) = panel.handle_event(&event, &self.tuning, framebuffer_size) {
+                self.apply_tuning_action(action);
+            }
+            return;
+        }
+        let cursor = self.cursor_world_pos();
+        let console_visible = self.console.visible;
+        if let Some(editor) = self.editor.as_mut().filter(|_| !console_visible) {
+            if let Some(action) = editor.handle_event(&event, &self.input, &self.level_data, cursor)
+            {
+                self.apply_editor_action(action);
+                return;
+            }
+            // Only camera controls and the console get through while editing
+            if editor.editing()
+                && !matches!(
+                    event,
+                    geng::Event::Wheel { .. }
+                        | geng::Event::MouseMove { .. }
+                        | geng::Event::KeyDown {
+                            key: geng::Key::F1 | geng::Key::F3 | geng::Key::Escape
+                        }
+                )
+            {
+                return;
+            }
+        }
+        let gameplay_input = match event {
+            geng::Event::KeyDown { key } => !matches!(
+                key,
+                geng::Key::Escape | geng::Key::F1 | geng::Key::F2 | geng::Key::F3 | geng::Key::F11
+            ),
+            geng::Event::MouseDown { .. } => true,
+            _ => false,
+        };
+        if gameplay_input && !self.console.visible {
+            self.timer_running = true;
+        }
+        let character = self.controls(InputDevice::KeyboardMouse, PlayerBody::Character);
+        let ball = self.controls(InputDevice::KeyboardMouse, PlayerBody::Ball);
+        match event {
+            geng::Event::KeyDown {
+                key: geng::Key::Escape,
+            } if !self.console.visible => self.pause(),
+            geng::Event::KeyDown {
+                key: geng::Key::Enter,
+            } if self.race.is_some() && matches!(self.level_state, LevelState::Complete { .. }) => {
+                self.back_to_lobby()
+            }
+            geng::Event::MouseDown {
+                button: geng::MouseButton::Left,
+                ..
+            } if ball && self.spin && !self.pad_spin && self.settings.accessibility.toggle_spin => {
+                self.throw_spin()
+            }
+            geng::Event::MouseDown {
+                button: geng::MouseButton::Left,
                 ..
-            } => {
+            } if ball => {
                 self.spin = true;
+                self.pad_spin = false;
+                self.aim_time = 0.0;
+                self.spin_angle = (self.cursor_world_pos() - self.player.character.pos).arg();
             }
             geng::Event::MouseUp {
                 button: geng::MouseButton::Left,
                 ..
-            } => {
-                self.spin = false;
-                if self.player.ball_in_hands {
-                    self.player.ball_in_hands = false;
-                    // self.player.ball.pos = self.player.character.pos;
-                    self.player.ball.vel = Vec2::rotate_90(self.player.ball.vel);
-                    self.player.ball.stand = false;
-                    self.player.chain_len = 1.0;
+            } if ball && !self.settings.accessibility.toggle_spin => self.throw_spin(),
+            geng::Event::KeyDown { key: geng::Key::F1 } => self.console.toggle(),
+            geng::Event::KeyDown { key } if self.console.visible => {
+                if let Some(line) = self.console.handle_key(key) {
+                    self.execute_command(&line);
+                }
+            }
+            geng::Event::Wheel { delta } if self.free_camera => {
+                self.camera.zoom_at(
+                    self.world_framebuffer_size(),
+                    self.window_to_world_screen(self.input.mouse_pos),
+                    FREE_CAMERA_ZOOM.powf(-delta as f32),
+                );
+            }
+            geng::Event::Wheel { delta } if ball => {
+                self.player.adjust_chain(-delta as f32 * WHEEL_CHAIN_STEP);
+            }
+            geng::Event::MouseMove { position } => {
+                let position = position.map(|x| x as f32);
+                if self.free_camera && self.input.button(geng::MouseButton::Middle) {
+                    self.camera.pan(
+                        self.world_framebuffer_size(),
+                        self.window_to_world_screen(self.last_mouse_pos),
+                        self.window_to_world_screen(position),
+                    );
                 }
-                self.player.chain_len = 2.0;
+                self.last_mouse_pos = position;
+            }
+            geng::Event::KeyDown { key } if character && key == self.settings.keys.jump.0 => {
+                self.player.jump.press()
+            }
+            geng::Event::KeyDown { key } if character && key == self.settings.keys.interact.0 => {
+                self.toggle_sign()
+            }
+            geng::Event::KeyDown { key } if ball && key == self.settings.keys.dash.0 => {
+                self.player.dash.press()
+            }
+            geng::Event::KeyDown { key } if ball && key == self.settings.keys.release.0 => {
+                self.player.release_ball()
+            }
+            geng::Event::KeyDown { key } if key == self.settings.keys.respawn.0 => {
+                self.start_transition(self.tuning.transitions.respawn, TransitionAction::Respawn)
             }
             geng::Event::KeyDown { key } => match key {
-                geng::Key::W => {}
-                // geng::Key::Z => {
-                //     for _ in 0..4 {
-                //         self.level.pop();
-                //     }
-                //     self.tiles.pop();
-                // }
-                geng::Key::P => {
-                    self.save = Some(self.player.clone());
-                }
-                // geng::Key::S if self.geng.window().is_key_pressed(geng::Key::LCtrl) => {
-                //     serde_json::to_writer(
-                //         std::fs::File::create("level.json").unwrap(),
-                //         &(&self.level, &self.tiles),
-                //     )
-                //     .unwrap();
-                // }
-                geng::Key::L => {
-                    if let Some(save) = &self.save {
-                        self.player = save.clone();
+                geng::Key::Num1 | geng::Key::Num2 | geng::Key::Num3 | geng::Key::Num4
+                    if ball && self.player.ball_in_hands =>
+                {
+                    let index = match key {
+                        geng::Key::Num1 => 0,
+                        geng::Key::Num2 => 1,
+                        geng::Key::Num3 => 2,
+                        _ => 3,
+                    };
+                    self.player.set_ball_kind(BallKind::ALL[index]);
+                }
+                geng::Key::P if self.debug => self.save_point(),
+                geng::Key::F2 if self.debug => self.free_camera = !self.free_camera,
+                geng::Key::F3 => self.debug_draw = !self.debug_draw,
+                geng::Key::F4 if self.debug => {
+                    self.tuning_panel = match self.tuning_panel {
+                        Some(_) => None,
+                        None => Some(default()),
                     }
                 }
-                geng::Key::R => self.player = Player::new(),
+                #[cfg(not(target_arch = "wasm32"))]
+                geng::Key::F5 if self.debug => self.reload_tuning(),
+                geng::Key::L if self.debug => self
+                    .start_transition(self.tuning.transitions.respawn, TransitionAction::Respawn),
                 _ => {}
             },
             _ => {}
@@ -0,0 +1,126 @@
+use super::*;
+
+const SKY_BANDS: usize = 32;
+const CLOUD_SIZE: Vec2<f32> = vec2(180.0, 50.0);
+// How much the clouds follow the camera, far ones move less
+const CLOUD_PARALLAX: f32 = 20.0;
+const CLOUD_PUFFS: [(f32, f32, f32); 3] = [(-0.3, 0.0, 0.6), (0.0, 0.15, 0.8), (0.3, 0.0, 0.6)];
+
+pub(super) fn lerp_color(a: Color<f32>, b: Color<f32>, t: f32) -> Color<f32> {
+    Color::rgba(
+        a.r + (b.r - a.r) * t,
+        a.g + (b.g - a.g) * t,
+        a.b + (b.b - a.b) * t,
+        a.a + (b.a - a.a) * t,
+    )
+}
+
+// Stable pseudo random value in 0..1 so every cloud keeps its lane
+fn cloud_noise(index: usize, salt: f32) -> f32 {
+    ((index as f32 * 12.9898 + salt * 78.233).sin() * 43758.547)
+        .fract()
+        .abs()
+}
+
+struct AmbienceColors {
+    sky: Color<f32>,
+    horizon: Color<f32>,
+    clouds: Color<f32>,
+}
+
+impl Ambience {
+    fn sample(&self, time: f32, camera_x: f32) -> Option<AmbienceColors> {
+        let mut keyframes = self.keyframes.clone();
+        keyframes.sort_by(|a, b| a.at.partial_cmp(&b.at).unwrap());
+        let first = *keyframes.first()?;
+        let last = *keyframes.last()?;
+        let (from, to, t) = match self.driver {
+            AmbienceDriver::Time => {
+                let at = (time / self.cycle.max(EPS)).fract();
+                match keyframes.iter().position(|keyframe| keyframe.at > at) {
+                    Some(0) | None => {
+                        // Wraps from the last keyframe of the day to the first of the next
+                        let length = first.at + 1.0 - last.at;
+                        let passed = (at - last.at).rem_euclid(1.0);
+                        (last, first, passed / length.max(EPS))
+                    }
+                    Some(index) => {
+                        let (from, to) = (keyframes[index - 1], keyframes[index]);
+                        (from, to, (at - from.at) / (to.at - from.at).max(EPS))
+                    }
+                }
+            }
+            AmbienceDriver::CameraX => {
+                match keyframes.iter().position(|keyframe| keyframe.at > camera_x) {
+                    Some(0) => (first, first, 0.0),
+                    None => (last, last, 0.0),
+                    Some(index) => {
+                        let (from, to) = (keyframes[index - 1], keyframes[index]);
+                        (from, to, (camera_x - from.at) / (to.at - from.at).max(EPS))
+                    }
+                }
+            }
+        };
+        let t = clamp(t, 0.0..=1.0);
+        Some(AmbienceColors {
+            sky: lerp_color(from.sky.rgba(), to.sky.rgba(), t),
+            horizon: lerp_color(from.horizon.rgba(), to.horizon.rgba(), t),
+            clouds: lerp_color(from.clouds.rgba(), to.clouds.rgba(), t),
+        })
+    }
+}
+
+impl Game {
+    // Sky gradient and clouds behind the parallax layers, drawn in screen space
+    pub(super) fn draw_ambience(&self, framebuffer: &mut ugli::Framebuffer, camera: &Camera) {
+        let ambience = match &self.ambience {
+            Some(ambience) => ambience,
+            None => return,
+        };
+        let colors = match ambience.sample(self.time, camera.center.x) {
+            Some(colors) => colors,
+            None => return,
+        };
+        ugli::clear(framebuffer, Some(colors.sky), None);
+        let ui_size = UiCamera::size(framebuffer.size().map(|x| x as f32));
+        let band_height = ui_size.y / SKY_BANDS as f32;
+        for band in 0..SKY_BANDS {
+            let t = (band as f32 + 0.5) / SKY_BANDS as f32;
+            self.renderer.draw_ui_rect(
+                framebuffer,
+                AABB::pos_size(
+                    vec2(0.0, band as f32 * band_height),
+                    vec2(ui_size.x, band_height),
+                ),
+                lerp_color(colors.horizon, colors.sky, t),
+            );
+        }
+        let span = ui_size.x + CLOUD_SIZE.x * 2.0;
+        for index in 0..ambience.clouds {
+            let depth = 0.3 + 0.7 * cloud_noise(index, 1.0);
+            let x = (cloud_noise(index, 2.0) * span + self.time * ambience.cloud_speed * depth
+                - camera.center.x * CLOUD_PARALLAX * depth)
+                .rem_euclid(span)
+                - CLOUD_SIZE.x;
+            let y = ui_size.y * (0.55 + 0.4 * cloud_noise(index, 3.0));
+            let size = CLOUD_SIZE * (0.5 + depth);
+            for &(dx, dy, scale) in &CLOUD_PUFFS {
+                self.renderer.draw_sprite(
+                    framebuffer,
+                    &UiCamera,
+                    &self.assets.atlas.texture.borrow(),
+                    Some(self.assets.atlas.uv("ball")),
+                    vec2(x + dx * size.x, y + dy * size.y),
+                    vec2(size.x * scale * 0.6, size.y * scale * 1.6),
+                    0.0,
+                    false,
+                    Color {
+                        a: colors.clouds.a * depth,
+                        ..colors.clouds
+                    },
+                    vec2(0.5, 0.5),
+                );
+            }
+        }
+    }
+}
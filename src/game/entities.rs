@@ -0,0 +1,803 @@
+use super::*;
+
+// Closer than this a pulled ball latches on
+const HOOK_LATCH_DISTANCE: f32 = 0.3;
+// Pulled balls speed up to at least this, so slow throws still reach the hook
+const HOOK_MIN_SPEED: f32 = 8.0;
+const HOOK_PULSE_SPEED: f32 = 6.0;
+const HOOK_PULSE: f32 = 0.15;
+const TUTORIAL_FADE_SPEED: f32 = 3.0;
+
+// Level objects as plain component bags. Adding a new kind means a constructor
+// here and an arm in `Game::activate`, the physics, trigger, hazard, area,
+// animation and sprite systems below stay the same.
+#[derive(Clone)]
+pub struct Entity {
+    pub pos: Vec2<f32>,
+    pub behavior: Behavior,
+    pub trigger: Option<Trigger>,
+    pub sprite: Option<Sprite>,
+    pub body: Option<Body>,
+    pub path: Option<PlatformPath>,
+    pub hazard: Option<Hazard>,
+    pub animated: Option<Animated>,
+    pub area: Option<Area>,
+}
+
+#[derive(Debug, Clone)]
+pub enum Behavior {
+    Collect,
+    Pickup(PickupKind),
+    Checkpoint,
+    Goal,
+    Switch {
+        id: String,
+        on: bool,
+    },
+    Hook {
+        radius: f32,
+        strength: f32,
+    },
+    // Read with the interact key in `toggle_sign`, never triggered
+    Sign {
+        text: String,
+    },
+    // Shatters when the ball hits it hard, see `break_block`
+    Breakable,
+    // Drawn only, the level outline of all hazard tiles does the colliding
+    HazardTile,
+    // Slides from the entity position towards `span` away while closed
+    Door {
+        switch: String,
+        span: Vec2<f32>,
+    },
+    // Index of the tile batch drawn at the entity position
+    Platform {
+        batch: usize,
+    },
+    Enemy,
+    // Zones act on whatever is inside their area, see `Environment`
+    ForceZone {
+        acceleration: Vec2<f32>,
+    },
+    GravityZone {
+        direction: GravityDirection,
+    },
+    Water,
+    // Fades in while the character is in its area until the action is done
+    Hint {
+        text: String,
+        action: Option<HintAction>,
+        done: bool,
+    },
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum TriggerBy {
+    Character,
+    Ball,
+    Either,
+}
+
+#[derive(Clone)]
+pub struct Trigger {
+    pub radius: f32,
+    pub by: TriggerBy,
+    touching: bool,
+}
+
+// Segments in the collision world, relative to the entity and moved along with it
+#[derive(Clone)]
+pub struct Body {
+    pub outline: Vec<Segment>,
+    // Open doors keep their body out of the world
+    pub solid: bool,
+    pub vel: Vec2<f32>,
+    segment_ids: Vec<usize>,
+}
+
+// Follows a path at a steady speed, carrying its body along
+#[derive(Clone)]
+pub struct PlatformPath {
+    pub path: Vec<Vec2<f32>>,
+    pub mode: PathMode,
+    pub speed: f32,
+    pub distance: f32,
+    pub prev_pos: Vec2<f32>,
+}
+
+// Hurts the character on touch. The ball is pushed out of it, or defeats it
+// when hitting faster than ENEMY_DEFEAT_SPEED
+#[derive(Clone)]
+pub struct Hazard {
+    pub radius: f32,
+}
+
+// Eases towards the target at a fixed rate, like a door sliding open
+#[derive(Clone)]
+pub struct Animated {
+    pub value: f32,
+    pub target: f32,
+    pub speed: f32,
+}
+
+#[derive(Clone)]
+pub struct Area {
+    pub aabb: AABB<f32>,
+}
+
+#[derive(Clone)]
+pub struct Sprite {
+    pub uv: &'static str,
+    pub size: f32,
+    pub rotation: f32,
+    pub color: fn(&Theme) -> Color<f32>,
+    pub opacity: f32,
+    // Bobbing frequency, zero keeps the sprite still
+    pub bob: f32,
+}
+
+impl Trigger {
+    pub fn new(radius: f32, by: TriggerBy) -> Self {
+        Self {
+            radius,
+            by,
+            touching: false,
+        }
+    }
+}
+
+impl Body {
+    fn new(outline: Vec<Segment>) -> Self {
+        Self {
+            outline,
+            solid: true,
+            vel: vec2(0.0, 0.0),
+            segment_ids: Vec::new(),
+        }
+    }
+    pub fn remove(&mut self, world: &mut CollisionWorld) {
+        for id in self.segment_ids.drain(..) {
+            world.remove(id);
+        }
+    }
+    fn insert(&mut self, world: &mut CollisionWorld, pos: Vec2<f32>) {
+        self.remove(world);
+        if !self.solid {
+            return;
+        }
+        for segment in &self.outline {
+            self.segment_ids.push(world.insert(Segment {
+                p1: segment.p1 + pos,
+                p2: segment.p2 + pos,
+                vel: self.vel,
+                ..*segment
+            }));
+        }
+    }
+}
+
+impl PlatformPath {
+    fn new(path: &[Vec2<f32>], mode: PathMode, speed: f32) -> Self {
+        Self {
+            path: path.to_vec(),
+            mode,
+            speed,
+            distance: 0.0,
+            prev_pos: path_position(path, mode, 0.0),
+        }
+    }
+}
+
+impl Hazard {
+    fn collide(&self, pos: Vec2<f32>, ball: &Ball) -> Option<Collision> {
+        let delta = ball.pos - pos;
+        let penetration = self.radius + ball.size - delta.len();
+        if penetration > 0.0 && delta.len() > EPS {
+            Some(Collision {
+                normal: delta.normalize(),
+                penetration,
+            })
+        } else {
+            None
+        }
+    }
+}
+
+impl Sprite {
+    fn ball(radius: f32, color: fn(&Theme) -> Color<f32>) -> Self {
+        Self {
+            uv: "ball",
+            size: radius * 2.0,
+            rotation: 0.0,
+            color,
+            opacity: 1.0,
+            bob: 0.0,
+        }
+    }
+}
+
+impl Entity {
+    fn new(pos: Vec2<f32>, behavior: Behavior) -> Self {
+        Self {
+            pos,
+            behavior,
+            trigger: None,
+            sprite: None,
+            body: None,
+            path: None,
+            hazard: None,
+            animated: None,
+            area: None,
+        }
+    }
+    fn area(aabb: AABB<f32>, behavior: Behavior) -> Self {
+        Self {
+            area: Some(Area { aabb }),
+            ..Self::new(aabb.center(), behavior)
+        }
+    }
+    pub fn force_zone(zone: &ForceZone) -> Self {
+        let acceleration = zone.acceleration;
+        Self::area(zone.aabb, Behavior::ForceZone { acceleration })
+    }
+    pub fn gravity_zone(zone: &GravityZone) -> Self {
+        let direction = zone.direction;
+        Self::area(zone.aabb, Behavior::GravityZone { direction })
+    }
+    pub fn water(water: &WaterVolume) -> Self {
+        Self::area(water.aabb, Behavior::Water)
+    }
+    pub fn hint(hint: &TutorialHint) -> Self {
+        let behavior = Behavior::Hint {
+            text: hint.text.clone(),
+            action: hint.action,
+            done: false,
+        };
+        Self {
+            animated: Some(Animated {
+                value: 0.0,
+                target: 0.0,
+                speed: TUTORIAL_FADE_SPEED,
+            }),
+            ..Self::area(hint.aabb, behavior)
+        }
+    }
+    pub fn collectible(collectible: &Collectible) -> Self {
+        let (uv, size, rotation) = match collectible.kind {
+            CollectibleKind::Coin => ("ball", 2.0, 0.0),
+            CollectibleKind::Gem => ("block", 1.4, f32::PI / 4.0),
+        };
+        Self {
+            trigger: Some(Trigger::new(collectible.radius(), TriggerBy::Either)),
+            sprite: Some(Sprite {
+                uv,
+                size: collectible.radius() * size,
+                rotation,
+                bob: 3.0,
+                ..Sprite::ball(0.0, |theme| theme.collectible.rgba())
+            }),
+            ..Self::new(collectible.pos, Behavior::Collect)
+        }
+    }
+    pub fn pickup(pickup: &Pickup) -> Self {
+        Self {
+            trigger: Some(Trigger::new(Pickup::RADIUS, TriggerBy::Character)),
+            sprite: Some(Sprite {
+                bob: 2.0,
+                ..Sprite::ball(Pickup::RADIUS, |theme| theme.pickup.rgba())
+            }),
+            ..Self::new(pickup.pos, Behavior::Pickup(pickup.kind))
+        }
+    }
+    pub fn checkpoint(checkpoint: &Checkpoint) -> Self {
+        Self {
+            trigger: Some(Trigger::new(checkpoint.radius, TriggerBy::Character)),
+            sprite: Some(Sprite {
+                opacity: 0.4,
+                ..Sprite::ball(checkpoint.radius, |theme| theme.checkpoint.rgba())
+            }),
+            ..Self::new(checkpoint.pos, Behavior::Checkpoint)
+        }
+    }
+    pub fn goal(goal: &Goal) -> Self {
+        Self {
+            trigger: Some(Trigger::new(goal.radius, TriggerBy::Either)),
+            sprite: Some(Sprite::ball(goal.radius, |theme| theme.goal.rgba())),
+            ..Self::new(goal.pos, Behavior::Goal)
+        }
+    }
+    pub fn hook(hook: &HookPoint) -> Self {
+        let behavior = Behavior::Hook {
+            radius: hook.radius,
+            strength: hook.strength,
+        };
+        Self {
+            sprite: Some(Sprite {
+                uv: "chain",
+                size: HookPoint::SIZE * 2.0,
+                ..Sprite::ball(0.0, |theme| theme.highlight.rgba())
+            }),
+            ..Self::new(hook.pos, behavior)
+        }
+    }
+    pub fn switch(switch: &Switch) -> Self {
+        let behavior = Behavior::Switch {
+            id: switch.id.clone(),
+            on: false,
+        };
+        Self {
+            trigger: Some(Trigger::new(Switch::RADIUS, TriggerBy::Ball)),
+            sprite: Some(Sprite {
+                opacity: 0.5,
+                ..Sprite::ball(Switch::RADIUS, |theme| theme.switch.rgba())
+            }),
+            ..Self::new(switch.pos, behavior)
+        }
+    }
+    pub fn sign(sign: &Sign) -> Self {
+        let behavior = Behavior::Sign {
+            text: sign.text.clone(),
+        };
+        Self {
+            sprite: Some(Sprite {
+                uv: "block",
+                size: 0.8,
+                opacity: 0.8,
+                ..Sprite::ball(0.0, |theme| theme.highlight.rgba())
+            }),
+            ..Self::new(sign.pos, behavior)
+        }
+    }
+    // Tiles sit at their corner, the entity at their center
+    fn block(pos: Vec2<f32>, behavior: Behavior, color: fn(&Theme) -> Color<f32>) -> Self {
+        Self {
+            sprite: Some(Sprite {
+                uv: "block",
+                size: 1.0,
+                ..Sprite::ball(0.0, color)
+            }),
+            ..Self::new(pos + vec2(0.5, 0.5), behavior)
+        }
+    }
+    pub fn breakable(pos: Vec2<f32>) -> Self {
+        let outline = tile_segments(vec2(-0.5, -0.5))
+            .iter()
+            .map(|&segment| Segment {
+                material: Material::Breakable,
+                ..segment
+            })
+            .collect();
+        Self {
+            body: Some(Body::new(outline)),
+            ..Self::block(pos, Behavior::Breakable, |theme| theme.breakable.rgba())
+        }
+    }
+    pub fn hazard_tile(hazard: &HazardTile) -> Self {
+        Self::block(hazard.pos, Behavior::HazardTile, |theme| {
+            theme.hazard.rgba()
+        })
+    }
+    pub fn door(door: &Door) -> Self {
+        let span = door.p2 - door.p1;
+        let behavior = Behavior::Door {
+            switch: door.switch.clone(),
+            span,
+        };
+        Self {
+            body: Some(Body::new(vec![Segment::new(vec2(0.0, 0.0), span)])),
+            animated: Some(Animated {
+                value: 0.0,
+                target: 0.0,
+                speed: DOOR_SPEED,
+            }),
+            ..Self::new(door.p1, behavior)
+        }
+    }
+    pub fn platform(config: &PlatformConfig, batch: usize) -> Self {
+        let outline = tile_outline(config.tiles.iter().copied())
+            .into_iter()
+            .map(|segment| Segment {
+                material: config.material,
+                ..segment
+            })
+            .collect();
+        let path = PlatformPath::new(&config.path, config.mode, config.speed);
+        let pos = path.prev_pos;
+        Self {
+            body: Some(Body::new(outline)),
+            path: Some(path),
+            ..Self::new(pos, Behavior::Platform { batch })
+        }
+    }
+    pub fn enemy(config: &EnemyConfig) -> Self {
+        let path = PlatformPath::new(&config.path, config.mode, config.speed);
+        let pos = path.prev_pos;
+        Self {
+            hazard: Some(Hazard {
+                radius: config.radius,
+            }),
+            sprite: Some(Sprite {
+                uv: "player",
+                ..Sprite::ball(config.radius, |theme| theme.enemy.rgba())
+            }),
+            path: Some(path),
+            ..Self::new(pos, Behavior::Enemy)
+        }
+    }
+    // Where it is drawn between the last two fixed steps
+    pub fn interpolated_pos(&self, alpha: f32) -> Vec2<f32> {
+        match &self.path {
+            Some(path) => path.prev_pos + (self.pos - path.prev_pos) * alpha,
+            None => self.pos,
+        }
+    }
+}
+
+// Areas acting on the player, shared with the simulation and versus
+pub(super) fn zone_entities(level: &Level) -> impl Iterator<Item = Entity> + '_ {
+    level
+        .water
+        .iter()
+        .map(Entity::water)
+        .chain(level.force_zones.iter().map(Entity::force_zone))
+        .chain(level.gravity_zones.iter().map(Entity::gravity_zone))
+}
+
+pub(super) fn platform_entities(level: &Level) -> impl Iterator<Item = Entity> + '_ {
+    level
+        .platforms
+        .iter()
+        .enumerate()
+        .map(|(batch, config)| Entity::platform(config, batch))
+}
+
+pub(super) fn level_entities(level: &Level, mutators: Mutators) -> Vec<Entity> {
+    zone_entities(level)
+        .chain(platform_entities(level))
+        .chain(level.hints.iter().map(Entity::hint))
+        .chain(level.hazards.iter().map(Entity::hazard_tile))
+        .chain(level.breakables.iter().copied().map(Entity::breakable))
+        .chain(level.doors.iter().map(Entity::door))
+        .chain(level.enemies.iter().map(Entity::enemy))
+        .chain(level.goal.iter().map(Entity::goal))
+        .chain(level.collectibles.iter().map(Entity::collectible))
+        .chain(level.pickups.iter().map(Entity::pickup))
+        .chain(level.hooks.iter().map(Entity::hook))
+        .chain(level.switches.iter().map(Entity::switch))
+        .chain(level.signs.iter().map(Entity::sign))
+        .chain(
+            level
+                .checkpoints
+                .iter()
+                .filter(|_| !mutators.no_checkpoints)
+                .map(Entity::checkpoint),
+        )
+        .collect()
+}
+
+// Puts the bodies of freshly created or restored entities into the world
+pub(super) fn insert_bodies(entities: &mut [Entity], world: &mut CollisionWorld) {
+    for entity in entities {
+        if let Some(body) = &mut entity.body {
+            body.segment_ids.clear();
+            body.insert(world, entity.pos);
+        }
+    }
+}
+
+// Moves path followers one fixed step, bodies carry their velocity into the
+// world so whatever stands on them moves along
+pub(super) fn update_physics(entities: &mut [Entity], world: &mut CollisionWorld, delta_time: f32) {
+    for entity in entities {
+        let path = match &mut entity.path {
+            Some(path) => path,
+            None => continue,
+        };
+        path.prev_pos = entity.pos;
+        path.distance += path.speed * delta_time;
+        entity.pos = path_position(&path.path, path.mode, path.distance);
+        if let Some(body) = &mut entity.body {
+            body.vel = (entity.pos - path.prev_pos) / delta_time;
+            body.insert(world, entity.pos);
+        }
+    }
+}
+
+// Indices of entities whose trigger was entered this step
+fn entered_triggers(entities: &mut [Entity], player: &Player) -> Vec<usize> {
+    let touches = |trigger: &Trigger, pos: Vec2<f32>, ball: &Ball| {
+        (ball.pos - pos).len() < trigger.radius + ball.size
+    };
+    let mut entered = Vec::new();
+    for (index, entity) in entities.iter_mut().enumerate() {
+        let pos = entity.pos;
+        let trigger = match &mut entity.trigger {
+            Some(trigger) => trigger,
+            None => continue,
+        };
+        let by_character = touches(trigger, pos, &player.character);
+        let by_ball = !player.ball_in_hands && touches(trigger, pos, &player.ball);
+        let now_touching = match trigger.by {
+            TriggerBy::Character => by_character,
+            TriggerBy::Ball => by_ball,
+            TriggerBy::Either => by_character || by_ball,
+        };
+        if now_touching && !trigger.touching {
+            entered.push(index);
+        }
+        trigger.touching = now_touching;
+    }
+    entered
+}
+
+impl Game {
+    pub(super) fn update_triggers(&mut self) {
+        for index in entered_triggers(&mut self.entities, &self.player) {
+            self.activate(index);
+        }
+    }
+    fn activate(&mut self, index: usize) {
+        let entity = &mut self.entities[index];
+        let pos = entity.pos;
+        match &mut entity.behavior {
+            Behavior::Collect => {
+                entity.trigger = None;
+                entity.sprite = None;
+                *self.collected.entry(self.level_index).or_default() += 1;
+                self.events.push(GameEvent::Pickup { pos });
+            }
+            Behavior::Pickup(kind) => {
+                let player = &mut self.player;
+                match *kind {
+                    PickupKind::StickyBall => {
+                        player.ball.sticky = !player.ball.sticky;
+                        player.ball.anchor = None;
+                    }
+                    PickupKind::Ball(kind) => player.set_ball_kind(kind),
+                }
+                self.events.push(GameEvent::Pickup { pos });
+            }
+            Behavior::Checkpoint => {
+                if self.active_checkpoint == Some(index) {
+                    return;
+                }
+                if let Some(sprite) = &mut entity.sprite {
+                    sprite.opacity = 1.0;
+                }
+                if let Some(previous) = self.active_checkpoint.replace(index) {
+                    if let Some(sprite) = &mut self.entities[previous].sprite {
+                        sprite.opacity = 0.4;
+                    }
+                }
+                self.save_point();
+            }
+            Behavior::Switch { id, on } => {
+                *on = !*on;
+                let (id, on) = (id.clone(), *on);
+                if let Some(sprite) = &mut entity.sprite {
+                    sprite.opacity = if on { 1.0 } else { 0.5 };
+                }
+                self.toggle_doors(&id, on);
+            }
+            Behavior::Goal => {
+                if let LevelState::Playing = self.level_state {
+                    self.complete_level();
+                }
+            }
+            // Hooks pull continuously in `update_hooks` instead of on entering
+            Behavior::Hook { .. }
+            | Behavior::Sign { .. }
+            | Behavior::Breakable
+            | Behavior::HazardTile
+            | Behavior::Door { .. }
+            | Behavior::Platform { .. }
+            | Behavior::Enemy
+            | Behavior::ForceZone { .. }
+            | Behavior::GravityZone { .. }
+            | Behavior::Water
+            | Behavior::Hint { .. } => {}
+        }
+    }
+    // Swaps in entities from a snapshot, with their bodies put back into the world
+    pub(super) fn set_entities(&mut self, entities: Vec<Entity>) {
+        for body in self
+            .entities
+            .iter_mut()
+            .filter_map(|entity| entity.body.as_mut())
+        {
+            body.remove(&mut self.level);
+        }
+        self.entities = entities;
+        insert_bodies(&mut self.entities, &mut self.level);
+    }
+    pub(super) fn update_hazards(&mut self) {
+        let player = &mut self.player;
+        for entity in &mut self.entities {
+            let hazard = match &entity.hazard {
+                Some(hazard) => hazard,
+                None => continue,
+            };
+            if !player.ball_in_hands {
+                let ball = &mut player.ball;
+                if let Some(collision) = hazard.collide(entity.pos, ball) {
+                    if ball.vel.len() > ENEMY_DEFEAT_SPEED {
+                        entity.hazard = None;
+                        entity.sprite = None;
+                        self.events
+                            .push(GameEvent::EnemyDefeated { pos: entity.pos });
+                        continue;
+                    }
+                    ball.pos += collision.normal * collision.penetration;
+                    let relative_vel = Vec2::dot(collision.normal, ball.vel);
+                    if relative_vel < 0.0 {
+                        ball.vel -= collision.normal * relative_vel;
+                    }
+                }
+            }
+            if hazard.collide(entity.pos, &player.character).is_some() && !player.noclip {
+                player.character.hurt = true;
+            }
+        }
+    }
+    pub(super) fn entity_positions(&self, filter: impl Fn(&Behavior) -> bool) -> Vec<Vec2<f32>> {
+        self.entities
+            .iter()
+            .filter(|entity| filter(&entity.behavior))
+            .map(|entity| entity.pos)
+            .collect()
+    }
+    pub(super) fn update_animations(&mut self, delta_time: f32) {
+        for animated in self
+            .entities
+            .iter_mut()
+            .filter_map(|entity| entity.animated.as_mut())
+        {
+            let step = animated.speed * delta_time;
+            animated.value += clamp(animated.target - animated.value, -step..=step);
+        }
+    }
+    // The hook the flying ball is being pulled towards, if any
+    fn hook_in_range(&self) -> Option<(Vec2<f32>, f32)> {
+        let ball = &self.player.ball;
+        if !self.player.chained() || ball.anchor.is_some() || ball.stand {
+            return None;
+        }
+        self.entities
+            .iter()
+            .filter_map(|entity| match entity.behavior {
+                Behavior::Hook { radius, strength } => {
+                    let distance = (entity.pos - ball.pos).len();
+                    (distance < radius).then_some((entity.pos, strength, distance))
+                }
+                _ => None,
+            })
+            .min_by(|a, b| a.2.partial_cmp(&b.2).unwrap())
+            .map(|(pos, strength, _)| (pos, strength))
+    }
+    // Steers a thrown ball into a nearby hook and anchors it there
+    pub(super) fn update_hooks(&mut self, delta_time: f32) {
+        let (pos, strength) = match self.hook_in_range() {
+            Some(hook) => hook,
+            None => return,
+        };
+        let ball = &mut self.player.ball;
+        let offset = pos - ball.pos;
+        if offset.len() < HOOK_LATCH_DISTANCE {
+            ball.pos = pos;
+            ball.vel = vec2(0.0, 0.0);
+            ball.angular_vel = 0.0;
+            ball.anchor = Some(-ball.up);
+            ball.stand = true;
+            self.events.push(GameEvent::Hooked { pos });
+            return;
+        }
+        let speed = partial_max(ball.vel.len(), HOOK_MIN_SPEED);
+        let target = offset.normalize() * speed;
+        ball.vel += (target - ball.vel) * (strength * delta_time).min(1.0);
+    }
+    fn toggle_doors(&mut self, id: &str, open: bool) {
+        for entity in &mut self.entities {
+            if !matches!(&entity.behavior, Behavior::Door { switch, .. } if switch == id) {
+                continue;
+            }
+            if let Some(body) = &mut entity.body {
+                body.solid = !open;
+                body.insert(&mut self.level, entity.pos);
+            }
+            if let Some(animated) = &mut entity.animated {
+                animated.target = if open { 1.0 } else { 0.0 };
+            }
+        }
+    }
+    pub(super) fn collectible_count(&self) -> usize {
+        self.entities
+            .iter()
+            .filter(|entity| matches!(entity.behavior, Behavior::Collect))
+            .count()
+    }
+    pub(super) fn draw_doors(
+        &self,
+        framebuffer: &mut ugli::Framebuffer,
+        camera: &Camera,
+        theme: &Theme,
+    ) {
+        for entity in &self.entities {
+            let (span, animated) = match (&entity.behavior, &entity.animated) {
+                (Behavior::Door { span, .. }, Some(animated)) => (*span, animated),
+                _ => continue,
+            };
+            self.renderer.draw_bar(
+                framebuffer,
+                camera,
+                entity.pos,
+                entity.pos + span * (1.0 - animated.value),
+                DOOR_WIDTH,
+                &self.assets.atlas.texture.borrow(),
+                Some(self.assets.atlas.uv("block")),
+                theme.door.rgba(),
+            );
+        }
+    }
+    pub(super) fn draw_sprites(
+        &self,
+        framebuffer: &mut ugli::Framebuffer,
+        camera: &Camera,
+        view: AABB<f32>,
+        theme: &Theme,
+    ) {
+        let hook_target = self.hook_in_range().map(|(pos, _)| pos);
+        let alpha = self.accumulator / FIXED_DELTA_TIME;
+        for entity in &self.entities {
+            let sprite = match &entity.sprite {
+                Some(sprite) => sprite,
+                None => continue,
+            };
+            let pos = entity.interpolated_pos(alpha);
+            if !view.add_padding(CULL_MARGIN + sprite.size).contains(pos) {
+                continue;
+            }
+            let bob = if sprite.bob == 0.0 {
+                0.0
+            } else {
+                (self.time * sprite.bob + pos.x).sin() * 0.1
+            };
+            let mut color = (sprite.color)(theme);
+            color.a *= sprite.opacity;
+            let mut size = sprite.size;
+            if let Behavior::Hook { radius, .. } = entity.behavior {
+                if hook_target == Some(entity.pos) {
+                    let pulse = 1.0 + (self.time * HOOK_PULSE_SPEED).sin() * HOOK_PULSE;
+                    size *= pulse;
+                    self.renderer.draw_sprite(
+                        framebuffer,
+                        camera,
+                        &self.assets.atlas.texture.borrow(),
+                        Some(self.assets.atlas.uv("ball")),
+                        entity.pos,
+                        vec2(radius, radius) * 2.0 * pulse,
+                        0.0,
+                        false,
+                        Color {
+                            a: color.a * 0.15,
+                            ..color
+                        },
+                        vec2(0.5, 0.5),
+                    );
+                }
+            }
+            self.renderer.draw_sprite(
+                framebuffer,
+                camera,
+                &self.assets.atlas.texture.borrow(),
+                Some(self.assets.atlas.uv(sprite.uv)),
+                pos + vec2(0.0, bob),
+                vec2(size, size),
+                sprite.rotation,
+                false,
+                color,
+                vec2(0.5, 0.5),
+            );
+        }
+    }
+}
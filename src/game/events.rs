@@ -0,0 +1,179 @@
+use super::*;
+
+// Something that happened during a fixed step. Physics only reports these,
+// sounds, particles, shake and the rest of the feedback react to them.
+#[derive(Debug, Clone)]
+pub enum GameEvent {
+    Throw {
+        pos: Vec2<f32>,
+    },
+    Landing {
+        speed: f32,
+        pos: Vec2<f32>,
+    },
+    ChainTaut {
+        pos: Vec2<f32>,
+    },
+    Tumble {
+        speed: f32,
+        pos: Vec2<f32>,
+    },
+    ChainSnapped {
+        pos: Vec2<f32>,
+    },
+    Hooked {
+        pos: Vec2<f32>,
+    },
+    Zip {
+        pos: Vec2<f32>,
+        dir: Vec2<f32>,
+    },
+    Impact {
+        speed: f32,
+        size: f32,
+        pos: Vec2<f32>,
+    },
+    BlockBroken {
+        pos: Vec2<f32>,
+    },
+    EnemyDefeated {
+        pos: Vec2<f32>,
+    },
+    Pickup {
+        pos: Vec2<f32>,
+    },
+    Death,
+    LevelComplete {
+        time: f32,
+    },
+}
+
+impl Game {
+    pub(super) fn process_events(&mut self) {
+        for event in mem::take(&mut self.events) {
+            crash_log::record_event(&event);
+            self.react(event);
+        }
+    }
+    // Heard from the camera, muffled when a wall stands between the character and the sound
+    fn play_at(&self, event: SoundEvent, scale: f64, pos: Vec2<f32>) {
+        let occluded = self.level.blocked(self.player.character.pos, pos);
+        self.audio
+            .play_at(&self.assets, event, scale, pos, occluded);
+    }
+    fn play_sound_at(&self, sound: &geng::Sound, pos: Vec2<f32>) {
+        let occluded = self.level.blocked(self.player.character.pos, pos);
+        self.audio.play_sound_at(sound, pos, occluded);
+    }
+    fn react(&mut self, event: GameEvent) {
+        match event {
+            GameEvent::Throw { pos } => {
+                self.lifetime_stats.throws += 1;
+                if matches!(self.level_state, LevelState::Playing) {
+                    self.stats.throws += 1;
+                }
+                self.play_at(SoundEvent::Throw, 1.0, pos);
+                if self.bullet_time {
+                    self.bullet_time_left = BULLET_TIME_DURATION;
+                }
+            }
+            GameEvent::Landing { speed, pos } => {
+                if self.timer_running && matches!(self.level_state, LevelState::Playing) {
+                    self.stats.touched_ground = true;
+                }
+                if speed > LANDING_SOUND_SPEED {
+                    self.play_at(SoundEvent::Landing, 1.0, pos);
+                }
+            }
+            GameEvent::ChainTaut { pos } | GameEvent::Hooked { pos } => {
+                self.play_at(SoundEvent::ChainRattle, 1.0, pos)
+            }
+            GameEvent::ChainSnapped { pos } => {
+                self.play_at(SoundEvent::ChainRattle, 1.0, pos);
+                self.camera
+                    .add_shake(BREAK_SHAKE * self.settings.accessibility.effects_scale());
+            }
+            GameEvent::Zip { pos, dir } => {
+                self.play_at(SoundEvent::ChainRattle, 1.0, pos);
+                self.play_at(SoundEvent::Throw, 1.0, pos);
+                let accessibility = &self.settings.accessibility;
+                self.camera
+                    .kick(dir * ZIP_KICK * accessibility.effects_scale());
+                if !accessibility.reduced_effects {
+                    self.spawn_zip_streaks(pos, dir, self.theme().chain.rgba());
+                }
+            }
+            GameEvent::Tumble { speed, pos } => {
+                self.play_at(
+                    SoundEvent::Impact,
+                    (speed / IMPACT_SOUND_FULL_SPEED) as f64,
+                    pos,
+                );
+                let amount = (speed - TUMBLE_SPEED) * self.settings.accessibility.effects_scale();
+                self.camera.add_shake(amount * IMPACT_SHAKE);
+            }
+            GameEvent::Impact { speed, size, pos } => {
+                if speed > IMPACT_SOUND_SPEED {
+                    self.play_at(
+                        SoundEvent::Impact,
+                        (speed / IMPACT_SOUND_FULL_SPEED) as f64,
+                        pos,
+                    );
+                }
+                if speed > IMPACT_SHAKE_SPEED {
+                    let amount = (speed - IMPACT_SHAKE_SPEED) * size;
+                    let amount = amount * self.settings.accessibility.effects_scale();
+                    self.camera.add_shake(amount * IMPACT_SHAKE);
+                    self.effects.add_aberration(amount * IMPACT_ABERRATION);
+                }
+            }
+            GameEvent::BlockBroken { pos } => {
+                self.lifetime_stats.blocks_broken += 1;
+                self.spawn_debris(pos, self.theme().breakable.rgba());
+                self.play_sound_at(&self.assets.break_sound, pos);
+                self.camera
+                    .add_shake(BREAK_SHAKE * self.settings.accessibility.effects_scale());
+            }
+            GameEvent::EnemyDefeated { pos } => {
+                self.spawn_debris(pos, self.theme().enemy.rgba());
+                self.play_sound_at(&self.assets.break_sound, pos);
+            }
+            GameEvent::Pickup { pos } => self.play_at(SoundEvent::Pickup, 1.0, pos),
+            GameEvent::Death => {
+                self.lifetime_stats.deaths += 1;
+                if matches!(self.level_state, LevelState::Playing) {
+                    self.stats.deaths += 1;
+                }
+                self.audio.play(&self.assets, SoundEvent::Death);
+                let accessibility = &self.settings.accessibility;
+                self.camera
+                    .add_shake(DEATH_SHAKE * accessibility.effects_scale());
+                if !accessibility.reduced_effects {
+                    self.effects.flash(Color::WHITE);
+                }
+            }
+            GameEvent::LevelComplete { time } => {
+                if let Some(recorder) = &self.input_recorder {
+                    recorder.save();
+                }
+                if let Some(race) = &mut self.race {
+                    race.finish(time);
+                }
+                self.check_achievements(true);
+                self.save_stats();
+                let settings = &self.settings;
+                if self.custom_level.is_none()
+                    && settings.leaderboard
+                    && !settings.leaderboard_url.is_empty()
+                {
+                    self.leaderboard = Some(LeaderboardRequest::submit(
+                        &settings.leaderboard_url,
+                        &self.assets.levels.levels[self.level_index].0,
+                        &settings.player_name,
+                        time,
+                    ));
+                }
+            }
+        }
+    }
+}
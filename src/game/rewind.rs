@@ -0,0 +1,139 @@
+use super::*;
+
+// Snapshots are taken every few fixed ticks and played back at the same pace
+const REWIND_INTERVAL: usize = 6;
+const REWIND_GHOSTS: usize = 6;
+
+// Everything in the level that changes during play, so a restore can't leave
+// a puzzle half solved with the player back before it
+#[derive(Clone)]
+pub(super) struct WorldState {
+    entities: Vec<Entity>,
+    active_checkpoint: Option<usize>,
+    collected: usize,
+}
+
+#[derive(Clone)]
+pub(super) struct Snapshot {
+    player: Player,
+    camera_center: Vec2<f32>,
+    camera_fov: f32,
+    world: WorldState,
+}
+
+#[derive(Default)]
+pub(super) struct Rewind {
+    history: VecDeque<Snapshot>,
+    ticks: usize,
+    active: bool,
+}
+
+impl Game {
+    pub(super) fn snapshot(&self) -> Snapshot {
+        Snapshot {
+            player: self.player.clone(),
+            camera_center: self.camera.center,
+            camera_fov: self.camera.fov,
+            world: self.world_state(),
+        }
+    }
+    pub(super) fn world_state(&self) -> WorldState {
+        WorldState {
+            entities: self.entities.clone(),
+            active_checkpoint: self.active_checkpoint,
+            collected: self.collected.get(&self.level_index).copied().unwrap_or(0),
+        }
+    }
+    // The level as it was loaded, for deaths before any checkpoint
+    pub(super) fn initial_world_state(&self) -> WorldState {
+        WorldState {
+            entities: level_entities(&self.level_data, self.mutators()),
+            active_checkpoint: None,
+            collected: 0,
+        }
+    }
+    pub(super) fn restore_world(&mut self, world: &WorldState) {
+        self.set_entities(world.entities.clone());
+        self.active_checkpoint = world.active_checkpoint;
+        self.collected.insert(self.level_index, world.collected);
+    }
+    pub(super) fn restore(&mut self, snapshot: &Snapshot) {
+        self.player = snapshot.player.clone();
+        self.player.tuning = self.player_tuning();
+        self.camera.center = snapshot.camera_center;
+        self.camera.target_position = snapshot.camera_center;
+        self.camera.fov = snapshot.camera_fov;
+        self.restore_world(&snapshot.world);
+        self.prev_character_pos = self.player.character.pos;
+        self.prev_ball_pos = self.player.ball.pos;
+        self.ball_trail.clear();
+    }
+    fn rewind_capacity(&self) -> usize {
+        (self.rewind_config.seconds / (REWIND_INTERVAL as f32 * FIXED_DELTA_TIME)) as usize
+    }
+    fn rewind_held(&self) -> bool {
+        !self.console.visible
+            && self.race.is_none()
+            && matches!(self.level_state, LevelState::Playing)
+            && (self.input.key(self.settings.keys.rewind.0)
+                || self.gamepad.held(PadButton::LeftTrigger2))
+    }
+    // Records or plays back history, returns true while rewinding so the
+    // regular step gets skipped
+    pub(super) fn update_rewind(&mut self) -> bool {
+        let capacity = self.rewind_capacity();
+        if capacity == 0 {
+            self.rewind = default();
+            return false;
+        }
+        self.rewind.ticks += 1;
+        let step = self.rewind.ticks.is_multiple_of(REWIND_INTERVAL);
+        self.rewind.active = self.rewind_held() && !self.rewind.history.is_empty();
+        if self.rewind.active {
+            if step {
+                let snapshot = self.rewind.history.pop_back().unwrap();
+                self.restore(&snapshot);
+                if self.timer_running {
+                    self.level_time +=
+                        self.rewind_config.cost * REWIND_INTERVAL as f32 * FIXED_DELTA_TIME;
+                }
+            }
+            return true;
+        }
+        if step {
+            self.rewind.history.push_back(self.snapshot());
+            while self.rewind.history.len() > capacity {
+                self.rewind.history.pop_front();
+            }
+        }
+        false
+    }
+    // Where the rewind is headed, oldest first and fading in towards the present
+    pub(super) fn draw_rewind_ghosts(
+        &self,
+        framebuffer: &mut ugli::Framebuffer,
+        camera: &Camera,
+        color: Color<f32>,
+    ) {
+        if !self.rewind.active {
+            return;
+        }
+        let history = &self.rewind.history;
+        let step = (history.len() / REWIND_GHOSTS).max(1);
+        for (index, snapshot) in history.iter().step_by(step).enumerate() {
+            let player = &snapshot.player;
+            let frame = GhostFrame {
+                time: 0.0,
+                character: player.character.pos,
+                ball: player.ball.pos,
+                ball_size: player.ball.size,
+            };
+            let fade = (index + 1) as f32 / (REWIND_GHOSTS + 1) as f32;
+            let color = Color {
+                a: color.a * fade,
+                ..color
+            };
+            self.draw_ghost(framebuffer, camera, &frame, player.character.size, color);
+        }
+    }
+}
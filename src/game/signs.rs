@@ -0,0 +1,96 @@
+use super::*;
+
+const SIGN_TEXT_SIZE: f32 = 22.0;
+const SIGN_PADDING: f32 = 12.0;
+// Corner size of the popup panel and the share of the block texture it samples
+const SIGN_BORDER: f32 = 8.0;
+const SIGN_UV_BORDER: f32 = 0.15;
+// World units between the sign and the bottom of its popup
+const SIGN_OFFSET: f32 = 1.0;
+
+impl Game {
+    // Closest sign the character can read from where it stands
+    fn sign_in_range(&self) -> Option<usize> {
+        let character = self.player.character.pos;
+        self.entities
+            .iter()
+            .enumerate()
+            .filter(|(_, entity)| matches!(entity.behavior, Behavior::Sign { .. }))
+            .map(|(index, entity)| (index, (entity.pos - character).len()))
+            .filter(|&(_, distance)| distance < Sign::RADIUS)
+            .min_by(|a, b| a.1.partial_cmp(&b.1).unwrap())
+            .map(|(index, _)| index)
+    }
+    pub(super) fn toggle_sign(&mut self) {
+        self.reading = match self.reading {
+            Some(_) => None,
+            None => self.sign_in_range(),
+        };
+    }
+    // Walking away closes the popup
+    pub(super) fn update_signs(&mut self) {
+        if self.reading.is_some() && self.reading != self.sign_in_range() {
+            self.reading = None;
+        }
+    }
+    pub(super) fn draw_signs(&self, framebuffer: &mut ugli::Framebuffer, theme: &Theme) {
+        let index = match self.sign_in_range() {
+            Some(index) => index,
+            None => return,
+        };
+        let entity = &self.entities[index];
+        let text = match &entity.behavior {
+            Behavior::Sign { text } => text,
+            _ => return,
+        };
+        let framebuffer_size = framebuffer.size().map(|x| x as f32);
+        let anchor = entity.pos + vec2(0.0, SIGN_OFFSET);
+        let window_pos = self.world_screen_to_window(
+            self.camera
+                .world_to_screen(self.world_framebuffer_size(), anchor),
+        );
+        let pos = UiCamera.screen_to_world(framebuffer_size, window_pos);
+        let text_size = SIGN_TEXT_SIZE * self.settings.accessibility.text_scale();
+        let color = theme.hud.rgba();
+        if self.reading != Some(index) {
+            let key = format!("{:?}", self.settings.keys.interact.0);
+            self.draw_ui_text(
+                framebuffer,
+                &trf("sign.prompt", &[&key]),
+                pos,
+                0.5,
+                text_size * 0.8,
+                color,
+            );
+            return;
+        }
+        let text = tr(text);
+        let lines: Vec<&str> = text.lines().collect();
+        // Measured in pixels so glyph rounding doesn't squeeze the panel
+        let scale = UiCamera::scale(framebuffer_size);
+        let width = lines
+            .iter()
+            .map(|line| self.assets.font.measure(line, text_size * scale).width() / scale)
+            .fold(0.0, f32::max);
+        let panel = AABB {
+            x_min: pos.x - width / 2.0 - SIGN_PADDING,
+            x_max: pos.x + width / 2.0 + SIGN_PADDING,
+            y_min: pos.y,
+            y_max: pos.y + lines.len() as f32 * text_size + SIGN_PADDING * 2.0,
+        };
+        self.renderer.draw_nine_slice(
+            framebuffer,
+            &UiCamera,
+            panel,
+            SIGN_BORDER,
+            &self.assets.atlas.texture.borrow(),
+            self.assets.atlas.uv("block"),
+            SIGN_UV_BORDER,
+            theme.tiles.rgba(),
+        );
+        for (line_index, line) in lines.iter().enumerate() {
+            let y = panel.y_max - SIGN_PADDING - (line_index + 1) as f32 * text_size;
+            self.draw_ui_text(framebuffer, line, vec2(pos.x, y), 0.5, text_size, color);
+        }
+    }
+}
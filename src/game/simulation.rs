@@ -0,0 +1,215 @@
+use super::*;
+
+// Player physics on a level without a window or GPU, so runs can be scripted and checked.
+// Static segments, moving platforms and hazard tiles collide, other entities are ignored.
+pub struct Simulation {
+    world: CollisionWorld,
+    entities: Vec<Entity>,
+    pub gravity: f32,
+    player: Player,
+    pub time: f32,
+}
+
+impl Simulation {
+    pub const DELTA_TIME: f32 = FIXED_DELTA_TIME;
+    pub fn new(level: &Level) -> Self {
+        Self::with_tuning(level, default())
+    }
+    pub fn with_tuning(level: &Level, tuning: Tuning) -> Self {
+        let mut world = CollisionWorld::new(&optimize_segments(&level.segments));
+        let mut entities: Vec<Entity> = zone_entities(level)
+            .chain(platform_entities(level))
+            .collect();
+        insert_bodies(&mut entities, &mut world);
+        for hazard in &level.hazards {
+            for segment in tile_segments(hazard.pos) {
+                world.insert(Segment {
+                    material: hazard.material,
+                    ..segment
+                });
+            }
+        }
+        Self {
+            world,
+            entities,
+            gravity: tuning.gravity,
+            player: Player::new(level.spawn_pos(), tuning),
+            time: 0.0,
+        }
+    }
+    pub fn character_pos(&self) -> Vec2<f32> {
+        self.player.character.pos
+    }
+    pub fn ball_pos(&self) -> Vec2<f32> {
+        self.player.ball.pos
+    }
+    pub fn ball_in_hands(&self) -> bool {
+        self.player.ball_in_hands
+    }
+    pub fn character_grounded(&self) -> bool {
+        self.player.character.grounded
+    }
+    pub fn ball_resting(&self) -> bool {
+        self.player.ball.stand
+    }
+    pub fn walk(&mut self, direction: f32) {
+        self.player.walk = clamp(direction, -1.0..=1.0);
+    }
+    pub fn jump(&mut self, held: bool) {
+        if held && !self.player.jump_held {
+            self.player.jump.press();
+        }
+        self.player.jump_held = held;
+    }
+    pub fn reel(&mut self, reel: bool) {
+        self.player.reel = reel;
+    }
+    pub fn throw(&mut self, velocity: Vec2<f32>) {
+        self.player.throw_vel = velocity;
+        self.player.throw_spin = 0.0;
+        self.player.throw.press();
+    }
+    pub fn release_ball(&mut self) {
+        self.player.release_ball();
+    }
+    pub fn dash(&mut self) {
+        self.player.dash.press();
+    }
+    pub fn step(&mut self) {
+        let delta_time = Self::DELTA_TIME;
+        update_physics(&mut self.entities, &mut self.world, delta_time);
+        if self.player.ball_in_hands {
+            self.player.ball.vel = vec2(0.0, 1.0);
+        }
+        let env = Environment {
+            level: &self.world,
+            entities: &self.entities,
+            gravity: self.gravity,
+        };
+        self.player.step(&env, delta_time);
+        self.player.character.impacts.clear();
+        self.player.ball.impacts.clear();
+        self.time += delta_time;
+    }
+    pub fn run(&mut self, duration: f32) {
+        let end = self.time + duration;
+        while self.time < end {
+            self.step();
+        }
+    }
+    // Steps until the condition holds, false if it never did within the duration
+    pub fn run_until(&mut self, duration: f32, condition: impl Fn(&Self) -> bool) -> bool {
+        let end = self.time + duration;
+        while self.time < end {
+            self.step();
+            if condition(self) {
+                return true;
+            }
+        }
+        false
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn level(segments: &[(Vec2<f32>, Vec2<f32>)], spawn: Vec2<f32>) -> Level {
+        Level {
+            spawn: Some(spawn),
+            segments: segments
+                .iter()
+                .map(|&(p1, p2)| Segment::new(p1, p2))
+                .collect(),
+            ..default()
+        }
+    }
+
+    fn floor() -> (Vec2<f32>, Vec2<f32>) {
+        (vec2(-50.0, 0.0), vec2(50.0, 0.0))
+    }
+
+    #[test]
+    fn character_lands_on_floor() {
+        let mut simulation = Simulation::new(&level(&[floor()], vec2(0.0, 5.0)));
+        assert!(simulation.run_until(2.0, |simulation| simulation.character_grounded()));
+        simulation.run(0.5);
+        assert!(simulation.character_grounded());
+        assert!((simulation.character_pos().y - 1.0).abs() < 0.05);
+    }
+
+    #[test]
+    fn walking_moves_character() {
+        let mut simulation = Simulation::new(&level(&[floor()], vec2(0.0, 2.0)));
+        simulation.run(0.5);
+        simulation.walk(1.0);
+        simulation.run(1.0);
+        assert!(
+            simulation.character_pos().x > 3.0,
+            "{:?}",
+            simulation.character_pos()
+        );
+    }
+
+    #[test]
+    fn jump_leaves_ground() {
+        let mut simulation = Simulation::new(&level(&[floor()], vec2(0.0, 2.0)));
+        simulation.run(0.5);
+        simulation.jump(true);
+        simulation.run(0.2);
+        assert!(simulation.character_pos().y > 2.0);
+        simulation.jump(false);
+        assert!(simulation.run_until(2.0, |simulation| simulation.character_grounded()));
+    }
+
+    #[test]
+    fn substeps_follow_speed() {
+        let mut player = Player::new(vec2(0.0, 0.0), default());
+        assert_eq!(player.substeps(Simulation::DELTA_TIME), 1);
+        // Half the character radius per substep at 240 ticks a second
+        player.character.vel = vec2(300.0, 0.0);
+        assert_eq!(player.substeps(Simulation::DELTA_TIME), 3);
+        player.character.vel = vec2(1e6, 0.0);
+        assert_eq!(
+            player.substeps(Simulation::DELTA_TIME),
+            player.tuning.max_substeps
+        );
+    }
+
+    #[test]
+    fn zip_reaches_anchored_ball() {
+        let mut simulation = Simulation::new(&level(&[floor()], vec2(0.0, 2.0)));
+        simulation.run(0.5);
+        simulation.throw(vec2(10.0, 0.0));
+        simulation.run(0.01);
+        let anchor = vec2(8.0, 6.0);
+        let player = &mut simulation.player;
+        player.ball.pos = anchor;
+        player.ball.vel = vec2(0.0, 0.0);
+        player.ball.anchor = Some(vec2(0.0, -1.0));
+        player.chain_len = 12.0;
+        simulation.dash();
+        assert!(simulation.run_until(ZIP_MAX_TIME, |simulation| simulation.ball_in_hands()));
+        assert!((simulation.character_pos() - anchor).len() < 2.0);
+        assert!(simulation.player.ball.anchor.is_none());
+        // The cooldown outlasts the zip itself
+        assert!(simulation.player.dash_cooldown > 0.0);
+    }
+
+    #[test]
+    fn thrown_ball_catches_on_platform() {
+        let platform = (vec2(4.0, 3.0), vec2(12.0, 3.0));
+        let mut simulation = Simulation::new(&level(&[floor(), platform], vec2(0.0, 2.0)));
+        simulation.run(0.5);
+        simulation.throw(vec2(10.0, 14.0));
+        simulation.run(0.01);
+        assert!(!simulation.ball_in_hands());
+        simulation.run(3.0);
+        let ball = simulation.ball_pos();
+        assert!(simulation.ball_resting(), "{:?}", ball);
+        assert!(ball.y > platform.0.y, "{:?}", ball);
+        // Hanging from the chain under the ball
+        let character = simulation.character_pos();
+        assert!(character.y < ball.y && (character.x - ball.x).abs() < 0.5);
+    }
+}
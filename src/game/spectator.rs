@@ -0,0 +1,189 @@
+use super::*;
+
+const SPECTATOR_SPEEDS: [f32; 5] = [0.25, 0.5, 1.0, 2.0, 4.0];
+const SPECTATOR_NORMAL_SPEED: usize = 2;
+const SPECTATOR_TEXT_SIZE: f32 = 24.0;
+
+pub enum SpectatorSource {
+    // Recorded inputs fed through the regular simulation
+    Inputs,
+    // Only positions, the world keeps running around them
+    Ghost(Ghost),
+}
+
+// Watching a recorded run, real input only drives playback and the camera
+pub struct Spectator {
+    source: SpectatorSource,
+    paused: bool,
+    speed: usize,
+    step: bool,
+    time: f32,
+    dragging: bool,
+}
+
+impl Spectator {
+    pub fn new(source: SpectatorSource) -> Self {
+        Self {
+            source,
+            paused: false,
+            speed: SPECTATOR_NORMAL_SPEED,
+            step: false,
+            time: 0.0,
+            dragging: false,
+        }
+    }
+    fn speed(&self) -> f32 {
+        SPECTATOR_SPEEDS[self.speed]
+    }
+    // A step while paused is exactly one fixed tick
+    fn delta_time(&mut self, delta_time: f32) -> f32 {
+        if !self.paused {
+            delta_time * self.speed()
+        } else if mem::take(&mut self.step) {
+            FIXED_DELTA_TIME
+        } else {
+            0.0
+        }
+    }
+}
+
+impl Game {
+    pub fn with_spectator(mut self, source: SpectatorSource) -> Self {
+        self.spectate(source);
+        self
+    }
+    pub(super) fn spectate(&mut self, source: SpectatorSource) {
+        if let SpectatorSource::Ghost(_) = source {
+            // The run is already fixed, nothing to race against or to record
+            self.ghost = None;
+            self.timer_running = false;
+        }
+        self.spectator = Some(Spectator::new(source));
+    }
+    pub(super) fn restart_spectator(&mut self) {
+        if let Some(spectator) = self.spectator.take() {
+            self.spectate(spectator.source);
+        }
+    }
+    pub(super) fn spectator_delta_time(&mut self, delta_time: f32) -> f32 {
+        match &mut self.spectator {
+            Some(spectator) => spectator.delta_time(delta_time),
+            None => delta_time,
+        }
+    }
+    // Moves the player along a ghost track, returns true so the regular step gets skipped
+    pub(super) fn update_spectator(&mut self, delta_time: f32) -> bool {
+        let spectator = match &mut self.spectator {
+            Some(spectator) => spectator,
+            None => return false,
+        };
+        let ghost = match &spectator.source {
+            SpectatorSource::Ghost(ghost) => ghost,
+            SpectatorSource::Inputs => return false,
+        };
+        let end = ghost.frames.last().map_or(0.0, |frame| frame.time);
+        spectator.time = (spectator.time + delta_time).min(end);
+        if spectator.time >= end {
+            spectator.paused = true;
+        }
+        if let Some(frame) = ghost.sample(spectator.time) {
+            let player = &mut self.player;
+            player.ball_in_hands = false;
+            player.character.pos = frame.character;
+            player.ball.pos = frame.ball;
+            player.ball.size = frame.ball_size;
+            let dt = delta_time.max(EPS);
+            player.character.vel = (player.character.pos - self.prev_character_pos) / dt;
+            player.ball.vel = (player.ball.pos - self.prev_ball_pos) / dt;
+        }
+        self.level_time = spectator.time;
+        true
+    }
+    pub(super) fn handle_spectator_input(&mut self, event: geng::Event) {
+        let spectator = self.spectator.as_mut().unwrap();
+        let dragging = spectator.dragging;
+        match event {
+            geng::Event::KeyDown { key } => match key {
+                geng::Key::Space => spectator.paused = !spectator.paused,
+                geng::Key::Up => {
+                    spectator.speed = (spectator.speed + 1).min(SPECTATOR_SPEEDS.len() - 1)
+                }
+                geng::Key::Down => spectator.speed = spectator.speed.saturating_sub(1),
+                geng::Key::Right => {
+                    spectator.paused = true;
+                    spectator.step = true;
+                }
+                geng::Key::C => self.free_camera = !self.free_camera,
+                geng::Key::Escape => self.pause(),
+                _ => {}
+            },
+            geng::Event::MouseDown {
+                button: geng::MouseButton::Middle,
+                ..
+            } => spectator.dragging = true,
+            geng::Event::MouseUp {
+                button: geng::MouseButton::Middle,
+                ..
+            } => spectator.dragging = false,
+            geng::Event::Wheel { delta } if self.free_camera => {
+                self.camera.zoom_at(
+                    self.world_framebuffer_size(),
+                    self.window_to_world_screen(self.last_mouse_pos),
+                    FREE_CAMERA_ZOOM.powf(-delta as f32),
+                );
+            }
+            geng::Event::MouseMove { position } => {
+                let position = position.map(|x| x as f32);
+                if self.free_camera && dragging {
+                    self.camera.pan(
+                        self.world_framebuffer_size(),
+                        self.window_to_world_screen(self.last_mouse_pos),
+                        self.window_to_world_screen(position),
+                    );
+                }
+                self.last_mouse_pos = position;
+            }
+            _ => {}
+        }
+    }
+    pub(super) fn draw_spectator(&self, framebuffer: &mut ugli::Framebuffer, color: Color<f32>) {
+        let spectator = match &self.spectator {
+            Some(spectator) => spectator,
+            None => return,
+        };
+        let ui_size = UiCamera::size(self.framebuffer_size.map(|x| x as f32));
+        let mut status = trf(
+            "spectator.status",
+            &[
+                &format!("{}", spectator.speed()),
+                &format_time(self.level_time),
+            ],
+        );
+        if spectator.paused {
+            status = format!("{}  {}", status, tr("spectator.paused"));
+        }
+        if self.free_camera {
+            status = format!("{}  {}", status, tr("spectator.free_camera"));
+        }
+        let text_size = SPECTATOR_TEXT_SIZE * self.settings.accessibility.text_scale();
+        self.draw_ui_text(
+            framebuffer,
+            &status,
+            vec2(ui_size.x / 2.0, text_size * 2.5),
+            0.5,
+            text_size,
+            color,
+        );
+        self.draw_ui_text(
+            framebuffer,
+            &tr("spectator.controls"),
+            vec2(ui_size.x / 2.0, text_size),
+            0.5,
+            text_size * 0.75,
+            Color {
+                a: color.a * 0.7,
+                ..color
+            },
+        );
+    }
+}
@@ -0,0 +1,28 @@
+use super::*;
+
+impl Game {
+    // Lifetime stats pile up on the game and only get written at level boundaries,
+    // touching the save every step would rewrite the file every frame
+    pub(super) fn save_stats(&mut self) {
+        let pending = mem::take(&mut self.lifetime_stats);
+        self.progress.stats.add(&pending);
+    }
+    pub(super) fn check_achievements(&mut self, completed: bool) {
+        if self.editor.is_some() {
+            return;
+        }
+        let mut lifetime = self.progress.stats.clone();
+        lifetime.add(&self.lifetime_stats);
+        let count = self.collectible_count();
+        let check = AchievementCheck {
+            lifetime: &lifetime,
+            run: completed.then_some(&self.stats),
+            collected_all: count > 0
+                && self.collected.get(&self.level_index).copied() == Some(count),
+        };
+        for achievement in newly_unlocked(&self.progress, &check) {
+            self.progress.achievements.insert(achievement.id.to_owned());
+            self.toasts.push(achievement);
+        }
+    }
+}
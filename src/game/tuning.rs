@@ -0,0 +1,154 @@
+use super::*;
+
+// Gameplay feel, loaded from config.json; missing fields keep their defaults
+#[derive(Debug, Copy, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Tuning {
+    pub gravity: f32,
+    pub walk_speed: f32,
+    pub walk_acceleration: f32,
+    pub air_acceleration: f32,
+    pub jump_speed: f32,
+    pub jump_cut: f32,
+    pub coyote_time: f32,
+    pub ball_swing_distance: f32,
+    pub max_spin_speed: f32,
+    pub min_throw_speed: f32,
+    pub max_throw_speed: f32,
+    pub throw_charge_time: f32,
+    pub reel_acceleration: f32,
+    pub max_reel_speed: f32,
+    pub reel_force: f32,
+    pub min_chain_len: f32,
+    pub max_chain_len: f32,
+    pub chain_adjust_speed: f32,
+    // Smoothed chain tension, in units per second of stretch, that snaps the chain
+    pub chain_break_tension: f32,
+    pub max_fixed_steps: usize,
+    // Cap on physics substeps per fixed tick, faster bodies rely on swept collision alone
+    pub max_substeps: usize,
+    pub transitions: TransitionConfig,
+}
+
+impl Default for Tuning {
+    fn default() -> Self {
+        Self {
+            gravity: 50.0,
+            walk_speed: 8.0,
+            walk_acceleration: 60.0,
+            air_acceleration: 25.0,
+            jump_speed: 18.0,
+            jump_cut: 0.5,
+            coyote_time: 0.1,
+            ball_swing_distance: 0.8,
+            max_spin_speed: 20.0,
+            min_throw_speed: 10.0,
+            max_throw_speed: 30.0,
+            throw_charge_time: 1.0,
+            reel_acceleration: 20.0,
+            max_reel_speed: 12.0,
+            reel_force: 80.0,
+            min_chain_len: 0.1,
+            max_chain_len: 12.0,
+            chain_adjust_speed: 6.0,
+            chain_break_tension: 45.0,
+            max_fixed_steps: 60,
+            max_substeps: 8,
+            transitions: default(),
+        }
+    }
+}
+
+impl Tuning {
+    pub const PATH: &'static str = "config.json";
+    // Names in the order `get` and `set` index them
+    pub const FIELDS: [&'static str; 21] = [
+        "gravity",
+        "walk_speed",
+        "walk_acceleration",
+        "air_acceleration",
+        "jump_speed",
+        "jump_cut",
+        "coyote_time",
+        "ball_swing_distance",
+        "max_spin_speed",
+        "min_throw_speed",
+        "max_throw_speed",
+        "throw_charge_time",
+        "reel_acceleration",
+        "max_reel_speed",
+        "reel_force",
+        "min_chain_len",
+        "max_chain_len",
+        "chain_adjust_speed",
+        "chain_break_tension",
+        "max_fixed_steps",
+        "max_substeps",
+    ];
+    pub fn parse(json: &str) -> anyhow::Result<Self> {
+        Ok(serde_json::from_str(json)?)
+    }
+    pub fn get(&self, index: usize) -> f32 {
+        match index {
+            0 => self.gravity,
+            1 => self.walk_speed,
+            2 => self.walk_acceleration,
+            3 => self.air_acceleration,
+            4 => self.jump_speed,
+            5 => self.jump_cut,
+            6 => self.coyote_time,
+            7 => self.ball_swing_distance,
+            8 => self.max_spin_speed,
+            9 => self.min_throw_speed,
+            10 => self.max_throw_speed,
+            11 => self.throw_charge_time,
+            12 => self.reel_acceleration,
+            13 => self.max_reel_speed,
+            14 => self.reel_force,
+            15 => self.min_chain_len,
+            16 => self.max_chain_len,
+            17 => self.chain_adjust_speed,
+            18 => self.chain_break_tension,
+            19 => self.max_fixed_steps as f32,
+            _ => self.max_substeps as f32,
+        }
+    }
+    pub fn set(&mut self, index: usize, value: f32) {
+        let value = value.max(0.0);
+        let field = match index {
+            0 => &mut self.gravity,
+            1 => &mut self.walk_speed,
+            2 => &mut self.walk_acceleration,
+            3 => &mut self.air_acceleration,
+            4 => &mut self.jump_speed,
+            5 => &mut self.jump_cut,
+            6 => &mut self.coyote_time,
+            7 => &mut self.ball_swing_distance,
+            8 => &mut self.max_spin_speed,
+            9 => &mut self.min_throw_speed,
+            10 => &mut self.max_throw_speed,
+            11 => &mut self.throw_charge_time,
+            12 => &mut self.reel_acceleration,
+            13 => &mut self.max_reel_speed,
+            14 => &mut self.reel_force,
+            15 => &mut self.min_chain_len,
+            16 => &mut self.max_chain_len,
+            17 => &mut self.chain_adjust_speed,
+            18 => &mut self.chain_break_tension,
+            19 => {
+                self.max_fixed_steps = (value.round() as usize).max(1);
+                return;
+            }
+            _ => {
+                self.max_substeps = (value.round() as usize).max(1);
+                return;
+            }
+        };
+        *field = value;
+    }
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn save(&self) -> anyhow::Result<()> {
+        std::fs::write(Self::PATH, serde_json::to_string_pretty(self)?)?;
+        Ok(())
+    }
+}
@@ -0,0 +1,70 @@
+use super::*;
+
+const TUTORIAL_TEXT_SIZE: f32 = 28.0;
+
+impl Game {
+    fn hint_performed(&self, action: HintAction) -> bool {
+        let player = &self.player;
+        match action {
+            HintAction::Move => player.walk != 0.0,
+            HintAction::Jump => player.jumping,
+            HintAction::Spin => self.spin,
+            HintAction::Throw => self
+                .events
+                .iter()
+                .any(|event| matches!(event, GameEvent::Throw { .. })),
+            HintAction::Reel => player.reel && !player.ball_in_hands,
+            HintAction::AdjustChain => player.chain_adjust != 0.0,
+        }
+    }
+    // Runs before the step's events are consumed so throws still count
+    pub(super) fn update_hints(&mut self) {
+        let character = self.player.character.pos;
+        for index in 0..self.entities.len() {
+            let entity = &self.entities[index];
+            let (action, inside) = match (&entity.behavior, &entity.area) {
+                (Behavior::Hint { action, .. }, Some(area)) => {
+                    (*action, area.aabb.contains(character))
+                }
+                _ => continue,
+            };
+            let performed = inside && action.is_some_and(|action| self.hint_performed(action));
+            let entity = &mut self.entities[index];
+            if let Behavior::Hint { done, .. } = &mut entity.behavior {
+                *done |= performed;
+                if let Some(animated) = &mut entity.animated {
+                    animated.target = if inside && !*done { 1.0 } else { 0.0 };
+                }
+            }
+        }
+    }
+    pub(super) fn draw_hints(&self, framebuffer: &mut ugli::Framebuffer, theme: &Theme) {
+        let framebuffer_size = framebuffer.size().map(|x| x as f32);
+        let world_size = self.world_framebuffer_size();
+        for entity in &self.entities {
+            let (text, aabb, opacity) = match (&entity.behavior, &entity.area, &entity.animated) {
+                (Behavior::Hint { text, .. }, Some(area), Some(animated)) => {
+                    (text, area.aabb, animated.value)
+                }
+                _ => continue,
+            };
+            if opacity <= 0.0 {
+                continue;
+            }
+            let anchor = vec2(aabb.center().x, aabb.y_max);
+            let pos = self.world_screen_to_window(self.camera.world_to_screen(world_size, anchor));
+            let mut color = theme.hud.rgba();
+            color.a *= opacity;
+            self.assets.font.draw_aligned(
+                framebuffer,
+                &tr(text),
+                pos,
+                0.5,
+                TUTORIAL_TEXT_SIZE
+                    * self.settings.accessibility.text_scale()
+                    * UiCamera::scale(framebuffer_size),
+                color,
+            );
+        }
+    }
+}
@@ -0,0 +1,585 @@
+use super::*;
+
+const VERSUS_KNOCKOUTS: usize = 3;
+// How far below the arena a fighter counts as knocked off
+const VERSUS_FALL_DEPTH: f32 = 8.0;
+const VERSUS_ROUND_DELAY: f32 = 1.5;
+// Share of the ball's closing speed passed on to the character it hits
+const VERSUS_KNOCKBACK: f32 = 0.8;
+const VERSUS_MIN_HIT_SPEED: f32 = 2.0;
+const VERSUS_TITLE_SIZE: f32 = 64.0;
+const VERSUS_TEXT_SIZE: f32 = 32.0;
+
+struct VersusKeys {
+    left: geng::Key,
+    right: geng::Key,
+    jump: geng::Key,
+    throw: geng::Key,
+    reel: geng::Key,
+}
+
+// Both fighters share the keyboard, the second one can use a gamepad instead
+const VERSUS_KEYS: [VersusKeys; 2] = [
+    VersusKeys {
+        left: geng::Key::A,
+        right: geng::Key::D,
+        jump: geng::Key::W,
+        throw: geng::Key::Space,
+        reel: geng::Key::LShift,
+    },
+    VersusKeys {
+        left: geng::Key::Left,
+        right: geng::Key::Right,
+        jump: geng::Key::Up,
+        throw: geng::Key::Enter,
+        reel: geng::Key::RShift,
+    },
+];
+
+struct Fighter {
+    player: Player,
+    prev_character_pos: Vec2<f32>,
+    prev_ball_pos: Vec2<f32>,
+    // Holding throw spins the ball around, letting go flings it the way it points
+    spin: bool,
+    spin_angle: f32,
+    aim_time: f32,
+    knockouts: usize,
+}
+
+impl Fighter {
+    fn new(spawn: Vec2<f32>, tuning: Tuning) -> Self {
+        Self {
+            player: Player::new(spawn, tuning),
+            prev_character_pos: spawn,
+            prev_ball_pos: spawn,
+            spin: false,
+            spin_angle: 0.0,
+            aim_time: 0.0,
+            knockouts: 0,
+        }
+    }
+    fn respawn(&mut self, spawn: Vec2<f32>) {
+        let tuning = self.player.tuning;
+        *self = Self {
+            knockouts: self.knockouts,
+            ..Self::new(spawn, tuning)
+        };
+    }
+    fn throw_power(&self) -> f32 {
+        clamp(
+            self.aim_time / self.player.tuning.throw_charge_time,
+            0.0..=1.0,
+        )
+    }
+    fn start_spin(&mut self) {
+        if !self.player.ball_in_hands {
+            self.player.release_ball();
+            return;
+        }
+        self.spin = true;
+        self.aim_time = 0.0;
+        self.spin_angle = f32::PI / 2.0;
+    }
+    fn throw(&mut self) {
+        if !mem::take(&mut self.spin) {
+            return;
+        }
+        let tuning = &self.player.tuning;
+        let speed = tuning.min_throw_speed
+            + (tuning.max_throw_speed - tuning.min_throw_speed) * self.throw_power();
+        self.player.throw_vel = Vec2::rotated(vec2(1.0, 0.0), self.spin_angle) * speed;
+        self.player.throw_spin = self.throw_power() * tuning.max_spin_speed;
+        self.player.throw.press();
+    }
+}
+
+impl Player {
+    fn knock_back(&mut self, dir: Vec2<f32>, speed: f32) {
+        self.character.vel += dir * speed;
+        self.character.grounded = false;
+        self.character.stand = false;
+        if speed > TUMBLE_SPEED && self.tumble.is_none() {
+            let right = vec2(self.character.up.y, -self.character.up.x);
+            self.tumble = Some(0.0);
+            self.tumble_spin = -Vec2::dot(dir, right).signum() * TUMBLE_SPIN_SPEED;
+        }
+    }
+}
+
+#[derive(Debug, Copy, Clone)]
+enum VersusState {
+    Playing,
+    // None when both went off together
+    RoundOver { winner: Option<usize>, timer: f32 },
+    MatchOver { winner: usize },
+}
+
+// Local two player sumo, each fighter tries to knock the other off the arena
+pub struct Versus {
+    assets: Rc<Assets>,
+    renderer: Renderer,
+    line_renderer: LineRenderer,
+    tile_renderer: TileRenderer,
+    chain_renderer: ChainRenderer,
+    arena_index: usize,
+    arena: Level,
+    zones: Vec<Entity>,
+    world: CollisionWorld,
+    tile_chunks: Vec<TileBatch>,
+    bounds: AABB<f32>,
+    spawns: [Vec2<f32>; 2],
+    tuning: Tuning,
+    theme: Theme,
+    camera: Camera,
+    fighters: Vec<Fighter>,
+    state: VersusState,
+    accumulator: f32,
+    input: InputState,
+    gamepad: Gamepad,
+    framebuffer_size: Vec2<f32>,
+    transition: Option<SceneTransition>,
+}
+
+impl Versus {
+    pub fn new(geng: &Rc<Geng>, assets: &Rc<Assets>, arena: usize) -> Self {
+        let tuning = Tuning::parse(&assets.config).unwrap_or_default();
+        let mut versus = Self {
+            assets: assets.clone(),
+            renderer: Renderer::new(geng, &assets.shaders),
+            line_renderer: LineRenderer::new(geng, &assets.shaders),
+            tile_renderer: TileRenderer::new(geng, &assets.shaders),
+            chain_renderer: ChainRenderer::new(geng, &assets.shaders),
+            arena_index: 0,
+            arena: default(),
+            zones: Vec::new(),
+            world: CollisionWorld::new(&[]),
+            tile_chunks: Vec::new(),
+            bounds: AABB::pos_size(vec2(0.0, 0.0), vec2(0.0, 0.0)),
+            spawns: [vec2(0.0, 0.0); 2],
+            tuning,
+            theme: assets.themes.theme().clone(),
+            camera: Camera::new(30.0),
+            fighters: Vec::new(),
+            state: VersusState::Playing,
+            accumulator: 0.0,
+            input: default(),
+            gamepad: Gamepad::new(),
+            framebuffer_size: vec2(1.0, 1.0),
+            transition: None,
+        };
+        versus.load_arena(arena);
+        versus
+    }
+    fn load_arena(&mut self, index: usize) {
+        let arenas = &self.assets.arenas.levels;
+        self.arena_index = index % arenas.len();
+        self.arena = arenas[self.arena_index].1.borrow().clone();
+        self.world = CollisionWorld::new(&optimize_segments(&self.arena.segments));
+        self.zones = zone_entities(&self.arena).collect();
+        for hazard in &self.arena.hazards {
+            for segment in tile_segments(hazard.pos) {
+                self.world.insert(Segment {
+                    material: hazard.material,
+                    ..segment
+                });
+            }
+        }
+        self.bounds = self
+            .world
+            .bounds()
+            .unwrap_or_else(|| AABB::pos_size(vec2(0.0, 0.0), vec2(0.0, 0.0)));
+        // Arenas are symmetric, the second fighter starts mirrored across the middle
+        let spawn = self.arena.spawn_pos();
+        let center = self.bounds.center().x;
+        self.spawns = [spawn, vec2(2.0 * center - spawn.x, spawn.y)];
+        let block_uv = self.assets.atlas.uv("block");
+        self.tile_chunks = self.tile_renderer.chunks(&self.arena.tiles, block_uv);
+        self.theme = self.assets.themes.level_theme(&self.arena.palette);
+        self.camera = Camera::new(30.0);
+        self.camera.bounds = Some(self.bounds.add_padding(KILL_PLANE_MARGIN));
+        self.camera.center = self.bounds.center();
+        self.camera.target_position = self.camera.center;
+        self.fighters = self
+            .spawns
+            .iter()
+            .map(|&spawn| Fighter::new(spawn, self.tuning))
+            .collect();
+        self.state = VersusState::Playing;
+    }
+    fn start_round(&mut self) {
+        for (fighter, &spawn) in self.fighters.iter_mut().zip(&self.spawns) {
+            fighter.respawn(spawn);
+        }
+        self.state = VersusState::Playing;
+    }
+    fn knocked_out(&self, fighter: &Fighter) -> bool {
+        let character = &fighter.player.character;
+        character.hurt
+            || character.pos.y < self.bounds.y_min - VERSUS_FALL_DEPTH
+            || !self
+                .bounds
+                .add_padding(KILL_PLANE_MARGIN)
+                .contains(character.pos)
+    }
+    fn update_controls(&mut self, delta_time: f32) {
+        let input = &self.input;
+        let pad = &self.gamepad;
+        let playing = matches!(self.state, VersusState::Playing);
+        for (index, (fighter, keys)) in self.fighters.iter_mut().zip(&VERSUS_KEYS).enumerate() {
+            let pad = Some(pad).filter(|_| index == 1);
+            let player = &mut fighter.player;
+            player.walk = 0.0;
+            if playing {
+                if input.key(keys.left) {
+                    player.walk -= 1.0;
+                }
+                if input.key(keys.right) {
+                    player.walk += 1.0;
+                }
+                if let Some(stick) = pad.and_then(|pad| pad.stick()) {
+                    player.walk = clamp(player.walk + stick.x, -1.0..=1.0);
+                }
+            }
+            let jump = playing
+                && (input.key(keys.jump) || pad.is_some_and(|pad| pad.held(PadButton::South)));
+            if jump && !player.jump_held {
+                player.jump.press();
+            }
+            player.jump_held = jump;
+            player.reel = playing
+                && (input.key(keys.reel)
+                    || pad.is_some_and(|pad| pad.held(PadButton::RightTrigger)));
+            if fighter.spin {
+                fighter.aim_time += delta_time;
+                fighter.spin_angle +=
+                    fighter.throw_power() * self.tuning.max_spin_speed * delta_time;
+            }
+            let player = &mut fighter.player;
+            if player.ball_in_hands {
+                player.ball.vel = if fighter.spin {
+                    Vec2::rotated(vec2(1.0, 0.0), fighter.spin_angle)
+                } else {
+                    vec2(0.0, 1.0)
+                };
+            }
+        }
+    }
+    fn throw_input(&mut self, index: usize, pressed: bool) {
+        if !matches!(self.state, VersusState::Playing) {
+            return;
+        }
+        let fighter = &mut self.fighters[index];
+        if pressed {
+            fighter.start_spin();
+        } else {
+            fighter.throw();
+        }
+    }
+    // A swinging ball shoves the other character away along the hit
+    fn collide_fighters(&mut self) {
+        for attacker in 0..self.fighters.len() {
+            for victim in 0..self.fighters.len() {
+                if attacker == victim || !self.fighters[attacker].player.chained() {
+                    continue;
+                }
+                let ball = self.fighters[attacker].player.ball.clone();
+                let character = &mut self.fighters[victim].player.character;
+                let delta = character.pos - ball.pos;
+                let penetration = ball.size + character.size - delta.len();
+                if penetration <= 0.0 || delta.len() < EPS {
+                    continue;
+                }
+                let normal = delta.normalize();
+                character.pos += normal * penetration;
+                let speed = Vec2::dot(ball.vel - character.vel, normal);
+                if speed > VERSUS_MIN_HIT_SPEED {
+                    let knockback = speed * VERSUS_KNOCKBACK * ball.density / CHARACTER_DENSITY;
+                    self.fighters[victim].player.knock_back(normal, knockback);
+                }
+            }
+        }
+        if let [first, second] = &mut self.fighters[..] {
+            let (first, second) = (&mut first.player.character, &mut second.player.character);
+            let delta = second.pos - first.pos;
+            let penetration = first.size + second.size - delta.len();
+            if penetration > 0.0 && delta.len() > EPS {
+                let normal = delta.normalize();
+                first.pos -= normal * penetration / 2.0;
+                second.pos += normal * penetration / 2.0;
+            }
+        }
+    }
+    fn fixed_update(&mut self, delta_time: f32) {
+        self.update_controls(delta_time);
+        let env = Environment {
+            level: &self.world,
+            entities: &self.zones,
+            gravity: self.tuning.gravity,
+        };
+        for fighter in &mut self.fighters {
+            fighter.prev_character_pos = fighter.player.character.pos;
+            fighter.prev_ball_pos = fighter.player.ball.pos;
+            fighter.player.step(&env, delta_time);
+            fighter.player.update_rope(&env, delta_time);
+            fighter.player.character.impacts.clear();
+            fighter.player.ball.impacts.clear();
+            if !fighter.player.ball_in_hands && !self.bounds.contains(fighter.player.ball.pos) {
+                fighter.player.release_ball();
+            }
+        }
+        self.collide_fighters();
+        if !matches!(self.state, VersusState::Playing) {
+            return;
+        }
+        let out: Vec<bool> = self
+            .fighters
+            .iter()
+            .map(|fighter| self.knocked_out(fighter))
+            .collect();
+        let winner = match out[..] {
+            [false, false] => return,
+            [true, true] => None,
+            [first_out, _] => Some(if first_out { 1 } else { 0 }),
+            _ => unreachable!(),
+        };
+        if let Some(winner) = winner {
+            self.fighters[winner].knockouts += 1;
+        }
+        self.state = VersusState::RoundOver { winner, timer: 0.0 };
+    }
+    fn update_state(&mut self, delta_time: f32) {
+        if let VersusState::RoundOver { winner, timer } = &mut self.state {
+            *timer += delta_time;
+            if *timer < VERSUS_ROUND_DELAY {
+                return;
+            }
+            match winner.filter(|&winner| self.fighters[winner].knockouts >= VERSUS_KNOCKOUTS) {
+                Some(winner) => self.state = VersusState::MatchOver { winner },
+                None => self.start_round(),
+            }
+        }
+    }
+    fn update_camera(&mut self, delta_time: f32) {
+        let points: Vec<Vec2<f32>> = self
+            .fighters
+            .iter()
+            .flat_map(|fighter| [fighter.player.character.pos, fighter.player.ball.pos])
+            .collect();
+        let center =
+            points.iter().fold(vec2(0.0, 0.0), |sum, &pos| sum + pos) / points.len().max(1) as f32;
+        self.camera.target_position = center;
+        self.camera.fit(&points, self.framebuffer_size);
+        self.camera.update(delta_time);
+        self.camera.clamp_to_bounds(self.framebuffer_size);
+    }
+    fn back_to_menu(&mut self) {
+        self.transition = Some(SceneTransition::Pop);
+    }
+    fn draw_fighter(
+        &self,
+        framebuffer: &mut ugli::Framebuffer,
+        fighter: &Fighter,
+        color: Color<f32>,
+    ) {
+        let alpha = self.accumulator / FIXED_DELTA_TIME;
+        let player = &fighter.player;
+        let character = player
+            .character
+            .interpolate(fighter.prev_character_pos, alpha);
+        let ball = player.ball.interpolate(fighter.prev_ball_pos, alpha);
+        let camera = &self.camera;
+        if player.chained() {
+            let mut rope = player.rope.points.clone();
+            rope[0] = character.pos;
+            *rope.last_mut().unwrap() = ball.pos;
+            self.line_renderer.draw_ribbon(
+                framebuffer,
+                camera,
+                Color::WHITE,
+                rope.iter().map(|&pos| LinePoint {
+                    pos,
+                    width: CHAIN_LINE_WIDTH,
+                    color: self.theme.chain_line.rgba(),
+                }),
+                LineJoin::Round,
+            );
+            self.chain_renderer.draw(
+                framebuffer,
+                camera,
+                &rope,
+                vec2(CHAIN_WIDTH, CHAIN_LINK_LENGTH),
+                &self.assets.atlas.texture.borrow(),
+                self.assets.atlas.uv("chain"),
+                self.theme.chain.rgba(),
+            );
+        }
+        self.renderer.draw_sprite(
+            framebuffer,
+            camera,
+            &player.animation.frame(&self.assets),
+            None,
+            character.pos,
+            vec2(character.size, character.size) * 2.0,
+            player.lean + player.tumble_angle,
+            player.animation.facing_left,
+            color,
+            vec2(0.5, 0.5),
+        );
+        self.renderer.draw_sprite(
+            framebuffer,
+            camera,
+            &self.assets.atlas.texture.borrow(),
+            Some(self.assets.atlas.uv("ball")),
+            ball.pos,
+            vec2(ball.size, ball.size) * 2.0,
+            ball.angle,
+            false,
+            lerp_color(self.theme.ball.rgba(), color, 0.5),
+            vec2(0.5, 0.5),
+        );
+    }
+    fn fighter_colors(&self) -> [Color<f32>; 2] {
+        [self.theme.player.rgba(), self.theme.highlight.rgba()]
+    }
+}
+
+impl geng::State for Versus {
+    fn update(&mut self, delta_time: f64) {
+        let delta_time = delta_time as f32;
+        for PadEvent { button, pressed } in self.gamepad.poll() {
+            match button {
+                PadButton::RightTrigger2 => self.throw_input(1, pressed),
+                PadButton::Start if pressed => self.back_to_menu(),
+                _ => {}
+            }
+        }
+        self.update_state(delta_time);
+        for fighter in &mut self.fighters {
+            fighter
+                .player
+                .update_animation(fighter.spin, fighter.spin_angle, delta_time);
+        }
+        self.accumulator += delta_time;
+        let mut steps = 0;
+        while self.accumulator >= FIXED_DELTA_TIME {
+            if steps == self.tuning.max_fixed_steps {
+                self.accumulator = 0.0;
+                break;
+            }
+            self.fixed_update(FIXED_DELTA_TIME);
+            self.accumulator -= FIXED_DELTA_TIME;
+            steps += 1;
+        }
+        self.update_camera(delta_time);
+    }
+    fn draw(&mut self, framebuffer: &mut ugli::Framebuffer) {
+        self.framebuffer_size = framebuffer.size().map(|x| x as f32);
+        ugli::clear(framebuffer, Some(self.theme.background.rgba()), None);
+        for chunk in &self.tile_chunks {
+            self.tile_renderer.draw(
+                framebuffer,
+                &self.camera,
+                chunk,
+                Mat4::identity(),
+                &self.assets.atlas.texture.borrow(),
+                self.theme.tiles.rgba(),
+            );
+        }
+        for hazard in &self.arena.hazards {
+            self.renderer.draw_sprite(
+                framebuffer,
+                &self.camera,
+                &self.assets.atlas.texture.borrow(),
+                Some(self.assets.atlas.uv("block")),
+                hazard.pos + vec2(0.5, 0.5),
+                vec2(1.0, 1.0),
+                0.0,
+                false,
+                self.theme.hazard.rgba(),
+                vec2(0.5, 0.5),
+            );
+        }
+        let colors = self.fighter_colors();
+        for (fighter, color) in self.fighters.iter().zip(colors) {
+            self.draw_fighter(framebuffer, fighter, color);
+        }
+        let ui_size = UiCamera::size(self.framebuffer_size);
+        let font = &self.assets.font;
+        let hud = self.theme.hud.rgba();
+        draw_ui_text(
+            framebuffer,
+            font,
+            &trf(
+                "versus.score",
+                &[&self.fighters[0].knockouts, &self.fighters[1].knockouts],
+            ),
+            vec2(ui_size.x / 2.0, ui_size.y - VERSUS_TEXT_SIZE * 1.5),
+            0.5,
+            VERSUS_TEXT_SIZE,
+            hud,
+        );
+        let (title, color) = match self.state {
+            VersusState::Playing => return,
+            VersusState::RoundOver {
+                winner: Some(winner),
+                ..
+            } => (trf("versus.knockout", &[&(winner + 1)]), colors[winner]),
+            VersusState::RoundOver { winner: None, .. } => (tr("versus.draw"), hud),
+            VersusState::MatchOver { winner } => {
+                (trf("versus.winner", &[&(winner + 1)]), colors[winner])
+            }
+        };
+        draw_ui_text(
+            framebuffer,
+            font,
+            &title,
+            vec2(ui_size.x / 2.0, ui_size.y * 0.6),
+            0.5,
+            VERSUS_TITLE_SIZE,
+            color,
+        );
+        if let VersusState::MatchOver { .. } = self.state {
+            draw_ui_text(
+                framebuffer,
+                font,
+                &tr("versus.rematch"),
+                vec2(ui_size.x / 2.0, ui_size.y * 0.6 - VERSUS_TITLE_SIZE),
+                0.5,
+                VERSUS_TEXT_SIZE,
+                hud,
+            );
+        }
+    }
+    fn handle_event(&mut self, event: geng::Event) {
+        // Held keys repeat their key down, only the first one starts a spin
+        let repeat = matches!(event, geng::Event::KeyDown { key } if self.input.key(key));
+        self.input.handle_event(&event);
+        if repeat {
+            return;
+        }
+        match event {
+            geng::Event::KeyDown {
+                key: geng::Key::Escape,
+            } => self.back_to_menu(),
+            geng::Event::KeyDown { key: geng::Key::R }
+                if matches!(self.state, VersusState::MatchOver { .. }) =>
+            {
+                self.load_arena(self.arena_index + 1);
+            }
+            geng::Event::KeyDown { key } | geng::Event::KeyUp { key } => {
+                let pressed = matches!(event, geng::Event::KeyDown { .. });
+                if let Some(index) = VERSUS_KEYS.iter().position(|keys| keys.throw == key) {
+                    self.throw_input(index, pressed);
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+impl Scene for Versus {
+    fn scene_transition(&mut self) -> Option<SceneTransition> {
+        self.transition.take()
+    }
+}
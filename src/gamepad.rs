@@ -0,0 +1,140 @@
+use super::*;
+
+pub use gilrs::Button as PadButton;
+
+const STICK_DEAD_ZONE: f32 = 0.25;
+const MENU_STICK_THRESHOLD: f32 = 0.6;
+
+#[derive(Debug, Copy, Clone)]
+pub struct PadEvent {
+    pub button: PadButton,
+    pub pressed: bool,
+}
+
+// State of all connected pads merged together
+pub struct Gamepad {
+    gilrs: Option<gilrs::Gilrs>,
+    stick: Vec2<f32>,
+    held: HashSet<PadButton>,
+    stick_button: Option<PadButton>,
+}
+
+impl Gamepad {
+    pub fn new() -> Self {
+        Self {
+            gilrs: match gilrs::Gilrs::new() {
+                Ok(gilrs) => Some(gilrs),
+                Err(e) => {
+                    warn!("Gamepads unavailable: {}", e);
+                    None
+                }
+            },
+            stick: vec2(0.0, 0.0),
+            held: HashSet::new(),
+            stick_button: None,
+        }
+    }
+    pub fn poll(&mut self) -> Vec<PadEvent> {
+        let mut events = Vec::new();
+        let gilrs = match &mut self.gilrs {
+            Some(gilrs) => gilrs,
+            None => return events,
+        };
+        while let Some(event) = gilrs.next_event() {
+            match event.event {
+                gilrs::EventType::ButtonPressed(button, _) => {
+                    self.held.insert(button);
+                    events.push(PadEvent {
+                        button,
+                        pressed: true,
+                    });
+                }
+                gilrs::EventType::ButtonReleased(button, _) => {
+                    self.held.remove(&button);
+                    events.push(PadEvent {
+                        button,
+                        pressed: false,
+                    });
+                }
+                gilrs::EventType::AxisChanged(gilrs::Axis::LeftStickX, value, _) => {
+                    self.stick.x = value
+                }
+                gilrs::EventType::AxisChanged(gilrs::Axis::LeftStickY, value, _) => {
+                    self.stick.y = value
+                }
+                gilrs::EventType::Disconnected => {
+                    self.held.clear();
+                    self.stick = vec2(0.0, 0.0);
+                }
+                _ => {}
+            }
+        }
+        // Flicking the stick acts as the d-pad
+        let stick_button = if self.stick.len() < MENU_STICK_THRESHOLD {
+            None
+        } else if self.stick.x.abs() > self.stick.y.abs() {
+            Some(if self.stick.x > 0.0 {
+                PadButton::DPadRight
+            } else {
+                PadButton::DPadLeft
+            })
+        } else {
+            Some(if self.stick.y > 0.0 {
+                PadButton::DPadUp
+            } else {
+                PadButton::DPadDown
+            })
+        };
+        if stick_button != self.stick_button {
+            if let Some(button) = self.stick_button {
+                events.push(PadEvent {
+                    button,
+                    pressed: false,
+                });
+            }
+            if let Some(button) = stick_button {
+                events.push(PadEvent {
+                    button,
+                    pressed: true,
+                });
+            }
+            self.stick_button = stick_button;
+        }
+        events
+    }
+    pub fn held(&self, button: PadButton) -> bool {
+        self.held.contains(&button)
+    }
+    pub fn stick(&self) -> Option<Vec2<f32>> {
+        if self.stick.len() > STICK_DEAD_ZONE {
+            Some(self.stick.clamp(1.0))
+        } else {
+            None
+        }
+    }
+    // Pads drive the menus through the same keys as the keyboard
+    pub fn menu_events(&mut self) -> Vec<geng::Event> {
+        self.poll()
+            .into_iter()
+            .filter(|event| event.pressed)
+            .filter_map(|event| {
+                let key = match event.button {
+                    PadButton::DPadUp => geng::Key::Up,
+                    PadButton::DPadDown => geng::Key::Down,
+                    PadButton::DPadLeft => geng::Key::Left,
+                    PadButton::DPadRight => geng::Key::Right,
+                    PadButton::South => geng::Key::Enter,
+                    PadButton::East | PadButton::Start => geng::Key::Escape,
+                    _ => return None,
+                };
+                Some(geng::Event::KeyDown { key })
+            })
+            .collect()
+    }
+}
+
+impl Default for Gamepad {
+    fn default() -> Self {
+        Self::new()
+    }
+}
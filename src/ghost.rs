@@ -0,0 +1,45 @@
+use super::*;
+
+#[derive(Debug, Copy, Clone, Serialize, Deserialize)]
+pub struct GhostFrame {
+    pub time: f32,
+    pub character: Vec2<f32>,
+    pub ball: Vec2<f32>,
+    pub ball_size: f32,
+}
+
+// Best run of a level, sampled every fixed tick while the timer runs
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Ghost {
+    pub time: Option<f32>,
+    pub frames: Vec<GhostFrame>,
+}
+
+impl Ghost {
+    pub fn path(level: &str) -> String {
+        format!("ghost_{}.json", level)
+    }
+    pub fn beaten_by(&self, time: f32) -> bool {
+        self.time.is_none_or(|best| time < best)
+    }
+    pub fn sample(&self, time: f32) -> Option<GhostFrame> {
+        let index = self.frames.partition_point(|frame| frame.time < time);
+        let next = *self.frames.get(index)?;
+        let prev = match index.checked_sub(1) {
+            Some(prev) => self.frames[prev],
+            None => return Some(next),
+        };
+        let t = if next.time > prev.time {
+            (time - prev.time) / (next.time - prev.time)
+        } else {
+            0.0
+        };
+        Some(GhostFrame {
+            time,
+            character: prev.character + (next.character - prev.character) * t,
+            ball: prev.ball + (next.ball - prev.ball) * t,
+            ball_size: next.ball_size,
+        })
+    }
+}
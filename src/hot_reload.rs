@@ -0,0 +1,43 @@
+use notify::{DebouncedEvent, RecursiveMode, Watcher};
+use std::path::PathBuf;
+use std::sync::mpsc;
+
+const HOT_RELOAD_DELAY: std::time::Duration = std::time::Duration::from_millis(200);
+
+pub struct HotReload {
+    _watcher: notify::RecommendedWatcher,
+    events: mpsc::Receiver<DebouncedEvent>,
+}
+
+impl HotReload {
+    pub fn new(paths: &[PathBuf]) -> notify::Result<Self> {
+        let (sender, events) = mpsc::channel();
+        let mut watcher = notify::watcher(sender, HOT_RELOAD_DELAY)?;
+        for path in paths {
+            watcher.watch(path, RecursiveMode::Recursive)?;
+        }
+        Ok(Self {
+            _watcher: watcher,
+            events,
+        })
+    }
+    // Watched root: the static assets, shaders included
+    pub fn default_paths() -> Vec<PathBuf> {
+        std::env::current_dir().into_iter().collect()
+    }
+    pub fn changed(&self) -> Vec<PathBuf> {
+        let mut paths: Vec<PathBuf> = self
+            .events
+            .try_iter()
+            .filter_map(|event| match event {
+                DebouncedEvent::Write(path)
+                | DebouncedEvent::Create(path)
+                | DebouncedEvent::Rename(_, path) => Some(path),
+                _ => None,
+            })
+            .collect();
+        paths.sort();
+        paths.dedup();
+        paths
+    }
+}
@@ -0,0 +1,128 @@
+use super::*;
+
+const HUD_MARGIN: f32 = 20.0;
+const HUD_TEXT_SIZE: f32 = 32.0;
+const HINT_TEXT_SIZE: f32 = 20.0;
+const HINT_DURATION: f32 = 8.0;
+const HINT_FADE_TIME: f32 = 1.0;
+// Leaves room for the charge meter
+const HINT_BOTTOM: f32 = 80.0;
+const CONTROL_HINTS: &[&str] = &[
+    "hud.controls_move",
+    "hud.controls_throw",
+    "hud.controls_other",
+];
+
+pub struct HudInfo<'a> {
+    pub level_name: &'a str,
+    pub time: f32,
+    pub show_timer: bool,
+    pub text_scale: f32,
+    pub collected: Option<String>,
+    // Best medal still in reach and the time it needs
+    pub next_medal: Option<(Medal, f32)>,
+}
+
+// Text in UiCamera space, align goes from 0 (left) to 1 (right)
+pub fn draw_ui_text(
+    framebuffer: &mut ugli::Framebuffer,
+    font: &geng::Font,
+    text: &str,
+    pos: Vec2<f32>,
+    align: f32,
+    size: f32,
+    color: Color<f32>,
+) {
+    let framebuffer_size = framebuffer.size().map(|x| x as f32);
+    font.draw_aligned(
+        framebuffer,
+        text,
+        UiCamera.world_to_screen(framebuffer_size, pos),
+        align,
+        size * UiCamera::scale(framebuffer_size),
+        color,
+    );
+}
+
+pub fn format_time(time: f32) -> String {
+    format!("{}:{:06.3}", (time / 60.0) as u32, time % 60.0)
+}
+
+// Difference against a personal best, negative is faster
+pub fn format_delta(delta: f32) -> String {
+    format!("{}{:.3}", if delta < 0.0 { "-" } else { "+" }, delta.abs())
+}
+
+pub fn draw_hud(
+    framebuffer: &mut ugli::Framebuffer,
+    font: &geng::Font,
+    info: &HudInfo,
+    color: Color<f32>,
+) {
+    let ui_size = UiCamera::size(framebuffer.size().map(|x| x as f32));
+    let text_size = HUD_TEXT_SIZE * info.text_scale;
+    let hint_size = HINT_TEXT_SIZE * info.text_scale;
+    let top = ui_size.y - HUD_MARGIN - text_size;
+    draw_ui_text(
+        framebuffer,
+        font,
+        &trf("hud.level", &[&info.level_name]),
+        vec2(HUD_MARGIN, top),
+        0.0,
+        text_size,
+        color,
+    );
+    if info.show_timer {
+        draw_ui_text(
+            framebuffer,
+            font,
+            &format_time(info.time),
+            vec2(ui_size.x / 2.0, top),
+            0.5,
+            text_size,
+            color,
+        );
+        if let Some((medal, time)) = info.next_medal {
+            draw_ui_text(
+                framebuffer,
+                font,
+                &trf("hud.next_medal", &[&medal.name(), &format_time(time)]),
+                vec2(ui_size.x / 2.0, top - hint_size * 1.2),
+                0.5,
+                hint_size,
+                Color {
+                    a: color.a,
+                    ..medal.color()
+                },
+            );
+        }
+    }
+    if let Some(collected) = &info.collected {
+        draw_ui_text(
+            framebuffer,
+            font,
+            &trf("hud.collected", &[collected]),
+            vec2(ui_size.x - HUD_MARGIN, top),
+            1.0,
+            text_size,
+            color,
+        );
+    }
+    let hint_alpha = clamp((HINT_DURATION - info.time) / HINT_FADE_TIME, 0.0..=1.0);
+    if hint_alpha > 0.0 {
+        for (i, hint) in CONTROL_HINTS.iter().rev().enumerate() {
+            draw_ui_text(
+                framebuffer,
+                font,
+                &tr(hint),
+                vec2(ui_size.x / 2.0, HINT_BOTTOM + i as f32 * hint_size * 1.2),
+                0.5,
+                hint_size,
+                Color {
+                    a: color.a * hint_alpha,
+                    ..color
+                },
+            );
+        }
+    }
+}
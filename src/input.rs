@@ -0,0 +1,229 @@
+use super::*;
+
+// Held keys and buttons tracked from events instead of polling the window,
+// so replayed events drive the game exactly like live ones
+pub struct InputState {
+    pub keys: HashSet<geng::Key>,
+    pub buttons: HashSet<geng::MouseButton>,
+    pub mouse_pos: Vec2<f32>,
+    // Cursor in world space as it was when the event got recorded
+    pub replay_cursor: Option<Vec2<f32>>,
+}
+
+impl Default for InputState {
+    fn default() -> Self {
+        Self {
+            keys: HashSet::new(),
+            buttons: HashSet::new(),
+            mouse_pos: vec2(0.0, 0.0),
+            replay_cursor: None,
+        }
+    }
+}
+
+impl InputState {
+    pub fn handle_event(&mut self, event: &geng::Event) {
+        match *event {
+            geng::Event::KeyDown { key } => {
+                self.keys.insert(key);
+            }
+            geng::Event::KeyUp { key } => {
+                self.keys.remove(&key);
+            }
+            geng::Event::MouseDown { position, button } => {
+                self.mouse_pos = position.map(|x| x as f32);
+                self.buttons.insert(button);
+            }
+            geng::Event::MouseUp { position, button } => {
+                self.mouse_pos = position.map(|x| x as f32);
+                self.buttons.remove(&button);
+            }
+            geng::Event::MouseMove { position } => self.mouse_pos = position.map(|x| x as f32),
+            _ => {}
+        }
+    }
+    pub fn key(&self, key: geng::Key) -> bool {
+        self.keys.contains(&key)
+    }
+    pub fn button(&self, button: geng::MouseButton) -> bool {
+        self.buttons.contains(&button)
+    }
+}
+
+#[derive(Debug, Copy, Clone, Serialize, Deserialize)]
+enum RecordedButton {
+    Left,
+    Middle,
+    Right,
+}
+
+impl From<geng::MouseButton> for RecordedButton {
+    fn from(button: geng::MouseButton) -> Self {
+        match button {
+            geng::MouseButton::Left => Self::Left,
+            geng::MouseButton::Middle => Self::Middle,
+            geng::MouseButton::Right => Self::Right,
+        }
+    }
+}
+
+impl From<RecordedButton> for geng::MouseButton {
+    fn from(button: RecordedButton) -> Self {
+        match button {
+            RecordedButton::Left => Self::Left,
+            RecordedButton::Middle => Self::Middle,
+            RecordedButton::Right => Self::Right,
+        }
+    }
+}
+
+#[derive(Debug, Copy, Clone, Serialize, Deserialize)]
+enum RecordedEvent {
+    KeyDown(KeyBinding),
+    KeyUp(KeyBinding),
+    MouseDown(Vec2<f64>, RecordedButton),
+    MouseUp(Vec2<f64>, RecordedButton),
+    MouseMove(Vec2<f64>),
+    Wheel(f64),
+}
+
+impl RecordedEvent {
+    fn from_event(event: &geng::Event) -> Option<Self> {
+        Some(match *event {
+            geng::Event::KeyDown { key } => Self::KeyDown(KeyBinding(key)),
+            geng::Event::KeyUp { key } => Self::KeyUp(KeyBinding(key)),
+            geng::Event::MouseDown { position, button } => Self::MouseDown(position, button.into()),
+            geng::Event::MouseUp { position, button } => Self::MouseUp(position, button.into()),
+            geng::Event::MouseMove { position } => Self::MouseMove(position),
+            geng::Event::Wheel { delta } => Self::Wheel(delta),
+            _ => return None,
+        })
+    }
+    fn event(self) -> geng::Event {
+        match self {
+            Self::KeyDown(key) => geng::Event::KeyDown { key: key.0 },
+            Self::KeyUp(key) => geng::Event::KeyUp { key: key.0 },
+            Self::MouseDown(position, button) => geng::Event::MouseDown {
+                position,
+                button: button.into(),
+            },
+            Self::MouseUp(position, button) => geng::Event::MouseUp {
+                position,
+                button: button.into(),
+            },
+            Self::MouseMove(position) => geng::Event::MouseMove { position },
+            Self::Wheel(delta) => geng::Event::Wheel { delta },
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct RecordedInput {
+    tick: u64,
+    cursor: Vec2<f32>,
+    event: Option<RecordedEvent>,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct Recording {
+    pub level: usize,
+    events: Vec<RecordedInput>,
+}
+
+impl Recording {
+    pub fn load(path: &std::path::Path) -> anyhow::Result<Self> {
+        Ok(serde_json::from_str(&std::fs::read_to_string(path)?)?)
+    }
+    pub fn save(&self, path: &std::path::Path) -> anyhow::Result<()> {
+        std::fs::write(path, serde_json::to_string(self)?)?;
+        Ok(())
+    }
+}
+
+pub enum InputRecorder {
+    Record {
+        path: std::path::PathBuf,
+        recording: Recording,
+    },
+    Replay {
+        recording: Recording,
+        next: usize,
+    },
+}
+
+impl InputRecorder {
+    pub fn record(path: std::path::PathBuf, level: usize) -> Self {
+        Self::Record {
+            path,
+            recording: Recording {
+                level,
+                events: Vec::new(),
+            },
+        }
+    }
+    pub fn replay(recording: Recording) -> Self {
+        Self::Replay { recording, next: 0 }
+    }
+    pub fn level(&self) -> usize {
+        match self {
+            Self::Record { recording, .. } | Self::Replay { recording, .. } => recording.level,
+        }
+    }
+    pub fn replaying(&self) -> bool {
+        matches!(self, Self::Replay { .. })
+    }
+    pub fn capture(&mut self, tick: u64, event: &geng::Event, cursor: Vec2<f32>) {
+        if let Self::Record { recording, .. } = self {
+            if let Some(event) = RecordedEvent::from_event(event) {
+                recording.events.push(RecordedInput {
+                    tick,
+                    cursor,
+                    event: Some(event),
+                });
+            }
+        }
+    }
+    // The world cursor also moves with the camera, so it is sampled every tick
+    pub fn capture_cursor(&mut self, tick: u64, cursor: Vec2<f32>) {
+        if let Self::Record { recording, .. } = self {
+            if recording.events.last().map(|input| input.cursor) != Some(cursor) {
+                recording.events.push(RecordedInput {
+                    tick,
+                    cursor,
+                    event: None,
+                });
+            }
+        }
+    }
+    // Inputs recorded before the given tick ran, with the world cursor at that moment
+    pub fn due(&mut self, tick: u64) -> Vec<(Option<geng::Event>, Vec2<f32>)> {
+        let (recording, next) = match self {
+            Self::Replay { recording, next } => (recording, next),
+            Self::Record { .. } => return Vec::new(),
+        };
+        let mut events = Vec::new();
+        while let Some(input) = recording
+            .events
+            .get(*next)
+            .filter(|input| input.tick <= tick)
+        {
+            events.push((input.event.map(RecordedEvent::event), input.cursor));
+            *next += 1;
+        }
+        events
+    }
+    pub fn save(&self) {
+        if let Self::Record { path, recording } = self {
+            match recording.save(path) {
+                Ok(()) => info!("Saved input recording to {:?}", path),
+                Err(e) => error!("Failed to save input recording: {}", e),
+            }
+        }
+    }
+}
+
+impl Drop for InputRecorder {
+    fn drop(&mut self) {
+        self.save();
+    }
+}
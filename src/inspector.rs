@@ -0,0 +1,478 @@
+use super::*;
+
+const PANEL_WIDTH: f32 = 360.0;
+const PANEL_ROW_HEIGHT: f32 = 26.0;
+const PANEL_TEXT_SIZE: f32 = 22.0;
+const PANEL_PADDING: f32 = 12.0;
+const PANEL_HINTS: &str = "Up/Down - select, Left/Right - change, Backspace - clear, Esc - close";
+const SPEED_STEP: f32 = 0.5;
+const RADIUS_STEP: f32 = 0.25;
+const STRENGTH_STEP: f32 = 1.0;
+const WIND_STEP: f32 = 1.0;
+const WIND_ANGLE_STEP: f32 = f32::PI / 12.0;
+const HUE_STEP: f32 = 0.05;
+const FOV_STEP: f32 = 5.0;
+const BLEND_STEP: f32 = 0.25;
+const WAYPOINT_OFFSET: Vec2<f32> = vec2(2.0, 0.0);
+
+// Snapshot of one entity, edits replace it as a whole so undo just swaps it back
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub enum EntityData {
+    Spawn(Vec2<f32>),
+    Goal(Goal),
+    Checkpoint(Checkpoint),
+    Collectible(Collectible),
+    Pickup(Pickup),
+    Hook(HookPoint),
+    Switch(Switch),
+    Sign(Sign),
+    Door(Door),
+    Breakable(Vec2<f32>),
+    Hazard(HazardTile),
+    Platform(PlatformConfig),
+    Enemy(EnemyConfig),
+    ForceZone(ForceZone),
+    GravityZone(GravityZone),
+    CameraZone(CameraZone),
+    Water(WaterVolume),
+    Hint(TutorialHint),
+    Palette(LevelPalette),
+}
+
+impl EntityRef {
+    pub fn get(self, level: &Level) -> Option<EntityData> {
+        Some(match self {
+            Self::Spawn => EntityData::Spawn(level.spawn_pos()),
+            Self::Goal => EntityData::Goal(level.goal.clone()?),
+            Self::Checkpoint(i) => EntityData::Checkpoint(level.checkpoints.get(i)?.clone()),
+            Self::Collectible(i) => EntityData::Collectible(level.collectibles.get(i)?.clone()),
+            Self::Pickup(i) => EntityData::Pickup(level.pickups.get(i)?.clone()),
+            Self::Hook(i) => EntityData::Hook(level.hooks.get(i)?.clone()),
+            Self::Switch(i) => EntityData::Switch(level.switches.get(i)?.clone()),
+            Self::Sign(i) => EntityData::Sign(level.signs.get(i)?.clone()),
+            Self::Door(i) => EntityData::Door(level.doors.get(i)?.clone()),
+            Self::Breakable(i) => EntityData::Breakable(*level.breakables.get(i)?),
+            Self::Hazard(i) => EntityData::Hazard(level.hazards.get(i)?.clone()),
+            Self::Platform(i) => EntityData::Platform(level.platforms.get(i)?.clone()),
+            Self::Enemy(i) => EntityData::Enemy(level.enemies.get(i)?.clone()),
+            Self::ForceZone(i) => EntityData::ForceZone(level.force_zones.get(i)?.clone()),
+            Self::GravityZone(i) => EntityData::GravityZone(level.gravity_zones.get(i)?.clone()),
+            Self::CameraZone(i) => EntityData::CameraZone(level.camera_zones.get(i)?.clone()),
+            Self::Water(i) => EntityData::Water(level.water.get(i)?.clone()),
+            Self::Hint(i) => EntityData::Hint(level.hints.get(i)?.clone()),
+            Self::Palette => EntityData::Palette(level.palette.clone()),
+        })
+    }
+    pub fn set(self, level: &mut Level, data: EntityData) -> bool {
+        fn put<T>(slot: Option<&mut T>, value: T) -> bool {
+            match slot {
+                Some(slot) => {
+                    *slot = value;
+                    true
+                }
+                None => false,
+            }
+        }
+        match (self, data) {
+            (Self::Spawn, EntityData::Spawn(pos)) => put(Some(&mut level.spawn), Some(pos)),
+            (Self::Goal, EntityData::Goal(goal)) => put(level.goal.as_mut(), goal),
+            (Self::Checkpoint(i), EntityData::Checkpoint(data)) => {
+                put(level.checkpoints.get_mut(i), data)
+            }
+            (Self::Collectible(i), EntityData::Collectible(data)) => {
+                put(level.collectibles.get_mut(i), data)
+            }
+            (Self::Pickup(i), EntityData::Pickup(data)) => put(level.pickups.get_mut(i), data),
+            (Self::Hook(i), EntityData::Hook(data)) => put(level.hooks.get_mut(i), data),
+            (Self::Switch(i), EntityData::Switch(data)) => put(level.switches.get_mut(i), data),
+            (Self::Sign(i), EntityData::Sign(data)) => put(level.signs.get_mut(i), data),
+            (Self::Door(i), EntityData::Door(data)) => put(level.doors.get_mut(i), data),
+            (Self::Breakable(i), EntityData::Breakable(data)) => {
+                put(level.breakables.get_mut(i), data)
+            }
+            (Self::Hazard(i), EntityData::Hazard(data)) => put(level.hazards.get_mut(i), data),
+            (Self::Platform(i), EntityData::Platform(data)) => {
+                put(level.platforms.get_mut(i), data)
+            }
+            (Self::Enemy(i), EntityData::Enemy(data)) => put(level.enemies.get_mut(i), data),
+            (Self::ForceZone(i), EntityData::ForceZone(data)) => {
+                put(level.force_zones.get_mut(i), data)
+            }
+            (Self::GravityZone(i), EntityData::GravityZone(data)) => {
+                put(level.gravity_zones.get_mut(i), data)
+            }
+            (Self::CameraZone(i), EntityData::CameraZone(data)) => {
+                put(level.camera_zones.get_mut(i), data)
+            }
+            (Self::Water(i), EntityData::Water(data)) => put(level.water.get_mut(i), data),
+            (Self::Hint(i), EntityData::Hint(data)) => put(level.hints.get_mut(i), data),
+            (Self::Palette, EntityData::Palette(data)) => put(Some(&mut level.palette), data),
+            _ => false,
+        }
+    }
+}
+
+fn cycle<T: Copy + PartialEq>(options: &[T], current: T, direction: i32) -> T {
+    let index = options.iter().position(|&x| x == current).unwrap_or(0) as i32;
+    options[(index + direction).rem_euclid(options.len() as i32) as usize]
+}
+
+fn format_path(path: &[Vec2<f32>]) -> String {
+    path.iter()
+        .map(|p| format!("({:.1}, {:.1})", p.x, p.y))
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+fn adjust_path(path: &mut Vec<Vec2<f32>>, direction: i32) {
+    match direction {
+        1 => {
+            let last = path.last().copied().unwrap_or(vec2(0.0, 0.0));
+            path.push(last + WAYPOINT_OFFSET);
+        }
+        _ if path.len() > 1 => {
+            path.pop();
+        }
+        _ => {}
+    }
+}
+
+const PALETTE_ROWS: [&str; 4] = ["Background", "Tiles", "Hazard", "Light"];
+
+fn palette_color(palette: &mut LevelPalette, index: usize) -> &mut Option<HsvColor> {
+    match index {
+        0 => &mut palette.background,
+        1 => &mut palette.tiles,
+        2 => &mut palette.hazard,
+        _ => &mut palette.light,
+    }
+}
+
+impl EntityData {
+    pub fn title(&self) -> &'static str {
+        match self {
+            Self::Spawn(_) => "Spawn",
+            Self::Goal(_) => "Goal",
+            Self::Checkpoint(_) => "Checkpoint",
+            Self::Collectible(_) => "Collectible",
+            Self::Pickup(_) => "Pickup",
+            Self::Hook(_) => "Hook",
+            Self::Switch(_) => "Switch",
+            Self::Sign(_) => "Sign",
+            Self::Door(_) => "Door",
+            Self::Breakable(_) => "Breakable",
+            Self::Hazard(_) => "Hazard",
+            Self::Platform(_) => "Platform",
+            Self::Enemy(_) => "Enemy",
+            Self::ForceZone(_) => "Wind",
+            Self::GravityZone(_) => "Gravity zone",
+            Self::CameraZone(_) => "Camera zone",
+            Self::Water(_) => "Water",
+            Self::Hint(_) => "Hint",
+            Self::Palette(_) => "Level palette",
+        }
+    }
+    pub fn properties(&self) -> Vec<String> {
+        match self {
+            Self::Spawn(pos) | Self::Breakable(pos) => {
+                vec![format!("Position: ({:.1}, {:.1})", pos.x, pos.y)]
+            }
+            Self::Goal(Goal { radius, .. }) | Self::Checkpoint(Checkpoint { radius, .. }) => {
+                vec![format!("Radius: {:.2}", radius)]
+            }
+            Self::Collectible(collectible) => vec![format!("Kind: {:?}", collectible.kind)],
+            Self::Pickup(pickup) => vec![format!("Kind: {:?}", pickup.kind)],
+            Self::Hook(hook) => vec![
+                format!("Latch radius: {:.2}", hook.radius),
+                format!("Strength: {:.1}", hook.strength),
+            ],
+            Self::Switch(switch) => vec![format!("Id: {}", switch.id)],
+            Self::Sign(sign) => vec![format!("Text: {}", sign.text)],
+            Self::Door(door) => vec![format!("Switch: {}", door.switch)],
+            Self::Hazard(hazard) => vec![format!("Material: {:?}", hazard.material)],
+            Self::Platform(platform) => vec![
+                format!("Speed: {:.1}", platform.speed),
+                format!("Mode: {:?}", platform.mode),
+                format!("Material: {:?}", platform.material),
+                format!("Waypoints: {}", format_path(&platform.path)),
+            ],
+            Self::Enemy(enemy) => vec![
+                format!("Speed: {:.1}", enemy.speed),
+                format!("Mode: {:?}", enemy.mode),
+                format!("Radius: {:.2}", enemy.radius),
+                format!("Waypoints: {}", format_path(&enemy.path)),
+            ],
+            Self::ForceZone(zone) => vec![
+                format!("Strength: {:.1}", zone.acceleration.len()),
+                format!(
+                    "Angle: {:.0}",
+                    zone.acceleration.arg().to_degrees().rem_euclid(360.0)
+                ),
+            ],
+            Self::GravityZone(zone) => vec![format!("Direction: {:?}", zone.direction)],
+            Self::CameraZone(zone) => vec![
+                format!("Framing: {:?}", zone.framing),
+                match zone.fov {
+                    Some(fov) => format!("Zoom: {:.0}", fov),
+                    None => "Zoom: follow".to_owned(),
+                },
+                format!("Priority: {}", zone.priority),
+                format!("Blend: {:.2}s", zone.blend),
+            ],
+            Self::Water(water) => vec![format!(
+                "Size: {:.1} x {:.1}",
+                water.aabb.width(),
+                water.aabb.height()
+            )],
+            Self::Hint(hint) => vec![
+                format!("Text: {}", hint.text),
+                format!("Action: {:?}", hint.action),
+            ],
+            Self::Palette(palette) => {
+                let mut palette = palette.clone();
+                PALETTE_ROWS
+                    .iter()
+                    .enumerate()
+                    .map(|(index, name)| match palette_color(&mut palette, index) {
+                        Some(color) => format!("{} hue: {:.2}", name, color.h),
+                        None => format!("{}: theme", name),
+                    })
+                    .collect()
+            }
+        }
+    }
+    // Direction 0 resets the property, returns false if it can't be changed
+    pub fn adjust(&mut self, level: &Level, index: usize, direction: i32) -> bool {
+        let step = direction as f32;
+        match self {
+            Self::Goal(Goal { radius, .. }) | Self::Checkpoint(Checkpoint { radius, .. }) => {
+                *radius = (*radius + step * RADIUS_STEP).max(RADIUS_STEP)
+            }
+            Self::Collectible(collectible) => {
+                collectible.kind = cycle(
+                    &[CollectibleKind::Coin, CollectibleKind::Gem],
+                    collectible.kind,
+                    direction,
+                )
+            }
+            Self::Hook(hook) => match index {
+                0 => hook.radius = (hook.radius + step * RADIUS_STEP).max(RADIUS_STEP),
+                _ => hook.strength = (hook.strength + step * STRENGTH_STEP).max(0.0),
+            },
+            Self::Switch(switch) => {
+                let number: i32 = switch.id.trim_start_matches("switch").parse().unwrap_or(0);
+                switch.id = format!("switch{}", (number + direction).max(0));
+            }
+            Self::Door(door) => {
+                let ids: Vec<&str> = level.switches.iter().map(|s| s.id.as_str()).collect();
+                if ids.is_empty() {
+                    return false;
+                }
+                door.switch = cycle(&ids, door.switch.as_str(), direction).to_owned();
+            }
+            Self::Hazard(hazard) => {
+                hazard.material = cycle(
+                    &[Material::Spikes, Material::Lava],
+                    hazard.material,
+                    direction,
+                )
+            }
+            Self::Platform(platform) => match index {
+                0 => platform.speed = (platform.speed + step * SPEED_STEP).max(0.0),
+                1 => {
+                    platform.mode = cycle(
+                        &[PathMode::PingPong, PathMode::Loop],
+                        platform.mode,
+                        direction,
+                    )
+                }
+                2 => {
+                    platform.material = cycle(
+                        &[
+                            Material::Stone,
+                            Material::Ice,
+                            Material::Rubber,
+                            Material::Sticky,
+                        ],
+                        platform.material,
+                        direction,
+                    )
+                }
+                _ => adjust_path(&mut platform.path, direction),
+            },
+            Self::Enemy(enemy) => match index {
+                0 => enemy.speed = (enemy.speed + step * SPEED_STEP).max(0.0),
+                1 => {
+                    enemy.mode = cycle(&[PathMode::PingPong, PathMode::Loop], enemy.mode, direction)
+                }
+                2 => enemy.radius = (enemy.radius + step * RADIUS_STEP).max(RADIUS_STEP),
+                _ => adjust_path(&mut enemy.path, direction),
+            },
+            Self::ForceZone(zone) => {
+                let (strength, angle) = (zone.acceleration.len(), zone.acceleration.arg());
+                zone.acceleration = match index {
+                    0 => Vec2::rotated(vec2((strength + step * WIND_STEP).max(0.0), 0.0), angle),
+                    _ => Vec2::rotated(vec2(strength, 0.0), angle + step * WIND_ANGLE_STEP),
+                };
+            }
+            Self::GravityZone(zone) => {
+                zone.direction = cycle(
+                    &[
+                        GravityDirection::Down,
+                        GravityDirection::Left,
+                        GravityDirection::Up,
+                        GravityDirection::Right,
+                    ],
+                    zone.direction,
+                    direction,
+                )
+            }
+            Self::CameraZone(zone) => match index {
+                0 => {
+                    zone.framing = cycle(
+                        &[
+                            CameraFraming::Follow,
+                            CameraFraming::Fixed,
+                            CameraFraming::Horizontal,
+                            CameraFraming::Vertical,
+                        ],
+                        zone.framing,
+                        direction,
+                    )
+                }
+                1 => {
+                    zone.fov = match (zone.fov, direction) {
+                        (_, 0) => None,
+                        (Some(fov), _) => Some((fov + step * FOV_STEP).max(FOV_STEP)),
+                        (None, _) => Some(30.0),
+                    }
+                }
+                2 => zone.priority += direction,
+                _ => zone.blend = (zone.blend + step * BLEND_STEP).max(0.0),
+            },
+            Self::Hint(hint) => {
+                if index == 0 {
+                    return false;
+                }
+                hint.action = cycle(
+                    &[
+                        None,
+                        Some(HintAction::Move),
+                        Some(HintAction::Jump),
+                        Some(HintAction::Spin),
+                        Some(HintAction::Throw),
+                        Some(HintAction::Reel),
+                        Some(HintAction::AdjustChain),
+                    ],
+                    hint.action,
+                    direction,
+                )
+            }
+            Self::Palette(palette) => {
+                let color = palette_color(palette, index);
+                *color = match (*color, direction) {
+                    (_, 0) => None,
+                    (Some(color), _) => Some(HsvColor {
+                        h: (color.h + step * HUE_STEP).rem_euclid(1.0),
+                        ..color
+                    }),
+                    (None, _) => Some(HsvColor {
+                        h: 0.0,
+                        s: 0.5,
+                        v: 0.6,
+                        a: 1.0,
+                    }),
+                };
+            }
+            Self::Spawn(_)
+            | Self::Breakable(_)
+            | Self::Pickup(_)
+            | Self::Sign(_)
+            | Self::Water(_) => return false,
+        }
+        true
+    }
+}
+
+pub struct Inspector {
+    pub entity: EntityRef,
+    selected: usize,
+}
+
+impl Inspector {
+    pub fn new(entity: EntityRef) -> Self {
+        Self {
+            entity,
+            selected: 0,
+        }
+    }
+    // Keys edit a copy of the entity, the editor turns the result into an undoable command
+    pub fn handle_key(&mut self, key: geng::Key, level: &Level) -> Option<EditorCommand> {
+        let before = self.entity.get(level)?;
+        let count = before.properties().len();
+        let direction = match key {
+            geng::Key::Up => {
+                self.selected = (self.selected + count - 1) % count;
+                return None;
+            }
+            geng::Key::Down => {
+                self.selected = (self.selected + 1) % count;
+                return None;
+            }
+            geng::Key::Left => -1,
+            geng::Key::Right => 1,
+            geng::Key::Backspace => 0,
+            _ => return None,
+        };
+        let mut after = before.clone();
+        if !after.adjust(level, self.selected, direction) {
+            return None;
+        }
+        Some(EditorCommand::Replace {
+            entity: self.entity,
+            before,
+            after,
+        })
+    }
+    pub fn draw(
+        &self,
+        framebuffer: &mut ugli::Framebuffer,
+        renderer: &Renderer,
+        font: &geng::Font,
+        level: &Level,
+        color: Color<f32>,
+    ) {
+        let data = match self.entity.get(level) {
+            Some(data) => data,
+            None => return,
+        };
+        let properties = data.properties();
+        let ui_size = UiCamera::size(framebuffer.size().map(|x| x as f32));
+        let height = (properties.len() + 2) as f32 * PANEL_ROW_HEIGHT + PANEL_PADDING * 2.0;
+        let panel = AABB::pos_size(
+            vec2(PANEL_PADDING, ui_size.y - height - 60.0),
+            vec2(PANEL_WIDTH, height),
+        );
+        renderer.draw_ui_rect(framebuffer, panel, Color::rgba(0.0, 0.0, 0.0, 0.7));
+        let mut pos = vec2(panel.x_min + PANEL_PADDING, panel.y_max - PANEL_PADDING);
+        let mut line = |text: &str, color: Color<f32>| {
+            pos.y -= PANEL_ROW_HEIGHT;
+            draw_ui_text(framebuffer, font, text, pos, 0.0, PANEL_TEXT_SIZE, color);
+        };
+        line(data.title(), color);
+        for (index, property) in properties.iter().enumerate() {
+            if index == self.selected {
+                line(&format!("> {}", property), color);
+            } else {
+                line(
+                    &format!("  {}", property),
+                    Color {
+                        a: color.a * 0.7,
+                        ..color
+                    },
+                );
+            }
+        }
+        line(PANEL_HINTS, Color { a: 0.5, ..color });
+    }
+}
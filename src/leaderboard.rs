@@ -0,0 +1,136 @@
+use super::*;
+
+pub const LEADERBOARD_SIZE: usize = 10;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct Submission {
+    name: String,
+    time: f32,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LeaderboardEntry {
+    pub name: String,
+    pub time: f32,
+}
+
+// Expected reply to a submission, rank is 1-based
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Leaderboard {
+    pub top: Vec<LeaderboardEntry>,
+    pub rank: Option<usize>,
+}
+
+pub enum LeaderboardState {
+    Pending,
+    Ready(Leaderboard),
+    Offline,
+}
+
+// A time posted as json to `<endpoint>/<level>`, answered with the level's leaderboard
+pub struct LeaderboardRequest {
+    pub state: LeaderboardState,
+    #[cfg(not(target_arch = "wasm32"))]
+    receiver: std::sync::mpsc::Receiver<anyhow::Result<Leaderboard>>,
+    #[cfg(target_arch = "wasm32")]
+    request: web_sys::XmlHttpRequest,
+}
+
+impl LeaderboardRequest {
+    pub fn submit(endpoint: &str, level: &str, name: &str, time: f32) -> Self {
+        let url = format!("{}/{}", endpoint.trim_end_matches('/'), level);
+        let submission = Submission {
+            name: name.to_owned(),
+            time,
+        };
+        #[cfg(not(target_arch = "wasm32"))]
+        {
+            let (sender, receiver) = std::sync::mpsc::channel();
+            std::thread::spawn(move || {
+                let result = ureq::post(&url)
+                    .send_json(&submission)
+                    .map_err(anyhow::Error::from)
+                    .and_then(|response| Ok(response.into_json()?));
+                let _ = sender.send(result);
+            });
+            Self {
+                state: LeaderboardState::Pending,
+                receiver,
+            }
+        }
+        #[cfg(target_arch = "wasm32")]
+        {
+            let request = web_sys::XmlHttpRequest::new().unwrap();
+            let sent = request
+                .open("POST", &url)
+                .and_then(|()| request.set_request_header("Content-Type", "application/json"))
+                .and_then(|()| {
+                    request.send_with_opt_str(Some(&serde_json::to_string(&submission).unwrap()))
+                });
+            Self {
+                state: if sent.is_ok() {
+                    LeaderboardState::Pending
+                } else {
+                    LeaderboardState::Offline
+                },
+                request,
+            }
+        }
+    }
+    pub fn poll(&mut self) {
+        if !matches!(self.state, LeaderboardState::Pending) {
+            return;
+        }
+        #[cfg(not(target_arch = "wasm32"))]
+        let result = match self.receiver.try_recv() {
+            Ok(result) => result,
+            Err(std::sync::mpsc::TryRecvError::Empty) => return,
+            Err(std::sync::mpsc::TryRecvError::Disconnected) => Err(anyhow!("Request failed")),
+        };
+        #[cfg(target_arch = "wasm32")]
+        let result = {
+            const DONE: u16 = 4;
+            if self.request.ready_state() != DONE {
+                return;
+            }
+            match self.request.status() {
+                Ok(200) => self
+                    .request
+                    .response_text()
+                    .ok()
+                    .flatten()
+                    .ok_or_else(|| anyhow!("Empty response"))
+                    .and_then(|text| Ok(serde_json::from_str(&text)?)),
+                _ => Err(anyhow!("Request failed")),
+            }
+        };
+        self.state = match result {
+            Ok(leaderboard) => LeaderboardState::Ready(leaderboard),
+            Err(e) => {
+                warn!("Leaderboard unavailable: {}", e);
+                LeaderboardState::Offline
+            }
+        };
+    }
+    pub fn lines(&self) -> Vec<String> {
+        match &self.state {
+            LeaderboardState::Pending => vec![tr("leaderboard.loading")],
+            LeaderboardState::Offline => vec![tr("leaderboard.offline")],
+            LeaderboardState::Ready(leaderboard) => {
+                let mut lines = vec![tr("leaderboard.title")];
+                for (index, entry) in leaderboard.top.iter().take(LEADERBOARD_SIZE).enumerate() {
+                    lines.push(format!(
+                        "{}. {} {}",
+                        index + 1,
+                        entry.name,
+                        format_time(entry.time)
+                    ));
+                }
+                if let Some(rank) = leaderboard.rank {
+                    lines.push(trf("leaderboard.rank", &[&rank]));
+                }
+                lines
+            }
+        }
+    }
+}
@@ -0,0 +1,766 @@
+use super::*;
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub enum PathMode {
+    #[default]
+    PingPong,
+    Loop,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PlatformConfig {
+    pub tiles: Vec<Vec2<f32>>,
+    pub path: Vec<Vec2<f32>>,
+    pub speed: f32,
+    #[serde(default)]
+    pub mode: PathMode,
+    #[serde(default)]
+    pub material: Material,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct HazardTile {
+    pub pos: Vec2<f32>,
+    #[serde(default = "HazardTile::default_material")]
+    pub material: Material,
+}
+
+impl HazardTile {
+    fn default_material() -> Material {
+        Material::Spikes
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Checkpoint {
+    pub pos: Vec2<f32>,
+    #[serde(default = "Checkpoint::default_radius")]
+    pub radius: f32,
+}
+
+impl Checkpoint {
+    pub(crate) fn default_radius() -> f32 {
+        1.0
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Goal {
+    pub pos: Vec2<f32>,
+    #[serde(default = "Goal::default_radius")]
+    pub radius: f32,
+}
+
+impl Goal {
+    pub(crate) fn default_radius() -> f32 {
+        1.0
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ForceZone {
+    pub aabb: AABB<f32>,
+    pub acceleration: Vec2<f32>,
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub enum CameraFraming {
+    // Regular follow, only the zoom can be forced
+    #[default]
+    Follow,
+    // Stays on the zone center
+    Fixed,
+    // Follows along one axis, the other stays on the zone center
+    Horizontal,
+    Vertical,
+}
+
+// Overrides the camera while the character is inside
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct CameraZone {
+    pub aabb: AABB<f32>,
+    #[serde(default)]
+    pub framing: CameraFraming,
+    #[serde(default)]
+    pub fov: Option<f32>,
+    // The highest one wins where zones overlap
+    #[serde(default)]
+    pub priority: i32,
+    // Seconds to ease in and out
+    #[serde(default = "CameraZone::default_blend")]
+    pub blend: f32,
+}
+
+impl CameraZone {
+    fn default_blend() -> f32 {
+        0.5
+    }
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum GravityDirection {
+    Down,
+    Up,
+    Left,
+    Right,
+}
+
+impl GravityDirection {
+    pub fn vec(self) -> Vec2<f32> {
+        match self {
+            Self::Down => vec2(0.0, -1.0),
+            Self::Up => vec2(0.0, 1.0),
+            Self::Left => vec2(-1.0, 0.0),
+            Self::Right => vec2(1.0, 0.0),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct GravityZone {
+    pub aabb: AABB<f32>,
+    pub direction: GravityDirection,
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum HintAction {
+    Move,
+    Jump,
+    Spin,
+    Throw,
+    Reel,
+    AdjustChain,
+}
+
+// Text shown while the character is inside the area,
+// gone for the rest of the level once its action is performed
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TutorialHint {
+    pub aabb: AABB<f32>,
+    pub text: String,
+    #[serde(default)]
+    pub action: Option<HintAction>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WaterVolume {
+    pub aabb: AABB<f32>,
+}
+
+impl WaterVolume {
+    pub fn submersion(&self, pos: Vec2<f32>, radius: f32) -> f32 {
+        if pos.x < self.aabb.x_min || pos.x > self.aabb.x_max {
+            return 0.0;
+        }
+        let bottom = (pos.y - radius).max(self.aabb.y_min);
+        let top = (pos.y + radius).min(self.aabb.y_max);
+        clamp((top - bottom) / (2.0 * radius), 0.0..=1.0)
+    }
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub enum CollectibleKind {
+    #[default]
+    Coin,
+    Gem,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Collectible {
+    pub pos: Vec2<f32>,
+    #[serde(default)]
+    pub kind: CollectibleKind,
+}
+
+impl Collectible {
+    pub fn radius(&self) -> f32 {
+        match self.kind {
+            CollectibleKind::Coin => 0.4,
+            CollectibleKind::Gem => 0.5,
+        }
+    }
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub enum BallKind {
+    #[default]
+    Normal,
+    Heavy,
+    Rubber,
+    Hook,
+}
+
+impl BallKind {
+    pub const ALL: [Self; 4] = [Self::Normal, Self::Heavy, Self::Rubber, Self::Hook];
+    pub fn size(self) -> f32 {
+        match self {
+            Self::Normal => 0.5,
+            Self::Heavy => 0.8,
+            Self::Rubber => 0.5,
+            Self::Hook => 0.3,
+        }
+    }
+    pub fn density(self) -> f32 {
+        match self {
+            Self::Normal => 3.0,
+            Self::Heavy => 6.0,
+            Self::Rubber => 1.5,
+            Self::Hook => 2.0,
+        }
+    }
+    pub fn gravity_scale(self) -> f32 {
+        match self {
+            Self::Normal => 1.0,
+            Self::Heavy => 1.5,
+            Self::Rubber => 1.0,
+            Self::Hook => 0.5,
+        }
+    }
+    pub fn restitution(self) -> f32 {
+        match self {
+            Self::Rubber => 0.7,
+            _ => 0.0,
+        }
+    }
+    pub fn reel_speed(self) -> f32 {
+        match self {
+            Self::Heavy => 0.6,
+            Self::Hook => 1.5,
+            _ => 1.0,
+        }
+    }
+    pub fn grapples(self) -> bool {
+        self == Self::Hook
+    }
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum PickupKind {
+    StickyBall,
+    Ball(BallKind),
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Pickup {
+    pub pos: Vec2<f32>,
+    pub kind: PickupKind,
+}
+
+impl Pickup {
+    pub const RADIUS: f32 = 0.5;
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct HookPoint {
+    pub pos: Vec2<f32>,
+    // How close a thrown ball has to pass to get pulled in
+    #[serde(default = "HookPoint::default_radius")]
+    pub radius: f32,
+    // How quickly the ball turns towards the hook, per second
+    #[serde(default = "HookPoint::default_strength")]
+    pub strength: f32,
+}
+
+impl HookPoint {
+    pub const SIZE: f32 = 0.4;
+    fn default_radius() -> f32 {
+        3.0
+    }
+    fn default_strength() -> f32 {
+        12.0
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Switch {
+    pub id: String,
+    pub pos: Vec2<f32>,
+}
+
+impl Switch {
+    pub const RADIUS: f32 = 0.5;
+}
+
+// Text is a localization key, read by walking up and pressing the interact key
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Sign {
+    pub pos: Vec2<f32>,
+    pub text: String,
+}
+
+impl Sign {
+    pub const RADIUS: f32 = 1.5;
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Door {
+    pub switch: String,
+    pub p1: Vec2<f32>,
+    pub p2: Vec2<f32>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct EnemyConfig {
+    pub path: Vec<Vec2<f32>>,
+    pub speed: f32,
+    #[serde(default)]
+    pub mode: PathMode,
+    #[serde(default = "EnemyConfig::default_radius")]
+    pub radius: f32,
+}
+
+impl EnemyConfig {
+    fn default_radius() -> f32 {
+        0.8
+    }
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub enum LightAnchor {
+    #[default]
+    World,
+    Player,
+    Goal,
+    Hazards,
+}
+
+// For anchored lights pos is an offset from the anchor
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Light {
+    #[serde(default)]
+    pub anchor: LightAnchor,
+    #[serde(default = "Light::default_pos")]
+    pub pos: Vec2<f32>,
+    pub radius: f32,
+    // Falls back to the theme light color
+    #[serde(default)]
+    pub color: Option<Color<f32>>,
+}
+
+impl Light {
+    fn default_pos() -> Vec2<f32> {
+        vec2(0.0, 0.0)
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Lighting {
+    pub ambient: Color<f32>,
+    #[serde(default)]
+    pub lights: Vec<Light>,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct LevelMetadata {
+    pub name: Option<String>,
+    pub author: Option<String>,
+    pub description: Option<String>,
+}
+
+// Per level colors on top of the selected theme
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct LevelPalette {
+    pub background: Option<HsvColor>,
+    pub tiles: Option<HsvColor>,
+    pub hazard: Option<HsvColor>,
+    pub light: Option<HsvColor>,
+}
+
+// What moves the sky between keyframes
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum AmbienceDriver {
+    // Keyframes are fractions of a day lasting `cycle` seconds
+    Time,
+    // Keyframes are world x positions, giving each zone of the level its own sky
+    CameraX,
+}
+
+#[derive(Debug, Copy, Clone, Serialize, Deserialize)]
+pub struct AmbienceKeyframe {
+    pub at: f32,
+    pub sky: HsvColor,
+    pub horizon: HsvColor,
+    pub clouds: HsvColor,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Ambience {
+    pub driver: AmbienceDriver,
+    #[serde(default = "Ambience::default_cycle")]
+    pub cycle: f32,
+    pub keyframes: Vec<AmbienceKeyframe>,
+    #[serde(default)]
+    pub clouds: usize,
+    #[serde(default = "Ambience::default_cloud_speed")]
+    pub cloud_speed: f32,
+}
+
+impl Ambience {
+    fn default_cycle() -> f32 {
+        120.0
+    }
+    fn default_cloud_speed() -> f32 {
+        10.0
+    }
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+pub enum Medal {
+    Bronze,
+    Silver,
+    Gold,
+}
+
+impl Medal {
+    // Best first, the order a run chases them in
+    pub const ALL: [Self; 3] = [Self::Gold, Self::Silver, Self::Bronze];
+    pub fn name(self) -> String {
+        tr(match self {
+            Self::Bronze => "medal.bronze",
+            Self::Silver => "medal.silver",
+            Self::Gold => "medal.gold",
+        })
+    }
+    pub fn color(self) -> Color<f32> {
+        match self {
+            Self::Bronze => Color::rgb(0.8, 0.5, 0.2),
+            Self::Silver => Color::rgb(0.75, 0.75, 0.8),
+            Self::Gold => Color::rgb(1.0, 0.8, 0.1),
+        }
+    }
+}
+
+// Times to beat in seconds
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MedalTimes {
+    pub bronze: f32,
+    pub silver: f32,
+    pub gold: f32,
+}
+
+impl MedalTimes {
+    pub fn time(&self, medal: Medal) -> f32 {
+        match medal {
+            Medal::Bronze => self.bronze,
+            Medal::Silver => self.silver,
+            Medal::Gold => self.gold,
+        }
+    }
+    pub fn medal(&self, time: f32) -> Option<Medal> {
+        Medal::ALL
+            .iter()
+            .copied()
+            .find(|&medal| time <= self.time(medal))
+    }
+}
+
+// Gameplay modifiers, picked in the menu or forced by a level, both sets apply together
+#[derive(Debug, Copy, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Mutators {
+    pub low_gravity: bool,
+    pub unbreakable_chain: bool,
+    // A second ball hangs off the first on a short chain
+    pub double_ball: bool,
+    pub mirror: bool,
+    pub no_checkpoints: bool,
+}
+
+impl Mutators {
+    // Localization keys in the order `get` and `toggle` index them
+    pub const NAMES: [&'static str; 5] = [
+        "mutator.low_gravity",
+        "mutator.unbreakable_chain",
+        "mutator.double_ball",
+        "mutator.mirror",
+        "mutator.no_checkpoints",
+    ];
+    const LOW_GRAVITY: f32 = 0.5;
+    fn field(&mut self, index: usize) -> &mut bool {
+        match index {
+            0 => &mut self.low_gravity,
+            1 => &mut self.unbreakable_chain,
+            2 => &mut self.double_ball,
+            3 => &mut self.mirror,
+            _ => &mut self.no_checkpoints,
+        }
+    }
+    pub fn get(mut self, index: usize) -> bool {
+        *self.field(index)
+    }
+    pub fn toggle(&mut self, index: usize) {
+        let field = self.field(index);
+        *field = !*field;
+    }
+    pub fn union(mut self, other: Self) -> Self {
+        for index in 0..Self::NAMES.len() {
+            *self.field(index) |= other.get(index);
+        }
+        self
+    }
+    pub fn gravity_scale(&self) -> f32 {
+        if self.low_gravity {
+            Self::LOW_GRAVITY
+        } else {
+            1.0
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct RewindConfig {
+    // How far back the rewind reaches, zero turns it off
+    pub seconds: f32,
+    // Seconds added to the timer for every second rewound, on top of the time
+    // spent rewinding
+    pub cost: f32,
+}
+
+impl Default for RewindConfig {
+    fn default() -> Self {
+        Self {
+            seconds: 3.0,
+            cost: 0.5,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Level {
+    // Old tuple levels have no version and load as 0
+    pub version: u32,
+    pub metadata: LevelMetadata,
+    pub spawn: Option<Vec2<f32>>,
+    pub segments: Vec<Segment>,
+    pub tiles: Vec<Vec2<f32>>,
+    pub platforms: Vec<PlatformConfig>,
+    pub hazards: Vec<HazardTile>,
+    pub checkpoints: Vec<Checkpoint>,
+    pub goal: Option<Goal>,
+    pub force_zones: Vec<ForceZone>,
+    pub water: Vec<WaterVolume>,
+    pub breakables: Vec<Vec2<f32>>,
+    pub collectibles: Vec<Collectible>,
+    pub pickups: Vec<Pickup>,
+    pub hooks: Vec<HookPoint>,
+    pub switches: Vec<Switch>,
+    pub signs: Vec<Sign>,
+    pub doors: Vec<Door>,
+    pub enemies: Vec<EnemyConfig>,
+    pub gravity_zones: Vec<GravityZone>,
+    pub camera_zones: Vec<CameraZone>,
+    pub hints: Vec<TutorialHint>,
+    pub lighting: Option<Lighting>,
+    pub palette: LevelPalette,
+    pub ambience: Option<Ambience>,
+    pub rewind: RewindConfig,
+    pub medals: Option<MedalTimes>,
+    pub mutators: Mutators,
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum LevelError {
+    #[error("line {line}, column {column}: {message}")]
+    Parse {
+        line: usize,
+        column: usize,
+        message: String,
+    },
+    #[error("level version {found} is newer than supported {supported}")]
+    Version { found: u32, supported: u32 },
+}
+
+impl From<serde_json::Error> for LevelError {
+    fn from(e: serde_json::Error) -> Self {
+        // serde_json appends the position itself, it's kept separately here
+        let message = e.to_string();
+        let position = format!(" at line {} column {}", e.line(), e.column());
+        Self::Parse {
+            line: e.line(),
+            column: e.column(),
+            message: message
+                .strip_suffix(&position)
+                .unwrap_or(&message)
+                .to_owned(),
+        }
+    }
+}
+
+impl Level {
+    pub const VERSION: u32 = 1;
+    pub fn spawn_pos(&self) -> Vec2<f32> {
+        self.spawn.unwrap_or(vec2(0.0, 0.0))
+    }
+    pub fn from_json(data: &str) -> Result<Self, LevelError> {
+        // Old levels are stored as a (segments, tiles) pair
+        if let Ok((segments, tiles)) = serde_json::from_str(data) {
+            return Ok(Self {
+                segments,
+                tiles,
+                ..default()
+            });
+        }
+        if is_tiled_map(data) {
+            return Ok(import_tiled(data)?);
+        }
+        let level: Self = serde_json::from_str(data)?;
+        if level.version > Self::VERSION {
+            return Err(LevelError::Version {
+                found: level.version,
+                supported: Self::VERSION,
+            });
+        }
+        Ok(level)
+    }
+    pub fn to_json(&self) -> String {
+        serde_json::to_string_pretty(&Self {
+            version: Self::VERSION,
+            ..self.clone()
+        })
+        .unwrap()
+    }
+    // Edges shared with neighbours cancel out, so painted tiles keep a clean outline
+    pub fn set_tile(&mut self, pos: Vec2<f32>, filled: bool) -> bool {
+        match (self.tiles.iter().position(|&tile| tile == pos), filled) {
+            (Some(_), true) | (None, false) => return false,
+            (Some(index), false) => {
+                self.tiles.remove(index);
+            }
+            (None, true) => self.tiles.push(pos),
+        }
+        for edge in tile_segments(pos) {
+            let key = edge_key(&edge);
+            match self
+                .segments
+                .iter()
+                .position(|other| edge_key(other) == key)
+            {
+                Some(index) => {
+                    self.segments.remove(index);
+                }
+                None => self.segments.push(edge),
+            }
+        }
+        true
+    }
+    pub fn remove_segment(&mut self, segment: &Segment) -> bool {
+        let key = edge_key(segment);
+        match self.segments.iter().position(|other| {
+            edge_key(other) == key
+                && other.material == segment.material
+                && other.one_way == segment.one_way
+        }) {
+            Some(index) => {
+                self.segments.remove(index);
+                true
+            }
+            None => false,
+        }
+    }
+}
+
+pub struct LevelSet {
+    pub levels: Vec<(String, RefCell<Level>)>,
+}
+
+impl LevelSet {
+    pub fn find(&self, name: &str) -> Option<usize> {
+        self.levels
+            .iter()
+            .position(|(level_name, _)| level_name == name)
+    }
+}
+
+impl geng::LoadAsset for LevelSet {
+    fn load(geng: &Rc<Geng>, path: &str) -> geng::AssetFuture<Self> {
+        let geng = geng.clone();
+        let path = path.to_owned();
+        async move {
+            let list = <String as geng::LoadAsset>::load(&geng, &format!("{}/list.json", path));
+            let names: Vec<String> = serde_json::from_str(&list.await?)?;
+            let levels = future::try_join_all(names.iter().map(|name| {
+                <String as geng::LoadAsset>::load(&geng, &format!("{}/{}.json", path, name))
+            }))
+            .await?;
+            Ok(Self {
+                levels: names
+                    .into_iter()
+                    .zip(levels)
+                    .map(|(name, data)| {
+                        let level = Level::from_json(&data)
+                            .with_context(|| format!("{}/{}.json", path, name))?;
+                        Ok((name, RefCell::new(level)))
+                    })
+                    .collect::<Result<_, anyhow::Error>>()?,
+            })
+        }
+        .boxed_local()
+    }
+    const DEFAULT_EXT: Option<&'static str> = None;
+}
+
+pub fn tile_segments(pos: Vec2<f32>) -> [Segment; 4] {
+    [
+        Segment::new(pos, pos + vec2(1.0, 0.0)),
+        Segment::new(pos, pos + vec2(0.0, 1.0)),
+        Segment::new(pos + vec2(1.0, 1.0), pos + vec2(1.0, 0.0)),
+        Segment::new(pos + vec2(1.0, 1.0), pos + vec2(0.0, 1.0)),
+    ]
+}
+
+// Outer edges of a group of tiles, edges shared by two tiles are dropped
+pub fn tile_outline(tiles: impl IntoIterator<Item = Vec2<f32>>) -> Vec<Segment> {
+    let mut edges: HashMap<_, (Segment, usize)> = HashMap::new();
+    for tile in tiles {
+        for segment in tile_segments(tile) {
+            edges.entry(edge_key(&segment)).or_insert((segment, 0)).1 += 1;
+        }
+    }
+    optimize_segments(
+        &edges
+            .into_values()
+            .filter(|&(_, count)| count == 1)
+            .map(|(segment, _)| segment)
+            .collect::<Vec<_>>(),
+    )
+}
+
+fn path_edges(
+    path: &[Vec2<f32>],
+    mode: PathMode,
+) -> impl Iterator<Item = (Vec2<f32>, Vec2<f32>)> + '_ {
+    let closing = match mode {
+        PathMode::Loop if path.len() > 2 => Some((path[path.len() - 1], path[0])),
+        _ => None,
+    };
+    path.windows(2).map(|w| (w[0], w[1])).chain(closing)
+}
+
+pub fn path_position(path: &[Vec2<f32>], mode: PathMode, distance: f32) -> Vec2<f32> {
+    let start = path.first().copied().unwrap_or(vec2(0.0, 0.0));
+    let path_len: f32 = path_edges(path, mode)
+        .map(|(from, to)| (to - from).len())
+        .sum();
+    if path_len < EPS {
+        return start;
+    }
+    let mut distance = match mode {
+        PathMode::Loop => distance.rem_euclid(path_len),
+        PathMode::PingPong => {
+            let distance = distance.rem_euclid(2.0 * path_len);
+            path_len - (distance - path_len).abs()
+        }
+    };
+    for (from, to) in path_edges(path, mode) {
+        let len = (to - from).len();
+        if distance <= len {
+            return from + (to - from) * (distance / len.max(EPS));
+        }
+        distance -= len;
+    }
+    path.last().copied().unwrap_or(start)
+}
@@ -0,0 +1,70 @@
+use super::*;
+
+use miniz_oxide::inflate::TINFLStatus;
+
+// Levels keep untagged formats for old files, which needs a self describing encoding,
+// so codes are compact json, deflated and base64 encoded
+const LEVEL_CODE_PREFIX: &str = "BC1:";
+const COMPRESSION_LEVEL: u8 = 9;
+// Far above any real level, so a crafted code can't inflate into gigabytes
+const MAX_LEVEL_SIZE: usize = 4 << 20;
+
+pub fn encode_level(level: &Level) -> String {
+    let json = serde_json::to_vec(&Level {
+        version: Level::VERSION,
+        ..level.clone()
+    })
+    .unwrap();
+    let data = miniz_oxide::deflate::compress_to_vec(&json, COMPRESSION_LEVEL);
+    format!(
+        "{}{}",
+        LEVEL_CODE_PREFIX,
+        base64::encode_config(data, base64::URL_SAFE_NO_PAD)
+    )
+}
+
+pub fn decode_level(code: &str) -> anyhow::Result<Level> {
+    let code: String = code.chars().filter(|c| !c.is_whitespace()).collect();
+    let code = code
+        .strip_prefix(LEVEL_CODE_PREFIX)
+        .ok_or_else(|| anyhow!("Not a level code"))?;
+    let data = base64::decode_config(code, base64::URL_SAFE_NO_PAD)?;
+    let json =
+        miniz_oxide::inflate::decompress_to_vec_with_limit(&data, MAX_LEVEL_SIZE).map_err(|e| {
+            match e {
+                TINFLStatus::HasMoreOutput => anyhow!("Level code is too large"),
+                e => anyhow!("Corrupted level code: {:?}", e),
+            }
+        })?;
+    Ok(Level::from_json(std::str::from_utf8(&json)?)?)
+}
+
+// The web build has no clipboard access without a permission prompt, so it asks the user instead
+#[cfg(target_arch = "wasm32")]
+pub fn copy_to_clipboard(text: &str) -> anyhow::Result<()> {
+    web_sys::window()
+        .unwrap()
+        .prompt_with_message_and_default("Copy the level code:", text)
+        .map_err(|e| anyhow!("{:?}", e))?;
+    Ok(())
+}
+
+#[cfg(target_arch = "wasm32")]
+pub fn paste_from_clipboard() -> anyhow::Result<String> {
+    web_sys::window()
+        .unwrap()
+        .prompt_with_message("Paste a level code:")
+        .map_err(|e| anyhow!("{:?}", e))?
+        .ok_or_else(|| anyhow!("Cancelled"))
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+pub fn copy_to_clipboard(text: &str) -> anyhow::Result<()> {
+    arboard::Clipboard::new()?.set_text(text)?;
+    Ok(())
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+pub fn paste_from_clipboard() -> anyhow::Result<String> {
+    Ok(arboard::Clipboard::new()?.get_text()?)
+}
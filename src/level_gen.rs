@@ -0,0 +1,100 @@
+use super::*;
+
+const PLATFORM_COUNT: std::ops::RangeInclusive<usize> = 6..=10;
+const PLATFORM_WIDTH: std::ops::RangeInclusive<i32> = 3..=6;
+// Gaps stay well within a thrown ball's chain length so every jump can be swung
+const GAP: std::ops::RangeInclusive<i32> = 3..=8;
+const STEP: std::ops::RangeInclusive<i32> = -3..=3;
+const MIN_HEIGHT: i32 = -6;
+const MAX_HEIGHT: i32 = 12;
+const PIT_DEPTH: i32 = 1;
+const HAZARD_CHANCE: f64 = 0.3;
+const CEILING_CHANCE: f64 = 0.4;
+const CEILING_HEIGHT: std::ops::RangeInclusive<i32> = 5..=8;
+const MAX_ATTEMPTS: usize = 16;
+
+fn generate_candidate(rng: &mut StdRng) -> Level {
+    let mut tiles = Vec::new();
+    let mut hazards = Vec::new();
+    let mut x = 0;
+    let mut y = 0;
+    let mut spawn = None;
+    let mut goal = None;
+    let count = rng.gen_range(PLATFORM_COUNT);
+    for index in 0..count {
+        let width = rng.gen_range(PLATFORM_WIDTH);
+        for dx in 0..width {
+            tiles.push(vec2(x + dx, y));
+            tiles.push(vec2(x + dx, y - 1));
+        }
+        let top = |dx: i32| vec2((x + dx) as f32 + 0.5, y as f32 + 1.5);
+        if index == 0 {
+            spawn = Some(top(1));
+        } else if index + 1 == count {
+            goal = Some(top(width / 2));
+        } else if rng.gen_bool(CEILING_CHANCE) {
+            // Something to swing from above the next gap
+            let ceiling = y + rng.gen_range(CEILING_HEIGHT);
+            tiles.push(vec2(x + width - 1, ceiling));
+            tiles.push(vec2(x + width, ceiling));
+        }
+        if index + 1 == count {
+            break;
+        }
+        let gap = rng.gen_range(GAP);
+        if rng.gen_bool(HAZARD_CHANCE) {
+            let material = if rng.gen_bool(0.5) {
+                Material::Spikes
+            } else {
+                Material::Lava
+            };
+            for dx in 0..gap {
+                hazards.push(HazardTile {
+                    pos: vec2((x + width + dx) as f32, (y - PIT_DEPTH) as f32),
+                    material,
+                });
+            }
+        }
+        x += width + gap;
+        y = clamp(y + rng.gen_range(STEP), MIN_HEIGHT..=MAX_HEIGHT);
+    }
+    let tiles: Vec<Vec2<f32>> = tiles.into_iter().map(|p| p.map(|x| x as f32)).collect();
+    Level {
+        version: Level::VERSION,
+        spawn,
+        segments: tile_outline(tiles.iter().copied()),
+        tiles,
+        hazards,
+        goal: goal.map(|pos| Goal {
+            pos,
+            radius: Goal::default_radius(),
+        }),
+        ..default()
+    }
+}
+
+// Same seed always gives the same level
+pub fn generate_level(seed: u64) -> Level {
+    let mut rng = StdRng::seed_from_u64(seed);
+    let mut level = generate_candidate(&mut rng);
+    for _ in 1..MAX_ATTEMPTS {
+        if validate_level(&level).is_empty() {
+            break;
+        }
+        level = generate_candidate(&mut rng);
+    }
+    level.metadata.name = Some(format!("Endless #{}", seed));
+    level
+}
+
+// Days since the unix epoch, so everyone gets the same level on the same day
+pub fn daily_seed() -> u64 {
+    #[cfg(target_arch = "wasm32")]
+    let millis = js_sys::Date::now() as u64;
+    #[cfg(not(target_arch = "wasm32"))]
+    let millis = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .unwrap()
+        .as_millis() as u64;
+    millis / (24 * 60 * 60 * 1000)
+}
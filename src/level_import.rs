@@ -0,0 +1,94 @@
+use super::*;
+
+const IMPORT_THRESHOLD: f32 = 0.5;
+
+// Average alpha per cell, x goes right and y goes up
+fn cell_values(image: &image::RgbaImage, cell_size: u32) -> Vec<Vec<f32>> {
+    let width = image.width() / cell_size;
+    let height = image.height() / cell_size;
+    (0..width)
+        .map(|x| {
+            (0..height)
+                .map(|y| {
+                    let top = (height - 1 - y) * cell_size;
+                    let mut sum = 0.0;
+                    for dx in 0..cell_size {
+                        for dy in 0..cell_size {
+                            sum +=
+                                image.get_pixel(x * cell_size + dx, top + dy).0[3] as f32 / 255.0;
+                        }
+                    }
+                    sum / (cell_size * cell_size) as f32
+                })
+                .collect()
+        })
+        .collect()
+}
+
+// Marching squares over cell centers, cells outside the image count as empty
+fn marching_squares(values: &[Vec<f32>]) -> Vec<Segment> {
+    let width = values.len() as i32;
+    let height = values.first().map_or(0, |column| column.len()) as i32;
+    let get = |x: i32, y: i32| {
+        if (0..width).contains(&x) && (0..height).contains(&y) {
+            values[x as usize][y as usize]
+        } else {
+            0.0
+        }
+    };
+    let pos = |x: i32, y: i32| vec2(x as f32 + 0.5, y as f32 + 0.5);
+    let crossing = |(x1, y1): (i32, i32), (x2, y2): (i32, i32)| {
+        let (v1, v2) = (get(x1, y1), get(x2, y2));
+        let t = (IMPORT_THRESHOLD - v1) / (v2 - v1);
+        pos(x1, y1) + (pos(x2, y2) - pos(x1, y1)) * t
+    };
+    let mut segments = Vec::new();
+    for x in -1..width {
+        for y in -1..height {
+            let corners = [(x, y), (x + 1, y), (x + 1, y + 1), (x, y + 1)];
+            let inside = corners.map(|(x, y)| get(x, y) > IMPORT_THRESHOLD);
+            // Edges as corner pairs, always from the lower to the higher coordinate
+            // so neighboring squares compute identical points
+            let edges = [(0, 1), (1, 2), (3, 2), (0, 3)];
+            let points: Vec<Vec2<f32>> = edges
+                .iter()
+                .filter(|&&(a, b)| inside[a] != inside[b])
+                .map(|&(a, b)| crossing(corners[a], corners[b]))
+                .collect();
+            match points.len() {
+                2 => segments.push(Segment::new(points[0], points[1])),
+                4 => {
+                    // Saddle, the center decides which corners are connected
+                    let center = corners.iter().map(|&(x, y)| get(x, y)).sum::<f32>() / 4.0;
+                    if (center > IMPORT_THRESHOLD) == inside[0] {
+                        segments.push(Segment::new(points[0], points[1]));
+                        segments.push(Segment::new(points[2], points[3]));
+                    } else {
+                        segments.push(Segment::new(points[0], points[3]));
+                        segments.push(Segment::new(points[1], points[2]));
+                    }
+                }
+                _ => {}
+            }
+        }
+    }
+    segments
+}
+
+pub fn import_image(image: &image::RgbaImage, cell_size: u32) -> Level {
+    let values = cell_values(image, cell_size);
+    let mut tiles = Vec::new();
+    for (x, column) in values.iter().enumerate() {
+        for (y, &value) in column.iter().enumerate() {
+            if value > IMPORT_THRESHOLD {
+                tiles.push(vec2(x as f32, y as f32));
+            }
+        }
+    }
+    Level {
+        version: Level::VERSION,
+        segments: merge_collinear(&marching_squares(&values)),
+        tiles,
+        ..default()
+    }
+}
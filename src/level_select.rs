@@ -0,0 +1,257 @@
+use super::*;
+
+const LEVEL_SELECT_TITLE: &str = "menu.level_select";
+const LEVEL_SELECT_TITLE_SIZE: f32 = 56.0;
+const LEVEL_GRID_COLUMNS: usize = 4;
+const LEVEL_CARD_SIZE: Vec2<f32> = vec2(220.0, 130.0);
+const LEVEL_CARD_SPACING: f32 = 30.0;
+const LEVEL_CARD_TEXT_SIZE: f32 = 24.0;
+const LEVEL_CARD_PADDING: f32 = 12.0;
+const LEVEL_CARD_OPACITY: f32 = 0.15;
+const LOCKED_OPACITY: f32 = 0.4;
+
+pub struct LevelSelect {
+    geng: Rc<Geng>,
+    assets: Rc<Assets>,
+    settings: Option<AutoSave<Settings>>,
+    progress: AutoSave<SaveData>,
+    gamepad: Gamepad,
+    theme: Theme,
+    selected: usize,
+    framebuffer_size: Vec2<f32>,
+    transition: Option<SceneTransition>,
+}
+
+impl LevelSelect {
+    pub fn new(geng: &Rc<Geng>, assets: &Rc<Assets>, settings: AutoSave<Settings>) -> Self {
+        let themes = &assets.themes;
+        Self {
+            geng: geng.clone(),
+            assets: assets.clone(),
+            settings: Some(settings),
+            progress: SaveData::load(&assets.levels),
+            gamepad: Gamepad::new(),
+            theme: themes.theme().clone(),
+            selected: 0,
+            framebuffer_size: vec2(1.0, 1.0),
+            transition: None,
+        }
+    }
+    fn level_count(&self) -> usize {
+        self.assets.levels.levels.len()
+    }
+    fn card(&self, index: usize) -> AABB<f32> {
+        let ui_size = UiCamera::size(self.framebuffer_size);
+        let columns = LEVEL_GRID_COLUMNS.min(self.level_count()).max(1);
+        let step = LEVEL_CARD_SIZE + vec2(LEVEL_CARD_SPACING, LEVEL_CARD_SPACING);
+        let width = step.x * columns as f32 - LEVEL_CARD_SPACING;
+        let (column, row) = (index % columns, index / columns);
+        let pos = vec2(
+            (ui_size.x - width) / 2.0 + column as f32 * step.x,
+            ui_size.y * 0.7 - LEVEL_CARD_SIZE.y - row as f32 * step.y,
+        );
+        AABB::pos_size(pos, LEVEL_CARD_SIZE)
+    }
+    fn card_at(&self, position: Vec2<f64>) -> Option<usize> {
+        let pos = UiCamera.screen_to_world(self.framebuffer_size, position.map(|x| x as f32));
+        (0..self.level_count()).find(|&index| self.card(index).contains(pos))
+    }
+    fn play(&mut self, index: usize) {
+        if !self.progress.unlocked(&self.assets.levels, index) {
+            return;
+        }
+        if let Some(settings) = self.settings.take() {
+            self.progress.save();
+            self.transition = Some(SceneTransition::Replace(Box::new(Game::new(
+                &self.geng,
+                &self.assets,
+                settings,
+                index,
+            ))));
+        }
+    }
+    fn watch(&mut self, index: usize) {
+        if !self.progress.unlocked(&self.assets.levels, index) {
+            return;
+        }
+        let name = &self.assets.levels.levels[index].0;
+        let ghost = Ghost::clone(&AutoSave::load(&Ghost::path(name)));
+        if ghost.frames.is_empty() {
+            return;
+        }
+        if let Some(settings) = self.settings.take() {
+            self.progress.save();
+            self.transition = Some(SceneTransition::Replace(Box::new(
+                Game::new(&self.geng, &self.assets, settings, index)
+                    .with_spectator(SpectatorSource::Ghost(ghost)),
+            )));
+        }
+    }
+    fn back(&mut self) {
+        self.transition = Some(SceneTransition::Pop);
+    }
+    fn move_selection(&mut self, delta: i32) {
+        let count = self.level_count() as i32;
+        let selected = self.selected as i32 + delta;
+        if (0..count).contains(&selected) {
+            self.selected = selected as usize;
+        }
+    }
+    fn draw_card(&self, framebuffer: &mut ugli::Framebuffer, index: usize) {
+        let card = self.card(index);
+        let (name, level) = &self.assets.levels.levels[index];
+        let level = level.borrow();
+        let unlocked = self.progress.unlocked(&self.assets.levels, index);
+        let record = self.progress.record(name);
+        let color = if index == self.selected {
+            self.theme.highlight.rgba()
+        } else {
+            self.theme.hud.rgba()
+        };
+        let color = if unlocked {
+            color
+        } else {
+            Color {
+                a: color.a * LOCKED_OPACITY,
+                ..color
+            }
+        };
+        let corner = |pos| UiCamera.world_to_screen(self.framebuffer_size, pos);
+        self.geng.draw_2d().quad(
+            framebuffer,
+            AABB::from_corners(corner(card.bottom_left()), corner(card.top_right())),
+            Color {
+                a: color.a * LEVEL_CARD_OPACITY,
+                ..color
+            },
+        );
+        let line = |n: usize| {
+            vec2(
+                card.x_min + LEVEL_CARD_PADDING,
+                card.y_max - (LEVEL_CARD_TEXT_SIZE + LEVEL_CARD_PADDING) * (n + 1) as f32,
+            )
+        };
+        let title = level.metadata.name.as_deref().unwrap_or(name);
+        let mut lines = vec![format!("{}. {}", index + 1, title)];
+        if !unlocked {
+            lines.push(tr("level_select.locked"));
+        } else {
+            lines.push(match record.and_then(|record| record.best_time) {
+                Some(time) => trf("level_select.best", &[&format_time(time)]),
+                None => tr("level_select.not_completed"),
+            });
+            if let Some(medal) = record.and_then(|record| record.medal) {
+                lines.push(trf("level_select.medal", &[&medal.name()]));
+            }
+            if !level.collectibles.is_empty() {
+                lines.push(trf(
+                    "level_select.collected",
+                    &[
+                        &record.map_or(0, |record| record.collected),
+                        &level.collectibles.len(),
+                    ],
+                ));
+            }
+        }
+        for (n, text) in lines.iter().enumerate() {
+            draw_ui_text(
+                framebuffer,
+                &self.assets.font,
+                text,
+                line(n),
+                0.0,
+                LEVEL_CARD_TEXT_SIZE,
+                color,
+            );
+        }
+    }
+}
+
+impl geng::State for LevelSelect {
+    fn update(&mut self, _delta_time: f64) {
+        for event in self.gamepad.menu_events() {
+            self.handle_event(event);
+        }
+    }
+    fn draw(&mut self, framebuffer: &mut ugli::Framebuffer) {
+        self.framebuffer_size = framebuffer.size().map(|x| x as f32);
+        ugli::clear(framebuffer, Some(self.theme.background.rgba()), None);
+        let ui_size = UiCamera::size(self.framebuffer_size);
+        draw_ui_text(
+            framebuffer,
+            &self.assets.font,
+            &tr(LEVEL_SELECT_TITLE),
+            vec2(ui_size.x / 2.0, ui_size.y * 0.8),
+            0.5,
+            LEVEL_SELECT_TITLE_SIZE,
+            self.theme.hud.rgba(),
+        );
+        if let Some(time) = self.progress.sum_of_best(&self.assets.levels) {
+            draw_ui_text(
+                framebuffer,
+                &self.assets.font,
+                &trf("level_select.sum_of_best", &[&format_time(time)]),
+                vec2(ui_size.x / 2.0, ui_size.y * 0.8 - LEVEL_SELECT_TITLE_SIZE),
+                0.5,
+                LEVEL_CARD_TEXT_SIZE,
+                self.theme.hud.rgba(),
+            );
+        }
+        for index in 0..self.level_count() {
+            self.draw_card(framebuffer, index);
+        }
+        draw_ui_text(
+            framebuffer,
+            &self.assets.font,
+            &tr("level_select.watch_hint"),
+            vec2(ui_size.x / 2.0, LEVEL_CARD_TEXT_SIZE),
+            0.5,
+            LEVEL_CARD_TEXT_SIZE,
+            self.theme.hud.rgba(),
+        );
+    }
+    fn handle_event(&mut self, event: geng::Event) {
+        if let Some(settings) = &mut self.settings {
+            if settings.handle_fullscreen_key(&self.geng, &event) {
+                return;
+            }
+        }
+        let columns = LEVEL_GRID_COLUMNS as i32;
+        match event {
+            geng::Event::KeyDown { key } => match key {
+                geng::Key::Left | geng::Key::A => self.move_selection(-1),
+                geng::Key::Right | geng::Key::D => self.move_selection(1),
+                geng::Key::Up | geng::Key::W => self.move_selection(-columns),
+                geng::Key::Down | geng::Key::S => self.move_selection(columns),
+                geng::Key::Enter | geng::Key::Space => self.play(self.selected),
+                geng::Key::G => self.watch(self.selected),
+                geng::Key::Escape | geng::Key::Backspace => self.back(),
+                _ => {}
+            },
+            geng::Event::MouseMove { position } => {
+                if let Some(index) = self.card_at(position) {
+                    self.selected = index;
+                }
+            }
+            geng::Event::MouseDown {
+                position,
+                button: geng::MouseButton::Left,
+            } => {
+                if let Some(index) = self.card_at(position) {
+                    self.selected = index;
+                    self.play(index);
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+impl Scene for LevelSelect {
+    fn presence(&self) -> Option<Presence> {
+        Some(Presence::new(tr("presence.level_select")))
+    }
+    fn scene_transition(&mut self) -> Option<SceneTransition> {
+        self.transition.take()
+    }
+}
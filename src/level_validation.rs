@@ -0,0 +1,131 @@
+use super::*;
+
+use std::collections::VecDeque;
+
+const BOUNDS_MARGIN: f32 = 1.0;
+const FLOOD_MARGIN: i32 = 2;
+
+#[derive(Debug, Clone)]
+pub struct LevelIssue {
+    pub pos: Option<Vec2<f32>>,
+    pub message: String,
+}
+
+impl LevelIssue {
+    fn new(pos: Option<Vec2<f32>>, message: impl Into<String>) -> Self {
+        Self {
+            pos,
+            message: message.into(),
+        }
+    }
+}
+
+fn geometry_bounds(level: &Level) -> Option<AABB<f32>> {
+    level
+        .segments
+        .iter()
+        .flat_map(|segment| [segment.p1, segment.p2])
+        .chain(
+            level
+                .tiles
+                .iter()
+                .flat_map(|&tile| [tile, tile + vec2(1.0, 1.0)]),
+        )
+        .map(|p| AABB::from_corners(p, p))
+        .reduce(|a, b| AABB {
+            x_min: a.x_min.min(b.x_min),
+            y_min: a.y_min.min(b.y_min),
+            x_max: a.x_max.max(b.x_max),
+            y_max: a.y_max.max(b.y_max),
+        })
+}
+
+// Walks free cells from the spawn, tiles are the only thing that blocks
+fn goal_reachable(level: &Level, spawn: Vec2<f32>, goal: Vec2<f32>) -> bool {
+    let cell = |pos: Vec2<f32>| pos.map(|x| x.floor() as i32);
+    let solid: HashSet<Vec2<i32>> = level.tiles.iter().map(|&tile| cell(tile)).collect();
+    let (start, target) = (cell(spawn), cell(goal));
+    let mut cells: Vec<Vec2<i32>> = solid.iter().copied().collect();
+    cells.extend([start, target]);
+    let min = vec2(
+        cells.iter().map(|p| p.x).min().unwrap() - FLOOD_MARGIN,
+        cells.iter().map(|p| p.y).min().unwrap() - FLOOD_MARGIN,
+    );
+    let max = vec2(
+        cells.iter().map(|p| p.x).max().unwrap() + FLOOD_MARGIN,
+        cells.iter().map(|p| p.y).max().unwrap() + FLOOD_MARGIN,
+    );
+    let mut visited = HashSet::new();
+    let mut queue = VecDeque::new();
+    visited.insert(start);
+    queue.push_back(start);
+    while let Some(pos) = queue.pop_front() {
+        if pos == target {
+            return true;
+        }
+        for next in [
+            pos + vec2(1, 0),
+            pos - vec2(1, 0),
+            pos + vec2(0, 1),
+            pos - vec2(0, 1),
+        ] {
+            if next.x < min.x || next.y < min.y || next.x > max.x || next.y > max.y {
+                continue;
+            }
+            if !solid.contains(&next) && visited.insert(next) {
+                queue.push_back(next);
+            }
+        }
+    }
+    false
+}
+
+pub fn validate_level(level: &Level) -> Vec<LevelIssue> {
+    let mut issues = Vec::new();
+    let spawn = level.spawn_pos();
+    if level.spawn.is_none() {
+        issues.push(LevelIssue::new(Some(spawn), "No spawn point"));
+    }
+    if level
+        .tiles
+        .iter()
+        .any(|&tile| AABB::pos_size(tile, vec2(1.0, 1.0)).contains(spawn))
+    {
+        issues.push(LevelIssue::new(Some(spawn), "Spawn is inside a tile"));
+    }
+    match &level.goal {
+        None => issues.push(LevelIssue::new(None, "No goal")),
+        Some(goal) if !goal_reachable(level, spawn, goal.pos) => {
+            issues.push(LevelIssue::new(
+                Some(goal.pos),
+                "Goal is walled off from the spawn",
+            ));
+        }
+        Some(_) => {}
+    }
+    let mut seen = HashSet::new();
+    for segment in &level.segments {
+        let middle = (segment.p1 + segment.p2) / 2.0;
+        if (segment.p2 - segment.p1).len() < EPS {
+            issues.push(LevelIssue::new(Some(middle), "Zero length segment"));
+        } else if !seen.insert(edge_key(segment)) {
+            issues.push(LevelIssue::new(Some(middle), "Duplicate segment"));
+        }
+    }
+    if let Some(bounds) = geometry_bounds(level) {
+        let bounds = bounds.add_padding(BOUNDS_MARGIN);
+        for entity in EntityRef::all(level) {
+            let (pos, data) = match (entity.anchor(level), entity.get(level)) {
+                (Some(pos), Some(data)) => (pos, data),
+                _ => continue,
+            };
+            if !bounds.contains(pos) {
+                issues.push(LevelIssue::new(
+                    Some(pos),
+                    format!("{} is outside the level", data.title()),
+                ));
+            }
+        }
+    }
+    issues
+}
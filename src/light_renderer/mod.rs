@@ -0,0 +1,121 @@
+use super::*;
+
+#[derive(ugli::Vertex, Clone)]
+struct LightVertex {
+    a_pos: Vec2<f32>,
+}
+
+const LIGHT_RAYS: usize = 64;
+const EDGE_RAY_ANGLE: f32 = 1e-3;
+
+#[derive(Debug, Copy, Clone)]
+pub struct PointLight {
+    pub pos: Vec2<f32>,
+    pub radius: f32,
+    pub color: Color<f32>,
+}
+
+// Casts rays at every occluder corner (and slightly to both sides of it) plus a ring for the falloff edge
+fn visibility_polygon(light: &PointLight, occluders: &[Segment]) -> Vec<Vec2<f32>> {
+    let mut angles: Vec<f32> = (0..LIGHT_RAYS)
+        .map(|i| i as f32 / LIGHT_RAYS as f32 * 2.0 * f32::PI)
+        .collect();
+    for segment in occluders {
+        for point in [segment.p1, segment.p2] {
+            let angle = (point - light.pos).arg();
+            angles.extend([angle - EDGE_RAY_ANGLE, angle, angle + EDGE_RAY_ANGLE]);
+        }
+    }
+    for angle in &mut angles {
+        *angle = angle.rem_euclid(2.0 * f32::PI);
+    }
+    angles.sort_by(|a, b| a.partial_cmp(b).unwrap());
+    angles
+        .into_iter()
+        .map(|angle| {
+            let dir = vec2(angle.cos(), angle.sin());
+            let distance = occluders
+                .iter()
+                .filter_map(|segment| segment.ray_hit(light.pos, dir, light.radius))
+                .fold(light.radius, f32::min);
+            light.pos + dir * distance
+        })
+        .collect()
+}
+
+pub struct LightRenderer {
+    geng: Rc<Geng>,
+    pub(crate) program: Rc<ugli::Program>,
+    vertices: ugli::VertexBuffer<LightVertex>,
+    light_map: Option<ugli::Texture>,
+}
+
+impl LightRenderer {
+    pub fn new(geng: &Rc<Geng>, shaders: &Shaders) -> Self {
+        Self {
+            geng: geng.clone(),
+            program: shaders.programs().light.clone(),
+            vertices: ugli::VertexBuffer::new_dynamic(geng.ugli(), Vec::new()),
+            light_map: None,
+        }
+    }
+    // ugli only has alpha blending, so overlapping lights blend towards each other instead of adding up
+    pub fn render(
+        &mut self,
+        camera: &Camera,
+        size: Vec2<usize>,
+        ambient: Color<f32>,
+        lights: &[PointLight],
+        world: &CollisionWorld,
+    ) -> &ugli::Texture {
+        if self.light_map.as_ref().map(|texture| texture.size()) != Some(size) {
+            self.light_map = Some(ugli::Texture::new_uninitialized(self.geng.ugli(), size));
+        }
+        let light_map = self.light_map.as_mut().unwrap();
+        let mut framebuffer = ugli::Framebuffer::new_color(
+            self.geng.ugli(),
+            ugli::ColorAttachment::Texture(light_map),
+        );
+        ugli::clear(&mut framebuffer, Some(ambient), None);
+        let camera_uniforms = camera.uniforms(size.map(|x| x as f32));
+        for light in lights {
+            // Hazards glow, so they don't cast shadows
+            let occluders: Vec<Segment> = world
+                .query(AABB::pos_size(
+                    light.pos - vec2(light.radius, light.radius),
+                    vec2(light.radius, light.radius) * 2.0,
+                ))
+                .filter(|segment| !segment.material.hazard())
+                .copied()
+                .collect();
+            let outline = visibility_polygon(light, &occluders);
+            self.vertices.clear();
+            for i in 0..outline.len() {
+                let next = outline[(i + 1) % outline.len()];
+                for a_pos in [light.pos, outline[i], next] {
+                    self.vertices.push(LightVertex { a_pos });
+                }
+            }
+            ugli::draw(
+                &mut framebuffer,
+                &self.program,
+                ugli::DrawMode::Triangles,
+                &self.vertices,
+                (
+                    &camera_uniforms,
+                    ugli::uniforms! {
+                        u_center: light.pos,
+                        u_radius: light.radius,
+                        u_color: light.color,
+                    },
+                ),
+                ugli::DrawParameters {
+                    blend_mode: Some(default()),
+                    ..default()
+                },
+            );
+        }
+        drop(framebuffer);
+        self.light_map.as_ref().unwrap()
+    }
+}
@@ -3,49 +3,108 @@ use super::*;
 #[derive(ugli::Vertex, Clone)]
 pub struct Vertex {
     pub a_pos: Vec2<f32>,
+    pub a_color: Color<f32>,
+    pub a_distance: f32,
+}
+
+// Tags each point with the distance travelled along the line, used for dashes
+fn line_vertices(
+    points: impl IntoIterator<Item = (Vec2<f32>, Color<f32>)>,
+) -> impl Iterator<Item = Vertex> {
+    let mut prev: Option<Vec2<f32>> = None;
+    let mut distance = 0.0;
+    points.into_iter().map(move |(pos, color)| {
+        if let Some(prev) = prev {
+            distance += (pos - prev).len();
+        }
+        prev = Some(pos);
+        Vertex {
+            a_pos: pos,
+            a_color: color,
+            a_distance: distance,
+        }
+    })
+}
+
+fn solid(
+    points: impl IntoIterator<Item = Vec2<f32>>,
+) -> impl Iterator<Item = (Vec2<f32>, Color<f32>)> {
+    points.into_iter().map(|point| (point, Color::WHITE))
+}
+
+#[derive(ugli::Vertex, Clone)]
+struct ThickVertex {
+    a_pos: Vec2<f32>,
+    a_edge: f32,
+    a_color: Color<f32>,
+}
+
+#[derive(Debug, Copy, Clone)]
+pub struct LinePoint {
+    pub pos: Vec2<f32>,
+    pub width: f32,
+    pub color: Color<f32>,
+}
+
+const MITER_LIMIT: f32 = 4.0;
+// Per full turn, a join only covers the angle between its two segments
+const ROUND_JOIN_SEGMENTS: usize = 16;
+const THICK_LINE_SOFTNESS: f32 = 0.3;
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum LineJoin {
+    Miter,
+    Round,
+}
+
+struct LineBatch {
+    mode: ugli::DrawMode,
+    color: Color<f32>,
+    range: std::ops::Range<usize>,
 }
 
 pub struct LineRenderer {
-    geng: Rc<Geng>,
-    program: ugli::Program,
+    pub(crate) program: Rc<ugli::Program>,
+    pub(crate) thick_program: Rc<ugli::Program>,
+    vertices: RefCell<ugli::VertexBuffer<Vertex>>,
+    thick_vertices: RefCell<ugli::VertexBuffer<ThickVertex>>,
+    batch_vertices: RefCell<ugli::VertexBuffer<Vertex>>,
+    batches: RefCell<Vec<LineBatch>>,
 }
 
 impl LineRenderer {
-    pub fn new(geng: &Rc<Geng>) -> Self {
+    pub fn new(geng: &Rc<Geng>, shaders: &Shaders) -> Self {
         Self {
-            geng: geng.clone(),
-            program: geng
-                .shader_lib()
-                .compile(include_str!("program.glsl"))
-                .unwrap(),
+            program: shaders.programs().line.clone(),
+            thick_program: shaders.programs().line_thick.clone(),
+            vertices: RefCell::new(ugli::VertexBuffer::new_dynamic(geng.ugli(), Vec::new())),
+            thick_vertices: RefCell::new(ugli::VertexBuffer::new_dynamic(geng.ugli(), Vec::new())),
+            batch_vertices: RefCell::new(ugli::VertexBuffer::new_dynamic(geng.ugli(), Vec::new())),
+            batches: RefCell::new(Vec::new()),
         }
     }
-    pub fn draw(
+    fn draw_vertices(
         &self,
         framebuffer: &mut ugli::Framebuffer,
-        camera: &Camera,
+        camera: &impl AbstractCamera,
+        mode: ugli::DrawMode,
         color: Color<f32>,
-        points: impl IntoIterator<Item = Vec2<f32>>,
+        dash: Vec2<f32>,
+        vertices: ugli::VertexBufferSlice<Vertex>,
     ) {
         let camera_uniforms = camera.uniforms(framebuffer.size().map(|x| x as f32));
         let uniforms = (
             camera_uniforms,
             ugli::uniforms! {
                 u_color: color,
+                u_dash: dash,
             },
         );
-        let vertices = ugli::VertexBuffer::new_dynamic(
-            self.geng.ugli(),
-            points
-                .into_iter()
-                .map(|point| Vertex { a_pos: point })
-                .collect(),
-        );
         ugli::draw(
             framebuffer,
             &self.program,
-            ugli::DrawMode::Lines { line_width: 1.0 },
-            &vertices,
+            mode,
+            vertices,
             uniforms,
             ugli::DrawParameters {
                 blend_mode: Some(default()),
@@ -53,32 +112,150 @@ impl LineRenderer {
             },
         );
     }
+    #[allow(clippy::too_many_arguments)]
+    fn draw_immediate(
+        &self,
+        framebuffer: &mut ugli::Framebuffer,
+        camera: &impl AbstractCamera,
+        mode: ugli::DrawMode,
+        color: Color<f32>,
+        dash: Vec2<f32>,
+        points: impl IntoIterator<Item = (Vec2<f32>, Color<f32>)>,
+    ) {
+        let mut vertices = self.vertices.borrow_mut();
+        vertices.clear();
+        vertices.extend(line_vertices(points));
+        let len = vertices.len();
+        self.draw_vertices(
+            framebuffer,
+            camera,
+            mode,
+            color,
+            dash,
+            vertices.slice(0..len),
+        );
+    }
+    pub fn draw(
+        &self,
+        framebuffer: &mut ugli::Framebuffer,
+        camera: &impl AbstractCamera,
+        color: Color<f32>,
+        points: impl IntoIterator<Item = Vec2<f32>>,
+    ) {
+        self.draw_immediate(
+            framebuffer,
+            camera,
+            ugli::DrawMode::Lines { line_width: 1.0 },
+            color,
+            vec2(0.0, 0.0),
+            solid(points),
+        );
+    }
+    pub fn draw_ui(
+        &self,
+        framebuffer: &mut ugli::Framebuffer,
+        color: Color<f32>,
+        points: impl IntoIterator<Item = Vec2<f32>>,
+    ) {
+        self.draw(framebuffer, &UiCamera, color, points);
+    }
     pub fn draw_strip(
         &self,
         framebuffer: &mut ugli::Framebuffer,
-        camera: &Camera,
+        camera: &impl AbstractCamera,
+        color: Color<f32>,
+        points: impl IntoIterator<Item = Vec2<f32>>,
+    ) {
+        self.draw_immediate(
+            framebuffer,
+            camera,
+            ugli::DrawMode::LineStrip { line_width: 1.0 },
+            color,
+            vec2(0.0, 0.0),
+            solid(points),
+        );
+    }
+    pub fn draw_gradient_strip(
+        &self,
+        framebuffer: &mut ugli::Framebuffer,
+        camera: &impl AbstractCamera,
+        points: impl IntoIterator<Item = (Vec2<f32>, Color<f32>)>,
+    ) {
+        self.draw_immediate(
+            framebuffer,
+            camera,
+            ugli::DrawMode::LineStrip { line_width: 1.0 },
+            Color::WHITE,
+            vec2(0.0, 0.0),
+            points,
+        );
+    }
+    #[allow(clippy::too_many_arguments)]
+    pub fn draw_dashed_strip(
+        &self,
+        framebuffer: &mut ugli::Framebuffer,
+        camera: &impl AbstractCamera,
         color: Color<f32>,
         points: impl IntoIterator<Item = Vec2<f32>>,
+        dash: f32,
+        gap: f32,
+    ) {
+        self.draw_immediate(
+            framebuffer,
+            camera,
+            ugli::DrawMode::LineStrip { line_width: 1.0 },
+            color,
+            vec2(dash, gap),
+            solid(points),
+        );
+    }
+    pub fn draw_thick(
+        &self,
+        framebuffer: &mut ugli::Framebuffer,
+        camera: &impl AbstractCamera,
+        color: Color<f32>,
+        points: impl IntoIterator<Item = Vec2<f32>>,
+        width: f32,
+        join: LineJoin,
+    ) {
+        self.draw_ribbon(
+            framebuffer,
+            camera,
+            color,
+            points.into_iter().map(|pos| LinePoint {
+                pos,
+                width,
+                color: Color::WHITE,
+            }),
+            join,
+        );
+    }
+    pub fn draw_ribbon(
+        &self,
+        framebuffer: &mut ugli::Framebuffer,
+        camera: &impl AbstractCamera,
+        color: Color<f32>,
+        points: impl IntoIterator<Item = LinePoint>,
+        join: LineJoin,
     ) {
+        let points: Vec<LinePoint> = points.into_iter().collect();
+        let mut vertices = self.thick_vertices.borrow_mut();
+        vertices.clear();
+        expand_polyline(&mut vertices, &points, join);
+        let len = vertices.len();
         let camera_uniforms = camera.uniforms(framebuffer.size().map(|x| x as f32));
         let uniforms = (
             camera_uniforms,
             ugli::uniforms! {
                 u_color: color,
+                u_softness: THICK_LINE_SOFTNESS,
             },
         );
-        let vertices = ugli::VertexBuffer::new_dynamic(
-            self.geng.ugli(),
-            points
-                .into_iter()
-                .map(|point| Vertex { a_pos: point })
-                .collect(),
-        );
         ugli::draw(
             framebuffer,
-            &self.program,
-            ugli::DrawMode::LineStrip { line_width: 1.0 },
-            &vertices,
+            &self.thick_program,
+            ugli::DrawMode::Triangles,
+            vertices.slice(0..len),
             uniforms,
             ugli::DrawParameters {
                 blend_mode: Some(default()),
@@ -86,4 +263,146 @@ impl LineRenderer {
             },
         );
     }
+    pub fn begin(&self) {
+        self.batch_vertices.borrow_mut().clear();
+        self.batches.borrow_mut().clear();
+    }
+    fn add(
+        &self,
+        mode: ugli::DrawMode,
+        color: Color<f32>,
+        points: impl IntoIterator<Item = Vec2<f32>>,
+    ) {
+        let mut vertices = self.batch_vertices.borrow_mut();
+        let start = vertices.len();
+        vertices.extend(line_vertices(solid(points)));
+        self.batches.borrow_mut().push(LineBatch {
+            mode,
+            color,
+            range: start..vertices.len(),
+        });
+    }
+    pub fn add_lines(&self, color: Color<f32>, points: impl IntoIterator<Item = Vec2<f32>>) {
+        self.add(ugli::DrawMode::Lines { line_width: 1.0 }, color, points);
+    }
+    pub fn add_strip(&self, color: Color<f32>, points: impl IntoIterator<Item = Vec2<f32>>) {
+        self.add(ugli::DrawMode::LineStrip { line_width: 1.0 }, color, points);
+    }
+    pub fn flush(&self, framebuffer: &mut ugli::Framebuffer, camera: &impl AbstractCamera) {
+        let vertices = self.batch_vertices.borrow();
+        for batch in self.batches.borrow_mut().drain(..) {
+            self.draw_vertices(
+                framebuffer,
+                camera,
+                batch.mode,
+                batch.color,
+                vec2(0.0, 0.0),
+                vertices.slice(batch.range),
+            );
+        }
+    }
+}
+
+fn segment_normal(p1: Vec2<f32>, p2: Vec2<f32>) -> Vec2<f32> {
+    let dir = p2 - p1;
+    if dir.len() < EPS {
+        return vec2(0.0, 0.0);
+    }
+    dir.normalize().rotate_90()
+}
+
+fn push_quad(vertices: &mut Vec<ThickVertex>, corners: [(Vec2<f32>, f32, Color<f32>); 4]) {
+    for index in [0, 1, 2, 0, 2, 3] {
+        let (a_pos, a_edge, a_color) = corners[index];
+        vertices.push(ThickVertex {
+            a_pos,
+            a_edge,
+            a_color,
+        });
+    }
+}
+
+// Expands a polyline into triangles, a_edge goes from -1 to 1 across the line
+fn expand_polyline(vertices: &mut Vec<ThickVertex>, points: &[LinePoint], join: LineJoin) {
+    if points.len() < 2 {
+        return;
+    }
+    let normals: Vec<Vec2<f32>> = points
+        .windows(2)
+        .map(|segment| segment_normal(segment[0].pos, segment[1].pos))
+        .collect();
+    match join {
+        LineJoin::Miter => {
+            let offsets: Vec<Vec2<f32>> = (0..points.len())
+                .map(|i| {
+                    let half_width = points[i].width / 2.0;
+                    let n0 = normals[i.saturating_sub(1)];
+                    let n1 = normals[i.min(normals.len() - 1)];
+                    let miter = n0 + n1;
+                    if miter.len() < EPS {
+                        return n1 * half_width;
+                    }
+                    let miter = miter.normalize();
+                    let scale = 1.0 / Vec2::dot(miter, n1).max(1.0 / MITER_LIMIT);
+                    miter * half_width * scale
+                })
+                .collect();
+            for i in 0..points.len() - 1 {
+                let (p1, p2) = (&points[i], &points[i + 1]);
+                let (o1, o2) = (offsets[i], offsets[i + 1]);
+                push_quad(
+                    vertices,
+                    [
+                        (p1.pos - o1, -1.0, p1.color),
+                        (p2.pos - o2, -1.0, p2.color),
+                        (p2.pos + o2, 1.0, p2.color),
+                        (p1.pos + o1, 1.0, p1.color),
+                    ],
+                );
+            }
+        }
+        LineJoin::Round => {
+            for (segment, &normal) in points.windows(2).zip(&normals) {
+                let (p1, p2) = (&segment[0], &segment[1]);
+                let o1 = normal * p1.width / 2.0;
+                let o2 = normal * p2.width / 2.0;
+                push_quad(
+                    vertices,
+                    [
+                        (p1.pos - o1, -1.0, p1.color),
+                        (p2.pos - o2, -1.0, p2.color),
+                        (p2.pos + o2, 1.0, p2.color),
+                        (p1.pos + o1, 1.0, p1.color),
+                    ],
+                );
+            }
+            // Only the wedge on the outer side of each turn, the inner side is already
+            // covered by the segment quads and blending it again shows through
+            for (point, normal) in points[1..].iter().zip(normals.windows(2)) {
+                let turn = Vec2::skew(normal[0], normal[1]);
+                if turn.abs() < EPS {
+                    continue;
+                }
+                let side = if turn > 0.0 { -1.0 } else { 1.0 };
+                let (start, end) = (normal[0] * side, normal[1] * side);
+                let sweep = Vec2::skew(start, end).atan2(Vec2::dot(start, end));
+                let steps = (sweep.abs() / (2.0 * f32::PI) * ROUND_JOIN_SEGMENTS as f32)
+                    .ceil()
+                    .max(1.0) as usize;
+                let rim = |i: usize| {
+                    point.pos
+                        + Vec2::rotated(start * point.width / 2.0, sweep * i as f32 / steps as f32)
+                };
+                for i in 0..steps {
+                    for (a_pos, a_edge) in [(point.pos, 0.0), (rim(i), 1.0), (rim(i + 1), 1.0)] {
+                        vertices.push(ThickVertex {
+                            a_pos,
+                            a_edge,
+                            a_color: point.color,
+                        });
+                    }
+                }
+            }
+        }
+    }
 }
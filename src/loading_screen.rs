@@ -0,0 +1,200 @@
+use super::*;
+
+const LOADING_TITLE: &str = "Ball & Chain";
+const LOADING_TITLE_SIZE: f32 = 64.0;
+const LOADING_TIP_SIZE: f32 = 24.0;
+const LOADING_BAR_SIZE: Vec2<f32> = vec2(400.0, 16.0);
+const LOADING_BAR_BORDER: f32 = 2.0;
+// Keeps the bar from jumping when several assets finish in one frame
+const LOADING_BAR_SPEED: f32 = 4.0;
+
+const LOADING_TIPS: [&str; 8] = [
+    "Hold the left mouse button to charge a throw, release to let go",
+    "Hold the right mouse button to reel the chain in",
+    "Hit cracked blocks hard enough with the ball to break them",
+    "Strike a switch with the ball to open its doors",
+    "Touching a checkpoint saves where you respawn",
+    "Collect every coin and gem in a level for a full clear",
+    "F12 takes a screenshot, F9 saves the last five seconds as a GIF",
+    "Alt+Enter or F11 toggles fullscreen",
+];
+
+// Counts asset loads as they are requested and as they finish
+#[derive(Default)]
+pub struct LoadProgress {
+    total: Cell<usize>,
+    loaded: Cell<usize>,
+}
+
+impl LoadProgress {
+    pub fn new() -> Rc<Self> {
+        default()
+    }
+    pub fn fraction(&self) -> f32 {
+        match self.total.get() {
+            0 => 0.0,
+            total => self.loaded.get() as f32 / total as f32,
+        }
+    }
+    pub fn load<T: geng::LoadAsset + 'static>(
+        self: &Rc<Self>,
+        geng: &Rc<Geng>,
+        path: &str,
+    ) -> geng::AssetFuture<T> {
+        self.total.set(self.total.get() + 1);
+        let future = T::load(geng, path);
+        let progress = self.clone();
+        let path = path.to_owned();
+        async move {
+            let result = future.await;
+            progress.loaded.set(progress.loaded.get() + 1);
+            result.with_context(|| format!("Failed to load {}", path))
+        }
+        .boxed_local()
+    }
+}
+
+pub struct AssetLoadingScreen {
+    geng: Rc<Geng>,
+    progress: Rc<LoadProgress>,
+    shown: f32,
+    tip: &'static str,
+}
+
+impl AssetLoadingScreen {
+    pub fn new(geng: &Rc<Geng>, progress: &Rc<LoadProgress>) -> Self {
+        Self {
+            geng: geng.clone(),
+            progress: progress.clone(),
+            shown: 0.0,
+            tip: LOADING_TIPS.choose(&mut global_rng()).unwrap(),
+        }
+    }
+}
+
+impl geng::ProgressScreen for AssetLoadingScreen {}
+
+impl geng::State for AssetLoadingScreen {
+    fn update(&mut self, delta_time: f64) {
+        let target = self.progress.fraction();
+        self.shown += (target - self.shown) * (LOADING_BAR_SPEED * delta_time as f32).min(1.0);
+    }
+    fn draw(&mut self, framebuffer: &mut ugli::Framebuffer) {
+        ugli::clear(framebuffer, Some(Color::BLACK), None);
+        let size = framebuffer.size().map(|x| x as f32);
+        let font = self.geng.default_font();
+        font.draw_aligned(
+            framebuffer,
+            LOADING_TITLE,
+            vec2(size.x / 2.0, size.y * 0.6),
+            0.5,
+            LOADING_TITLE_SIZE,
+            Color::WHITE,
+        );
+        let bar = AABB::pos_size(size / 2.0 - LOADING_BAR_SIZE / 2.0, LOADING_BAR_SIZE);
+        let draw_2d = self.geng.draw_2d();
+        draw_2d.quad(
+            framebuffer,
+            bar.add_padding(LOADING_BAR_BORDER),
+            Color::WHITE,
+        );
+        draw_2d.quad(framebuffer, bar, Color::BLACK);
+        draw_2d.quad(
+            framebuffer,
+            AABB::pos_size(
+                bar.bottom_left(),
+                vec2(bar.width() * self.shown, bar.height()),
+            ),
+            Color::WHITE,
+        );
+        font.draw_aligned(
+            framebuffer,
+            self.tip,
+            vec2(size.x / 2.0, size.y * 0.3),
+            0.5,
+            LOADING_TIP_SIZE,
+            Color::rgba(1.0, 1.0, 1.0, 0.7),
+        );
+    }
+}
+
+const LOAD_ERROR_TITLE: &str = "Failed to load the game";
+const LOAD_ERROR_RETRY: &str = "Press Enter or R to retry";
+const LOAD_ERROR_TITLE_SIZE: f32 = 48.0;
+const LOAD_ERROR_TEXT_SIZE: f32 = 24.0;
+
+// Startup failures end up here instead of a panic, retrying runs the whole load again
+pub struct LoadErrorScreen {
+    geng: Rc<Geng>,
+    lines: Vec<String>,
+    retry: Option<Box<dyn FnOnce() -> Box<dyn geng::State>>>,
+    retrying: bool,
+}
+
+impl LoadErrorScreen {
+    pub fn new(
+        geng: &Rc<Geng>,
+        error: anyhow::Error,
+        retry: impl FnOnce() -> Box<dyn geng::State> + 'static,
+    ) -> Self {
+        error!("{:?}", error);
+        Self {
+            geng: geng.clone(),
+            lines: error.chain().map(|cause| cause.to_string()).collect(),
+            retry: Some(Box::new(retry)),
+            retrying: false,
+        }
+    }
+}
+
+impl geng::State for LoadErrorScreen {
+    fn draw(&mut self, framebuffer: &mut ugli::Framebuffer) {
+        ugli::clear(framebuffer, Some(Color::BLACK), None);
+        let size = framebuffer.size().map(|x| x as f32);
+        let font = self.geng.default_font();
+        let mut y = size.y * 0.7;
+        font.draw_aligned(
+            framebuffer,
+            LOAD_ERROR_TITLE,
+            vec2(size.x / 2.0, y),
+            0.5,
+            LOAD_ERROR_TITLE_SIZE,
+            Color::rgb(1.0, 0.4, 0.4),
+        );
+        y -= LOAD_ERROR_TITLE_SIZE;
+        for line in &self.lines {
+            font.draw_aligned(
+                framebuffer,
+                line,
+                vec2(size.x / 2.0, y),
+                0.5,
+                LOAD_ERROR_TEXT_SIZE,
+                Color::WHITE,
+            );
+            y -= LOAD_ERROR_TEXT_SIZE * 1.5;
+        }
+        font.draw_aligned(
+            framebuffer,
+            LOAD_ERROR_RETRY,
+            vec2(size.x / 2.0, size.y * 0.2),
+            0.5,
+            LOAD_ERROR_TEXT_SIZE,
+            Color::rgba(1.0, 1.0, 1.0, 0.7),
+        );
+    }
+    fn handle_event(&mut self, event: geng::Event) {
+        if let geng::Event::KeyDown {
+            key: geng::Key::Enter | geng::Key::R,
+        } = event
+        {
+            self.retrying = true;
+        }
+    }
+    fn transition(&mut self) -> Option<geng::Transition> {
+        if !self.retrying {
+            return None;
+        }
+        let retry = self.retry.take()?;
+        Some(geng::Transition::Switch(retry()))
+    }
+}
@@ -0,0 +1,84 @@
+use super::*;
+
+pub const DEFAULT_LANGUAGE: &str = "en";
+// Code and name in the language itself, the tables live in static/lang/<code>.json
+pub const LANGUAGES: [(&str, &str); 2] = [("en", "English"), ("ru", "Русский")];
+
+pub struct StringTables {
+    tables: HashMap<String, HashMap<String, String>>,
+}
+
+impl geng::LoadAsset for StringTables {
+    fn load(geng: &Rc<Geng>, path: &str) -> geng::AssetFuture<Self> {
+        let tables = LANGUAGES.map(|(code, _)| {
+            let data = <String as geng::LoadAsset>::load(geng, &format!("{}/{}.json", path, code));
+            async move {
+                let table: HashMap<String, String> = serde_json::from_str(&data.await?)?;
+                Ok::<_, anyhow::Error>((code.to_owned(), table))
+            }
+        });
+        async move {
+            Ok(Self {
+                tables: future::try_join_all(tables).await?.into_iter().collect(),
+            })
+        }
+        .boxed_local()
+    }
+    const DEFAULT_EXT: Option<&'static str> = None;
+}
+
+#[derive(Default)]
+struct Localization {
+    tables: HashMap<String, HashMap<String, String>>,
+    language: String,
+}
+
+// Every screen draws text, so the current table is global instead of threaded through
+thread_local! {
+    static LOCALIZATION: RefCell<Localization> = RefCell::new(default());
+}
+
+pub fn init_localization(strings: &StringTables, language: &str) {
+    LOCALIZATION.with(|localization| {
+        localization.borrow_mut().tables = strings.tables.clone();
+    });
+    set_language(language);
+}
+
+pub fn set_language(language: &str) {
+    LOCALIZATION.with(|localization| localization.borrow_mut().language = language.to_owned());
+}
+
+pub fn language_name(language: &str) -> &'static str {
+    LANGUAGES
+        .iter()
+        .find(|(code, _)| *code == language)
+        .map_or(LANGUAGES[0].1, |(_, name)| name)
+}
+
+// Falls back to English and then to the key itself, so level text can be given
+// either as a key or as plain text
+pub fn tr(key: &str) -> String {
+    LOCALIZATION.with(|localization| {
+        let localization = localization.borrow();
+        [localization.language.as_str(), DEFAULT_LANGUAGE]
+            .iter()
+            .find_map(|language| localization.tables.get(*language)?.get(key))
+            .cloned()
+            .unwrap_or_else(|| key.to_owned())
+    })
+}
+
+// Fills the `{}` placeholders of a translated string in order
+pub fn trf(key: &str, args: &[&dyn std::fmt::Display]) -> String {
+    let template = tr(key);
+    let mut parts = template.split("{}");
+    let mut result = parts.next().unwrap_or_default().to_owned();
+    for (index, part) in parts.enumerate() {
+        if let Some(arg) = args.get(index) {
+            result += &arg.to_string();
+        }
+        result += part;
+    }
+    result
+}
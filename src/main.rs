@@ -2,15 +2,107 @@
 
 use geng::prelude::*;
 
+pub mod achievements;
+pub mod animation;
+pub mod asset_source;
+pub mod audio;
 pub mod camera;
+pub mod chain_renderer;
+pub mod collision;
+pub mod console;
+pub mod crash_log;
+pub mod editor;
+pub mod frame_limiter;
 pub mod game;
+pub mod gamepad;
+pub mod ghost;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod hot_reload;
+pub mod hud;
+pub mod input;
+pub mod inspector;
+pub mod leaderboard;
+pub mod level;
+pub mod level_code;
+pub mod level_gen;
+pub mod level_import;
+pub mod level_select;
+pub mod level_validation;
+pub mod light_renderer;
 pub mod line_renderer;
+pub mod loading_screen;
+pub mod localization;
+pub mod menu;
+pub mod parallax_renderer;
+pub mod pause;
+pub mod post_process;
+pub mod presence;
+pub mod progress;
+pub mod race;
+pub mod race_lobby;
 pub mod renderer;
+pub mod results;
+pub mod scene_stack;
+pub mod screenshot;
+pub mod settings;
+pub mod shaders;
+pub mod theme;
+pub mod tile_renderer;
+pub mod tiled;
+pub mod transition;
+pub mod tuning_panel;
 
+pub use achievements::*;
+pub use animation::*;
+pub use asset_source::*;
+pub use audio::*;
 pub use camera::*;
+pub use chain_renderer::*;
+pub use collision::*;
+pub use console::*;
+pub use crash_log::CrashNotice;
+pub use editor::*;
+pub use frame_limiter::*;
 pub use game::*;
+pub use gamepad::*;
+pub use ghost::*;
+#[cfg(not(target_arch = "wasm32"))]
+pub use hot_reload::*;
+pub use hud::*;
+pub use input::*;
+pub use inspector::*;
+pub use leaderboard::*;
+pub use level::*;
+pub use level_code::*;
+pub use level_gen::*;
+pub use level_import::*;
+pub use level_select::*;
+pub use level_validation::*;
+pub use light_renderer::*;
 pub use line_renderer::*;
+pub use loading_screen::*;
+pub use localization::*;
+pub use menu::*;
+pub use parallax_renderer::*;
+pub use pause::*;
+pub use post_process::*;
+pub use presence::*;
+pub use progress::*;
+pub use race::*;
+pub use race_lobby::*;
 pub use renderer::*;
+pub use results::*;
+pub use scene_stack::*;
+pub use screenshot::*;
+pub use settings::*;
+pub use shaders::*;
+pub use theme::*;
+pub use tile_renderer::*;
+pub use tiled::*;
+pub use transition::*;
+pub use tuning_panel::*;
+
+const EPS: f32 = 1e-5;
 
 pub fn hsv(h: f32, s: f32, v: f32) -> Color<f32> {
     hsva(h, s, v, 1.0)
@@ -78,45 +170,329 @@ pub struct Animation {
     frames: Vec<ugli::Texture>,
 }
 
+impl Animation {
+    fn decode_frames(data: &[u8]) -> anyhow::Result<Vec<image::RgbaImage>> {
+        use image::AnimationDecoder;
+        image::codecs::png::PngDecoder::new(data)?
+            .apng()
+            .into_frames()
+            .map(|frame| Ok(frame?.into_buffer()))
+            .collect()
+    }
+    fn from_frames(geng: &Geng, frames: Vec<image::RgbaImage>) -> Self {
+        Self {
+            frames: frames
+                .into_iter()
+                .map(|frame| ugli::Texture::from_image_image(geng.ugli(), frame))
+                .collect(),
+        }
+    }
+    pub fn from_png(geng: &Geng, data: &[u8]) -> anyhow::Result<Self> {
+        Ok(Self::from_frames(geng, Self::decode_frames(data)?))
+    }
+}
+
 impl geng::LoadAsset for Animation {
     fn load(geng: &Rc<Geng>, path: &str) -> geng::AssetFuture<Self> {
         let data = <Vec<u8> as geng::LoadAsset>::load(geng, path);
         let geng = geng.clone();
         async move {
             let data = data.await?;
-            use image::AnimationDecoder;
+            // Native decodes on worker threads like geng does for plain textures,
+            // only the upload needs the GL context of the main thread
+            #[cfg(not(target_arch = "wasm32"))]
+            let frames = global_threadpool()
+                .spawn(move || Animation::decode_frames(&data))
+                .await??;
+            #[cfg(target_arch = "wasm32")]
+            let frames = Animation::decode_frames(&data)?;
+            Ok(Animation::from_frames(&geng, frames))
+        }
+        .boxed_local()
+    }
+    const DEFAULT_EXT: Option<&'static str> = Some("png");
+}
+
+#[derive(Deserialize)]
+struct AtlasData {
+    texture: String,
+    regions: HashMap<String, AABB<f32>>,
+}
+
+pub struct Atlas {
+    pub texture: RefCell<ugli::Texture>,
+    regions: HashMap<String, AABB<f32>>,
+}
+
+impl Atlas {
+    pub fn uv(&self, name: &str) -> AABB<f32> {
+        self.regions[name]
+    }
+}
+
+impl geng::LoadAsset for Atlas {
+    fn load(geng: &Rc<Geng>, path: &str) -> geng::AssetFuture<Self> {
+        let geng = geng.clone();
+        let dir = match path.rfind('/') {
+            Some(index) => path[..index].to_owned(),
+            None => ".".to_owned(),
+        };
+        let data = <String as geng::LoadAsset>::load(&geng, path);
+        async move {
+            let data: AtlasData = serde_json::from_str(&data.await?)?;
+            let texture = <ugli::Texture as geng::LoadAsset>::load(
+                &geng,
+                &format!("{}/{}", dir, data.texture),
+            )
+            .await?;
+            let size = texture.size().map(|x| x as f32);
             Ok(Self {
-                frames: image::codecs::png::PngDecoder::new(data.as_slice())
-                    .unwrap()
-                    .apng()
-                    .into_frames()
-                    .map(|frame| {
-                        let frame = frame.unwrap();
-                        ugli::Texture::from_image_image(geng.ugli(), frame.into_buffer())
+                regions: data
+                    .regions
+                    .into_iter()
+                    .map(|(name, rect)| {
+                        (
+                            name,
+                            AABB {
+                                x_min: rect.x_min / size.x,
+                                y_min: rect.y_min / size.y,
+                                x_max: rect.x_max / size.x,
+                                y_max: rect.y_max / size.y,
+                            },
+                        )
                     })
                     .collect(),
+                texture: RefCell::new(texture),
             })
         }
         .boxed_local()
     }
-    const DEFAULT_EXT: Option<&'static str> = Some("png");
+    const DEFAULT_EXT: Option<&'static str> = Some("json");
 }
 
-#[derive(geng::Assets)]
 pub struct Assets {
-    player: ugli::Texture,
-    #[asset(path = "level.json")]
-    level: String,
-    ball: ugli::Texture,
-    chain: ugli::Texture,
-    block: ugli::Texture,
+    atlas: Atlas,
+    font: Font,
+    background_far: RefCell<ugli::Texture>,
+    background_mid: RefCell<ugli::Texture>,
+    background_near: RefCell<ugli::Texture>,
+    player_idle: RefCell<Animation>,
+    player_walk: RefCell<Animation>,
+    player_spin: RefCell<Animation>,
+    player_fly: RefCell<Animation>,
+    player_reel: RefCell<Animation>,
+    levels: LevelSet,
+    arenas: LevelSet,
+    shaders: Shaders,
+    strings: StringTables,
+    themes: Themes,
+    config: String,
+    break_sound: geng::Sound,
+    throw_sounds: Vec<geng::Sound>,
+    chain_rattle_sounds: Vec<geng::Sound>,
+    impact_sounds: Vec<geng::Sound>,
+    landing_sounds: Vec<geng::Sound>,
+    death_sounds: Vec<geng::Sound>,
+    pickup_sounds: Vec<geng::Sound>,
+    chain_loop_sounds: Vec<geng::Sound>,
 }
 
-impl Assets {}
+impl Assets {
+    // Every file is a separate tracked load so the loading screen can show progress
+    pub fn load(
+        geng: &Rc<Geng>,
+        base_path: &str,
+        progress: &Rc<LoadProgress>,
+    ) -> impl Future<Output = anyhow::Result<Self>> {
+        let path = |name: &str| format!("{}/{}", base_path, name);
+        let sounds = |name: &str| {
+            future::try_join_all((1..=3).map(|i| {
+                progress.load::<geng::Sound>(geng, &path(&format!("sounds/{}_{}.wav", name, i)))
+            }))
+        };
+        let loads = (
+            progress.load(geng, &path("atlas.json")),
+            progress.load(geng, &path("font.ttf")),
+            progress.load(geng, &path("background/far.png")),
+            progress.load(geng, &path("background/mid.png")),
+            progress.load(geng, &path("background/near.png")),
+            progress.load(geng, &path("player_idle.png")),
+            progress.load(geng, &path("player_walk.png")),
+            progress.load(geng, &path("player_spin.png")),
+            progress.load(geng, &path("player_fly.png")),
+            progress.load(geng, &path("player_reel.png")),
+            progress.load(geng, &path("levels")),
+            progress.load(geng, &path("arenas")),
+            progress.load(geng, &path("shaders")),
+            progress.load(geng, &path("lang")),
+            progress.load(geng, &path("themes.json")),
+            progress.load(geng, &path("config.json")),
+            progress.load(geng, &path("break.wav")),
+            sounds("throw"),
+            sounds("chain_rattle"),
+            sounds("impact"),
+            sounds("landing"),
+            sounds("death"),
+            sounds("pickup"),
+            sounds("chain_loop"),
+        );
+        async move {
+            let (
+                atlas,
+                font,
+                background_far,
+                background_mid,
+                background_near,
+                player_idle,
+                player_walk,
+                player_spin,
+                player_fly,
+                player_reel,
+                levels,
+                arenas,
+                shaders,
+                strings,
+                themes,
+                config,
+                break_sound,
+                throw_sounds,
+                chain_rattle_sounds,
+                impact_sounds,
+                landing_sounds,
+                death_sounds,
+                pickup_sounds,
+                chain_loop_sounds,
+            ) = futures::try_join!(
+                loads.0, loads.1, loads.2, loads.3, loads.4, loads.5, loads.6, loads.7, loads.8,
+                loads.9, loads.10, loads.11, loads.12, loads.13, loads.14, loads.15, loads.16,
+                loads.17, loads.18, loads.19, loads.20, loads.21, loads.22, loads.23
+            )?;
+            Ok(Self {
+                atlas,
+                font,
+                background_far: RefCell::new(background_far),
+                background_mid: RefCell::new(background_mid),
+                background_near: RefCell::new(background_near),
+                player_idle: RefCell::new(player_idle),
+                player_walk: RefCell::new(player_walk),
+                player_spin: RefCell::new(player_spin),
+                player_fly: RefCell::new(player_fly),
+                player_reel: RefCell::new(player_reel),
+                levels,
+                arenas,
+                shaders,
+                strings,
+                themes,
+                config,
+                break_sound,
+                throw_sounds,
+                chain_rattle_sounds,
+                impact_sounds,
+                landing_sounds,
+                death_sounds,
+                pickup_sounds,
+                chain_loop_sounds,
+            })
+        }
+    }
+}
+
+#[derive(StructOpt)]
+struct Opt {
+    /// Level file to play instead of the level list
+    level: Option<std::path::PathBuf>,
+    /// Open the level in the editor, a missing file starts empty
+    #[structopt(long)]
+    editor: bool,
+    #[structopt(long)]
+    fullscreen: bool,
+    #[structopt(long)]
+    mute: bool,
+    #[structopt(long)]
+    debug_draw: bool,
+    /// Keyboard and mouse play the character, a gamepad plays the ball
+    #[structopt(long)]
+    coop: bool,
+    /// Seed for gameplay randomness, for reproducible runs
+    #[structopt(long)]
+    fixed_seed: Option<u64>,
+    /// Play generated levels starting from this seed
+    #[structopt(long)]
+    seed: Option<u64>,
+    /// Convert a level image into level json and exit
+    #[structopt(long)]
+    import_image: Option<std::path::PathBuf>,
+    #[structopt(long, default_value = "level.json")]
+    output: std::path::PathBuf,
+    /// Image pixels per level unit
+    #[structopt(long, default_value = "20")]
+    cell_size: u32,
+    /// Record input events of a run into a file
+    #[structopt(long)]
+    record: Option<std::path::PathBuf>,
+    /// Replay input events recorded with --record
+    #[structopt(long)]
+    replay: Option<std::path::PathBuf>,
+    /// Watch a recorded ghost file, e.g. to check a leaderboard run
+    #[structopt(long)]
+    spectate: Option<std::path::PathBuf>,
+    /// Level index the spectated ghost was recorded on
+    #[structopt(long, default_value = "0")]
+    spectate_level: usize,
+    /// Host a race server on this address, e.g. 0.0.0.0:1155
+    #[structopt(long)]
+    server: Option<String>,
+    /// Level index the server races on
+    #[structopt(long, default_value = "0")]
+    race_level: usize,
+    /// Join a race server, e.g. ws://localhost:1155
+    #[structopt(long)]
+    connect: Option<String>,
+    #[structopt(long)]
+    name: Option<String>,
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+fn import_image_file(path: &std::path::Path, opt: &Opt) -> anyhow::Result<()> {
+    let image = image::open(path)
+        .with_context(|| format!("Failed to open {}", path.display()))?
+        .to_rgba8();
+    let level = import_image(&image, opt.cell_size);
+    std::fs::write(&opt.output, level.to_json())
+        .with_context(|| format!("Failed to write {}", opt.output.display()))?;
+    Ok(())
+}
 
 fn main() {
     logger::init().unwrap();
     geng::setup_panic_handler();
+    let crashed = crash_log::init();
+    let mut opt = Opt::from_args();
+    #[cfg(not(target_arch = "wasm32"))]
+    if let Some(path) = &opt.import_image {
+        if let Err(e) = import_image_file(path, &opt) {
+            eprintln!("{:?}", e);
+            std::process::exit(1);
+        }
+        return;
+    }
+    #[cfg(not(target_arch = "wasm32"))]
+    if let Some(addr) = &opt.server {
+        geng::net::Server::new(RaceServer::new(opt.race_level), addr.as_str()).run();
+        return;
+    }
+    // Resolved before switching to the static dir, so paths stay relative to the caller
+    let absolute = |path: &std::path::Path| std::env::current_dir().unwrap().join(path);
+    let level_path = opt
+        .level
+        .clone()
+        .or_else(|| opt.editor.then(|| "level.json".into()))
+        .map(|path| absolute(&path));
+    for path in
+        IntoIterator::into_iter([&mut opt.record, &mut opt.replay, &mut opt.spectate]).flatten()
+    {
+        *path = absolute(path);
+    }
     if let Some(dir) = std::env::var_os("CARGO_MANIFEST_DIR") {
         std::env::set_current_dir(std::path::Path::new(&dir).join("static")).unwrap();
     } else {
@@ -128,19 +504,207 @@ fn main() {
         }
     }
 
+    let mut settings = AutoSave::<Settings>::load(SETTINGS_PATH);
+    settings.launch = LaunchOptions {
+        mute: opt.mute,
+        debug_draw: opt.debug_draw,
+        fixed_seed: opt.fixed_seed,
+        frame_limit: default(),
+    };
+    settings.apply_frame_limit();
+    let frame_limit = settings.launch.frame_limit.clone();
     let geng = Rc::new(Geng::new(geng::ContextOptions {
         title: "GMTK 2021 - Ball & Chain".to_owned(),
+        vsync: settings.vsync,
         ..default()
     }));
-    let assets = <Assets as geng::LoadAsset>::load(&geng, ".");
+    geng.window()
+        .set_fullscreen(settings.fullscreen || opt.fullscreen);
+    let connection = opt.connect.as_deref().map(geng::net::client::connect);
+    let connection = async move {
+        match connection {
+            Some(connection) => Some(connection.await),
+            None => None,
+        }
+    }
+    .boxed_local();
+    let launch = Launch {
+        opt,
+        level_path,
+        settings,
+    };
+    let mut state = load(&geng, launch, connection);
+    if let Some(log_path) = crashed {
+        state = Box::new(CrashNotice::new(&geng, &log_path, state));
+    }
     geng::run(
         geng.clone(),
-        geng::LoadingScreen::new(&geng, geng::EmptyLoadingScreen, assets, {
+        FrameLimiter::new(ScreenshotCapture::new(&geng, state), frame_limit),
+    );
+}
+
+// Everything from the command line that a failed start needs to try again
+struct Launch {
+    opt: Opt,
+    level_path: Option<std::path::PathBuf>,
+    settings: AutoSave<Settings>,
+}
+
+struct LaunchFiles {
+    level: Option<Level>,
+    recorder: Option<InputRecorder>,
+    spectated: Option<Ghost>,
+}
+
+impl Launch {
+    fn read_files(&self) -> anyhow::Result<LaunchFiles> {
+        let opt = &self.opt;
+        let level = match &self.level_path {
+            Some(path) => match std::fs::read_to_string(path) {
+                Ok(data) => Some(
+                    Level::from_json(&data)
+                        .with_context(|| format!("Failed to parse {}", path.display()))?,
+                ),
+                Err(_) if opt.editor => None,
+                Err(e) => {
+                    return Err(e).with_context(|| format!("Failed to read {}", path.display()))
+                }
+            },
+            None => None,
+        };
+        let recorder = match (&opt.record, &opt.replay) {
+            (_, Some(path)) => Some(InputRecorder::replay(
+                Recording::load(path)
+                    .with_context(|| format!("Failed to load {}", path.display()))?,
+            )),
+            (Some(path), None) => Some(InputRecorder::record(path.clone(), 0)),
+            (None, None) => None,
+        };
+        let spectated = match &opt.spectate {
+            Some(path) => {
+                let ghost = std::fs::read_to_string(path)
+                    .map_err(anyhow::Error::from)
+                    .and_then(|data| Ok(serde_json::from_str::<Ghost>(&data)?))
+                    .with_context(|| format!("Failed to load {}", path.display()))?;
+                Some(ghost)
+            }
+            None => None,
+        };
+        Ok(LaunchFiles {
+            level,
+            recorder,
+            spectated,
+        })
+    }
+    fn start(
+        self,
+        geng: &Rc<Geng>,
+        mut assets: Assets,
+        connection: Option<RaceConnection>,
+        files: LaunchFiles,
+    ) -> Box<dyn geng::State> {
+        let Self {
+            opt,
+            level_path,
+            settings,
+        } = self;
+        let LaunchFiles {
+            level,
+            recorder,
+            spectated,
+        } = files;
+        for texture in [
+            assets.background_far.get_mut(),
+            assets.background_mid.get_mut(),
+            assets.background_near.get_mut(),
+        ] {
+            texture.set_wrap_mode(ugli::WrapMode::Repeat);
+        }
+        for sound in &mut assets.chain_loop_sounds {
+            sound.looped = true;
+        }
+        init_localization(&assets.strings, &settings.language);
+        let assets = Rc::new(assets);
+        if recorder.is_none()
+            && spectated.is_none()
+            && level_path.is_none()
+            && opt.seed.is_none()
+            && !opt.coop
+            && connection.is_none()
+        {
+            let menu = Menu::new(geng, &assets, settings);
+            return Box::new(SceneStack::new(geng, Box::new(menu)));
+        }
+        // The menu always sits at the bottom so leaving the game goes back to it
+        let menu = Menu::new(geng, &assets, Settings::reload(&settings.launch));
+        let mut stack = SceneStack::new(geng, Box::new(menu));
+        if let Some(connection) = connection {
+            let mut lobby = RaceLobby::new(geng, &assets, settings, None, connection);
+            if let Some(name) = opt.name {
+                lobby = lobby.with_name(name);
+            }
+            stack.push(Box::new(lobby));
+            return Box::new(stack);
+        }
+        let index = match &recorder {
+            Some(recorder) => recorder.level(),
+            None if spectated.is_some() => opt.spectate_level,
+            None => 0,
+        };
+        let mut game = Game::new(geng, &assets, settings, index);
+        if opt.coop {
+            game = game.with_coop();
+        }
+        if let Some(seed) = opt.seed {
+            game = game.with_endless(seed);
+        }
+        if let Some(level) = level {
+            game = game.with_custom_level(level);
+        }
+        if let Some(path) = level_path.filter(|_| opt.editor) {
+            game = game.with_editor(path);
+        }
+        if let Some(recorder) = recorder {
+            let replaying = recorder.replaying();
+            game = game.with_input_recorder(recorder);
+            if replaying {
+                game = game.with_spectator(SpectatorSource::Inputs);
+            }
+        }
+        if let Some(ghost) = spectated {
+            game = game.with_spectator(SpectatorSource::Ghost(ghost));
+        }
+        stack.push(Box::new(game));
+        Box::new(stack)
+    }
+}
+
+// A failed start shows the error, retrying reloads the assets but keeps the race connection
+fn load(
+    geng: &Rc<Geng>,
+    launch: Launch,
+    connection: future::LocalBoxFuture<'static, Option<RaceConnection>>,
+) -> Box<dyn geng::State> {
+    let progress = LoadProgress::new();
+    let assets = match asset_root() {
+        Ok(root) => Assets::load(geng, &root, &progress).boxed_local(),
+        Err(e) => future::ready(Err(e)).boxed_local(),
+    };
+    Box::new(geng::LoadingScreen::new(
+        geng,
+        AssetLoadingScreen::new(geng, &progress),
+        future::join(assets, connection),
+        {
             let geng = geng.clone();
-            move |assets| {
-                let mut assets = assets.unwrap();
-                Game::new(&geng, &Rc::new(assets))
+            move |(assets, connection): (anyhow::Result<Assets>, Option<RaceConnection>)| {
+                match assets.and_then(|assets| Ok((assets, launch.read_files()?))) {
+                    Ok((assets, files)) => launch.start(&geng, assets, connection, files),
+                    Err(e) => Box::new(LoadErrorScreen::new(&geng, e, {
+                        let geng = geng.clone();
+                        move || load(&geng, launch, future::ready(connection).boxed_local())
+                    })),
+                }
             }
-        }),
-    );
+        },
+    ))
 }
@@ -0,0 +1,476 @@
+use super::*;
+
+const MENU_TITLE: &str = "menu.title";
+const MENU_TITLE_SIZE: f32 = 72.0;
+const MENU_ITEM_SIZE: f32 = 36.0;
+const MENU_ITEM_SPACING: f32 = 50.0;
+// Longer lists get squeezed to fit above the bottom edge
+const MENU_LIST_HEIGHT: f32 = 350.0;
+const GAME_SPEED_STEP: f32 = 0.1;
+const LANGUAGE_ITEM: usize = 10;
+pub const ACCESSIBILITY_ITEM: usize = 11;
+const VOLUME_STEP: f64 = 0.1;
+const MAX_FPS_OPTIONS: [Option<u32>; 5] = [None, Some(30), Some(60), Some(120), Some(144)];
+
+pub enum MenuInput {
+    Activate,
+    Adjust(i32),
+    Back,
+}
+
+fn menu_item_spacing(count: usize) -> f32 {
+    if count > 1 {
+        partial_min(MENU_ITEM_SPACING, MENU_LIST_HEIGHT / (count - 1) as f32)
+    } else {
+        MENU_ITEM_SPACING
+    }
+}
+
+fn menu_item_size(count: usize) -> f32 {
+    MENU_ITEM_SIZE * menu_item_spacing(count) / MENU_ITEM_SPACING
+}
+
+fn menu_item_pos(framebuffer_size: Vec2<f32>, index: usize, count: usize) -> Vec2<f32> {
+    let ui_size = UiCamera::size(framebuffer_size);
+    vec2(
+        ui_size.x / 2.0,
+        ui_size.y * 0.55 - index as f32 * menu_item_spacing(count),
+    )
+}
+
+fn menu_item_at(framebuffer_size: Vec2<f32>, count: usize, position: Vec2<f64>) -> Option<usize> {
+    let pos = UiCamera.screen_to_world(framebuffer_size, position.map(|x| x as f32));
+    (0..count).find(|&index| {
+        let center = menu_item_pos(framebuffer_size, index, count).y + menu_item_size(count) / 2.0;
+        (pos.y - center).abs() < menu_item_spacing(count) / 2.0
+    })
+}
+
+// Keyboard and mouse navigation shared by the main and pause menus
+pub fn menu_input(
+    event: &geng::Event,
+    selected: &mut usize,
+    count: usize,
+    framebuffer_size: Vec2<f32>,
+) -> Option<MenuInput> {
+    match *event {
+        geng::Event::KeyDown { key } => match key {
+            geng::Key::Up | geng::Key::W => *selected = (*selected + count - 1) % count,
+            geng::Key::Down | geng::Key::S => *selected = (*selected + 1) % count,
+            geng::Key::Left | geng::Key::A => return Some(MenuInput::Adjust(-1)),
+            geng::Key::Right | geng::Key::D => return Some(MenuInput::Adjust(1)),
+            geng::Key::Enter | geng::Key::Space => return Some(MenuInput::Activate),
+            geng::Key::Escape | geng::Key::Backspace => return Some(MenuInput::Back),
+            _ => {}
+        },
+        geng::Event::MouseMove { position } => {
+            if let Some(index) = menu_item_at(framebuffer_size, count, position) {
+                *selected = index;
+            }
+        }
+        geng::Event::MouseDown {
+            position,
+            button: geng::MouseButton::Left,
+        } => {
+            if let Some(index) = menu_item_at(framebuffer_size, count, position) {
+                *selected = index;
+                return Some(MenuInput::Activate);
+            }
+        }
+        _ => {}
+    }
+    None
+}
+
+pub fn draw_menu(
+    framebuffer: &mut ugli::Framebuffer,
+    font: &geng::Font,
+    title: &str,
+    items: &[String],
+    selected: usize,
+    theme: &Theme,
+) {
+    let framebuffer_size = framebuffer.size().map(|x| x as f32);
+    let ui_size = UiCamera::size(framebuffer_size);
+    draw_ui_text(
+        framebuffer,
+        font,
+        title,
+        vec2(ui_size.x / 2.0, ui_size.y * 0.75),
+        0.5,
+        MENU_TITLE_SIZE,
+        theme.hud.rgba(),
+    );
+    for (index, item) in items.iter().enumerate() {
+        let color = if index == selected {
+            theme.highlight
+        } else {
+            theme.hud
+        };
+        draw_ui_text(
+            framebuffer,
+            font,
+            item,
+            menu_item_pos(framebuffer_size, index, items.len()),
+            0.5,
+            menu_item_size(items.len()),
+            color.rgba(),
+        );
+    }
+}
+
+fn on_off(value: bool) -> String {
+    tr(if value { "common.on" } else { "common.off" })
+}
+
+fn percent(value: f64) -> String {
+    format!("{:.0}", value * 100.0)
+}
+
+// The last entry is always "Back"
+pub fn settings_items(settings: &Settings) -> Vec<String> {
+    vec![
+        trf(
+            "settings.master_volume",
+            &[&percent(settings.master_volume)],
+        ),
+        trf("settings.music_volume", &[&percent(settings.music_volume)]),
+        trf("settings.effects_volume", &[&percent(settings.sfx_volume)]),
+        trf("settings.fullscreen", &[&on_off(settings.fullscreen)]),
+        trf("settings.vsync", &[&on_off(settings.vsync)]),
+        match settings.max_fps {
+            Some(fps) => trf("settings.max_fps", &[&fps]),
+            None => trf("settings.max_fps", &[&tr("settings.unlimited")]),
+        },
+        trf("settings.throttle", &[&on_off(settings.throttle_unfocused)]),
+        trf("settings.timer", &[&on_off(settings.show_timer)]),
+        trf("settings.ghost", &[&on_off(settings.ghosts)]),
+        trf("settings.leaderboard", &[&on_off(settings.leaderboard)]),
+        trf("settings.language", &[&language_name(&settings.language)]),
+        tr("settings.accessibility"),
+        tr("menu.back"),
+    ]
+}
+
+// The last entry is always "Back"
+pub fn accessibility_items(settings: &Settings) -> Vec<String> {
+    let accessibility = &settings.accessibility;
+    let spin = if accessibility.toggle_spin {
+        "accessibility.spin_toggle"
+    } else {
+        "accessibility.spin_hold"
+    };
+    vec![
+        trf("accessibility.spin", &[&tr(spin)]),
+        trf(
+            "accessibility.reduced_effects",
+            &[&on_off(accessibility.reduced_effects)],
+        ),
+        trf(
+            "accessibility.high_contrast",
+            &[&on_off(accessibility.high_contrast)],
+        ),
+        trf(
+            "accessibility.game_speed",
+            &[&percent(accessibility.game_speed as f64)],
+        ),
+        trf(
+            "accessibility.large_text",
+            &[&on_off(accessibility.large_text)],
+        ),
+        trf(
+            "accessibility.chain_snapping",
+            &[&on_off(accessibility.chain_snapping)],
+        ),
+        tr("menu.back"),
+    ]
+}
+
+fn mutator_items(settings: &Settings) -> Vec<String> {
+    let mut items: Vec<String> = Mutators::NAMES
+        .iter()
+        .enumerate()
+        .map(|(index, name)| trf(name, &[&on_off(settings.mutators.get(index))]))
+        .collect();
+    items.push(tr("menu.back"));
+    items
+}
+
+pub fn adjust_accessibility(settings: &mut Settings, index: usize, direction: i32) {
+    let accessibility = &mut settings.accessibility;
+    match index {
+        0 => accessibility.toggle_spin = !accessibility.toggle_spin,
+        1 => accessibility.reduced_effects = !accessibility.reduced_effects,
+        2 => accessibility.high_contrast = !accessibility.high_contrast,
+        3 => {
+            let speed = accessibility.game_speed + GAME_SPEED_STEP * direction as f32;
+            // Rounded so repeated steps don't drift away from the labels
+            accessibility.game_speed = clamp((speed * 10.0).round() / 10.0, MIN_GAME_SPEED..=1.0);
+        }
+        4 => accessibility.large_text = !accessibility.large_text,
+        5 => accessibility.chain_snapping = !accessibility.chain_snapping,
+        _ => {}
+    }
+}
+
+pub fn adjust_setting(geng: &Geng, settings: &mut Settings, index: usize, direction: i32) {
+    let step = |volume: &mut f64| {
+        *volume = clamp(*volume + VOLUME_STEP * direction as f64, 0.0..=1.0);
+    };
+    match index {
+        0 => step(&mut settings.master_volume),
+        1 => step(&mut settings.music_volume),
+        2 => step(&mut settings.sfx_volume),
+        3 => {
+            settings.fullscreen = !settings.fullscreen;
+            geng.window().set_fullscreen(settings.fullscreen);
+        }
+        4 => settings.vsync = !settings.vsync,
+        5 => {
+            let options = MAX_FPS_OPTIONS.len() as i32;
+            let index = MAX_FPS_OPTIONS
+                .iter()
+                .position(|&fps| fps == settings.max_fps)
+                .unwrap_or(0) as i32;
+            settings.max_fps = MAX_FPS_OPTIONS[(index + direction).rem_euclid(options) as usize];
+            settings.apply_frame_limit();
+        }
+        6 => {
+            settings.throttle_unfocused = !settings.throttle_unfocused;
+            settings.apply_frame_limit();
+        }
+        7 => settings.show_timer = !settings.show_timer,
+        8 => settings.ghosts = !settings.ghosts,
+        9 => settings.leaderboard = !settings.leaderboard,
+        LANGUAGE_ITEM => {
+            let count = LANGUAGES.len() as i32;
+            let index = LANGUAGES
+                .iter()
+                .position(|(code, _)| *code == settings.language)
+                .unwrap_or(0) as i32;
+            settings.language = LANGUAGES[(index + direction).rem_euclid(count) as usize]
+                .0
+                .to_owned();
+            set_language(&settings.language);
+        }
+        _ => {}
+    }
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+enum MenuScreen {
+    Main,
+    Settings,
+    Accessibility,
+    Mutators,
+    Achievements,
+}
+
+pub struct Menu {
+    geng: Rc<Geng>,
+    assets: Rc<Assets>,
+    // Handed over to the game once it starts
+    settings: Option<AutoSave<Settings>>,
+    // Read only here, so a snapshot that can't overwrite what the game saved
+    progress: SaveData,
+    launch: LaunchOptions,
+    gamepad: Gamepad,
+    theme: Theme,
+    screen: MenuScreen,
+    selected: usize,
+    framebuffer_size: Vec2<f32>,
+    transition: Option<SceneTransition>,
+}
+
+impl Menu {
+    pub fn new(geng: &Rc<Geng>, assets: &Rc<Assets>, settings: AutoSave<Settings>) -> Self {
+        let themes = &assets.themes;
+        Self {
+            geng: geng.clone(),
+            assets: assets.clone(),
+            progress: SaveData::load(&assets.levels).clone(),
+            launch: settings.launch.clone(),
+            settings: Some(settings),
+            gamepad: Gamepad::new(),
+            theme: themes.theme().clone(),
+            screen: MenuScreen::Main,
+            selected: 0,
+            framebuffer_size: vec2(1.0, 1.0),
+            transition: None,
+        }
+    }
+    fn items(&self) -> Vec<String> {
+        match self.screen {
+            MenuScreen::Main => {
+                let play = if self.progress.levels.is_empty() {
+                    "menu.play"
+                } else {
+                    "menu.continue"
+                };
+                let mut items = vec![
+                    play,
+                    "menu.level_select",
+                    "menu.endless",
+                    "menu.daily",
+                    "menu.coop",
+                    "menu.versus",
+                    "menu.mutators",
+                    "menu.settings",
+                    "menu.achievements",
+                ];
+                if cfg!(not(target_arch = "wasm32")) {
+                    items.push("menu.quit");
+                }
+                items.into_iter().map(tr).collect()
+            }
+            MenuScreen::Settings => settings_items(self.settings.as_ref().unwrap()),
+            MenuScreen::Accessibility => accessibility_items(self.settings.as_ref().unwrap()),
+            MenuScreen::Mutators => mutator_items(self.settings.as_ref().unwrap()),
+            MenuScreen::Achievements => {
+                let mut items = achievement_lines(&self.progress);
+                items.push(tr("menu.back"));
+                items
+            }
+        }
+    }
+    fn open(&mut self, screen: MenuScreen) {
+        self.screen = screen;
+        self.selected = 0;
+    }
+    fn start_game(&mut self, coop: bool) {
+        let settings = self.settings.take().unwrap();
+        let level = self.progress.next_level(&self.assets.levels);
+        let mut game = Game::new(&self.geng, &self.assets, settings, level);
+        if coop {
+            game = game.with_coop();
+        }
+        self.transition = Some(SceneTransition::Push(Box::new(game)));
+    }
+    fn start_endless(&mut self, seed: u64) {
+        let settings = self.settings.take().unwrap();
+        self.transition = Some(SceneTransition::Push(Box::new(
+            Game::new(&self.geng, &self.assets, settings, 0).with_endless(seed),
+        )));
+    }
+    fn start_versus(&mut self) {
+        self.transition = Some(SceneTransition::Push(Box::new(Versus::new(
+            &self.geng,
+            &self.assets,
+            0,
+        ))));
+    }
+    fn level_select(&mut self) {
+        let settings = self.settings.take().unwrap();
+        self.transition = Some(SceneTransition::Push(Box::new(LevelSelect::new(
+            &self.geng,
+            &self.assets,
+            settings,
+        ))));
+    }
+    fn activate(&mut self) {
+        let index = self.selected;
+        match self.screen {
+            MenuScreen::Main => match index {
+                0 => self.start_game(false),
+                1 => self.level_select(),
+                2 => self.start_endless(global_rng().gen()),
+                3 => self.start_endless(daily_seed()),
+                4 => self.start_game(true),
+                5 => self.start_versus(),
+                6 => self.open(MenuScreen::Mutators),
+                7 => self.open(MenuScreen::Settings),
+                8 => self.open(MenuScreen::Achievements),
+                _ => self.transition = Some(SceneTransition::Pop),
+            },
+            MenuScreen::Settings if index == ACCESSIBILITY_ITEM => {
+                self.open(MenuScreen::Accessibility)
+            }
+            MenuScreen::Achievements => {
+                if index + 1 == self.items().len() {
+                    self.back();
+                }
+            }
+            MenuScreen::Settings | MenuScreen::Accessibility | MenuScreen::Mutators => {
+                if index + 1 < self.items().len() {
+                    self.adjust(1);
+                } else {
+                    self.back();
+                }
+            }
+        }
+    }
+    fn adjust(&mut self, direction: i32) {
+        let settings = self.settings.as_mut().unwrap();
+        match self.screen {
+            MenuScreen::Main | MenuScreen::Achievements => {}
+            MenuScreen::Settings => adjust_setting(&self.geng, settings, self.selected, direction),
+            MenuScreen::Accessibility => adjust_accessibility(settings, self.selected, direction),
+            MenuScreen::Mutators if self.selected < Mutators::NAMES.len() => {
+                settings.mutators.toggle(self.selected)
+            }
+            MenuScreen::Mutators => {}
+        }
+    }
+    fn back(&mut self) {
+        match self.screen {
+            MenuScreen::Main => {}
+            MenuScreen::Settings | MenuScreen::Mutators | MenuScreen::Achievements => {
+                self.open(MenuScreen::Main)
+            }
+            MenuScreen::Accessibility => self.open(MenuScreen::Settings),
+        }
+    }
+}
+
+impl geng::State for Menu {
+    fn update(&mut self, _delta_time: f64) {
+        for event in self.gamepad.menu_events() {
+            self.handle_event(event);
+        }
+    }
+    fn draw(&mut self, framebuffer: &mut ugli::Framebuffer) {
+        self.framebuffer_size = framebuffer.size().map(|x| x as f32);
+        ugli::clear(framebuffer, Some(self.theme.background.rgba()), None);
+        draw_menu(
+            framebuffer,
+            &self.assets.font,
+            &tr(MENU_TITLE),
+            &self.items(),
+            self.selected,
+            &self.theme,
+        );
+    }
+    fn handle_event(&mut self, event: geng::Event) {
+        if let Some(settings) = &mut self.settings {
+            if settings.handle_fullscreen_key(&self.geng, &event) {
+                return;
+            }
+        }
+        let count = self.items().len();
+        match menu_input(&event, &mut self.selected, count, self.framebuffer_size) {
+            Some(MenuInput::Activate) => self.activate(),
+            Some(MenuInput::Adjust(direction)) => self.adjust(direction),
+            Some(MenuInput::Back) => self.back(),
+            _ => {}
+        }
+    }
+}
+
+impl Scene for Menu {
+    // Whatever was pushed on top owned the settings and may have changed progress
+    fn focus(&mut self) {
+        if self.settings.is_none() {
+            self.settings = Some(Settings::reload(&self.launch));
+        }
+        self.progress = SaveData::load(&self.assets.levels).clone();
+    }
+    fn blur(&mut self) {
+        self.settings = None;
+    }
+    fn presence(&self) -> Option<Presence> {
+        Some(Presence::new(tr("presence.menu")))
+    }
+    fn scene_transition(&mut self) -> Option<SceneTransition> {
+        self.transition.take()
+    }
+}
@@ -0,0 +1,90 @@
+use super::*;
+
+#[derive(ugli::Vertex, Clone)]
+struct ParallaxVertex {
+    a_pos: Vec2<f32>,
+}
+
+#[derive(Debug, Copy, Clone)]
+pub struct ParallaxLayer {
+    // 0 moves with the world, 1 stays fixed on screen
+    pub factor: f32,
+    pub bottom: f32,
+    pub size: Vec2<f32>,
+    pub opacity: f32,
+}
+
+pub struct ParallaxRenderer {
+    quad: ugli::VertexBuffer<ParallaxVertex>,
+    pub(crate) program: Rc<ugli::Program>,
+}
+
+impl ParallaxRenderer {
+    pub fn new(geng: &Rc<Geng>, shaders: &Shaders) -> Self {
+        Self {
+            quad: ugli::VertexBuffer::new_static(
+                geng.ugli(),
+                [(0.0, 0.0), (1.0, 0.0), (1.0, 1.0), (0.0, 1.0)]
+                    .iter()
+                    .map(|&(x, y)| ParallaxVertex { a_pos: vec2(x, y) })
+                    .collect(),
+            ),
+            program: shaders.programs().parallax.clone(),
+        }
+    }
+    pub fn draw(
+        &self,
+        framebuffer: &mut ugli::Framebuffer,
+        camera: &Camera,
+        layer: &ParallaxLayer,
+        texture: &ugli::Texture,
+        color: Color<f32>,
+    ) {
+        let framebuffer_size = framebuffer.size().map(|x| x as f32);
+        let visible = camera.view_bounds(framebuffer_size);
+        let origin = camera.center * layer.factor + vec2(0.0, layer.bottom);
+        let top = origin.y + layer.size.y;
+        if top < visible.y_min {
+            return;
+        }
+        let rect = AABB {
+            x_min: visible.x_min,
+            x_max: visible.x_max,
+            y_min: visible.y_min,
+            y_max: partial_min(visible.y_max, top),
+        };
+        let uv = |pos: Vec2<f32>| {
+            vec2(
+                (pos.x - origin.x) / layer.size.x,
+                1.0 - (pos.y - origin.y) / layer.size.y,
+            )
+        };
+        let uv_min = uv(rect.bottom_left());
+        let uv_max = uv(rect.top_right());
+        let camera_uniforms = camera.uniforms(framebuffer_size);
+        let uniforms = (
+            camera_uniforms,
+            ugli::uniforms! {
+                u_model_matrix: Mat4::translate(rect.bottom_left().extend(0.0))
+                    * Mat4::scale(rect.size().extend(1.0)),
+                u_uv_rect: vec4(uv_min.x, uv_min.y, uv_max.x, uv_max.y),
+                u_texture: texture,
+                u_color: Color {
+                    a: color.a * layer.opacity,
+                    ..color
+                },
+            },
+        );
+        ugli::draw(
+            framebuffer,
+            &self.program,
+            ugli::DrawMode::TriangleFan,
+            &self.quad,
+            uniforms,
+            ugli::DrawParameters {
+                blend_mode: Some(default()),
+                ..default()
+            },
+        );
+    }
+}
@@ -0,0 +1,204 @@
+use super::*;
+
+const PAUSE_TITLE: &str = "pause.title";
+const PAUSE_DIM: f32 = 0.6;
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum PauseAction {
+    Resume,
+    Restart,
+    BackToMenu,
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+enum PauseScreen {
+    Main,
+    Settings,
+    Accessibility,
+}
+
+pub struct PauseMenu {
+    screen: PauseScreen,
+    selected: usize,
+}
+
+impl Default for PauseMenu {
+    fn default() -> Self {
+        Self {
+            screen: PauseScreen::Main,
+            selected: 0,
+        }
+    }
+}
+
+impl PauseMenu {
+    fn items(&self, settings: &Settings) -> Vec<String> {
+        match self.screen {
+            PauseScreen::Main => [
+                "pause.resume",
+                "pause.restart",
+                "menu.settings",
+                "pause.back_to_menu",
+            ]
+            .iter()
+            .map(|key| tr(key))
+            .collect(),
+            PauseScreen::Settings => settings_items(settings),
+            PauseScreen::Accessibility => accessibility_items(settings),
+        }
+    }
+    fn open(&mut self, screen: PauseScreen) {
+        self.screen = screen;
+        self.selected = 0;
+    }
+    pub fn handle_event(
+        &mut self,
+        geng: &Geng,
+        settings: &mut Settings,
+        event: &geng::Event,
+        framebuffer_size: Vec2<f32>,
+    ) -> Option<PauseAction> {
+        let count = self.items(settings).len();
+        let input = menu_input(event, &mut self.selected, count, framebuffer_size)?;
+        match (self.screen, input) {
+            (PauseScreen::Main, MenuInput::Back) => Some(PauseAction::Resume),
+            (PauseScreen::Main, MenuInput::Activate) => match self.selected {
+                0 => Some(PauseAction::Resume),
+                1 => Some(PauseAction::Restart),
+                2 => {
+                    self.open(PauseScreen::Settings);
+                    None
+                }
+                _ => Some(PauseAction::BackToMenu),
+            },
+            (PauseScreen::Settings, MenuInput::Activate) if self.selected == ACCESSIBILITY_ITEM => {
+                self.open(PauseScreen::Accessibility);
+                None
+            }
+            (PauseScreen::Settings, MenuInput::Activate) if self.selected + 1 < count => {
+                adjust_setting(geng, settings, self.selected, 1);
+                None
+            }
+            (PauseScreen::Settings, MenuInput::Adjust(direction)) => {
+                adjust_setting(geng, settings, self.selected, direction);
+                None
+            }
+            (PauseScreen::Settings, MenuInput::Activate | MenuInput::Back) => {
+                self.open(PauseScreen::Main);
+                None
+            }
+            (PauseScreen::Accessibility, MenuInput::Activate) if self.selected + 1 < count => {
+                adjust_accessibility(settings, self.selected, 1);
+                None
+            }
+            (PauseScreen::Accessibility, MenuInput::Adjust(direction)) => {
+                adjust_accessibility(settings, self.selected, direction);
+                None
+            }
+            (PauseScreen::Accessibility, MenuInput::Activate | MenuInput::Back) => {
+                self.open(PauseScreen::Settings);
+                None
+            }
+            _ => None,
+        }
+    }
+    pub fn draw(
+        &self,
+        framebuffer: &mut ugli::Framebuffer,
+        geng: &Geng,
+        font: &geng::Font,
+        settings: &Settings,
+        theme: &Theme,
+    ) {
+        let framebuffer_size = framebuffer.size().map(|x| x as f32);
+        geng.draw_2d().quad(
+            framebuffer,
+            AABB::pos_size(vec2(0.0, 0.0), framebuffer_size),
+            // Background colored so the menu text stays readable in every theme
+            Color {
+                a: PAUSE_DIM,
+                ..theme.background.rgba()
+            },
+        );
+        draw_menu(
+            framebuffer,
+            font,
+            &tr(PAUSE_TITLE),
+            &self.items(settings),
+            self.selected,
+            theme,
+        );
+    }
+}
+
+// Pushed over the game, which picks up the chosen action when it gets focus back
+pub struct Pause {
+    geng: Rc<Geng>,
+    assets: Rc<Assets>,
+    settings: AutoSave<Settings>,
+    theme: Theme,
+    menu: PauseMenu,
+    gamepad: Gamepad,
+    action: Rc<Cell<Option<PauseAction>>>,
+    transition: Option<SceneTransition>,
+}
+
+impl Pause {
+    pub fn new(
+        geng: &Rc<Geng>,
+        assets: &Rc<Assets>,
+        settings: AutoSave<Settings>,
+        theme: Theme,
+        action: &Rc<Cell<Option<PauseAction>>>,
+    ) -> Self {
+        Self {
+            geng: geng.clone(),
+            assets: assets.clone(),
+            settings,
+            theme,
+            menu: default(),
+            gamepad: Gamepad::new(),
+            action: action.clone(),
+            transition: None,
+        }
+    }
+}
+
+impl geng::State for Pause {
+    fn update(&mut self, _delta_time: f64) {
+        for event in self.gamepad.menu_events() {
+            self.handle_event(event);
+        }
+    }
+    fn draw(&mut self, framebuffer: &mut ugli::Framebuffer) {
+        self.menu.draw(
+            framebuffer,
+            &self.geng,
+            &self.assets.font,
+            &self.settings,
+            &self.theme,
+        );
+    }
+    fn handle_event(&mut self, event: geng::Event) {
+        if self.settings.handle_fullscreen_key(&self.geng, &event) {
+            return;
+        }
+        let framebuffer_size = self.geng.window().size().map(|x| x as f32);
+        let action =
+            self.menu
+                .handle_event(&self.geng, &mut self.settings, &event, framebuffer_size);
+        if action.is_some() {
+            self.action.set(action);
+            self.transition = Some(SceneTransition::Pop);
+        }
+    }
+}
+
+impl Scene for Pause {
+    fn overlay(&self) -> bool {
+        true
+    }
+    fn scene_transition(&mut self) -> Option<SceneTransition> {
+        self.transition.take()
+    }
+}
@@ -0,0 +1,108 @@
+use super::*;
+
+#[derive(ugli::Vertex, Clone)]
+struct Vertex {
+    a_pos: Vec2<f32>,
+}
+
+const ABERRATION_DECAY: f32 = 0.1;
+const MAX_ABERRATION: f32 = 0.05;
+const FLASH_DECAY: f32 = 2.0;
+
+// Screen-space effects, gameplay code pokes these and they fade out by themselves
+#[derive(Debug, Clone)]
+pub struct Effects {
+    pub vignette: f32,
+    pub aberration: f32,
+    pub flash: f32,
+    pub flash_color: Color<f32>,
+}
+
+impl Default for Effects {
+    fn default() -> Self {
+        Self {
+            vignette: 0.3,
+            aberration: 0.0,
+            flash: 0.0,
+            flash_color: Color::WHITE,
+        }
+    }
+}
+
+impl Effects {
+    pub fn add_aberration(&mut self, amount: f32) {
+        self.aberration = partial_min(self.aberration + amount, MAX_ABERRATION);
+    }
+    pub fn flash(&mut self, color: Color<f32>) {
+        self.flash = 1.0;
+        self.flash_color = color;
+    }
+    pub fn update(&mut self, delta_time: f32) {
+        self.aberration = partial_max(self.aberration - ABERRATION_DECAY * delta_time, 0.0);
+        self.flash = partial_max(self.flash - FLASH_DECAY * delta_time, 0.0);
+    }
+}
+
+pub struct PostProcess {
+    quad: ugli::VertexBuffer<Vertex>,
+    pub(crate) program: Rc<ugli::Program>,
+    white_texture: ugli::Texture,
+}
+
+impl PostProcess {
+    pub fn new(geng: &Rc<Geng>, shaders: &Shaders) -> Self {
+        Self {
+            quad: ugli::VertexBuffer::new_static(
+                geng.ugli(),
+                vec![
+                    Vertex {
+                        a_pos: vec2(-1.0, -1.0),
+                    },
+                    Vertex {
+                        a_pos: vec2(1.0, -1.0),
+                    },
+                    Vertex {
+                        a_pos: vec2(1.0, 1.0),
+                    },
+                    Vertex {
+                        a_pos: vec2(-1.0, 1.0),
+                    },
+                ],
+            ),
+            program: shaders.programs().post_process.clone(),
+            white_texture: ugli::Texture::new_with(geng.ugli(), vec2(1, 1), |_| Color::WHITE),
+        }
+    }
+    // Draws the scene into the viewport, given in framebuffer pixels, multiplied by the light map
+    pub fn apply(
+        &self,
+        framebuffer: &mut ugli::Framebuffer,
+        scene: &ugli::Texture,
+        light_map: Option<&ugli::Texture>,
+        viewport: AABB<f32>,
+        effects: &Effects,
+    ) {
+        let framebuffer_size = framebuffer.size().map(|x| x as f32);
+        let to_clip = |pos: Vec2<f32>| {
+            vec2(pos.x / framebuffer_size.x, pos.y / framebuffer_size.y) * 2.0 - vec2(1.0, 1.0)
+        };
+        let min = to_clip(viewport.bottom_left());
+        let max = to_clip(viewport.top_right());
+        ugli::draw(
+            framebuffer,
+            &self.program,
+            ugli::DrawMode::TriangleFan,
+            &self.quad,
+            ugli::uniforms! {
+                u_rect: vec4(min.x, min.y, max.x, max.y),
+                u_texture: scene,
+                u_light_map: light_map.unwrap_or(&self.white_texture),
+                u_vignette: effects.vignette,
+                u_aberration: effects.aberration,
+                u_flash: effects.flash,
+                u_flash_color: effects.flash_color,
+            },
+            ugli::DrawParameters::default(),
+        );
+    }
+}
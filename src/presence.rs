@@ -0,0 +1,217 @@
+use super::*;
+
+// What the screen on top of the stack is doing, shown to friends on Discord
+#[derive(Debug, Clone, PartialEq)]
+pub struct Presence {
+    pub details: String,
+    pub state: Option<String>,
+    // Seconds since the current attempt started
+    pub elapsed: Option<f32>,
+}
+
+impl Presence {
+    pub fn new(details: String) -> Self {
+        Self {
+            details,
+            state: None,
+            elapsed: None,
+        }
+    }
+}
+
+#[cfg(not(all(feature = "discord", not(target_arch = "wasm32"))))]
+#[derive(Default)]
+pub struct PresenceReporter;
+
+#[cfg(not(all(feature = "discord", not(target_arch = "wasm32"))))]
+impl PresenceReporter {
+    pub fn update(&mut self, _presence: Option<Presence>) {}
+}
+
+#[cfg(all(feature = "discord", not(target_arch = "wasm32")))]
+pub use discord::PresenceReporter;
+
+#[cfg(all(feature = "discord", not(target_arch = "wasm32")))]
+mod discord {
+    use super::*;
+    use std::io::{Read, Write};
+    use std::sync::mpsc;
+    use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+    // Presence stays off unless the build names a Discord application
+    const CLIENT_ID: Option<&str> = option_env!("DISCORD_CLIENT_ID");
+    // Discord drops activity updates sent more often than this
+    const UPDATE_INTERVAL: Duration = Duration::from_secs(5);
+    const RECONNECT_INTERVAL: Duration = Duration::from_secs(15);
+    // Start times closer than this are the same attempt still going
+    const START_TOLERANCE: u64 = 2;
+
+    const OP_HANDSHAKE: u32 = 0;
+    const OP_FRAME: u32 = 1;
+
+    trait Ipc: Read + Write + Send {}
+    impl<T: Read + Write + Send> Ipc for T {}
+
+    #[derive(Debug, Clone, PartialEq)]
+    struct Activity {
+        details: String,
+        state: Option<String>,
+        start: Option<u64>,
+    }
+
+    impl Activity {
+        fn same_as(&self, other: &Self) -> bool {
+            let close = match (self.start, other.start) {
+                (Some(a), Some(b)) => a.abs_diff(b) <= START_TOLERANCE,
+                (a, b) => a == b,
+            };
+            self.details == other.details && self.state == other.state && close
+        }
+    }
+
+    pub struct PresenceReporter {
+        sender: Option<mpsc::Sender<Option<Activity>>>,
+        last: Option<Activity>,
+    }
+
+    impl Default for PresenceReporter {
+        fn default() -> Self {
+            let sender = CLIENT_ID.map(|client_id| {
+                let (sender, receiver) = mpsc::channel();
+                std::thread::spawn(move || run(client_id, receiver));
+                sender
+            });
+            Self { sender, last: None }
+        }
+    }
+
+    impl PresenceReporter {
+        pub fn update(&mut self, presence: Option<Presence>) {
+            let sender = match &self.sender {
+                Some(sender) => sender,
+                None => return,
+            };
+            let now = SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .unwrap()
+                .as_secs();
+            let activity = presence.map(|presence| Activity {
+                details: presence.details,
+                state: presence.state,
+                start: presence
+                    .elapsed
+                    .map(|elapsed| now.saturating_sub(elapsed as u64)),
+            });
+            let changed = match (&self.last, &activity) {
+                (Some(last), Some(activity)) => !last.same_as(activity),
+                (last, activity) => last.is_some() != activity.is_some(),
+            };
+            if changed {
+                // The thread only goes away with the channel, so errors are ignored
+                let _ = sender.send(activity.clone());
+                self.last = activity;
+            }
+        }
+    }
+
+    fn socket_dir() -> std::path::PathBuf {
+        ["XDG_RUNTIME_DIR", "TMPDIR", "TMP", "TEMP"]
+            .iter()
+            .find_map(std::env::var_os)
+            .map_or_else(|| "/tmp".into(), Into::into)
+    }
+
+    fn connect() -> Option<Box<dyn Ipc>> {
+        (0..10).find_map(|index| -> Option<Box<dyn Ipc>> {
+            let name = format!("discord-ipc-{}", index);
+            #[cfg(unix)]
+            {
+                let stream = std::os::unix::net::UnixStream::connect(socket_dir().join(name));
+                Some(Box::new(stream.ok()?))
+            }
+            #[cfg(windows)]
+            {
+                let pipe = std::fs::OpenOptions::new()
+                    .read(true)
+                    .write(true)
+                    .open(format!(r"\\?\pipe\{}", name));
+                Some(Box::new(pipe.ok()?))
+            }
+        })
+    }
+
+    fn send(ipc: &mut dyn Ipc, op: u32, payload: &serde_json::Value) -> std::io::Result<()> {
+        let data = serde_json::to_vec(payload)?;
+        let mut frame = Vec::with_capacity(8 + data.len());
+        frame.extend_from_slice(&op.to_le_bytes());
+        frame.extend_from_slice(&(data.len() as u32).to_le_bytes());
+        frame.extend_from_slice(&data);
+        ipc.write_all(&frame)?;
+        // Every message gets a reply, reading it keeps the pipe from filling up
+        let mut header = [0; 8];
+        ipc.read_exact(&mut header)?;
+        let len = u32::from_le_bytes([header[4], header[5], header[6], header[7]]);
+        std::io::copy(&mut ipc.take(len as u64), &mut std::io::sink())?;
+        Ok(())
+    }
+
+    fn set_activity(ipc: &mut dyn Ipc, activity: &Option<Activity>) -> std::io::Result<()> {
+        let activity = activity.as_ref().map(|activity| {
+            let mut value = serde_json::json!({ "details": activity.details });
+            if let Some(state) = &activity.state {
+                value["state"] = state.as_str().into();
+            }
+            if let Some(start) = activity.start {
+                value["timestamps"] = serde_json::json!({ "start": start });
+            }
+            value
+        });
+        static NONCE: std::sync::atomic::AtomicU64 = std::sync::atomic::AtomicU64::new(0);
+        let nonce = NONCE.fetch_add(1, std::sync::atomic::Ordering::Relaxed);
+        send(
+            ipc,
+            OP_FRAME,
+            &serde_json::json!({
+                "cmd": "SET_ACTIVITY",
+                "args": { "pid": std::process::id(), "activity": activity },
+                "nonce": nonce.to_string(),
+            }),
+        )
+    }
+
+    // Runs until the game exits. Any failure drops the connection and the newest
+    // activity is retried later, so a missing Discord client goes unnoticed
+    fn run(client_id: &str, receiver: mpsc::Receiver<Option<Activity>>) {
+        let mut ipc: Option<Box<dyn Ipc>> = None;
+        let mut activity = None;
+        let mut pending = false;
+        loop {
+            match receiver.recv_timeout(RECONNECT_INTERVAL) {
+                // Only the newest state matters after waiting out the rate limit
+                Ok(received) => {
+                    activity = receiver.try_iter().last().unwrap_or(received);
+                    pending = true;
+                }
+                Err(mpsc::RecvTimeoutError::Timeout) => {}
+                Err(mpsc::RecvTimeoutError::Disconnected) => return,
+            }
+            if !pending {
+                continue;
+            }
+            if ipc.is_none() {
+                ipc = connect().and_then(|mut connection| {
+                    let handshake = serde_json::json!({ "v": 1, "client_id": client_id });
+                    send(&mut *connection, OP_HANDSHAKE, &handshake).ok()?;
+                    Some(connection)
+                });
+            }
+            if let Some(connection) = &mut ipc {
+                match set_activity(&mut **connection, &activity) {
+                    Ok(()) => pending = false,
+                    Err(_) => ipc = None,
+                }
+            }
+            std::thread::sleep(UPDATE_INTERVAL);
+        }
+    }
+}
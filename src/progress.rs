@@ -0,0 +1,146 @@
+use super::*;
+
+// Same file as before versioning, so old saves get migrated in place
+pub const SAVE_PATH: &str = "progress.json";
+pub const SAVE_VERSION: u32 = 2;
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct LevelRecord {
+    pub completed: bool,
+    pub best_time: Option<f32>,
+    pub collected: usize,
+    pub medal: Option<Medal>,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct LifetimeStats {
+    pub throws: usize,
+    // Distance the ball traveled while out of hand
+    pub distance_swung: f32,
+    pub blocks_broken: usize,
+    pub deaths: usize,
+}
+
+impl LifetimeStats {
+    pub fn add(&mut self, other: &Self) {
+        self.throws += other.throws;
+        self.distance_swung += other.distance_swung;
+        self.blocks_broken += other.blocks_broken;
+        self.deaths += other.deaths;
+    }
+}
+
+// Per level results, keyed by level name so reordering the list keeps them
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SaveData {
+    // Missing in saves written before versioning
+    #[serde(default)]
+    pub version: u32,
+    pub levels: HashMap<String, LevelRecord>,
+    pub unlocked: HashSet<String>,
+    pub stats: LifetimeStats,
+    pub achievements: HashSet<String>,
+    // Settings as of the last completed level, settings.json stays authoritative
+    pub settings: Settings,
+}
+
+impl Default for SaveData {
+    fn default() -> Self {
+        Self {
+            version: SAVE_VERSION,
+            levels: HashMap::new(),
+            unlocked: HashSet::new(),
+            stats: default(),
+            achievements: HashSet::new(),
+            settings: default(),
+        }
+    }
+}
+
+impl SaveData {
+    pub fn load(levels: &LevelSet) -> AutoSave<Self> {
+        let mut save = AutoSave::<Self>::load(SAVE_PATH);
+        if save.version < SAVE_VERSION {
+            save.migrate(levels);
+        }
+        save
+    }
+    fn migrate(&mut self, levels: &LevelSet) {
+        info!("Migrating save data from version {}", self.version);
+        if self.version < 1 {
+            // Unlocks used to be derived from beating the previous level
+            for pair in levels.levels.windows(2) {
+                if self
+                    .record(&pair[0].0)
+                    .is_some_and(|record| record.completed)
+                {
+                    self.unlocked.insert(pair[1].0.clone());
+                }
+            }
+        }
+        if self.version < 2 {
+            // Medals came later, best times already earned them
+            for (name, level) in &levels.levels {
+                let level = level.borrow();
+                let medals = match &level.medals {
+                    Some(medals) => medals,
+                    None => continue,
+                };
+                if let Some(record) = self.levels.get_mut(name) {
+                    record.medal = record.best_time.and_then(|time| medals.medal(time));
+                }
+            }
+        }
+        self.version = SAVE_VERSION;
+    }
+    pub fn record(&self, level: &str) -> Option<&LevelRecord> {
+        self.levels.get(level)
+    }
+    pub fn complete(&mut self, levels: &LevelSet, index: usize, time: f32, collected: usize) {
+        let record = self
+            .levels
+            .entry(levels.levels[index].0.clone())
+            .or_default();
+        record.completed = true;
+        record.best_time = Some(match record.best_time {
+            Some(best) => best.min(time),
+            None => time,
+        });
+        record.collected = record.collected.max(collected);
+        let medal = levels.levels[index]
+            .1
+            .borrow()
+            .medals
+            .as_ref()
+            .and_then(|medals| medals.medal(time));
+        record.medal = record.medal.max(medal);
+        if let Some((next, _)) = levels.levels.get(index + 1) {
+            self.unlocked.insert(next.clone());
+        }
+    }
+    pub fn sum_of_best(&self, levels: &LevelSet) -> Option<f32> {
+        levels
+            .levels
+            .iter()
+            .map(|(name, _)| self.record(name)?.best_time)
+            .sum()
+    }
+    // The first level is always open
+    pub fn unlocked(&self, levels: &LevelSet, index: usize) -> bool {
+        index == 0 || self.unlocked.contains(&levels.levels[index].0)
+    }
+    // First open level that is not beaten yet, where Play picks up
+    pub fn next_level(&self, levels: &LevelSet) -> usize {
+        (0..levels.levels.len())
+            .find(|&index| {
+                self.unlocked(levels, index)
+                    && !self
+                        .record(&levels.levels[index].0)
+                        .is_some_and(|record| record.completed)
+            })
+            .unwrap_or(0)
+    }
+}
@@ -0,0 +1,293 @@
+use super::*;
+
+const SNAPSHOT_INTERVAL: f32 = 0.05;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub enum ClientMessage {
+    Join { name: String },
+    Ready(bool),
+    Snapshot(GhostFrame),
+    Finish { time: f32 },
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LobbyPlayer {
+    pub id: usize,
+    pub name: String,
+    pub ready: bool,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RaceResult {
+    pub id: usize,
+    pub name: String,
+    pub time: Option<f32>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub enum ServerMessage {
+    Welcome { id: usize },
+    Lobby(Vec<LobbyPlayer>),
+    Start { level: usize },
+    Snapshot { id: usize, frame: GhostFrame },
+    Left { id: usize },
+    // Finished players first, ordered by time
+    Results(Vec<RaceResult>),
+}
+
+pub type RaceConnection = geng::net::client::Connection<ServerMessage, ClientMessage>;
+
+#[cfg(not(target_arch = "wasm32"))]
+struct ServerPlayer {
+    name: String,
+    in_lobby: bool,
+    ready: bool,
+    // Taking part in the current race, finished or not
+    racing: bool,
+    time: Option<f32>,
+    sender: Box<dyn geng::net::Sender<ServerMessage>>,
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+struct RaceState {
+    level: usize,
+    next_id: usize,
+    racing: bool,
+    players: HashMap<usize, ServerPlayer>,
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+impl RaceState {
+    fn broadcast(&mut self, message: ServerMessage) {
+        for player in self.players.values_mut() {
+            player.sender.send(message.clone());
+        }
+    }
+    fn results(&self) -> Vec<RaceResult> {
+        let mut results: Vec<RaceResult> = self
+            .players
+            .iter()
+            .filter(|(_, player)| player.racing)
+            .map(|(&id, player)| RaceResult {
+                id,
+                name: player.name.clone(),
+                time: player.time,
+            })
+            .collect();
+        results.sort_by(|a, b| match (a.time, b.time) {
+            (Some(a), Some(b)) => a.total_cmp(&b),
+            (Some(_), None) => std::cmp::Ordering::Less,
+            (None, Some(_)) => std::cmp::Ordering::Greater,
+            (None, None) => std::cmp::Ordering::Equal,
+        });
+        results
+    }
+    // Starts the race once everyone waiting in the lobby is ready
+    fn update_lobby(&mut self) {
+        let lobby = self
+            .players
+            .iter()
+            .filter(|(_, player)| player.in_lobby)
+            .map(|(&id, player)| LobbyPlayer {
+                id,
+                name: player.name.clone(),
+                ready: player.ready,
+            })
+            .collect::<Vec<_>>();
+        self.broadcast(ServerMessage::Lobby(lobby.clone()));
+        if self.racing || lobby.is_empty() || !lobby.iter().all(|player| player.ready) {
+            return;
+        }
+        info!("Starting a race with {} players", lobby.len());
+        self.racing = true;
+        for player in self.players.values_mut() {
+            player.racing = player.in_lobby;
+            player.in_lobby = false;
+            player.ready = false;
+            player.time = None;
+        }
+        let level = self.level;
+        self.broadcast(ServerMessage::Start { level });
+    }
+    fn update_results(&mut self) {
+        if !self.racing {
+            return;
+        }
+        let results = self.results();
+        self.broadcast(ServerMessage::Results(results.clone()));
+        if results.iter().all(|result| result.time.is_some()) {
+            self.racing = false;
+            self.update_lobby();
+        }
+    }
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+pub struct RaceServer {
+    state: Arc<Mutex<RaceState>>,
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+impl RaceServer {
+    pub fn new(level: usize) -> Self {
+        Self {
+            state: Arc::new(Mutex::new(RaceState {
+                level,
+                next_id: 0,
+                racing: false,
+                players: HashMap::new(),
+            })),
+        }
+    }
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+impl geng::net::server::App for RaceServer {
+    type Client = RaceServerClient;
+    type ServerMessage = ServerMessage;
+    type ClientMessage = ClientMessage;
+    fn connect(
+        &mut self,
+        mut sender: Box<dyn geng::net::Sender<ServerMessage>>,
+    ) -> RaceServerClient {
+        let mut state = self.state.lock().unwrap();
+        let id = state.next_id;
+        state.next_id += 1;
+        sender.send(ServerMessage::Welcome { id });
+        state.players.insert(
+            id,
+            ServerPlayer {
+                name: format!("Player {}", id + 1),
+                in_lobby: true,
+                ready: false,
+                racing: false,
+                time: None,
+                sender,
+            },
+        );
+        state.update_lobby();
+        RaceServerClient {
+            id,
+            state: self.state.clone(),
+        }
+    }
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+pub struct RaceServerClient {
+    id: usize,
+    state: Arc<Mutex<RaceState>>,
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+impl geng::net::Receiver<ClientMessage> for RaceServerClient {
+    fn handle(&mut self, message: ClientMessage) {
+        let mut state = self.state.lock().unwrap();
+        let id = self.id;
+        let player = match state.players.get_mut(&id) {
+            Some(player) => player,
+            None => return,
+        };
+        match message {
+            ClientMessage::Join { name } => {
+                player.name = name;
+                state.update_lobby();
+            }
+            ClientMessage::Ready(ready) => {
+                player.ready = ready;
+                player.in_lobby = true;
+                // Coming back to the lobby before finishing gives up the race
+                if player.racing && player.time.is_none() {
+                    player.racing = false;
+                    state.update_results();
+                }
+                state.update_lobby();
+            }
+            ClientMessage::Snapshot(frame) => {
+                if player.racing {
+                    state.broadcast(ServerMessage::Snapshot { id, frame });
+                }
+            }
+            ClientMessage::Finish { time } => {
+                // Clients are not trusted, a NaN would break sorting the results
+                if !time.is_finite() || time < 0.0 {
+                    warn!("Ignoring invalid finish time {} from {}", time, id);
+                    return;
+                }
+                if player.racing && player.time.is_none() {
+                    player.time = Some(time);
+                    state.update_results();
+                }
+            }
+        }
+    }
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+impl Drop for RaceServerClient {
+    fn drop(&mut self) {
+        let mut state = self.state.lock().unwrap();
+        state.players.remove(&self.id);
+        state.broadcast(ServerMessage::Left { id: self.id });
+        state.update_results();
+        state.update_lobby();
+    }
+}
+
+// Client side of a running race, remote players are only shown, never simulated
+pub struct Race {
+    connection: RaceConnection,
+    pub id: usize,
+    pub remote: HashMap<usize, GhostFrame>,
+    pub results: Vec<RaceResult>,
+    // Latest lobby state, shown right away when going back to the lobby
+    lobby: Vec<LobbyPlayer>,
+    finished: bool,
+    snapshot_timer: f32,
+}
+
+impl Race {
+    pub fn new(connection: RaceConnection, id: usize) -> Self {
+        Self {
+            connection,
+            id,
+            remote: HashMap::new(),
+            results: Vec::new(),
+            lobby: Vec::new(),
+            finished: false,
+            snapshot_timer: 0.0,
+        }
+    }
+    pub fn update(&mut self, frame: GhostFrame, delta_time: f32) {
+        for message in self.connection.new_messages().collect::<Vec<_>>() {
+            match message {
+                ServerMessage::Snapshot { id, frame } if id != self.id => {
+                    self.remote.insert(id, frame);
+                }
+                ServerMessage::Left { id } => {
+                    self.remote.remove(&id);
+                }
+                ServerMessage::Results(results) => self.results = results,
+                ServerMessage::Lobby(players) => self.lobby = players,
+                // Only players waiting in the lobby take part in a new race
+                ServerMessage::Start { level } => {
+                    info!("Another race started on level {}", level);
+                }
+                _ => {}
+            }
+        }
+        self.snapshot_timer -= delta_time;
+        if self.snapshot_timer <= 0.0 && !self.finished {
+            self.snapshot_timer = SNAPSHOT_INTERVAL;
+            self.connection.send(ClientMessage::Snapshot(frame));
+        }
+    }
+    pub fn finish(&mut self, time: f32) {
+        if !mem::replace(&mut self.finished, true) {
+            self.connection.send(ClientMessage::Finish { time });
+        }
+    }
+    pub fn into_lobby(self) -> (RaceConnection, Vec<LobbyPlayer>) {
+        (self.connection, self.lobby)
+    }
+}
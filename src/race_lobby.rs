@@ -0,0 +1,179 @@
+use super::*;
+
+const LOBBY_TITLE: &str = "lobby.title";
+const LOBBY_TITLE_SIZE: f32 = 56.0;
+const LOBBY_TEXT_SIZE: f32 = 32.0;
+const LOBBY_LINE_SPACING: f32 = 40.0;
+
+pub struct RaceLobby {
+    geng: Rc<Geng>,
+    assets: Rc<Assets>,
+    settings: Option<AutoSave<Settings>>,
+    connection: Option<RaceConnection>,
+    id: Option<usize>,
+    players: Vec<LobbyPlayer>,
+    ready: bool,
+    gamepad: Gamepad,
+    theme: Theme,
+    framebuffer_size: Vec2<f32>,
+    transition: Option<SceneTransition>,
+}
+
+impl RaceLobby {
+    pub fn new(
+        geng: &Rc<Geng>,
+        assets: &Rc<Assets>,
+        settings: AutoSave<Settings>,
+        id: Option<usize>,
+        mut connection: RaceConnection,
+    ) -> Self {
+        let themes = &assets.themes;
+        connection.send(ClientMessage::Ready(false));
+        Self {
+            geng: geng.clone(),
+            assets: assets.clone(),
+            settings: Some(settings),
+            connection: Some(connection),
+            id,
+            players: Vec::new(),
+            ready: false,
+            gamepad: Gamepad::new(),
+            theme: themes.theme().clone(),
+            framebuffer_size: vec2(1.0, 1.0),
+            transition: None,
+        }
+    }
+    pub fn with_name(mut self, name: String) -> Self {
+        if let Some(connection) = &mut self.connection {
+            connection.send(ClientMessage::Join { name });
+        }
+        self
+    }
+    pub fn with_players(mut self, players: Vec<LobbyPlayer>) -> Self {
+        self.players = players;
+        self
+    }
+    fn start(&mut self, level: usize) {
+        let id = match self.id {
+            Some(id) => id,
+            None => {
+                warn!("Race started before the server assigned an id");
+                return;
+            }
+        };
+        let (settings, connection) = match (self.settings.take(), self.connection.take()) {
+            (Some(settings), Some(connection)) => (settings, connection),
+            _ => return,
+        };
+        let level = level.min(self.assets.levels.levels.len() - 1);
+        self.transition = Some(SceneTransition::Replace(Box::new(
+            Game::new(&self.geng, &self.assets, settings, level)
+                .with_race(Race::new(connection, id)),
+        )));
+    }
+    fn back(&mut self) {
+        self.transition = Some(SceneTransition::Pop);
+    }
+}
+
+impl geng::State for RaceLobby {
+    fn update(&mut self, _delta_time: f64) {
+        for event in self.gamepad.menu_events() {
+            self.handle_event(event);
+        }
+        let messages = match &mut self.connection {
+            Some(connection) => connection.new_messages().collect::<Vec<_>>(),
+            None => return,
+        };
+        for message in messages {
+            match message {
+                ServerMessage::Welcome { id } => self.id = Some(id),
+                ServerMessage::Lobby(players) => self.players = players,
+                ServerMessage::Start { level } => self.start(level),
+                _ => {}
+            }
+        }
+    }
+    fn draw(&mut self, framebuffer: &mut ugli::Framebuffer) {
+        self.framebuffer_size = framebuffer.size().map(|x| x as f32);
+        ugli::clear(framebuffer, Some(self.theme.background.rgba()), None);
+        let ui_size = UiCamera::size(self.framebuffer_size);
+        draw_ui_text(
+            framebuffer,
+            &self.assets.font,
+            &tr(LOBBY_TITLE),
+            vec2(ui_size.x / 2.0, ui_size.y * 0.8),
+            0.5,
+            LOBBY_TITLE_SIZE,
+            self.theme.hud.rgba(),
+        );
+        for (index, player) in self.players.iter().enumerate() {
+            let color = if Some(player.id) == self.id {
+                self.theme.highlight
+            } else {
+                self.theme.hud
+            };
+            let status = tr(if player.ready {
+                "lobby.ready"
+            } else {
+                "lobby.waiting"
+            });
+            draw_ui_text(
+                framebuffer,
+                &self.assets.font,
+                &format!("{} - {}", player.name, status),
+                vec2(
+                    ui_size.x / 2.0,
+                    ui_size.y * 0.65 - index as f32 * LOBBY_LINE_SPACING,
+                ),
+                0.5,
+                LOBBY_TEXT_SIZE,
+                color.rgba(),
+            );
+        }
+        let hint = tr(if self.ready {
+            "lobby.hint_ready"
+        } else {
+            "lobby.hint_not_ready"
+        });
+        draw_ui_text(
+            framebuffer,
+            &self.assets.font,
+            &hint,
+            vec2(ui_size.x / 2.0, 60.0),
+            0.5,
+            LOBBY_TEXT_SIZE * 0.75,
+            self.theme.hud.rgba(),
+        );
+    }
+    fn handle_event(&mut self, event: geng::Event) {
+        if let Some(settings) = &mut self.settings {
+            if settings.handle_fullscreen_key(&self.geng, &event) {
+                return;
+            }
+        }
+        match event {
+            geng::Event::KeyDown {
+                key: geng::Key::Enter | geng::Key::Space,
+            } => {
+                if let Some(connection) = &mut self.connection {
+                    self.ready = !self.ready;
+                    connection.send(ClientMessage::Ready(self.ready));
+                }
+            }
+            geng::Event::KeyDown {
+                key: geng::Key::Escape | geng::Key::Backspace,
+            } => self.back(),
+            _ => {}
+        }
+    }
+}
+
+impl Scene for RaceLobby {
+    fn presence(&self) -> Option<Presence> {
+        Some(Presence::new(tr("presence.lobby")))
+    }
+    fn scene_transition(&mut self) -> Option<SceneTransition> {
+        self.transition.take()
+    }
+}
@@ -7,11 +7,12 @@ pub struct Vertex {
 
 pub struct Renderer {
     quad: ugli::VertexBuffer<Vertex>,
-    program: ugli::Program,
+    pub(crate) program: Rc<ugli::Program>,
+    white_texture: ugli::Texture,
 }
 
 impl Renderer {
-    pub fn new(geng: &Rc<Geng>) -> Self {
+    pub fn new(geng: &Rc<Geng>, shaders: &Shaders) -> Self {
         Self {
             quad: ugli::VertexBuffer::new_static(
                 geng.ugli(),
@@ -30,26 +31,173 @@ impl Renderer {
                     },
                 ],
             ),
-            program: geng
-                .shader_lib()
-                .compile(include_str!("program.glsl"))
-                .unwrap(),
+            program: shaders.programs().renderer.clone(),
+            white_texture: ugli::Texture::new_with(geng.ugli(), vec2(1, 1), |_| Color::WHITE),
         }
     }
+    #[allow(clippy::too_many_arguments)]
+    pub fn draw_bar(
+        &self,
+        framebuffer: &mut ugli::Framebuffer,
+        camera: &impl AbstractCamera,
+        p1: Vec2<f32>,
+        p2: Vec2<f32>,
+        width: f32,
+        texture: &ugli::Texture,
+        uv: Option<AABB<f32>>,
+        color: Color<f32>,
+    ) {
+        let e1 = p2 - p1;
+        if e1.len() < EPS {
+            return;
+        }
+        let e2 = Vec2::rotate_90(e1).normalize() * width;
+        self.draw(
+            framebuffer,
+            camera,
+            Mat4::translate(p1.extend(0.0))
+                * Mat4::from_orts(e1.extend(0.0), e2.extend(0.0), vec3(0.0, 0.0, 1.0))
+                * Mat4::translate(vec3(0.0, -0.5, 0.0)),
+            texture,
+            uv,
+            color,
+        );
+    }
+    // Pivot is in sprite units, (0.5, 0.5) rotates around and places the center at pos
+    #[allow(clippy::too_many_arguments)]
+    pub fn draw_sprite(
+        &self,
+        framebuffer: &mut ugli::Framebuffer,
+        camera: &impl AbstractCamera,
+        texture: &ugli::Texture,
+        uv: Option<AABB<f32>>,
+        pos: Vec2<f32>,
+        size: Vec2<f32>,
+        rotation: f32,
+        flip_x: bool,
+        color: Color<f32>,
+        pivot: Vec2<f32>,
+    ) {
+        let flip = if flip_x { -1.0 } else { 1.0 };
+        self.draw(
+            framebuffer,
+            camera,
+            Mat4::translate(pos.extend(0.0))
+                * Mat4::rotate_z(rotation)
+                * Mat4::scale(vec3(size.x * flip, size.y, 1.0))
+                * Mat4::translate(-pivot.extend(0.0)),
+            texture,
+            uv,
+            color,
+        );
+    }
+    pub fn draw_rect(
+        &self,
+        framebuffer: &mut ugli::Framebuffer,
+        camera: &impl AbstractCamera,
+        aabb: AABB<f32>,
+        color: Color<f32>,
+    ) {
+        self.draw(
+            framebuffer,
+            camera,
+            Mat4::translate(aabb.bottom_left().extend(0.0)) * Mat4::scale(aabb.size().extend(1.0)),
+            &self.white_texture,
+            None,
+            color,
+        );
+    }
+    pub fn draw_ui(
+        &self,
+        framebuffer: &mut ugli::Framebuffer,
+        matrix: Mat4<f32>,
+        texture: &ugli::Texture,
+        uv: Option<AABB<f32>>,
+        color: Color<f32>,
+    ) {
+        self.draw(framebuffer, &UiCamera, matrix, texture, uv, color);
+    }
+    pub fn draw_ui_rect(
+        &self,
+        framebuffer: &mut ugli::Framebuffer,
+        aabb: AABB<f32>,
+        color: Color<f32>,
+    ) {
+        self.draw_rect(framebuffer, &UiCamera, aabb, color);
+    }
+    // Corners keep their size, edges stretch along one axis and the middle along both.
+    // The border is a share of the uv region on the texture side and in camera units on screen
+    #[allow(clippy::too_many_arguments)]
+    pub fn draw_nine_slice(
+        &self,
+        framebuffer: &mut ugli::Framebuffer,
+        camera: &impl AbstractCamera,
+        aabb: AABB<f32>,
+        border: f32,
+        texture: &ugli::Texture,
+        uv: AABB<f32>,
+        uv_border: f32,
+        color: Color<f32>,
+    ) {
+        let border = partial_min(border, partial_min(aabb.width(), aabb.height()) / 2.0);
+        let xs = [
+            aabb.x_min,
+            aabb.x_min + border,
+            aabb.x_max - border,
+            aabb.x_max,
+        ];
+        let ys = [
+            aabb.y_min,
+            aabb.y_min + border,
+            aabb.y_max - border,
+            aabb.y_max,
+        ];
+        let (uv_x, uv_y) = (uv.width() * uv_border, uv.height() * uv_border);
+        let us = [uv.x_min, uv.x_min + uv_x, uv.x_max - uv_x, uv.x_max];
+        let vs = [uv.y_min, uv.y_min + uv_y, uv.y_max - uv_y, uv.y_max];
+        for i in 0..3 {
+            for j in 0..3 {
+                let rect = AABB {
+                    x_min: xs[i],
+                    x_max: xs[i + 1],
+                    y_min: ys[j],
+                    y_max: ys[j + 1],
+                };
+                self.draw(
+                    framebuffer,
+                    camera,
+                    Mat4::translate(rect.bottom_left().extend(0.0))
+                        * Mat4::scale(rect.size().extend(1.0)),
+                    texture,
+                    Some(AABB {
+                        x_min: us[i],
+                        x_max: us[i + 1],
+                        y_min: vs[j],
+                        y_max: vs[j + 1],
+                    }),
+                    color,
+                );
+            }
+        }
+    }
+    #[allow(clippy::too_many_arguments)]
     pub fn draw(
         &self,
         framebuffer: &mut ugli::Framebuffer,
-        camera: &Camera,
+        camera: &impl AbstractCamera,
         matrix: Mat4<f32>,
         texture: &ugli::Texture,
+        uv: Option<AABB<f32>>,
         color: Color<f32>,
     ) {
+        let uv = uv.unwrap_or(AABB::pos_size(vec2(0.0, 0.0), vec2(1.0, 1.0)));
         let camera_uniforms = camera.uniforms(framebuffer.size().map(|x| x as f32));
         let uniforms = (
             camera_uniforms,
             ugli::uniforms! {
                 u_model_matrix: matrix,
                 u_texture: texture,
+                u_uv_rect: vec4(uv.x_min, uv.y_min, uv.x_max, uv.y_max),
                 u_color: color,
             },
         );
@@ -0,0 +1,164 @@
+use super::*;
+
+const RESULTS_TITLE: &str = "results.title";
+const RESULTS_DIM: f32 = 0.6;
+const RESULTS_LINE_SIZE: f32 = 28.0;
+const RESULTS_LINE_SPACING: f32 = 34.0;
+
+// Counted from the level start until the goal is reached
+#[derive(Debug, Default, Copy, Clone)]
+pub struct RunStats {
+    pub throws: usize,
+    pub deaths: usize,
+    pub max_ball_speed: f32,
+    // Landed at least once after the timer started
+    pub touched_ground: bool,
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum ResultsAction {
+    NextLevel,
+    Retry,
+}
+
+#[derive(Default)]
+pub struct ResultsMenu {
+    selected: usize,
+}
+
+impl ResultsMenu {
+    const ITEMS: [&'static str; 2] = ["results.next_level", "results.retry"];
+    pub fn handle_event(
+        &mut self,
+        event: &geng::Event,
+        framebuffer_size: Vec2<f32>,
+    ) -> Option<ResultsAction> {
+        let count = Self::ITEMS.len();
+        match menu_input(event, &mut self.selected, count, framebuffer_size)? {
+            MenuInput::Activate if self.selected == 0 => Some(ResultsAction::NextLevel),
+            MenuInput::Activate => Some(ResultsAction::Retry),
+            _ => None,
+        }
+    }
+    pub fn draw(
+        &self,
+        framebuffer: &mut ugli::Framebuffer,
+        geng: &Geng,
+        font: &geng::Font,
+        lines: &[String],
+        theme: &Theme,
+    ) {
+        let framebuffer_size = framebuffer.size().map(|x| x as f32);
+        geng.draw_2d().quad(
+            framebuffer,
+            AABB::pos_size(vec2(0.0, 0.0), framebuffer_size),
+            Color {
+                a: RESULTS_DIM,
+                ..theme.background.rgba()
+            },
+        );
+        let items: Vec<String> = Self::ITEMS.iter().map(|key| tr(key)).collect();
+        draw_menu(
+            framebuffer,
+            font,
+            &tr(RESULTS_TITLE),
+            &items,
+            self.selected,
+            theme,
+        );
+        let ui_size = UiCamera::size(framebuffer_size);
+        for (index, line) in lines.iter().enumerate() {
+            draw_ui_text(
+                framebuffer,
+                font,
+                line,
+                vec2(
+                    ui_size.x / 2.0,
+                    ui_size.y * 0.4 - index as f32 * RESULTS_LINE_SPACING,
+                ),
+                0.5,
+                RESULTS_LINE_SIZE,
+                theme.goal.rgba(),
+            );
+        }
+    }
+}
+
+// Pushed over the game once a level is finished, the game picks up the chosen
+// action when it gets focus back
+pub struct LevelComplete {
+    geng: Rc<Geng>,
+    assets: Rc<Assets>,
+    theme: Theme,
+    lines: Vec<String>,
+    // Still polled here, the game below is not updated while covered
+    leaderboard: Option<LeaderboardRequest>,
+    menu: ResultsMenu,
+    gamepad: Gamepad,
+    action: Rc<Cell<Option<ResultsAction>>>,
+    transition: Option<SceneTransition>,
+}
+
+impl LevelComplete {
+    pub fn new(
+        geng: &Rc<Geng>,
+        assets: &Rc<Assets>,
+        theme: Theme,
+        lines: Vec<String>,
+        leaderboard: Option<LeaderboardRequest>,
+        action: &Rc<Cell<Option<ResultsAction>>>,
+    ) -> Self {
+        Self {
+            geng: geng.clone(),
+            assets: assets.clone(),
+            theme,
+            lines,
+            leaderboard,
+            menu: default(),
+            gamepad: Gamepad::new(),
+            action: action.clone(),
+            transition: None,
+        }
+    }
+}
+
+impl geng::State for LevelComplete {
+    fn update(&mut self, _delta_time: f64) {
+        if let Some(leaderboard) = &mut self.leaderboard {
+            leaderboard.poll();
+        }
+        for event in self.gamepad.menu_events() {
+            self.handle_event(event);
+        }
+    }
+    fn draw(&mut self, framebuffer: &mut ugli::Framebuffer) {
+        let mut lines = self.lines.clone();
+        if let Some(leaderboard) = &self.leaderboard {
+            lines.extend(leaderboard.lines());
+        }
+        self.menu.draw(
+            framebuffer,
+            &self.geng,
+            &self.assets.font,
+            &lines,
+            &self.theme,
+        );
+    }
+    fn handle_event(&mut self, event: geng::Event) {
+        let framebuffer_size = self.geng.window().size().map(|x| x as f32);
+        let action = self.menu.handle_event(&event, framebuffer_size);
+        if action.is_some() {
+            self.action.set(action);
+            self.transition = Some(SceneTransition::Pop);
+        }
+    }
+}
+
+impl Scene for LevelComplete {
+    fn overlay(&self) -> bool {
+        true
+    }
+    fn scene_transition(&mut self) -> Option<SceneTransition> {
+        self.transition.take()
+    }
+}
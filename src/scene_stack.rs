@@ -0,0 +1,172 @@
+use super::*;
+
+const FADE_DURATION: f32 = 0.2;
+
+pub enum SceneTransition {
+    Push(Box<dyn Scene>),
+    Pop,
+    Replace(Box<dyn Scene>),
+}
+
+// A screen on the scene stack. Only the top one is updated, drawn and gets input,
+// the ones below are told when they get covered and uncovered again.
+pub trait Scene: geng::State {
+    fn focus(&mut self) {}
+    fn blur(&mut self) {}
+    // Drawn over the scene below it, and opened and closed without a fade
+    fn overlay(&self) -> bool {
+        false
+    }
+    fn scene_transition(&mut self) -> Option<SceneTransition> {
+        None
+    }
+    fn presence(&self) -> Option<Presence> {
+        None
+    }
+}
+
+enum Fade {
+    Out(SceneTransition),
+    In,
+}
+
+pub struct SceneStack {
+    geng: Rc<Geng>,
+    scenes: Vec<Box<dyn Scene>>,
+    fade: Option<Fade>,
+    // Screen darkness, 1 is fully faded out
+    darkness: f32,
+    presence: PresenceReporter,
+}
+
+impl SceneStack {
+    pub fn new(geng: &Rc<Geng>, scene: Box<dyn Scene>) -> Self {
+        let mut stack = Self {
+            geng: geng.clone(),
+            scenes: Vec::new(),
+            fade: Some(Fade::In),
+            darkness: 1.0,
+            presence: default(),
+        };
+        stack.push(scene);
+        stack
+    }
+    // Pushed without a fade, for scenes that start covered (like the menu under a game
+    // launched from the command line)
+    pub fn push(&mut self, mut scene: Box<dyn Scene>) {
+        if let Some(top) = self.scenes.last_mut() {
+            top.blur();
+        }
+        scene.focus();
+        self.scenes.push(scene);
+    }
+    fn pop(&mut self) {
+        if let Some(mut scene) = self.scenes.pop() {
+            scene.blur();
+        }
+        if let Some(top) = self.scenes.last_mut() {
+            top.focus();
+        }
+    }
+    fn apply(&mut self, transition: SceneTransition) {
+        match transition {
+            SceneTransition::Push(scene) => self.push(scene),
+            SceneTransition::Pop => self.pop(),
+            SceneTransition::Replace(scene) => {
+                if let Some(mut old) = self.scenes.pop() {
+                    old.blur();
+                }
+                self.push(scene);
+            }
+        }
+    }
+}
+
+impl geng::State for SceneStack {
+    fn update(&mut self, delta_time: f64) {
+        let step = delta_time as f32 / FADE_DURATION;
+        match self.fade.take() {
+            Some(Fade::Out(transition)) => {
+                self.darkness += step;
+                if self.darkness < 1.0 {
+                    self.fade = Some(Fade::Out(transition));
+                } else {
+                    self.darkness = 1.0;
+                    self.apply(transition);
+                    self.fade = Some(Fade::In);
+                }
+            }
+            Some(Fade::In) => {
+                self.darkness -= step;
+                if self.darkness > 0.0 {
+                    self.fade = Some(Fade::In);
+                } else {
+                    self.darkness = 0.0;
+                }
+            }
+            None => {}
+        }
+        // Overlays leave it to the scene they cover
+        self.presence
+            .update(self.scenes.iter().rev().find_map(|scene| scene.presence()));
+        let top = match self.scenes.last_mut() {
+            Some(top) => top,
+            None => return,
+        };
+        top.update(delta_time);
+        if matches!(self.fade, Some(Fade::Out(_))) {
+            return;
+        }
+        if let Some(transition) = top.scene_transition() {
+            let instant = match &transition {
+                SceneTransition::Push(scene) => scene.overlay(),
+                SceneTransition::Pop => top.overlay(),
+                SceneTransition::Replace(_) => false,
+            };
+            if instant {
+                self.apply(transition);
+            } else {
+                self.fade = Some(Fade::Out(transition));
+            }
+        }
+    }
+    fn draw(&mut self, framebuffer: &mut ugli::Framebuffer) {
+        if self.scenes.is_empty() {
+            ugli::clear(framebuffer, Some(Color::BLACK), None);
+        }
+        let bottom = self
+            .scenes
+            .iter()
+            .rposition(|scene| !scene.overlay())
+            .unwrap_or(0);
+        for scene in &mut self.scenes[bottom..] {
+            scene.draw(framebuffer);
+        }
+        if self.darkness > 0.0 {
+            let framebuffer_size = framebuffer.size().map(|x| x as f32);
+            self.geng.draw_2d().quad(
+                framebuffer,
+                AABB::pos_size(vec2(0.0, 0.0), framebuffer_size),
+                Color {
+                    a: self.darkness,
+                    ..Color::BLACK
+                },
+            );
+        }
+    }
+    fn handle_event(&mut self, event: geng::Event) {
+        if matches!(self.fade, Some(Fade::Out(_))) {
+            return;
+        }
+        if let Some(top) = self.scenes.last_mut() {
+            top.handle_event(event);
+        }
+    }
+    fn transition(&mut self) -> Option<geng::Transition> {
+        if self.scenes.is_empty() {
+            Some(geng::Transition::Pop)
+        } else {
+            None
+        }
+    }
+}
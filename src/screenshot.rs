@@ -0,0 +1,231 @@
+use std::collections::VecDeque;
+
+use super::*;
+
+const TOAST_DURATION: f64 = 2.0;
+const TOAST_FONT_SIZE: f32 = 24.0;
+const TOAST_MARGIN: f32 = 20.0;
+const REPLAY_FPS: f64 = 15.0;
+const REPLAY_DURATION: f64 = 5.0;
+const REPLAY_WIDTH: usize = 320;
+
+fn timestamp() -> u128 {
+    #[cfg(target_arch = "wasm32")]
+    return js_sys::Date::now() as u128;
+    #[cfg(not(target_arch = "wasm32"))]
+    std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .unwrap()
+        .as_millis()
+}
+
+// Nearest-neighbor downscale to at most `width`, framebuffer rows come bottom up
+fn read_image(framebuffer: &ugli::Framebuffer, width: usize) -> image::RgbaImage {
+    let size = framebuffer.size();
+    let scale = (size.x as f32 / width as f32).max(1.0);
+    let image_size = size.map(|x| ((x as f32 / scale) as u32).max(1));
+    let data = framebuffer.read_color();
+    image::RgbaImage::from_fn(image_size.x, image_size.y, |x, y| {
+        let x = ((x as f32 * scale) as usize).min(size.x - 1);
+        let y = ((y as f32 * scale) as usize).min(size.y - 1);
+        let color = data.get(x, size.y - 1 - y);
+        image::Rgba([color.r, color.g, color.b, 255])
+    })
+}
+
+fn encode_png(image: &image::RgbaImage) -> anyhow::Result<Vec<u8>> {
+    let mut png = Vec::new();
+    image::png::PngEncoder::new(&mut png).encode(
+        image,
+        image.width(),
+        image.height(),
+        image::ColorType::Rgba8,
+    )?;
+    Ok(png)
+}
+
+fn encode_gif(frames: Vec<image::RgbaImage>) -> anyhow::Result<Vec<u8>> {
+    let mut gif = Vec::new();
+    {
+        let mut encoder = image::gif::GifEncoder::new(&mut gif);
+        encoder.set_repeat(image::gif::Repeat::Infinite)?;
+        let delay = image::Delay::from_numer_denom_ms(1000, REPLAY_FPS as u32);
+        encoder.encode_frames(
+            frames
+                .into_iter()
+                .map(|frame| image::Frame::from_parts(frame, 0, 0, delay)),
+        )?;
+    }
+    Ok(gif)
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+fn save(data: &[u8], name: &str) -> anyhow::Result<String> {
+    let dir = std::env::current_exe()?
+        .parent()
+        .map_or_else(|| std::path::PathBuf::from("."), |dir| dir.to_owned());
+    let path = dir.join(name);
+    std::fs::write(&path, data)?;
+    Ok(path.display().to_string())
+}
+
+// Hands the file to the browser as a download
+#[cfg(target_arch = "wasm32")]
+fn save(data: &[u8], name: &str) -> anyhow::Result<String> {
+    let mime = if name.ends_with(".gif") {
+        "image/gif"
+    } else {
+        "image/png"
+    };
+    let url = format!("data:{};base64,{}", mime, base64::encode(data));
+    let document = web_sys::window()
+        .and_then(|window| window.document())
+        .ok_or_else(|| anyhow!("No document"))?;
+    let link: web_sys::HtmlAnchorElement = document
+        .create_element("a")
+        .map_err(|_| anyhow!("Failed to create a link"))?
+        .dyn_into()
+        .map_err(|_| anyhow!("Failed to create a link"))?;
+    link.set_href(&url);
+    link.set_download(name);
+    link.click();
+    Ok(name.to_owned())
+}
+
+type ExportResult = (String, anyhow::Result<String>);
+
+// Root state taking F12 screenshots and F9 replays of whatever screen is showing
+pub struct ScreenshotCapture {
+    geng: Rc<Geng>,
+    states: geng::StateManager,
+    screenshot_requested: bool,
+    replay_requested: bool,
+    // Downscaled frames of the last few seconds
+    replay: VecDeque<image::RgbaImage>,
+    replay_timer: f64,
+    #[cfg(not(target_arch = "wasm32"))]
+    exports: (
+        std::sync::mpsc::Sender<ExportResult>,
+        std::sync::mpsc::Receiver<ExportResult>,
+    ),
+    toast: Option<(String, f64)>,
+}
+
+impl ScreenshotCapture {
+    pub fn new(geng: &Rc<Geng>, state: impl geng::State) -> Self {
+        let mut states = geng::StateManager::new();
+        states.push(Box::new(state));
+        Self {
+            geng: geng.clone(),
+            states,
+            screenshot_requested: false,
+            replay_requested: false,
+            replay: VecDeque::new(),
+            replay_timer: 0.0,
+            #[cfg(not(target_arch = "wasm32"))]
+            exports: std::sync::mpsc::channel(),
+            toast: None,
+        }
+    }
+    fn show(&mut self, message: String) {
+        self.toast = Some((message, TOAST_DURATION));
+    }
+    fn finish((name, result): ExportResult) -> String {
+        match result {
+            Ok(path) => {
+                info!("Saved {}", path);
+                format!("Saved {}", name)
+            }
+            Err(e) => {
+                warn!("Failed to save {}: {}", name, e);
+                format!("Failed to save {}", name)
+            }
+        }
+    }
+    // Encoding a replay takes a while, so native builds do it on another thread
+    fn export(
+        &mut self,
+        name: String,
+        encode: impl FnOnce() -> anyhow::Result<Vec<u8>> + Send + 'static,
+    ) {
+        #[cfg(not(target_arch = "wasm32"))]
+        {
+            self.show(format!("Saving {}...", name));
+            let sender = self.exports.0.clone();
+            std::thread::spawn(move || {
+                let result = encode().and_then(|data| save(&data, &name));
+                let _ = sender.send((name, result));
+            });
+        }
+        #[cfg(target_arch = "wasm32")]
+        {
+            let result = encode().and_then(|data| save(&data, &name));
+            let message = Self::finish((name, result));
+            self.show(message);
+        }
+    }
+}
+
+impl geng::State for ScreenshotCapture {
+    fn update(&mut self, delta_time: f64) {
+        #[cfg(not(target_arch = "wasm32"))]
+        while let Ok(result) = self.exports.1.try_recv() {
+            let message = Self::finish(result);
+            self.show(message);
+        }
+        if let Some((_, time_left)) = &mut self.toast {
+            *time_left -= delta_time;
+            if *time_left <= 0.0 {
+                self.toast = None;
+            }
+        }
+        self.replay_timer -= delta_time;
+        self.states.update(delta_time);
+    }
+    fn draw(&mut self, framebuffer: &mut ugli::Framebuffer) {
+        self.states.draw(framebuffer);
+        // Captured before the toast so it stays out of the picture
+        if self.replay_timer <= 0.0 {
+            self.replay_timer += 1.0 / REPLAY_FPS;
+            self.replay_timer = self.replay_timer.max(0.0);
+            self.replay.push_back(read_image(framebuffer, REPLAY_WIDTH));
+            while self.replay.len() > (REPLAY_FPS * REPLAY_DURATION) as usize {
+                self.replay.pop_front();
+            }
+        }
+        if mem::take(&mut self.screenshot_requested) {
+            let image = read_image(framebuffer, framebuffer.size().x);
+            self.export(format!("screenshot-{}.png", timestamp()), move || {
+                encode_png(&image)
+            });
+        }
+        if mem::take(&mut self.replay_requested) && !self.replay.is_empty() {
+            let frames = self.replay.iter().cloned().collect();
+            self.export(format!("replay-{}.gif", timestamp()), move || {
+                encode_gif(frames)
+            });
+        }
+        if let Some((message, time_left)) = &self.toast {
+            let alpha = (*time_left / TOAST_DURATION * 2.0).min(1.0) as f32;
+            self.geng.default_font().draw(
+                framebuffer,
+                message,
+                vec2(TOAST_MARGIN, TOAST_MARGIN),
+                TOAST_FONT_SIZE,
+                Color::rgba(1.0, 1.0, 1.0, alpha),
+            );
+        }
+    }
+    fn handle_event(&mut self, event: geng::Event) {
+        match event {
+            geng::Event::KeyDown {
+                key: geng::Key::F12,
+            } => self.screenshot_requested = true,
+            geng::Event::KeyDown { key: geng::Key::F9 } => self.replay_requested = true,
+            event => self.states.handle_event(event),
+        }
+    }
+    fn transition(&mut self) -> Option<geng::Transition> {
+        None
+    }
+}
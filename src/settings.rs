@@ -0,0 +1,247 @@
+use super::*;
+
+pub const SETTINGS_PATH: &str = "settings.json";
+
+pub const MIN_GAME_SPEED: f32 = 0.5;
+const REDUCED_EFFECTS_SCALE: f32 = 0.25;
+const LARGE_TEXT_SCALE: f32 = 1.3;
+
+const KEYS: [geng::Key; 64] = {
+    use geng::Key::*;
+    [
+        Num0, Num1, Num2, Num3, Num4, Num5, Num6, Num7, Num8, Num9, A, B, C, D, E, F, G, H, I, J,
+        K, L, M, N, O, P, Q, R, S, T, U, V, W, X, Y, Z, Escape, Space, Enter, Backspace, LShift,
+        RShift, LCtrl, RCtrl, LAlt, RAlt, Left, Right, Up, Down, PageUp, PageDown, F1, F2, F3, F4,
+        F5, F6, F7, F8, F9, F10, F11, F12,
+    ]
+};
+
+// Keys are stored by their name, geng::Key has no serde support
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct KeyBinding(pub geng::Key);
+
+impl std::str::FromStr for KeyBinding {
+    type Err = anyhow::Error;
+    fn from_str(name: &str) -> anyhow::Result<Self> {
+        KEYS.iter()
+            .find(|key| format!("{:?}", key).eq_ignore_ascii_case(name))
+            .map(|&key| Self(key))
+            .ok_or_else(|| anyhow!("Unknown key {:?}", name))
+    }
+}
+
+impl Serialize for KeyBinding {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&format!("{:?}", self.0))
+    }
+}
+
+impl<'de> Deserialize<'de> for KeyBinding {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        String::deserialize(deserializer)?
+            .parse()
+            .map_err(serde::de::Error::custom)
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct KeyBindings {
+    pub left: KeyBinding,
+    pub right: KeyBinding,
+    pub jump: KeyBinding,
+    pub reel: KeyBinding,
+    pub shorten: KeyBinding,
+    pub release: KeyBinding,
+    pub respawn: KeyBinding,
+    pub rewind: KeyBinding,
+    pub dash: KeyBinding,
+    pub interact: KeyBinding,
+}
+
+impl Default for KeyBindings {
+    fn default() -> Self {
+        Self {
+            left: KeyBinding(geng::Key::A),
+            right: KeyBinding(geng::Key::D),
+            jump: KeyBinding(geng::Key::Space),
+            reel: KeyBinding(geng::Key::W),
+            shorten: KeyBinding(geng::Key::S),
+            release: KeyBinding(geng::Key::Q),
+            respawn: KeyBinding(geng::Key::R),
+            rewind: KeyBinding(geng::Key::E),
+            dash: KeyBinding(geng::Key::LShift),
+            interact: KeyBinding(geng::Key::F),
+        }
+    }
+}
+
+impl KeyBindings {
+    pub fn get_mut(&mut self, action: &str) -> Option<&mut KeyBinding> {
+        Some(match action {
+            "left" => &mut self.left,
+            "right" => &mut self.right,
+            "jump" => &mut self.jump,
+            "reel" => &mut self.reel,
+            "shorten" => &mut self.shorten,
+            "release" => &mut self.release,
+            "respawn" => &mut self.respawn,
+            "rewind" => &mut self.rewind,
+            "dash" => &mut self.dash,
+            "interact" => &mut self.interact,
+            _ => return None,
+        })
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Accessibility {
+    // Click once to start spinning and again to throw instead of holding
+    pub toggle_spin: bool,
+    pub reduced_effects: bool,
+    pub high_contrast: bool,
+    // Multiplies every timestep, from MIN_GAME_SPEED up to 1
+    pub game_speed: f32,
+    pub large_text: bool,
+    // Off keeps the chain attached no matter how hard it gets pulled
+    pub chain_snapping: bool,
+}
+
+impl Default for Accessibility {
+    fn default() -> Self {
+        Self {
+            toggle_spin: false,
+            reduced_effects: false,
+            high_contrast: false,
+            game_speed: 1.0,
+            large_text: false,
+            chain_snapping: true,
+        }
+    }
+}
+
+impl Accessibility {
+    // Camera shake and aberration strength
+    pub fn effects_scale(&self) -> f32 {
+        if self.reduced_effects {
+            REDUCED_EFFECTS_SCALE
+        } else {
+            1.0
+        }
+    }
+    pub fn text_scale(&self) -> f32 {
+        if self.large_text {
+            LARGE_TEXT_SCALE
+        } else {
+            1.0
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Settings {
+    pub master_volume: f64,
+    pub music_volume: f64,
+    pub sfx_volume: f64,
+    pub fullscreen: bool,
+    pub vsync: bool,
+    // Unlimited when not set
+    pub max_fps: Option<u32>,
+    pub throttle_unfocused: bool,
+    // Keeps the world view between 4:3 and 21:9 with black bars
+    pub letterbox: bool,
+    pub show_timer: bool,
+    pub ghosts: bool,
+    pub leaderboard: bool,
+    // Times are only submitted once an endpoint is configured
+    pub leaderboard_url: String,
+    pub player_name: String,
+    pub keys: KeyBindings,
+    pub language: String,
+    pub accessibility: Accessibility,
+    pub mutators: Mutators,
+    #[serde(skip)]
+    pub launch: LaunchOptions,
+}
+
+// Set from the command line for a single session, never saved
+#[derive(Debug, Clone, Default)]
+pub struct LaunchOptions {
+    pub mute: bool,
+    pub debug_draw: bool,
+    pub fixed_seed: Option<u64>,
+    // Read by the frame limiter wrapping every screen
+    pub frame_limit: Rc<Cell<FrameLimit>>,
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Self {
+            master_volume: 1.0,
+            music_volume: 0.7,
+            sfx_volume: 1.0,
+            fullscreen: false,
+            vsync: true,
+            max_fps: None,
+            throttle_unfocused: true,
+            letterbox: true,
+            show_timer: true,
+            ghosts: true,
+            leaderboard: true,
+            leaderboard_url: String::new(),
+            player_name: "Player".to_owned(),
+            keys: default(),
+            language: DEFAULT_LANGUAGE.to_owned(),
+            accessibility: default(),
+            mutators: default(),
+            launch: default(),
+        }
+    }
+}
+
+impl Settings {
+    // Fresh copy for the next screen, launch options only live in memory
+    pub fn reload(launch: &LaunchOptions) -> AutoSave<Self> {
+        let mut settings = AutoSave::<Self>::load(SETTINGS_PATH);
+        settings.launch = launch.clone();
+        settings
+    }
+    fn master(&self) -> f64 {
+        if self.launch.mute {
+            0.0
+        } else {
+            self.master_volume
+        }
+    }
+    pub fn sfx(&self) -> f64 {
+        self.master() * self.sfx_volume
+    }
+    pub fn apply_frame_limit(&self) {
+        self.launch.frame_limit.set(FrameLimit {
+            max_fps: self.max_fps,
+            throttle_unfocused: self.throttle_unfocused,
+        });
+    }
+    // Alt+Enter and F11 toggle fullscreen on every screen
+    pub fn handle_fullscreen_key(&mut self, geng: &Geng, event: &geng::Event) -> bool {
+        let window = geng.window();
+        let alt = window.is_key_pressed(geng::Key::LAlt) || window.is_key_pressed(geng::Key::RAlt);
+        match event {
+            geng::Event::KeyDown {
+                key: geng::Key::F11,
+            } => {}
+            geng::Event::KeyDown {
+                key: geng::Key::Enter,
+            } if alt => {}
+            _ => return false,
+        }
+        self.fullscreen = !self.fullscreen;
+        window.set_fullscreen(self.fullscreen);
+        true
+    }
+    pub fn music(&self) -> f64 {
+        self.master() * self.music_volume
+    }
+}
@@ -0,0 +1,133 @@
+use super::*;
+
+const SHADER_NAMES: [&str; 10] = [
+    "camera",
+    "renderer",
+    "line",
+    "line_thick",
+    "tile",
+    "parallax",
+    "chain",
+    "light",
+    "post_process",
+    "transition",
+];
+
+// Sources from static/shaders. A line `#include "name.glsl"` pastes in another
+// file from the same directory, so shared declarations live in one place.
+pub struct Shaders {
+    sources: RefCell<HashMap<String, String>>,
+    programs: RefCell<Programs>,
+}
+
+// Compiled while loading, so a broken shader fails the load instead of a renderer
+#[derive(Clone)]
+pub struct Programs {
+    pub renderer: Rc<ugli::Program>,
+    pub line: Rc<ugli::Program>,
+    pub line_thick: Rc<ugli::Program>,
+    pub tile: Rc<ugli::Program>,
+    pub parallax: Rc<ugli::Program>,
+    pub chain: Rc<ugli::Program>,
+    pub light: Rc<ugli::Program>,
+    pub post_process: Rc<ugli::Program>,
+    pub transition: Rc<ugli::Program>,
+}
+
+impl Programs {
+    fn compile(geng: &Geng, sources: &HashMap<String, String>) -> anyhow::Result<Self> {
+        let compile = |name| -> anyhow::Result<Rc<ugli::Program>> {
+            let source = expand(sources, name, &mut Vec::new())?;
+            let program = geng
+                .shader_lib()
+                .compile(&source)
+                .with_context(|| format!("Failed to compile shader {:?}", name))?;
+            Ok(Rc::new(program))
+        };
+        Ok(Self {
+            renderer: compile("renderer")?,
+            line: compile("line")?,
+            line_thick: compile("line_thick")?,
+            tile: compile("tile")?,
+            parallax: compile("parallax")?,
+            chain: compile("chain")?,
+            light: compile("light")?,
+            post_process: compile("post_process")?,
+            transition: compile("transition")?,
+        })
+    }
+}
+
+fn expand(
+    sources: &HashMap<String, String>,
+    name: &str,
+    including: &mut Vec<String>,
+) -> anyhow::Result<String> {
+    if including.iter().any(|other| other == name) {
+        anyhow::bail!("Shader {:?} includes itself", name);
+    }
+    let source = sources
+        .get(name)
+        .ok_or_else(|| anyhow!("Unknown shader {:?}", name))?;
+    including.push(name.to_owned());
+    let mut result = String::new();
+    for line in source.lines() {
+        match line.trim().strip_prefix("#include \"") {
+            Some(file) => {
+                let file = file.trim_end_matches('"');
+                let include = file.strip_suffix(".glsl").unwrap_or(file);
+                result += &expand(sources, include, including)?;
+            }
+            None => result += line,
+        }
+        result.push('\n');
+    }
+    including.pop();
+    Ok(result)
+}
+
+impl Shaders {
+    pub fn programs(&self) -> Ref<'_, Programs> {
+        self.programs.borrow()
+    }
+    // Everything is rebuilt since any shader may include the changed file,
+    // nothing is replaced unless all of them compile
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn recompile(&self, geng: &Geng) -> anyhow::Result<Programs> {
+        let programs = Programs::compile(geng, &self.sources.borrow())?;
+        *self.programs.borrow_mut() = programs.clone();
+        Ok(programs)
+    }
+    // Whether the name was known, unknown files in the directory are ignored
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn set_source(&self, name: &str, source: String) -> bool {
+        match self.sources.borrow_mut().get_mut(name) {
+            Some(old) => {
+                *old = source;
+                true
+            }
+            None => false,
+        }
+    }
+}
+
+impl geng::LoadAsset for Shaders {
+    fn load(geng: &Rc<Geng>, path: &str) -> geng::AssetFuture<Self> {
+        let sources = SHADER_NAMES.map(|name| {
+            let source =
+                <String as geng::LoadAsset>::load(geng, &format!("{}/{}.glsl", path, name));
+            async move { Ok::<_, anyhow::Error>((name.to_owned(), source.await?)) }
+        });
+        let geng = geng.clone();
+        async move {
+            let sources = future::try_join_all(sources).await?.into_iter().collect();
+            let programs = Programs::compile(&geng, &sources)?;
+            Ok(Self {
+                sources: RefCell::new(sources),
+                programs: RefCell::new(programs),
+            })
+        }
+        .boxed_local()
+    }
+    const DEFAULT_EXT: Option<&'static str> = None;
+}
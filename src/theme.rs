@@ -0,0 +1,130 @@
+use super::*;
+
+#[derive(Debug, Copy, Clone, Serialize, Deserialize)]
+pub struct HsvColor {
+    pub h: f32,
+    pub s: f32,
+    pub v: f32,
+    #[serde(default = "HsvColor::default_alpha")]
+    pub a: f32,
+}
+
+impl HsvColor {
+    fn default_alpha() -> f32 {
+        1.0
+    }
+    pub fn rgba(self) -> Color<f32> {
+        hsva(self.h, self.s, self.v, self.a)
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Theme {
+    pub background: HsvColor,
+    pub parallax: HsvColor,
+    pub tiles: HsvColor,
+    pub hazard: HsvColor,
+    pub enemy: HsvColor,
+    pub breakable: HsvColor,
+    pub checkpoint: HsvColor,
+    pub goal: HsvColor,
+    pub switch: HsvColor,
+    pub pickup: HsvColor,
+    pub door: HsvColor,
+    pub collectible: HsvColor,
+    pub hud: HsvColor,
+    pub highlight: HsvColor,
+    pub force_zone: HsvColor,
+    pub gravity_zone: HsvColor,
+    pub water: HsvColor,
+    pub player: HsvColor,
+    pub ball: HsvColor,
+    pub chain: HsvColor,
+    pub chain_line: HsvColor,
+    pub transition: HsvColor,
+    pub light: HsvColor,
+}
+
+impl Theme {
+    pub fn with_palette(&self, palette: &LevelPalette) -> Self {
+        Self {
+            background: palette.background.unwrap_or(self.background),
+            tiles: palette.tiles.unwrap_or(self.tiles),
+            hazard: palette.hazard.unwrap_or(self.hazard),
+            light: palette.light.unwrap_or(self.light),
+            ..self.clone()
+        }
+    }
+    // Chain and hazards stand out against any background
+    pub fn high_contrast(self) -> Self {
+        let bright = |h: f32, s: f32| HsvColor {
+            h,
+            s,
+            v: 1.0,
+            a: 1.0,
+        };
+        Self {
+            chain: bright(0.15, 1.0),
+            chain_line: bright(0.15, 1.0),
+            hazard: bright(0.85, 1.0),
+            ..self
+        }
+    }
+}
+
+const DEFAULT_THEME: &str = "default";
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Themes {
+    pub current: String,
+    pub palettes: HashMap<String, Theme>,
+}
+
+impl geng::LoadAsset for Themes {
+    fn load(geng: &Rc<Geng>, path: &str) -> geng::AssetFuture<Self> {
+        <String as geng::LoadAsset>::load(geng, path)
+            .map(|data| Themes::parse(&data?))
+            .boxed_local()
+    }
+    const DEFAULT_EXT: Option<&'static str> = Some("json");
+}
+
+impl Themes {
+    // An unknown current palette falls back to the default one
+    pub fn parse(data: &str) -> anyhow::Result<Self> {
+        let mut themes: Self = serde_json::from_str(data)?;
+        if !themes.palettes.contains_key(&themes.current) {
+            if !themes.palettes.contains_key(DEFAULT_THEME) {
+                anyhow::bail!(
+                    "Theme {:?} not found and there is no {:?} theme",
+                    themes.current,
+                    DEFAULT_THEME
+                );
+            }
+            warn!(
+                "Theme {:?} not found, using {:?}",
+                themes.current, DEFAULT_THEME
+            );
+            themes.current = DEFAULT_THEME.to_owned();
+        }
+        Ok(themes)
+    }
+    pub fn theme(&self) -> &Theme {
+        &self.palettes[&self.current]
+    }
+    // Accessibility palettes keep their colors regardless of the level
+    pub fn level_theme(&self, palette: &LevelPalette) -> Theme {
+        if self.current == DEFAULT_THEME {
+            self.theme().with_palette(palette)
+        } else {
+            self.theme().clone()
+        }
+    }
+    pub fn select(&mut self, name: &str) {
+        if self.palettes.contains_key(name) {
+            self.current = name.to_owned();
+        } else {
+            warn!("Theme {:?} not found", name);
+        }
+    }
+}
@@ -0,0 +1,104 @@
+use super::*;
+
+#[derive(ugli::Vertex, Clone)]
+struct TileVertex {
+    a_pos: Vec2<f32>,
+    a_vt: Vec2<f32>,
+}
+
+const CHUNK_SIZE: f32 = 16.0;
+
+pub struct TileBatch {
+    pub bounds: AABB<f32>,
+    vertices: ugli::VertexBuffer<TileVertex>,
+}
+
+pub struct TileRenderer {
+    geng: Rc<Geng>,
+    pub(crate) program: Rc<ugli::Program>,
+}
+
+impl TileRenderer {
+    pub fn new(geng: &Rc<Geng>, shaders: &Shaders) -> Self {
+        Self {
+            geng: geng.clone(),
+            program: shaders.programs().tile.clone(),
+        }
+    }
+    // Splits tiles into square chunks so offscreen parts can be skipped
+    pub fn chunks(&self, tiles: &[Vec2<f32>], uv: AABB<f32>) -> Vec<TileBatch> {
+        let mut chunks: HashMap<Vec2<i32>, Vec<Vec2<f32>>> = HashMap::new();
+        for &tile in tiles {
+            let chunk = tile.map(|x| (x / CHUNK_SIZE).floor() as i32);
+            chunks.entry(chunk).or_default().push(tile);
+        }
+        chunks.values().map(|tiles| self.batch(tiles, uv)).collect()
+    }
+    pub fn batch(&self, tiles: &[Vec2<f32>], uv: AABB<f32>) -> TileBatch {
+        let mut bounds: Option<AABB<f32>> = None;
+        for &tile in tiles {
+            let aabb = AABB::pos_size(tile, vec2(1.0, 1.0));
+            bounds = Some(match bounds {
+                Some(bounds) => AABB {
+                    x_min: bounds.x_min.min(aabb.x_min),
+                    y_min: bounds.y_min.min(aabb.y_min),
+                    x_max: bounds.x_max.max(aabb.x_max),
+                    y_max: bounds.y_max.max(aabb.y_max),
+                },
+                None => aabb,
+            });
+        }
+        let mut vertices = Vec::with_capacity(tiles.len() * 6);
+        for &tile in tiles {
+            let corner = |x: f32, y: f32| TileVertex {
+                a_pos: tile + vec2(x, y),
+                a_vt: vec2(
+                    uv.x_min + uv.width() * x,
+                    uv.y_min + uv.height() * (1.0 - y),
+                ),
+            };
+            vertices.extend([
+                corner(0.0, 0.0),
+                corner(1.0, 0.0),
+                corner(1.0, 1.0),
+                corner(0.0, 0.0),
+                corner(1.0, 1.0),
+                corner(0.0, 1.0),
+            ]);
+        }
+        TileBatch {
+            bounds: bounds.unwrap_or(AABB::pos_size(vec2(0.0, 0.0), vec2(0.0, 0.0))),
+            vertices: ugli::VertexBuffer::new_static(self.geng.ugli(), vertices),
+        }
+    }
+    pub fn draw(
+        &self,
+        framebuffer: &mut ugli::Framebuffer,
+        camera: &impl AbstractCamera,
+        batch: &TileBatch,
+        matrix: Mat4<f32>,
+        texture: &ugli::Texture,
+        color: Color<f32>,
+    ) {
+        let camera_uniforms = camera.uniforms(framebuffer.size().map(|x| x as f32));
+        let uniforms = (
+            camera_uniforms,
+            ugli::uniforms! {
+                u_model_matrix: matrix,
+                u_texture: texture,
+                u_color: color,
+            },
+        );
+        ugli::draw(
+            framebuffer,
+            &self.program,
+            ugli::DrawMode::Triangles,
+            &batch.vertices,
+            uniforms,
+            ugli::DrawParameters {
+                blend_mode: Some(default()),
+                ..default()
+            },
+        );
+    }
+}
@@ -0,0 +1,204 @@
+use super::*;
+
+// Levels exported from the Tiled editor as JSON, one tile is one world unit
+
+#[derive(Deserialize)]
+struct TiledMap {
+    height: usize,
+    tilewidth: f32,
+    tileheight: f32,
+    layers: Vec<TiledLayer>,
+}
+
+#[derive(Deserialize)]
+#[serde(tag = "type", rename_all = "lowercase")]
+enum TiledLayer {
+    TileLayer {
+        name: String,
+        width: usize,
+        height: usize,
+        #[serde(default)]
+        x: i32,
+        #[serde(default)]
+        y: i32,
+        data: Vec<u32>,
+    },
+    ObjectGroup {
+        objects: Vec<TiledObject>,
+    },
+    #[serde(other)]
+    Other,
+}
+
+#[derive(Deserialize)]
+struct TiledObject {
+    #[serde(default)]
+    name: String,
+    // Tiled 1.9 renamed "type" to "class"
+    #[serde(default, rename = "type", alias = "class")]
+    kind: String,
+    x: f32,
+    y: f32,
+    #[serde(default)]
+    width: f32,
+    #[serde(default)]
+    height: f32,
+}
+
+impl TiledObject {
+    fn role(&self) -> &str {
+        if self.kind.is_empty() {
+            &self.name
+        } else {
+            &self.kind
+        }
+    }
+}
+
+// Merges the edges between solid and empty cells into the longest possible segments,
+// so edges between two solid tiles produce nothing
+fn trace_contours(solid: &HashSet<Vec2<i32>>) -> Vec<Segment> {
+    let mut segments = Vec::new();
+    if solid.is_empty() {
+        return segments;
+    }
+    let min = vec2(
+        solid.iter().map(|p| p.x).min().unwrap(),
+        solid.iter().map(|p| p.y).min().unwrap(),
+    );
+    let max = vec2(
+        solid.iter().map(|p| p.x).max().unwrap(),
+        solid.iter().map(|p| p.y).max().unwrap(),
+    );
+    let is_solid = |x: i32, y: i32| solid.contains(&vec2(x, y));
+    for y in min.y..=max.y + 1 {
+        let mut start = None;
+        for x in min.x..=max.x + 1 {
+            let edge = x <= max.x && is_solid(x, y) != is_solid(x, y - 1);
+            match (edge, start) {
+                (true, None) => start = Some(x),
+                (false, Some(from)) => {
+                    segments.push(Segment::new(
+                        vec2(from as f32, y as f32),
+                        vec2(x as f32, y as f32),
+                    ));
+                    start = None;
+                }
+                _ => {}
+            }
+        }
+    }
+    for x in min.x..=max.x + 1 {
+        let mut start = None;
+        for y in min.y..=max.y + 1 {
+            let edge = y <= max.y && is_solid(x, y) != is_solid(x - 1, y);
+            match (edge, start) {
+                (true, None) => start = Some(y),
+                (false, Some(from)) => {
+                    segments.push(Segment::new(
+                        vec2(x as f32, from as f32),
+                        vec2(x as f32, y as f32),
+                    ));
+                    start = None;
+                }
+                _ => {}
+            }
+        }
+    }
+    segments
+}
+
+impl TiledMap {
+    fn into_level(self) -> Level {
+        let mut level = Level::default();
+        let mut solid = HashSet::new();
+        let map_height = self.height as i32;
+        for layer in &self.layers {
+            match layer {
+                TiledLayer::TileLayer {
+                    name,
+                    width,
+                    height,
+                    x,
+                    y,
+                    data,
+                } => {
+                    for (index, &gid) in data.iter().enumerate().take(width * height) {
+                        if gid == 0 {
+                            continue;
+                        }
+                        let cell = vec2(
+                            x + (index % width) as i32,
+                            map_height - 1 - (y + (index / width) as i32),
+                        );
+                        let pos = cell.map(|x| x as f32);
+                        match name.to_lowercase().as_str() {
+                            "hazards" => level.hazards.push(HazardTile {
+                                pos,
+                                material: Material::Spikes,
+                            }),
+                            "breakables" => level.breakables.push(pos),
+                            _ => {
+                                solid.insert(cell);
+                                level.tiles.push(pos);
+                            }
+                        }
+                    }
+                }
+                TiledLayer::ObjectGroup { objects } => {
+                    for object in objects {
+                        let pos = vec2(
+                            (object.x + object.width / 2.0) / self.tilewidth,
+                            map_height as f32 - (object.y + object.height / 2.0) / self.tileheight,
+                        );
+                        let radius = partial_max(
+                            object.width / self.tilewidth,
+                            object.height / self.tileheight,
+                        ) / 2.0;
+                        match object.role().to_lowercase().as_str() {
+                            "spawn" => level.spawn = Some(pos),
+                            "goal" => {
+                                level.goal = Some(Goal {
+                                    pos,
+                                    radius: if radius > 0.0 {
+                                        radius
+                                    } else {
+                                        Goal::default_radius()
+                                    },
+                                })
+                            }
+                            "checkpoint" => level.checkpoints.push(Checkpoint {
+                                pos,
+                                radius: if radius > 0.0 {
+                                    radius
+                                } else {
+                                    Checkpoint::default_radius()
+                                },
+                            }),
+                            "hazard" => level.hazards.push(HazardTile {
+                                pos: pos.map(|x| x.floor()),
+                                material: Material::Spikes,
+                            }),
+                            role => warn!("Unknown Tiled object {:?}", role),
+                        }
+                    }
+                }
+                TiledLayer::Other => {}
+            }
+        }
+        level.segments = trace_contours(&solid);
+        level.version = Level::VERSION;
+        level
+    }
+}
+
+// Tiled exports always carry these, our own levels never do
+pub fn is_tiled_map(data: &str) -> bool {
+    serde_json::from_str::<HashMap<String, serde::de::IgnoredAny>>(data)
+        .is_ok_and(|map| map.contains_key("tiledversion") || map.contains_key("layers"))
+}
+
+pub fn import_tiled(data: &str) -> Result<Level, serde_json::Error> {
+    let map: TiledMap = serde_json::from_str(data)?;
+    Ok(map.into_level())
+}
@@ -0,0 +1,137 @@
+use super::*;
+
+#[derive(ugli::Vertex, Clone)]
+struct Vertex {
+    a_pos: Vec2<f32>,
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum TransitionKind {
+    Fade,
+    Iris,
+    Dissolve,
+}
+
+// The `transitions` section of config.json
+#[derive(Debug, Copy, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct TransitionConfig {
+    pub fade_duration: f32,
+    pub iris_duration: f32,
+    pub dissolve_duration: f32,
+    pub death: TransitionKind,
+    pub respawn: TransitionKind,
+    pub level_change: TransitionKind,
+    pub reduced_motion: bool,
+}
+
+impl Default for TransitionConfig {
+    fn default() -> Self {
+        Self {
+            fade_duration: 0.6,
+            iris_duration: 0.8,
+            dissolve_duration: 0.4,
+            death: TransitionKind::Iris,
+            respawn: TransitionKind::Fade,
+            level_change: TransitionKind::Dissolve,
+            reduced_motion: false,
+        }
+    }
+}
+
+pub struct ScreenTransition {
+    kind: TransitionKind,
+    duration: f32,
+    time: f32,
+}
+
+impl ScreenTransition {
+    pub fn new(kind: TransitionKind, config: &TransitionConfig) -> Self {
+        let kind = if config.reduced_motion {
+            TransitionKind::Fade
+        } else {
+            kind
+        };
+        let duration = match kind {
+            TransitionKind::Fade => config.fade_duration,
+            TransitionKind::Iris => config.iris_duration,
+            TransitionKind::Dissolve => config.dissolve_duration,
+        };
+        Self {
+            kind,
+            duration: duration.max(0.01),
+            time: 0.0,
+        }
+    }
+    pub fn update(&mut self, delta_time: f32) -> bool {
+        let half = self.duration / 2.0;
+        let was_covering = self.time < half;
+        self.time += delta_time;
+        was_covering && self.time >= half
+    }
+    pub fn finished(&self) -> bool {
+        self.time >= self.duration
+    }
+    pub fn amount(&self) -> f32 {
+        clamp(
+            1.0 - (2.0 * self.time / self.duration - 1.0).abs(),
+            0.0..=1.0,
+        )
+    }
+}
+
+pub struct TransitionRenderer {
+    quad: ugli::VertexBuffer<Vertex>,
+    pub(crate) program: Rc<ugli::Program>,
+}
+
+impl TransitionRenderer {
+    pub fn new(geng: &Rc<Geng>, shaders: &Shaders) -> Self {
+        Self {
+            quad: ugli::VertexBuffer::new_static(
+                geng.ugli(),
+                vec![
+                    Vertex {
+                        a_pos: vec2(-1.0, -1.0),
+                    },
+                    Vertex {
+                        a_pos: vec2(1.0, -1.0),
+                    },
+                    Vertex {
+                        a_pos: vec2(1.0, 1.0),
+                    },
+                    Vertex {
+                        a_pos: vec2(-1.0, 1.0),
+                    },
+                ],
+            ),
+            program: shaders.programs().transition.clone(),
+        }
+    }
+    pub fn draw(
+        &self,
+        framebuffer: &mut ugli::Framebuffer,
+        transition: &ScreenTransition,
+        center: Vec2<f32>,
+        color: Color<f32>,
+    ) {
+        let framebuffer_size = framebuffer.size().map(|x| x as f32);
+        ugli::draw(
+            framebuffer,
+            &self.program,
+            ugli::DrawMode::TriangleFan,
+            &self.quad,
+            ugli::uniforms! {
+                u_kind: transition.kind as i32,
+                u_amount: transition.amount(),
+                u_center: center,
+                u_framebuffer_size: framebuffer_size,
+                u_color: color,
+            },
+            ugli::DrawParameters {
+                blend_mode: Some(default()),
+                ..default()
+            },
+        );
+    }
+}
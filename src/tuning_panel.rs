@@ -0,0 +1,230 @@
+use super::*;
+
+const PANEL_WIDTH: f32 = 460.0;
+const PANEL_ROW_HEIGHT: f32 = 24.0;
+const PANEL_TEXT_SIZE: f32 = 20.0;
+const PANEL_PADDING: f32 = 12.0;
+const PANEL_TOP_MARGIN: f32 = 60.0;
+const SLIDER_WIDTH: f32 = 140.0;
+const SLIDER_HEIGHT: f32 = 8.0;
+// Sliders span from zero to twice the default value
+const SLIDER_RANGE: f32 = 2.0;
+const KEY_STEPS: f32 = 40.0;
+const PANEL_HINTS: &str = "Up/Down - select, Left/Right or drag - change, Enter - press";
+const BUTTONS: [&str; 3] = [
+    "Teleport to cursor",
+    "Infinite chain",
+    "Save to config.json",
+];
+
+#[derive(Debug, Copy, Clone)]
+pub enum TuningAction {
+    Changed(Tuning),
+    Teleport,
+    ToggleInfiniteChain,
+    Save,
+}
+
+// Debug panel for live feel tweaks, edits go straight to the game's tuning
+#[derive(Default)]
+pub struct TuningPanel {
+    selected: usize,
+    dragging: Option<usize>,
+    // Waiting for a click in the world to teleport to
+    teleport_armed: bool,
+}
+
+impl TuningPanel {
+    fn count() -> usize {
+        Tuning::FIELDS.len() + BUTTONS.len()
+    }
+    fn panel(ui_size: Vec2<f32>) -> AABB<f32> {
+        let height = (Self::count() + 2) as f32 * PANEL_ROW_HEIGHT + PANEL_PADDING * 2.0;
+        AABB::pos_size(
+            vec2(
+                ui_size.x - PANEL_WIDTH - PANEL_PADDING,
+                ui_size.y - height - PANEL_TOP_MARGIN,
+            ),
+            vec2(PANEL_WIDTH, height),
+        )
+    }
+    // Row 0 is the title, items follow
+    fn row(ui_size: Vec2<f32>, index: usize) -> AABB<f32> {
+        let panel = Self::panel(ui_size);
+        let top = panel.y_max - PANEL_PADDING - (index + 1) as f32 * PANEL_ROW_HEIGHT;
+        AABB::from_corners(
+            vec2(panel.x_min + PANEL_PADDING, top - PANEL_ROW_HEIGHT),
+            vec2(panel.x_max - PANEL_PADDING, top),
+        )
+    }
+    fn slider(ui_size: Vec2<f32>, index: usize) -> AABB<f32> {
+        let row = Self::row(ui_size, index);
+        let center = row.center().y;
+        AABB::from_corners(
+            vec2(row.x_max - SLIDER_WIDTH, center - SLIDER_HEIGHT / 2.0),
+            vec2(row.x_max, center + SLIDER_HEIGHT / 2.0),
+        )
+    }
+    fn item_at(framebuffer_size: Vec2<f32>, position: Vec2<f64>) -> Option<usize> {
+        let ui_size = UiCamera::size(framebuffer_size);
+        let pos = UiCamera.screen_to_world(framebuffer_size, position.map(|x| x as f32));
+        (0..Self::count()).find(|&index| Self::row(ui_size, index).contains(pos))
+    }
+    fn range(index: usize) -> f32 {
+        Tuning::default().get(index) * SLIDER_RANGE
+    }
+    fn drag(
+        &self,
+        tuning: &Tuning,
+        index: usize,
+        framebuffer_size: Vec2<f32>,
+        position: Vec2<f64>,
+    ) -> TuningAction {
+        let ui_size = UiCamera::size(framebuffer_size);
+        let pos = UiCamera.screen_to_world(framebuffer_size, position.map(|x| x as f32));
+        let slider = Self::slider(ui_size, index);
+        let t = clamp((pos.x - slider.x_min) / slider.width(), 0.0..=1.0);
+        let mut tuning = *tuning;
+        tuning.set(index, t * Self::range(index));
+        TuningAction::Changed(tuning)
+    }
+    fn activate(&mut self) -> Option<TuningAction> {
+        match self.selected.checked_sub(Tuning::FIELDS.len()) {
+            Some(0) => self.teleport_armed = true,
+            Some(1) => return Some(TuningAction::ToggleInfiniteChain),
+            Some(_) => return Some(TuningAction::Save),
+            None => {}
+        }
+        None
+    }
+    // Whether the panel takes the event instead of gameplay
+    pub fn captures(&self, event: &geng::Event, framebuffer_size: Vec2<f32>) -> bool {
+        match *event {
+            geng::Event::KeyDown { key } => matches!(
+                key,
+                geng::Key::Up
+                    | geng::Key::Down
+                    | geng::Key::Left
+                    | geng::Key::Right
+                    | geng::Key::Enter
+            ),
+            geng::Event::MouseDown { position, .. } => {
+                self.teleport_armed || Self::item_at(framebuffer_size, position).is_some()
+            }
+            geng::Event::MouseMove { .. } | geng::Event::MouseUp { .. } => self.dragging.is_some(),
+            _ => false,
+        }
+    }
+    pub fn handle_event(
+        &mut self,
+        event: &geng::Event,
+        tuning: &Tuning,
+        framebuffer_size: Vec2<f32>,
+    ) -> Option<TuningAction> {
+        let fields = Tuning::FIELDS.len();
+        match *event {
+            geng::Event::KeyDown { key } => match key {
+                geng::Key::Up => {
+                    self.selected = (self.selected + Self::count() - 1) % Self::count()
+                }
+                geng::Key::Down => self.selected = (self.selected + 1) % Self::count(),
+                geng::Key::Left | geng::Key::Right if self.selected < fields => {
+                    let direction = if key == geng::Key::Left { -1.0 } else { 1.0 };
+                    let step = Self::range(self.selected) / KEY_STEPS;
+                    let mut tuning = *tuning;
+                    tuning.set(self.selected, tuning.get(self.selected) + direction * step);
+                    return Some(TuningAction::Changed(tuning));
+                }
+                geng::Key::Enter => return self.activate(),
+                _ => {}
+            },
+            geng::Event::MouseDown {
+                position,
+                button: geng::MouseButton::Left,
+            } => match Self::item_at(framebuffer_size, position) {
+                Some(index) => {
+                    self.selected = index;
+                    if index < fields {
+                        self.dragging = Some(index);
+                        return Some(self.drag(tuning, index, framebuffer_size, position));
+                    }
+                    return self.activate();
+                }
+                None if mem::take(&mut self.teleport_armed) => {
+                    return Some(TuningAction::Teleport);
+                }
+                None => {}
+            },
+            geng::Event::MouseMove { position } => {
+                if let Some(index) = self.dragging {
+                    return Some(self.drag(tuning, index, framebuffer_size, position));
+                }
+            }
+            geng::Event::MouseUp { .. } => self.dragging = None,
+            _ => {}
+        }
+        None
+    }
+    pub fn draw(
+        &self,
+        framebuffer: &mut ugli::Framebuffer,
+        renderer: &Renderer,
+        font: &geng::Font,
+        tuning: &Tuning,
+        infinite_chain: bool,
+        color: Color<f32>,
+    ) {
+        let ui_size = UiCamera::size(framebuffer.size().map(|x| x as f32));
+        renderer.draw_ui_rect(
+            framebuffer,
+            Self::panel(ui_size),
+            Color::rgba(0.0, 0.0, 0.0, 0.7),
+        );
+        let dim = Color {
+            a: color.a * 0.7,
+            ..color
+        };
+        let mut text = |text: &str, row: AABB<f32>, color: Color<f32>| {
+            let pos = vec2(
+                row.x_min,
+                row.y_min + (PANEL_ROW_HEIGHT - PANEL_TEXT_SIZE) / 2.0,
+            );
+            draw_ui_text(framebuffer, font, text, pos, 0.0, PANEL_TEXT_SIZE, color);
+        };
+        let title = Self::row(ui_size, 0).translate(vec2(0.0, PANEL_ROW_HEIGHT));
+        text("Tuning", title, color);
+        for index in 0..Self::count() {
+            let label = match index.checked_sub(Tuning::FIELDS.len()) {
+                None => format!("{}: {:.2}", Tuning::FIELDS[index], tuning.get(index)),
+                Some(0) if self.teleport_armed => "Teleport to cursor: click the world".to_owned(),
+                Some(1) => format!(
+                    "{}: {}",
+                    BUTTONS[1],
+                    if infinite_chain { "on" } else { "off" }
+                ),
+                Some(button) => BUTTONS[button].to_owned(),
+            };
+            let (label, label_color) = if index == self.selected {
+                (format!("> {}", label), color)
+            } else {
+                (format!("  {}", label), dim)
+            };
+            text(&label, Self::row(ui_size, index), label_color);
+        }
+        let hints = Self::row(ui_size, Self::count());
+        text(PANEL_HINTS, hints, Color { a: 0.5, ..color });
+        for index in 0..Tuning::FIELDS.len() {
+            let slider = Self::slider(ui_size, index);
+            let t = clamp(tuning.get(index) / Self::range(index), 0.0..=1.0);
+            renderer.draw_ui_rect(framebuffer, slider, Color { a: 0.3, ..color });
+            renderer.draw_ui_rect(
+                framebuffer,
+                AABB::pos_size(
+                    slider.bottom_left(),
+                    vec2(slider.width() * t, slider.height()),
+                ),
+                if index == self.selected { color } else { dim },
+            );
+        }
+    }
+}
@@ -0,0 +1,3 @@
+// Shared by every shader drawn through the world or ui camera
+uniform mat4 u_projection_matrix;
+uniform mat4 u_view_matrix;
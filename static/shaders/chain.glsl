@@ -2,13 +2,13 @@ varying vec2 v_vt;
 
 #ifdef VERTEX_SHADER
 attribute vec2 a_pos;
+attribute vec2 a_vt;
 
-uniform mat4 u_projection_matrix;
-uniform mat4 u_view_matrix;
+#include "camera.glsl"
 uniform mat4 u_model_matrix;
 
 void main() {
-    v_vt = vec2(a_pos.x, 1.0 - a_pos.y);
+    v_vt = a_vt;
     gl_Position = u_projection_matrix * u_view_matrix * u_model_matrix * vec4(a_pos, 0.0, 1.0);
 }
 #endif
@@ -19,4 +19,4 @@ uniform vec4 u_color;
 void main() {
     gl_FragColor = texture2D(u_texture, v_vt) * u_color;
 }
-#endif
\ No newline at end of file
+#endif
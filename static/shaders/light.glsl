@@ -0,0 +1,22 @@
+varying vec2 v_pos;
+
+#ifdef VERTEX_SHADER
+attribute vec2 a_pos;
+
+#include "camera.glsl"
+
+void main() {
+    v_pos = a_pos;
+    gl_Position = u_projection_matrix * u_view_matrix * vec4(a_pos, 0.0, 1.0);
+}
+#endif
+
+#ifdef FRAGMENT_SHADER
+uniform vec2 u_center;
+uniform float u_radius;
+uniform vec4 u_color;
+void main() {
+    float falloff = 1.0 - clamp(length(v_pos - u_center) / u_radius, 0.0, 1.0);
+    gl_FragColor = vec4(u_color.rgb, u_color.a * falloff * falloff);
+}
+#endif
@@ -0,0 +1,27 @@
+varying vec4 v_color;
+varying float v_distance;
+
+#ifdef VERTEX_SHADER
+attribute vec2 a_pos;
+attribute vec4 a_color;
+attribute float a_distance;
+
+#include "camera.glsl"
+
+void main() {
+    v_color = a_color;
+    v_distance = a_distance;
+    gl_Position = u_projection_matrix * u_view_matrix * vec4(a_pos, 0.0, 1.0);
+}
+#endif
+
+#ifdef FRAGMENT_SHADER
+uniform vec4 u_color;
+uniform vec2 u_dash;
+void main() {
+    if (u_dash.x > 0.0 && mod(v_distance, u_dash.x + u_dash.y) > u_dash.x) {
+        discard;
+    }
+    gl_FragColor = v_color * u_color;
+}
+#endif
@@ -0,0 +1,26 @@
+varying float v_edge;
+varying vec4 v_color;
+
+#ifdef VERTEX_SHADER
+attribute vec2 a_pos;
+attribute float a_edge;
+attribute vec4 a_color;
+
+#include "camera.glsl"
+
+void main() {
+    v_edge = a_edge;
+    v_color = a_color;
+    gl_Position = u_projection_matrix * u_view_matrix * vec4(a_pos, 0.0, 1.0);
+}
+#endif
+
+#ifdef FRAGMENT_SHADER
+uniform vec4 u_color;
+uniform float u_softness;
+void main() {
+    float alpha = 1.0 - smoothstep(1.0 - u_softness, 1.0, abs(v_edge));
+    vec4 color = v_color * u_color;
+    gl_FragColor = vec4(color.rgb, color.a * alpha);
+}
+#endif
@@ -0,0 +1,24 @@
+varying vec2 v_vt;
+
+#ifdef VERTEX_SHADER
+attribute vec2 a_pos;
+
+#include "camera.glsl"
+uniform mat4 u_model_matrix;
+uniform vec4 u_uv_rect;
+
+void main() {
+    v_vt = mix(u_uv_rect.xy, u_uv_rect.zw, a_pos);
+    gl_Position = u_projection_matrix * u_view_matrix * u_model_matrix * vec4(a_pos, 0.0, 1.0);
+}
+#endif
+
+#ifdef FRAGMENT_SHADER
+uniform sampler2D u_texture;
+uniform vec4 u_color;
+void main() {
+    // Layers repeat horizontally, below the layer the bottom row is stretched down
+    vec2 vt = vec2(v_vt.x, clamp(v_vt.y, 0.01, 0.99));
+    gl_FragColor = texture2D(u_texture, vt) * u_color;
+}
+#endif
@@ -0,0 +1,32 @@
+varying vec2 v_vt;
+
+#ifdef VERTEX_SHADER
+attribute vec2 a_pos;
+
+uniform vec4 u_rect;
+
+void main() {
+    v_vt = a_pos * 0.5 + 0.5;
+    gl_Position = vec4(mix(u_rect.xy, u_rect.zw, v_vt), 0.0, 1.0);
+}
+#endif
+
+#ifdef FRAGMENT_SHADER
+uniform sampler2D u_texture;
+uniform sampler2D u_light_map;
+uniform float u_vignette;
+uniform float u_aberration;
+uniform float u_flash;
+uniform vec4 u_flash_color;
+
+void main() {
+    vec2 offset = (v_vt - 0.5) * u_aberration;
+    vec4 color = texture2D(u_texture, v_vt);
+    color.r = texture2D(u_texture, v_vt + offset).r;
+    color.b = texture2D(u_texture, v_vt - offset).b;
+    color.rgb *= texture2D(u_light_map, v_vt).rgb;
+    color.rgb *= 1.0 - u_vignette * smoothstep(0.3, 0.8, length(v_vt - 0.5));
+    color.rgb = mix(color.rgb, u_flash_color.rgb, u_flash * u_flash_color.a);
+    gl_FragColor = vec4(color.rgb, 1.0);
+}
+#endif
@@ -0,0 +1,33 @@
+#ifdef VERTEX_SHADER
+attribute vec2 a_pos;
+
+void main() {
+    gl_Position = vec4(a_pos, 0.0, 1.0);
+}
+#endif
+
+#ifdef FRAGMENT_SHADER
+uniform int u_kind;
+uniform float u_amount;
+uniform vec2 u_center;
+uniform vec2 u_framebuffer_size;
+uniform vec4 u_color;
+
+float hash(vec2 p) {
+    return fract(sin(dot(p, vec2(12.9898, 78.233))) * 43758.5453);
+}
+
+void main() {
+    float alpha;
+    if (u_kind == 0) {
+        alpha = u_amount;
+    } else if (u_kind == 1) {
+        float radius = length(u_framebuffer_size) * (1.0 - u_amount);
+        alpha = smoothstep(radius - 2.0, radius + 2.0, length(gl_FragCoord.xy - u_center));
+    } else {
+        float noise = hash(floor(gl_FragCoord.xy / 4.0));
+        alpha = step(noise, u_amount * 1.01);
+    }
+    gl_FragColor = vec4(u_color.rgb, u_color.a * alpha);
+}
+#endif